@@ -0,0 +1,214 @@
+use std::cmp::Reverse;
+
+use serde::Deserialize;
+
+/// Files the dependency graph reads dependencies from; a PR touching none
+/// of them has nothing to look up.
+const MANIFESTS: [&str; 20] = [
+    "package.json",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.toml",
+    "Cargo.lock",
+    "go.mod",
+    "go.sum",
+    "requirements.txt",
+    "Pipfile",
+    "Pipfile.lock",
+    "pyproject.toml",
+    "poetry.lock",
+    "Gemfile",
+    "Gemfile.lock",
+    "composer.json",
+    "composer.lock",
+    "pom.xml",
+    "build.gradle",
+    "packages.lock.json",
+];
+
+pub fn is_manifest(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    MANIFESTS.contains(&name)
+}
+
+/// A dependency added or removed between two commits, from GitHub's
+/// dependency review API. A version bump is a removal and an addition.
+#[derive(Deserialize)]
+pub struct Change {
+    /// `added` or `removed`.
+    pub change_type: String,
+    pub manifest: String,
+    pub ecosystem: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+/// An advisory affecting one version of a dependency.
+#[derive(Clone, Deserialize)]
+pub struct Vulnerability {
+    /// `critical`, `high`, `moderate` or `low`.
+    pub severity: String,
+    pub advisory_ghsa_id: String,
+    pub advisory_summary: String,
+    pub advisory_url: String,
+    /// Filled in from the advisory database; the dependency review API
+    /// only names the GHSA id.
+    #[serde(default)]
+    pub cve_id: Option<String>,
+}
+
+impl Vulnerability {
+    pub fn rank(&self) -> u8 {
+        match self.severity.as_str() {
+            "critical" => 3,
+            "high" => 2,
+            "moderate" => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// A dependency the PR adds, removes or moves to another version, with the
+/// advisories that fixes and those it brings in.
+pub struct Bump {
+    pub manifest: String,
+    pub ecosystem: String,
+    pub name: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub fixed: Vec<Vulnerability>,
+    pub introduced: Vec<Vulnerability>,
+}
+
+/// Pairs each removal with the addition of the same dependency in the same
+/// manifest. Advisories only the old version had are fixed; those only the
+/// new one has are introduced. Worst advisories come first.
+pub fn bumps(changes: Vec<Change>) -> Vec<Bump> {
+    let mut bumps: Vec<Bump> = Vec::new();
+    let mut before: Vec<(usize, Vec<Vulnerability>)> = Vec::new();
+    let mut after: Vec<(usize, Vec<Vulnerability>)> = Vec::new();
+    for change in changes {
+        let index = match bumps
+            .iter()
+            .position(|bump| bump.manifest == change.manifest && bump.name == change.name)
+        {
+            Some(index) => index,
+            None => {
+                bumps.push(Bump {
+                    manifest: change.manifest,
+                    ecosystem: change.ecosystem,
+                    name: change.name,
+                    from: None,
+                    to: None,
+                    fixed: Vec::new(),
+                    introduced: Vec::new(),
+                });
+                bumps.len() - 1
+            }
+        };
+        if change.change_type == "removed" {
+            bumps[index].from = Some(change.version);
+            before.push((index, change.vulnerabilities));
+        } else {
+            bumps[index].to = Some(change.version);
+            after.push((index, change.vulnerabilities));
+        }
+    }
+    let affects = |side: &[(usize, Vec<Vulnerability>)], index: usize, id: &str| {
+        side.iter()
+            .filter(|(i, _)| *i == index)
+            .flat_map(|(_, vulnerabilities)| vulnerabilities)
+            .any(|vulnerability| vulnerability.advisory_ghsa_id == id)
+    };
+    for (index, vulnerabilities) in &before {
+        for vulnerability in vulnerabilities {
+            if !affects(&after, *index, &vulnerability.advisory_ghsa_id) {
+                bumps[*index].fixed.push(vulnerability.clone());
+            }
+        }
+    }
+    for (index, vulnerabilities) in &after {
+        for vulnerability in vulnerabilities {
+            if !affects(&before, *index, &vulnerability.advisory_ghsa_id) {
+                bumps[*index].introduced.push(vulnerability.clone());
+            }
+        }
+    }
+    for bump in &mut bumps {
+        bump.fixed
+            .sort_by_key(|vulnerability| Reverse(vulnerability.rank()));
+        bump.introduced
+            .sort_by_key(|vulnerability| Reverse(vulnerability.rank()));
+    }
+    bumps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bumps_pair_versions_and_diff_their_advisories() {
+        let vulnerability = |id: &str, severity: &str| {
+            serde_json::json!({
+                "severity": severity,
+                "advisory_ghsa_id": id,
+                "advisory_summary": "",
+                "advisory_url": "",
+            })
+        };
+        let changes: Vec<Change> = serde_json::from_value(serde_json::json!([
+            {
+                "change_type": "removed",
+                "manifest": "package-lock.json",
+                "ecosystem": "npm",
+                "name": "lodash",
+                "version": "4.17.15",
+                "vulnerabilities": [
+                    vulnerability("GHSA-low", "low"),
+                    vulnerability("GHSA-high", "high"),
+                    vulnerability("GHSA-kept", "moderate"),
+                ],
+            },
+            {
+                "change_type": "added",
+                "manifest": "package-lock.json",
+                "ecosystem": "npm",
+                "name": "lodash",
+                "version": "4.17.21",
+                "vulnerabilities": [
+                    vulnerability("GHSA-kept", "moderate"),
+                    vulnerability("GHSA-new", "critical"),
+                ],
+            },
+            {
+                "change_type": "added",
+                "manifest": "package.json",
+                "ecosystem": "npm",
+                "name": "left-pad",
+                "version": "1.3.0",
+            },
+        ]))
+        .unwrap();
+        let bumps = bumps(changes);
+        assert_eq!(bumps.len(), 2);
+        let lodash = &bumps[0];
+        assert_eq!(
+            (lodash.from.as_deref(), lodash.to.as_deref()),
+            (Some("4.17.15"), Some("4.17.21"))
+        );
+        let ids = |list: &[Vulnerability]| -> Vec<String> {
+            list.iter()
+                .map(|vulnerability| vulnerability.advisory_ghsa_id.clone())
+                .collect()
+        };
+        assert_eq!(ids(&lodash.fixed), ["GHSA-high", "GHSA-low"]);
+        assert_eq!(ids(&lodash.introduced), ["GHSA-new"]);
+        assert_eq!(bumps[1].from, None);
+        assert!(is_manifest("web/package.json"));
+        assert!(!is_manifest("src/main.rs"));
+    }
+}
@@ -0,0 +1,140 @@
+use textwrap::core::display_width;
+
+/// A piece of text that may carry terminal escape sequences.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// Printable text without escapes.
+    Text(&'a str),
+    /// A Select Graphic Rendition sequence such as `\x1b[31m`.
+    Style(&'a str),
+    /// An OSC 8 hyperlink opener or closer.
+    Link(&'a str),
+    /// Any other escape: cursor movement, screen clearing, window titles.
+    /// These would corrupt the layout if passed through.
+    Control(&'a str),
+}
+
+/// Splits `text` into printable runs and escape sequences. Unterminated
+/// sequences at the end of the input are treated as control sequences.
+pub fn parse(text: &str) -> Vec<Segment<'_>> {
+    let bytes = text.as_bytes();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != 0x1b {
+            i += 1;
+            continue;
+        }
+        if start < i {
+            segments.push(Segment::Text(&text[start..i]));
+        }
+        let end = escape_end(bytes, i);
+        let sequence = &text[i..end];
+        segments.push(match bytes.get(i + 1) {
+            Some(b'[') if sequence.ends_with('m') => Segment::Style(sequence),
+            Some(b']') if sequence.starts_with("\x1b]8;") => Segment::Link(sequence),
+            _ => Segment::Control(sequence),
+        });
+        i = end;
+        start = end;
+    }
+    if start < bytes.len() {
+        segments.push(Segment::Text(&text[start..]));
+    }
+    segments
+}
+
+/// Index just past the escape sequence starting at `start`.
+fn escape_end(bytes: &[u8], start: usize) -> usize {
+    match bytes.get(start + 1) {
+        Some(b'[') => bytes[start + 2..]
+            .iter()
+            .position(|b| (0x40..=0x7e).contains(b))
+            .map_or(bytes.len(), |pos| start + 2 + pos + 1),
+        Some(b']') => {
+            let mut i = start + 2;
+            while i < bytes.len() {
+                match bytes[i] {
+                    0x07 => return i + 1,
+                    0x1b if bytes.get(i + 1) == Some(&b'\\') => return i + 2,
+                    _ => i += 1,
+                }
+            }
+            bytes.len()
+        }
+        Some(_) => start + 2,
+        None => bytes.len(),
+    }
+}
+
+/// Removes every escape sequence, leaving only the printable text.
+pub fn strip(text: &str) -> String {
+    parse(text)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(text),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Cuts `line` to `width` display columns, keeping colours and hyperlinks
+/// and dropping control sequences. Styles and links still open at the cut
+/// are closed so they don't bleed into whatever is drawn next.
+pub fn crop(line: &str, width: usize) -> String {
+    let mut cropped = String::with_capacity(line.len());
+    crop_into(&mut cropped, line, width);
+    cropped
+}
+
+/// [`crop`] into an existing buffer, so a caller drawing many lines can
+/// reuse one allocation.
+pub fn crop_into(cropped: &mut String, line: &str, width: usize) {
+    let mut used = 0;
+    let mut styled = false;
+    let mut linked = false;
+    for segment in parse(line) {
+        match segment {
+            Segment::Text(text) => {
+                if used >= width {
+                    continue;
+                }
+                for ch in text.chars() {
+                    let ch_width = display_width(ch.encode_utf8(&mut [0; 4]));
+                    if used + ch_width > width {
+                        used = width;
+                        break;
+                    }
+                    cropped.push(ch);
+                    used += ch_width;
+                }
+            }
+            Segment::Style(sequence) => {
+                styled = true;
+                cropped.push_str(sequence);
+            }
+            Segment::Link(sequence) => {
+                linked = opens_link(sequence);
+                cropped.push_str(sequence);
+            }
+            Segment::Control(_) => {}
+        }
+    }
+    if linked {
+        cropped.push_str("\x1b]8;;\x07");
+    }
+    if styled {
+        cropped.push_str("\x1b[0m");
+    }
+}
+
+/// An OSC 8 sequence with a URL opens a link; an empty URL closes it.
+fn opens_link(sequence: &str) -> bool {
+    sequence
+        .trim_end_matches(['\x07', '\\'])
+        .trim_end_matches('\x1b')
+        .rsplit(';')
+        .next()
+        .is_some_and(|url| !url.is_empty())
+}
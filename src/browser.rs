@@ -0,0 +1,28 @@
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, anyhow};
+
+/// Opens `url` with the platform's default handler, honouring `$BROWSER`.
+pub fn open(url: &str) -> Result<()> {
+    let mut command = match std::env::var("BROWSER") {
+        Ok(browser) if !browser.is_empty() => Command::new(browser),
+        _ if cfg!(target_os = "macos") => Command::new("open"),
+        _ if cfg!(windows) => {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        }
+        _ => Command::new("xdg-open"),
+    };
+    let status = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("failed to launch browser")?;
+    if !status.success() {
+        return Err(anyhow!("browser exited with {status}"));
+    }
+    Ok(())
+}
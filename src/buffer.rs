@@ -0,0 +1,91 @@
+use std::{
+    fmt::{self, Display, Write as _},
+    io::{self, Write},
+    ops::Range,
+};
+
+use crossterm::{cursor::MoveTo, queue};
+
+/// A region of the terminal, in cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The column just past the right edge.
+    pub fn right(&self) -> u16 {
+        self.x.saturating_add(self.width)
+    }
+
+    /// The `width` columns at the right edge, or all of it when narrower.
+    pub fn right_columns(&self, width: u16) -> Self {
+        let width = width.min(self.width);
+        Self::new(self.right() - width, self.y, width, self.height)
+    }
+
+    /// Everything left of the `width` columns at the right edge.
+    pub fn without_right(&self, width: u16) -> Self {
+        Self::new(
+            self.x,
+            self.y,
+            self.width.saturating_sub(width),
+            self.height,
+        )
+    }
+
+    /// The rows from `top` down, relative to this area.
+    pub fn below(&self, top: u16) -> Self {
+        let top = top.min(self.height);
+        Self::new(self.x, self.y + top, self.width, self.height - top)
+    }
+}
+
+/// Styled text placed on the screen for one frame. Drawing code puts text
+/// at cells within the area it was given, and [`Buffer::flush`] writes it
+/// all out in the order it was put, so later text draws over earlier text.
+#[derive(Default)]
+pub struct Buffer {
+    text: String,
+    /// Where each piece of `text` goes.
+    spans: Vec<(u16, u16, Range<usize>)>,
+}
+
+impl Buffer {
+    /// Empties the buffer, keeping its allocations for the next frame.
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.spans.clear();
+    }
+
+    /// Puts `content` with its top-left at column `x`, row `y`. Callers
+    /// crop it to their area; escape sequences are copied through.
+    pub fn put(&mut self, x: u16, y: u16, content: impl Display) -> fmt::Result {
+        let start = self.text.len();
+        write!(self.text, "{content}")?;
+        self.spans.push((x, y, start..self.text.len()));
+        Ok(())
+    }
+
+    /// Writes everything put since the last clear to `out`, without
+    /// flushing it.
+    pub fn flush(&self, out: &mut impl Write) -> io::Result<()> {
+        for (x, y, range) in &self.spans {
+            queue!(out, MoveTo(*x, *y))?;
+            out.write_all(self.text[range.clone()].as_bytes())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    env,
+    io::{Write, stdout},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+/// Copies `text` to the system clipboard through the terminal using OSC 52,
+/// which also works over SSH, and through the platform clipboard tool when
+/// one is available, for terminals that ignore OSC 52.
+pub fn copy(text: &str) -> Result<()> {
+    let mut out = stdout();
+    write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes()))
+        .and_then(|_| out.flush())
+        .context("failed to write clipboard sequence")?;
+    if let Err(err) = copy_native(text) {
+        log::debug!("native clipboard unavailable: {err}");
+    }
+    Ok(())
+}
+
+fn copy_native(text: &str) -> Result<()> {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+        ("pbcopy", &[])
+    } else if cfg!(windows) {
+        ("clip", &[])
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        ("wl-copy", &[])
+    } else if env::var_os("DISPLAY").is_some() {
+        ("xclip", &["-selection", "clipboard"])
+    } else {
+        return Err(anyhow!("no display"));
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to run {program}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("{program} exited with {status}"));
+    }
+    Ok(())
+}
+
+pub fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (triple >> (18 - 6 * i)) & 0x3f;
+                encoded.push(ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
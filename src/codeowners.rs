@@ -0,0 +1,63 @@
+use crate::filter::glob_match;
+
+/// Where GitHub looks for a CODEOWNERS file, in the order it checks them.
+pub const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// The rules of a CODEOWNERS file. As on GitHub, the last rule matching a
+/// path decides its owners, and a rule listing none leaves it unowned.
+pub struct CodeOwners {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl CodeOwners {
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .map(|line| line.split_once('#').map_or(line, |(rule, _)| rule))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let pattern = parts.next()?;
+                Some((pattern.to_string(), parts.map(str::to_string).collect()))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Users and teams owning `path`, e.g. `@octocat` or `@org/team`.
+    pub fn owners(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern_matches(pattern, path))
+            .map_or(&[], |(_, owners)| owners)
+    }
+
+    /// The owners of any of `paths`, with how many of them each owns,
+    /// most first.
+    pub fn suggest<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for path in paths {
+            for owner in self.owners(path) {
+                match counts.iter_mut().find(|(other, _)| other == owner) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((owner.clone(), 1)),
+                }
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+}
+
+/// Matches with `.gitignore` semantics: a pattern is anchored to the root
+/// when it starts with or contains a slash, matches anywhere otherwise, and
+/// matching a directory matches everything under it.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let trimmed = pattern.trim_end_matches('/');
+    let pattern = match trimmed.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if trimmed.contains('/') => trimmed.to_string(),
+        None => format!("**/{trimmed}"),
+    };
+    glob_match(&pattern, path) || glob_match(&format!("{pattern}/**"), path)
+}
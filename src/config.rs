@@ -0,0 +1,405 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::{git, keymap::Profile};
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    pub keys: KeysConfig,
+    pub scroll: ScrollConfig,
+    pub diff: DiffConfig,
+    pub watch: WatchConfig,
+    pub checkout: CheckoutConfig,
+    pub worktree: WorktreeConfig,
+    pub hooks: HooksConfig,
+    pub checklist: ChecklistConfig,
+    pub spell: SpellConfig,
+    pub copy: CopyConfig,
+    /// Command used to write replies; falls back to `$EDITOR`.
+    pub editor: Option<String>,
+    /// Seconds between background thread refreshes; 0 disables them.
+    pub refresh_interval: u64,
+    /// Built-in or user-defined theme name.
+    pub theme: Option<String>,
+    pub color: ColorMode,
+    /// Render PR numbers, authors, paths and checks as OSC 8 links.
+    pub hyperlinks: HyperlinkMode,
+    /// Disable every action that writes to GitHub.
+    pub read_only: bool,
+    /// Read threads as plain lines instead of opening the TUI, for screen
+    /// readers.
+    pub plain: bool,
+    /// Add queued replies to your pending review on GitHub as they are
+    /// written, so the review can be finished on another machine.
+    pub sync_review: bool,
+    /// Order of the thread lists when the TUI opens.
+    pub sort: ThreadSort,
+    pub authors: AuthorStyle,
+    pub themes: HashMap<String, CustomTheme>,
+    /// Trackers whose keys are linked from the PR header.
+    pub issues: Vec<IssueTracker>,
+}
+
+/// An issue tracker whose keys are picked out of the PR's title, body and
+/// branch name.
+#[derive(Clone, Deserialize)]
+pub struct IssueTracker {
+    /// Matches a key, such as `\b[A-Z][A-Z0-9]+-\d+\b` for Jira.
+    pub pattern: String,
+    /// Where a key leads, with `{key}` replaced by it.
+    pub url: String,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Never,
+    #[serde(alias = "16")]
+    Basic,
+    #[serde(rename = "256")]
+    Ansi256,
+    Truecolor,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HyperlinkMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// How thread lists are ordered. Apart from `created`, threads are grouped
+/// by file and keep their usual order within it.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThreadSort {
+    /// Unresolved first, then oldest first.
+    #[default]
+    Created,
+    Path,
+    /// Files with the most added and removed lines first.
+    Changes,
+    /// Files with the most comments first.
+    Comments,
+}
+
+/// How comment authors are told apart.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthorStyle {
+    /// Every author in the theme's `author` colour.
+    Plain,
+    /// Each author in their own colour from the theme's palette.
+    #[default]
+    Color,
+    /// Coloured, with a badge of their initials in front.
+    Badge,
+}
+
+/// A user theme: a built-in `base` with individual colours replaced.
+#[derive(Deserialize)]
+pub struct CustomTheme {
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub colors: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DiffConfig {
+    /// Whether diff hunks are shown when the TUI opens.
+    pub show: bool,
+    /// Command that colours diff hunks, e.g. `delta --color-only`.
+    pub renderer: Option<String>,
+    /// Keep renderer output on disk so reopening a PR skips re-rendering
+    /// hunks that haven't changed.
+    pub cache: bool,
+    /// Columns between tab stops when expanding tabs in hunks.
+    pub tab_width: usize,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            show: true,
+            renderer: None,
+            cache: false,
+            tab_width: 4,
+        }
+    }
+}
+
+/// Shell commands run when something happens to the PR.
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HooksConfig {
+    pub on_open_pr: Option<String>,
+    pub on_comment_posted: Option<String>,
+    pub on_review_submitted: Option<String>,
+    pub on_approve: Option<String>,
+}
+
+/// Things to confirm before submitting a review, usually set per repository.
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ChecklistConfig {
+    pub items: Vec<String>,
+    /// Add the ticked and unticked items to the body of `gh cr review`.
+    pub append_to_review: bool,
+}
+
+/// How `copy-reference` formats the code it points at.
+#[derive(Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CopyConfig {
+    /// With `{path}`, `{line}`, `{sha}`, `{short_sha}`, `{permalink}` (at
+    /// the head commit) and `{pr}` filled in.
+    pub reference: String,
+}
+
+impl Default for CopyConfig {
+    fn default() -> Self {
+        Self {
+            reference: "{path}:{line}".into(),
+        }
+    }
+}
+
+/// Spell checking of the replies and notes written in the editor.
+#[derive(Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SpellConfig {
+    pub enabled: bool,
+    /// Dictionary to check against, such as `en_US` or `en_GB`.
+    pub language: String,
+    /// `hunspell`, or `aspell`, which speaks the same protocol.
+    pub command: String,
+    /// Words never flagged, such as names from the codebase.
+    pub words: Vec<String>,
+}
+
+impl Default for SpellConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            language: "en_US".into(),
+            command: "hunspell".into(),
+            words: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct CheckoutConfig {
+    /// Check out the PR head as a detached HEAD instead of a local branch.
+    pub detach: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WorktreeConfig {
+    /// Where PR worktrees are created; defaults to a sibling of the checkout.
+    pub dir: Option<PathBuf>,
+    /// Remote the PR head is fetched from.
+    pub remote: String,
+}
+
+impl Default for WorktreeConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            remote: "origin".into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    /// Poll for pushes, comments and check transitions.
+    pub enabled: bool,
+    /// Seconds between polls when `refresh-interval` is 0.
+    pub interval: u64,
+    /// Also raise a desktop notification for each change.
+    pub notify: bool,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 30,
+            notify: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ScrollConfig {
+    /// Lines moved per mouse wheel event.
+    pub wheel: usize,
+    /// Lines moved per scroll key press.
+    pub step: usize,
+    /// Lines kept on screen from the previous page when paging.
+    pub page_overlap: usize,
+    /// Draw a scrollbar and position indicator when content overflows.
+    pub scrollbar: bool,
+    /// Draw a one-column overview of additions, deletions and comments.
+    pub minimap: bool,
+}
+
+impl Default for ScrollConfig {
+    fn default() -> Self {
+        Self {
+            wheel: 3,
+            step: 1,
+            page_overlap: 1,
+            scrollbar: true,
+            minimap: false,
+        }
+    }
+}
+
+/// Bindings under `[keys]` apply everywhere; `[keys.<view>]` tables override
+/// them for a single thread view.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct KeysConfig {
+    pub profile: Profile,
+    pub unresolved: HashMap<String, KeyList>,
+    pub unskipped: HashMap<String, KeyList>,
+    pub skipped: HashMap<String, KeyList>,
+    #[serde(flatten)]
+    pub global: HashMap<String, KeyList>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        match self {
+            KeyList::One(key) => std::slice::from_ref(key).iter(),
+            KeyList::Many(keys) => keys.iter(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the user config, then layers the repository's `.gh-cr.toml` on
+    /// top of it table by table.
+    pub fn load() -> Result<Self> {
+        let mut merged = read_table(&Self::default_path()?)?.unwrap_or_default();
+        if let Some(mut repo) = read_table(&Self::repo_path())? {
+            // These run commands or choose what git fetches and where, so a
+            // cloned repository mustn't be able to set them.
+            for key in USER_ONLY {
+                if remove_key(&mut repo, key) {
+                    log::warn!("ignoring {key} in the repository config");
+                }
+            }
+            merge_tables(&mut merged, repo);
+        }
+        let config: Self = Value::Table(merged)
+            .try_into()
+            .context("invalid configuration")?;
+        // Git would take it as an option rather than a remote.
+        if config.worktree.remote.starts_with('-') {
+            return Err(anyhow!(
+                "invalid worktree.remote `{}`: remote names can't start with `-`",
+                config.worktree.remote
+            ));
+        }
+        Ok(config)
+    }
+
+    pub fn editor_command(&self) -> String {
+        self.editor
+            .clone()
+            .filter(|editor| !editor.trim().is_empty())
+            .or_else(|| env::var("EDITOR").ok().filter(|editor| !editor.is_empty()))
+            .unwrap_or_else(|| "vim".into())
+    }
+
+    fn repo_path() -> PathBuf {
+        git::toplevel()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".gh-cr.toml")
+    }
+
+    /// Where the user config lives, whether or not it exists yet.
+    pub fn default_path() -> Result<PathBuf> {
+        if let Ok(dir) = env::var("XDG_CONFIG_HOME")
+            && !dir.is_empty()
+        {
+            return Ok(Path::new(&dir).join("gh-cr").join("config.toml"));
+        }
+        let mut path = dirs_next::home_dir().ok_or_else(|| anyhow!("HOME not set"))?;
+        path.push(".config");
+        path.push("gh-cr");
+        path.push("config.toml");
+        Ok(path)
+    }
+}
+
+/// Settings naming a program to run, only taken from the user config.
+const USER_ONLY: [&str; 5] = [
+    "hooks",
+    "editor",
+    "diff.renderer",
+    "spell.command",
+    "worktree",
+];
+
+/// Removes a dotted `key` such as `diff.renderer` from `table`, returning
+/// whether it was there.
+fn remove_key(table: &mut Table, key: &str) -> bool {
+    match key.split_once('.') {
+        Some((head, rest)) => match table.get_mut(head) {
+            Some(Value::Table(inner)) => remove_key(inner, rest),
+            _ => false,
+        },
+        None => table.remove(key).is_some(),
+    }
+}
+
+fn read_table(path: &Path) -> Result<Option<Table>> {
+    match fs::read_to_string(path) {
+        Ok(raw) => toml::from_str(&raw)
+            .map(Some)
+            .with_context(|| format!("failed to parse {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(incoming)) => {
+                merge_tables(existing, incoming)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    borrow::Cow,
+    env, fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result, anyhow};
+use tempfile::NamedTempFile;
+use textwrap::core::display_width;
+
+use crate::ansi::{self, Segment};
+
+/// Pipes a thread's diff hunk through an external renderer such as
+/// `delta --color-only` and returns its coloured output line by line. The
+/// hunk is given file headers first so the renderer recognises a diff; when
+/// the output maps line for line onto the input, those headers are dropped.
+pub fn render_external(command: &str, path: &str, hunk: &str, width: u16) -> Result<Vec<String>> {
+    if command.trim().is_empty() {
+        return Err(anyhow!("diff renderer command is empty"));
+    }
+    // Through the shell, so arguments can be quoted as on the command line.
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(command)
+        .env("COLUMNS", width.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to launch diff renderer: {command}"))?;
+    // Fed from another thread: a renderer that streams its output would
+    // otherwise fill the stdout pipe on a large hunk while we're still
+    // writing, and both sides would block.
+    let input = format!("diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n{hunk}\n");
+    let writer = child.stdin.take().map(|mut stdin| {
+        thread::spawn(move || {
+            // A renderer may exit without reading everything; that's its
+            // output to judge, not a write error to report.
+            let _ = stdin.write_all(input.as_bytes());
+        })
+    });
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{command} exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut lines: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if lines.len() == hunk.lines().count() + 3 {
+        lines.drain(..3);
+    }
+    Ok(lines)
+}
+
+/// The new-file line number of each line in `hunk`, or `None` for the
+/// `@@` header and removed lines, which don't exist in the new file.
+pub fn new_line_numbers(hunk: &str) -> Vec<Option<usize>> {
+    let mut next = None;
+    hunk.lines()
+        .map(|line| {
+            if line.starts_with("@@") {
+                next = parse_new_start(line);
+                return None;
+            }
+            match (line.chars().next(), next) {
+                (Some('-'), _) | (_, None) => None,
+                (_, Some(number)) => {
+                    next = Some(number + 1);
+                    Some(number)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Byte ranges of an added line that reviewers usually flag: indentation
+/// mixing tabs and spaces, and trailing whitespace. Other lines have none.
+pub fn whitespace_errors(line: &str) -> Vec<Range<usize>> {
+    let Some(body) = line.strip_prefix('+') else {
+        return Vec::new();
+    };
+    let content = body.trim_end_matches([' ', '\t']).len();
+    let mut errors = Vec::new();
+    if content == 0 {
+        // A blank line that isn't empty is all trailing whitespace.
+        if !body.is_empty() {
+            errors.push(1..line.len());
+        }
+        return errors;
+    }
+    let indent = body.len() - body.trim_start_matches([' ', '\t']).len();
+    if body[..indent].contains(' ') && body[..indent].contains('\t') {
+        errors.push(1..1 + indent);
+    }
+    if content < body.len() {
+        errors.push(1 + content..line.len());
+    }
+    errors
+}
+
+/// Whether `line` is Git's `\ No newline at end of file` marker.
+pub fn is_no_newline_marker(line: &str) -> bool {
+    line.starts_with('\\')
+}
+
+/// Expands the tabs in a line of a hunk, coloured or not, to spaces up to
+/// the next multiple of `width` columns, so the terminal's own tab stops
+/// can't throw the layout off. Columns count from after the `+`, `-` or
+/// space marker, as in the file itself.
+pub fn expand_tabs(line: &str, width: usize) -> Cow<'_, str> {
+    if !line.contains('\t') {
+        return line.into();
+    }
+    let width = width.max(1);
+    let mut expanded = String::with_capacity(line.len() + width * 2);
+    // `None` until the marker has gone by.
+    let mut column: Option<usize> = None;
+    for segment in ansi::parse(line) {
+        let text = match segment {
+            Segment::Text(text) => text,
+            Segment::Style(escape) | Segment::Link(escape) | Segment::Control(escape) => {
+                expanded.push_str(escape);
+                continue;
+            }
+        };
+        for c in text.chars() {
+            match (c, column.as_mut()) {
+                ('\t', Some(column)) => {
+                    let spaces = width - *column % width;
+                    expanded.extend(std::iter::repeat_n(' ', spaces));
+                    *column += spaces;
+                }
+                (c, Some(column)) => {
+                    expanded.push(c);
+                    *column += display_width(c.encode_utf8(&mut [0; 4]));
+                }
+                (c, None) => {
+                    expanded.push(c);
+                    column = Some(0);
+                }
+            }
+        }
+    }
+    expanded.into()
+}
+
+/// Fewest unchanged lines worth folding away.
+const MIN_FOLD: usize = 4;
+
+/// Runs of a hunk's lines that are the unchanged body of a block, such as
+/// a function or loop, so they can be folded to show the shape of the code
+/// around the changes. Blocks are found by indentation rather than parsed,
+/// so any language works; a changed block can still have unchanged blocks
+/// inside it folded.
+pub fn unchanged_blocks(hunk: &str) -> Vec<Range<usize>> {
+    let lines: Vec<&str> = hunk.lines().collect();
+    let unchanged = |line: &str| line.is_empty() || line.starts_with(' ');
+    let blank = |line: &str| line.get(1..).is_none_or(|body| body.trim().is_empty());
+    let indent = |line: &str| -> usize {
+        line.get(1..).map_or(0, |body| {
+            body.chars()
+                .take_while(|c| c.is_whitespace())
+                .map(|c| if c == '\t' { 4 } else { 1 })
+                .sum()
+        })
+    };
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let opener = lines[i];
+        if blank(opener) || opener.starts_with('@') {
+            i += 1;
+            continue;
+        }
+        let depth = indent(opener);
+        let mut end = i + 1;
+        while end < lines.len() && (blank(lines[end]) || indent(lines[end]) > depth) {
+            end += 1;
+        }
+        // Blank lines after the body belong to whatever follows it.
+        while end > i + 1 && blank(lines[end - 1]) {
+            end -= 1;
+        }
+        let body = &lines[i + 1..end];
+        if body.len() >= MIN_FOLD && body.iter().all(|line| unchanged(line)) {
+            blocks.push(i + 1..end);
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    blocks
+}
+
+/// A row of a hunk as shown: one of its lines, or a run of them folded.
+pub enum Row {
+    Line(usize),
+    Folded(Range<usize>),
+}
+
+/// The rows showing `count` lines with the runs in `folds` collapsed.
+pub fn rows(count: usize, folds: &[Range<usize>]) -> Vec<Row> {
+    let mut rows = Vec::with_capacity(count);
+    let mut folds = folds.iter().peekable();
+    let mut i = 0;
+    while i < count {
+        match folds.next_if(|fold| fold.start == i) {
+            Some(fold) => {
+                rows.push(Row::Folded(fold.clone()));
+                i = fold.end;
+            }
+            None => {
+                rows.push(Row::Line(i));
+                i += 1;
+            }
+        }
+    }
+    rows
+}
+
+/// Start line of the new side from a header like `@@ -10,4 +12,6 @@`.
+fn parse_new_start(header: &str) -> Option<usize> {
+    let new = header
+        .split_whitespace()
+        .find(|part| part.starts_with('+'))?;
+    new[1..].split(',').next()?.parse().ok()
+}
+
+/// The new-side path from a `diff --git a/old b/new` file header, or `None`
+/// for any other line. Lets a diff be split into files as it streams in.
+pub fn file_header_path(line: &str) -> Option<&str> {
+    let paths = line.strip_prefix("diff --git ")?;
+    paths.rsplit_once(" b/").map(|(_, path)| path)
+}
+
+/// One file's part of a multi-file diff.
+pub struct FileDiff {
+    /// The new-side path.
+    pub path: String,
+    /// `added`, `modified`, `removed` or `renamed`, as GitHub names them.
+    pub status: &'static str,
+    /// The path before a rename.
+    pub previous: Option<String>,
+    /// Each hunk from its `@@` header on, with the first new-side line.
+    pub hunks: Vec<(Option<usize>, String)>,
+}
+
+/// Splits `git diff` output into files and hunks. Binary files and pure
+/// renames come back with no hunks.
+pub fn split_files(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    for line in diff.lines() {
+        if let Some(path) = file_header_path(line) {
+            files.push(FileDiff {
+                path: path.to_string(),
+                status: "modified",
+                previous: None,
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with("@@") {
+            file.hunks.push((parse_new_start(line), String::new()));
+        }
+        match file.hunks.last_mut() {
+            Some((_, hunk)) => {
+                hunk.push_str(line);
+                hunk.push('\n');
+            }
+            None if line.starts_with("new file mode") => file.status = "added",
+            None if line.starts_with("deleted file mode") => file.status = "removed",
+            None => {
+                if let Some(from) = line.strip_prefix("rename from ") {
+                    file.status = "renamed";
+                    file.previous = Some(from.to_string());
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Renderer output saved under `$XDG_CACHE_HOME/gh-cr/render`, one file per
+/// rendered hunk. Entries are keyed by the hunk's content rather than the
+/// thread, so an unchanged hunk hits even after the PR is pushed to, and a
+/// different command (say, another `--syntax-theme`) or width misses.
+/// Entries unused for [`CACHE_MAX_AGE`] are deleted when it's opened.
+#[derive(Clone)]
+pub struct RenderCache {
+    dir: PathBuf,
+}
+
+const CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+impl RenderCache {
+    pub fn open() -> Result<Self> {
+        let base = match env::var("XDG_CACHE_HOME") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => dirs_next::home_dir()
+                .ok_or_else(|| anyhow!("HOME not set"))?
+                .join(".cache"),
+        };
+        let cache = Self {
+            dir: base.join("gh-cr").join("render"),
+        };
+        // In the background, as a large cache takes a while to walk.
+        let pruned = cache.clone();
+        thread::spawn(move || pruned.prune(CACHE_MAX_AGE));
+        Ok(cache)
+    }
+
+    /// Deletes entries last written or read more than `max_age` ago.
+    fn prune(&self, max_age: Duration) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > max_age);
+            if stale && fs::remove_file(entry.path()).is_ok() {
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            log::debug!("pruned {removed} stale entries from the render cache");
+        }
+    }
+
+    /// [`render_external`], answered from the cache when the same hunk was
+    /// rendered the same way before.
+    pub fn render(&self, command: &str, path: &str, hunk: &str, width: u16) -> Result<Vec<String>> {
+        let entry = self.entry(command, path, hunk, width);
+        if let Ok(raw) = fs::read_to_string(&entry) {
+            log::trace!("render cache hit for {path}");
+            // Reading counts as use, so pruning keeps the entry.
+            let touched = fs::File::options()
+                .append(true)
+                .open(&entry)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            if let Err(err) = touched {
+                log::debug!("failed to touch cached hunk for {path}: {err}");
+            }
+            return Ok(raw.lines().map(str::to_string).collect());
+        }
+        let lines = render_external(command, path, hunk, width)?;
+        if let Err(err) = self.store(&entry, &lines) {
+            log::warn!("failed to cache rendered hunk for {path}: {err}");
+        }
+        Ok(lines)
+    }
+
+    fn entry(&self, command: &str, path: &str, hunk: &str, width: u16) -> PathBuf {
+        // The std hasher may change between Rust releases; that only costs
+        // one round of misses.
+        let mut hasher = DefaultHasher::new();
+        (command, path, hunk, width).hash(&mut hasher);
+        self.dir.join(format!("{:016x}", hasher.finish()))
+    }
+
+    fn store(&self, entry: &Path, lines: &[String]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+        // Written aside and renamed into place, so a concurrent run never
+        // reads a half-written entry.
+        let mut file = NamedTempFile::new_in(&self.dir)?;
+        file.write_all(contents.as_bytes())?;
+        file.persist(entry)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::style::{Color, Stylize};
+
+    use crate::ansi;
+
+    use super::*;
+
+    #[test]
+    fn whitespace_errors_on_added_lines() {
+        assert_eq!(whitespace_errors("+\t  let x = 1;  "), [1..4, 14..16]);
+        assert!(whitespace_errors("-old();  ").is_empty());
+        assert!(whitespace_errors("+    new();").is_empty());
+    }
+
+    #[test]
+    fn tabs_expand_from_after_the_marker() {
+        assert_eq!(expand_tabs("+\tx\ty", 4), "+    x   y");
+        let styled = format!("{}", "-\tx".with(Color::Red));
+        assert_eq!(ansi::strip(&expand_tabs(&styled, 2)), "-  x");
+    }
+
+    #[test]
+    fn unchanged_blocks_fold_around_changes() {
+        let hunk = "@@ -1,19 +1,19 @@\n fn a() {\n     one();\n     two();\n     three();\n     four();\n }\n\n fn b() {\n     one();\n-    two();\n+    deux();\n     three();\n }\n\n fn c() {\n     one();\n     two();\n     three();\n     four();\n }";
+        assert_eq!(unchanged_blocks(hunk), [2..6, 16..20]);
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::Write as _;
+
+use crate::{QueuedReply, Repo, Thread};
+
+/// Renders `threads`, and any replies not yet published, as a Markdown
+/// document linking each thread back to GitHub.
+pub fn markdown(
+    repo: &Repo,
+    pr_number: u64,
+    heading: &str,
+    threads: &[Thread],
+    queued: &[QueuedReply],
+) -> String {
+    let mut doc = String::new();
+    let pr_url = format!("{}/pull/{}", repo.url, pr_number);
+    let _ = writeln!(
+        doc,
+        "# [{}/{} #{}]({pr_url}): {heading}\n",
+        repo.owner, repo.name, pr_number
+    );
+    if threads.is_empty() {
+        doc.push_str("No threads.\n");
+    }
+    for thread in threads {
+        let location = thread.display_path();
+        let link = thread.permalink(&repo.url).or_else(|| {
+            thread
+                .comments
+                .first()
+                .and_then(|comment| comment.url.clone())
+        });
+        let status = if thread.is_resolved {
+            "resolved"
+        } else {
+            "unresolved"
+        };
+        match link {
+            Some(link) => {
+                let _ = writeln!(doc, "## [`{location}`]({link}) ({status})\n");
+            }
+            None => {
+                let _ = writeln!(doc, "## `{location}` ({status})\n");
+            }
+        }
+        if let Some(hunk) = &thread.diff_hunk {
+            let _ = writeln!(doc, "```diff\n{}\n```\n", hunk.trim_end());
+        }
+        for comment in &thread.comments {
+            let author = match &comment.url {
+                Some(url) => format!("[**{}**]({url})", comment.author),
+                None => format!("**{}**", comment.author),
+            };
+            let _ = writeln!(
+                doc,
+                "{author} on {}:\n",
+                comment.created_at.format("%Y-%m-%d %H:%M UTC")
+            );
+            for line in comment.body.trim_end().lines() {
+                let _ = writeln!(doc, "> {line}");
+            }
+            doc.push('\n');
+        }
+    }
+    if !queued.is_empty() {
+        doc.push_str("## Pending replies\n\n");
+        for reply in queued {
+            let target = threads.iter().find(|thread| {
+                thread
+                    .comments
+                    .iter()
+                    .any(|comment| comment.database_id == reply.comment_database_id)
+            });
+            match target {
+                Some(thread) => {
+                    let _ = writeln!(doc, "On `{}`:\n", thread.display_path());
+                }
+                None => doc.push_str("On a thread not in this export:\n\n"),
+            }
+            for line in reply.body.trim_end().lines() {
+                let _ = writeln!(doc, "> {line}");
+            }
+            doc.push('\n');
+        }
+    }
+    doc
+}
@@ -0,0 +1,120 @@
+use anyhow::{Result, anyhow};
+
+/// Which threads to show, parsed from a line such as
+/// `src/**/*.rs is:modified has:unresolved`. Globs match if any of them
+/// does, as do `by:` authors; every other term must hold as well.
+pub struct FileFilter {
+    pub raw: String,
+    globs: Vec<String>,
+    /// GitHub file statuses: `added`, `modified`, `removed`, `renamed`.
+    statuses: Vec<String>,
+    unresolved: bool,
+    /// Only threads whose line no longer exists in the PR.
+    outdated: bool,
+    /// Logins whose threads to show, from `by:login`.
+    authors: Vec<String>,
+}
+
+impl FileFilter {
+    /// `None` for a blank line, which clears the filter.
+    pub fn parse(raw: &str) -> Result<Option<Self>> {
+        let mut filter = Self {
+            raw: raw.trim().to_string(),
+            globs: Vec::new(),
+            statuses: Vec::new(),
+            unresolved: false,
+            outdated: false,
+            authors: Vec::new(),
+        };
+        for term in raw.split_whitespace() {
+            if term == "is:outdated" {
+                filter.outdated = true;
+            } else if let Some(login) = term.strip_prefix("by:") {
+                filter
+                    .authors
+                    .push(login.trim_start_matches('@').to_lowercase());
+            } else if let Some(status) = term.strip_prefix("is:") {
+                let status = match status {
+                    "added" | "new" => "added",
+                    "modified" | "changed" => "modified",
+                    "deleted" | "removed" => "removed",
+                    "renamed" | "moved" => "renamed",
+                    other => return Err(anyhow!("unknown file status `{other}`")),
+                };
+                filter.statuses.push(status.to_string());
+            } else if term == "has:unresolved" {
+                filter.unresolved = true;
+            } else if term.starts_with("has:") {
+                return Err(anyhow!("unknown filter `{term}`, expected has:unresolved"));
+            } else {
+                filter.globs.push(term.to_string());
+            }
+        }
+        if filter.raw.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(filter))
+    }
+
+    /// Whether matching needs each file's change type from GitHub.
+    pub fn needs_statuses(&self) -> bool {
+        !self.statuses.is_empty()
+    }
+
+    /// Whether a thread matches, given its file's change type, whether the
+    /// file has unresolved threads, and who started the thread.
+    pub fn matches(
+        &self,
+        path: &str,
+        status: Option<&str>,
+        has_unresolved: bool,
+        outdated: bool,
+        author: &str,
+    ) -> bool {
+        let glob = self.globs.is_empty() || self.globs.iter().any(|glob| glob_match(glob, path));
+        let status = self.statuses.is_empty()
+            || status.is_some_and(|status| self.statuses.iter().any(|want| want == status));
+        let author = self.authors.is_empty() || self.authors.contains(&author.to_lowercase());
+        glob && status
+            && author
+            && (!self.unresolved || has_unresolved)
+            && (!self.outdated || outdated)
+    }
+}
+
+/// Matches `path` against a glob where `*` and `?` stay within one path
+/// component and `**` spans any number of them. A pattern without a slash
+/// matches the file name in any directory, as in `.gitignore`.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    if !pattern.contains('/') {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        return matches_from(pattern.as_bytes(), name.as_bytes());
+    }
+    matches_from(pattern.as_bytes(), path.as_bytes())
+}
+
+fn matches_from(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            // `**/` also matches no directories at all.
+            matches_from(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .any(|(i, byte)| *byte == b'/' && matches_from(rest, &path[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| matches_from(rest, &path[i..])),
+        [b'*', rest @ ..] => {
+            let component = path
+                .iter()
+                .position(|byte| *byte == b'/')
+                .unwrap_or(path.len());
+            (0..=component).any(|i| matches_from(rest, &path[i..]))
+        }
+        [b'?', rest @ ..] => {
+            matches!(path.first(), Some(byte) if *byte != b'/') && matches_from(rest, &path[1..])
+        }
+        [byte, rest @ ..] => path.first() == Some(byte) && matches_from(rest, &path[1..]),
+    }
+}
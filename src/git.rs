@@ -0,0 +1,464 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+/// Root of the working tree the tool was started in, if any.
+pub fn toplevel() -> Option<PathBuf> {
+    output(&["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+/// Checked out branch name, or the short commit hash when HEAD is detached.
+pub fn head_name() -> Option<String> {
+    output(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .or_else(|| output(&["rev-parse", "--short", "HEAD"]))
+}
+
+/// Fetches the head of PR `number` from `remote` and checks it out, detached,
+/// in a worktree at `path`. An existing worktree there is moved to the new
+/// head rather than recreated.
+pub fn worktree_for_pr(remote: &str, number: u64, path: &Path) -> Result<()> {
+    // A named ref rather than FETCH_HEAD, which each worktree keeps its own of.
+    let head = format!("refs/remotes/{remote}/pr/{number}");
+    run(
+        None,
+        &[
+            "fetch",
+            "--",
+            remote,
+            &format!("+pull/{number}/head:{head}"),
+        ],
+    )?;
+    if path.join(".git").exists() {
+        run(Some(path), &["checkout", "--detach", &head])?;
+    } else {
+        let target = path.to_string_lossy();
+        run(None, &["worktree", "add", "--detach", &target, &head])?;
+    }
+    Ok(())
+}
+
+/// Applies `patch` to the working tree, falling back to a three-way merge
+/// for hunks that don't apply cleanly. Runs from the root, since git skips
+/// paths outside the current directory.
+pub fn apply_3way(patch: &[u8]) -> Result<()> {
+    log::debug!("running git apply --3way");
+    let root = toplevel().ok_or_else(|| anyhow!("not inside a git checkout"))?;
+    let mut child = Command::new("git")
+        .current_dir(root)
+        .args(["apply", "--3way", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn git")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(patch)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git apply failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Compares the commits a branch had on top of `base` before and after a
+/// rewrite, fetching the base branch and either side from `remote` first.
+/// GitHub serves commits by hash even once a force-push has left them
+/// unreachable.
+pub fn range_diff(remote: &str, base: &str, before: &str, after: &str) -> Result<String> {
+    run(None, &["fetch", "--no-tags", "--", remote, base])?;
+    fetch_missing(remote, &[before, after])?;
+    // Against the base branch rather than `before...after`, so commits the
+    // branch was rebased over don't show up as added.
+    let base = format!("{remote}/{base}");
+    run(
+        None,
+        &["range-diff", "--color=always", &base, before, after],
+    )
+}
+
+/// A file that conflicts when the PR is merged, with each conflicting
+/// region as Git marks it up, plus a little context.
+pub struct Conflict {
+    pub path: String,
+    pub regions: Vec<String>,
+}
+
+/// Test-merges `head` into the tip of the `base` branch without touching
+/// the working tree or index, and returns the files that conflict.
+pub fn conflicts(remote: &str, base: &str, head: &str) -> Result<Vec<Conflict>> {
+    run(None, &["fetch", "--no-tags", "--", remote, base])?;
+    fetch_missing(remote, &[head])?;
+    let base = format!("{remote}/{base}");
+    let args = ["merge-tree", "--write-tree", "--name-only", &base, head];
+    log::debug!("running git {:?}", args);
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("failed to spawn git")?;
+    // 1 means the merge has conflicts; anything else but 0 is a failure.
+    match output.status.code() {
+        Some(0) => return Ok(Vec::new()),
+        Some(1) => {}
+        _ => {
+            return Err(anyhow!(
+                "git merge-tree failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let tree = lines
+        .next()
+        .ok_or_else(|| anyhow!("git merge-tree printed nothing"))?;
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    for path in lines.take_while(|line| !line.is_empty()) {
+        if conflicts.iter().any(|conflict| conflict.path == path) {
+            continue;
+        }
+        // Files deleted on one side have no markers to show.
+        let merged = run(None, &["show", &format!("{tree}:{path}")]).unwrap_or_default();
+        conflicts.push(Conflict {
+            path: path.to_string(),
+            regions: conflict_regions(&merged, 2),
+        });
+    }
+    Ok(conflicts)
+}
+
+/// Each `<<<<<<<` to `>>>>>>>` block in `text` with `context` lines either
+/// side.
+fn conflict_regions(text: &str, context: usize) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut regions = Vec::new();
+    let mut start = None;
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with("<<<<<<<") {
+            start = Some(i);
+        } else if line.starts_with(">>>>>>>")
+            && let Some(first) = start.take()
+        {
+            let from = first.saturating_sub(context);
+            let to = (i + 1 + context).min(lines.len());
+            regions.push(lines[from..to].join("\n"));
+        }
+    }
+    regions
+}
+
+pub struct BlameLine {
+    pub author: String,
+    /// Author time as seconds since the Unix epoch.
+    pub time: i64,
+}
+
+/// Blames `count` lines of `path` from `start`, at `rev` when given and the
+/// working tree otherwise, keyed by line number.
+pub fn blame(
+    root: &Path,
+    path: &str,
+    rev: Option<&str>,
+    start: usize,
+    count: usize,
+) -> Result<HashMap<usize, BlameLine>> {
+    let range = format!("{start},+{count}");
+    let mut args = vec!["blame", "--porcelain", "-L", &range];
+    args.extend(rev);
+    args.extend(["--", path]);
+    let porcelain = run(Some(root), &args)?;
+
+    // Each entry starts with `<sha> <orig> <final> [<group>]`; commit details
+    // follow only the first time a commit appears.
+    let mut commits: HashMap<String, (String, i64)> = HashMap::new();
+    let mut lines = HashMap::new();
+    let mut current: Option<(String, usize)> = None;
+    for line in porcelain.lines() {
+        if line.starts_with('\t') {
+            if let Some((sha, number)) = current.take()
+                && let Some((author, time)) = commits.get(&sha)
+            {
+                lines.insert(
+                    number,
+                    BlameLine {
+                        author: author.clone(),
+                        time: *time,
+                    },
+                );
+            }
+            continue;
+        }
+        if let Some((sha, _)) = &current {
+            let entry = commits.entry(sha.clone()).or_default();
+            if let Some(author) = line.strip_prefix("author ") {
+                entry.0 = author.to_string();
+            } else if let Some(time) = line.strip_prefix("author-time ") {
+                entry.1 = time.parse().unwrap_or_default();
+            }
+            continue;
+        }
+        let mut parts = line.split(' ');
+        if let (Some(sha), Some(_), Some(number)) = (parts.next(), parts.next(), parts.next())
+            && let Ok(number) = number.parse()
+        {
+            current = Some((sha.to_string(), number));
+        }
+    }
+    Ok(lines)
+}
+
+/// A line of a file containing what [`grep`] searched for.
+pub struct GrepMatch {
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+}
+
+/// Searches `paths` as of `commit` for the fixed string `pattern`, fetching
+/// the commit from `remote` first if it isn't present. Binary files are
+/// skipped.
+pub fn grep(remote: &str, commit: &str, paths: &[&str], pattern: &str) -> Result<Vec<GrepMatch>> {
+    fetch_missing(remote, &[commit])?;
+    let pathspecs: Vec<String> = paths
+        .iter()
+        .map(|path| format!(":(top,literal){path}"))
+        .collect();
+    log::debug!("running git grep for {} paths at {commit}", paths.len());
+    let output = Command::new("git")
+        .args([
+            "grep",
+            "-n",
+            "-z",
+            "-I",
+            "-F",
+            "--full-name",
+            "-e",
+            pattern,
+            commit,
+            "--",
+        ])
+        .args(&pathspecs)
+        .output()
+        .context("failed to spawn git")?;
+    // Exit status 1 means nothing matched.
+    if !output.status.success() && output.status.code() != Some(1) {
+        return Err(anyhow!(
+            "git grep failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // Each line is `<commit>:<path>\0<line>\0<text>`.
+    let prefix = format!("{commit}:");
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.strip_prefix(&prefix)?.splitn(3, '\0');
+            Some(GrepMatch {
+                path: fields.next()?.to_string(),
+                line: fields.next()?.parse().ok()?,
+                text: fields.next()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Where a renamed file came from, and how much of it survived the move.
+pub struct Rename {
+    pub from: String,
+    /// Git's similarity index, 0 to 100.
+    pub similarity: Option<u8>,
+}
+
+/// Files renamed between the merge base of `base` and `head`, keyed by their
+/// new path, with Git's similarity for each. Commits missing locally are
+/// fetched from `remote` first.
+pub fn renames(remote: &str, base: &str, head: &str) -> Result<HashMap<String, Rename>> {
+    fetch_missing(remote, &[base, head])?;
+    let range = format!("{base}...{head}");
+    let output = run(
+        None,
+        &["diff", "--find-renames", "--name-status", "-z", &range],
+    )?;
+    // Each rename is `R<similarity>\0<old>\0<new>`; other changes have one path.
+    let mut fields = output.split('\0');
+    let mut renames = HashMap::new();
+    while let Some(status) = fields.next() {
+        let Some(similarity) = status.strip_prefix('R') else {
+            if status.starts_with('C') {
+                fields.next();
+            }
+            fields.next();
+            continue;
+        };
+        let (Some(from), Some(to)) = (fields.next(), fields.next()) else {
+            break;
+        };
+        renames.insert(
+            to.to_string(),
+            Rename {
+                from: from.to_string(),
+                similarity: similarity.parse().ok(),
+            },
+        );
+    }
+    Ok(renames)
+}
+
+/// `path` as of `commit`, fetching the commit from `remote` if needed.
+pub fn show_file(remote: &str, commit: &str, path: &str) -> Result<String> {
+    fetch_missing(remote, &[commit])?;
+    run(None, &["show", &format!("{commit}:{path}")])
+}
+
+/// The raw contents of `path` at `commit`, for binary files such as images.
+pub fn show_bytes(remote: &str, commit: &str, path: &str) -> Result<Vec<u8>> {
+    fetch_missing(remote, &[commit])?;
+    let spec = format!("{commit}:{path}");
+    log::debug!("running git show {spec}");
+    let output = Command::new("git")
+        .args(["show", &spec])
+        .output()
+        .context("failed to spawn git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git show failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+fn fetch_missing(remote: &str, commits: &[&str]) -> Result<()> {
+    for commit in commits {
+        if !has_object(commit) {
+            run(None, &["fetch", "--no-tags", "--", remote, commit])?;
+        }
+    }
+    Ok(())
+}
+
+/// Lockfiles GitHub treats as generated without being told.
+const LOCKFILES: [&str; 8] = [
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Gemfile.lock",
+    "poetry.lock",
+    "composer.lock",
+    "go.sum",
+];
+
+/// Which of `paths` are generated: lockfiles, and whatever `.gitattributes`
+/// marks `linguist-generated`, which can also unmark a lockfile.
+pub fn generated(paths: &[&str]) -> Result<HashSet<String>> {
+    let mut generated: HashSet<String> = paths
+        .iter()
+        .filter(|path| {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            LOCKFILES.contains(&name)
+        })
+        .map(|path| path.to_string())
+        .collect();
+    if paths.is_empty() {
+        return Ok(generated);
+    }
+    let mut args = vec!["check-attr", "-z", "linguist-generated", "--"];
+    args.extend(paths);
+    let output = run(None, &args)?;
+    // Each path comes as `path NUL attribute NUL value NUL`.
+    let fields: Vec<&str> = output.split('\0').collect();
+    for field in fields.chunks(3) {
+        if let [path, _, value] = field {
+            match *value {
+                "set" | "true" => {
+                    generated.insert(path.to_string());
+                }
+                "unset" | "false" => {
+                    generated.remove(*path);
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(generated)
+}
+
+/// The commits a range such as `main..feature` compares, as hashes. With
+/// three dots the base is where the two sides diverged, as `git diff`
+/// takes it. A side left out is `HEAD`.
+pub fn range_ends(range: &str) -> Result<(String, String)> {
+    let (base, head, symmetric) = match range.split_once("...") {
+        Some((base, head)) => (base, head, true),
+        None => {
+            let (base, head) = range
+                .split_once("..")
+                .ok_or_else(|| anyhow!("`{range}` isn't a range like `main..feature`"))?;
+            (base, head, false)
+        }
+    };
+    let resolve = |rev: &str| {
+        let rev = if rev.is_empty() { "HEAD" } else { rev };
+        run(
+            None,
+            &["rev-parse", "--verify", &format!("{rev}^{{commit}}")],
+        )
+    };
+    let (base, head) = (resolve(base)?, resolve(head)?);
+    if symmetric {
+        let base = run(None, &["merge-base", &base, &head])?;
+        return Ok((base, head));
+    }
+    Ok((base, head))
+}
+
+/// The diff from `base` to `head`, with renames detected.
+pub fn diff(base: &str, head: &str) -> Result<String> {
+    run(
+        None,
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--find-renames",
+            base,
+            head,
+        ],
+    )
+}
+
+/// Whether `rev` names an object present in the local repository.
+pub fn has_object(rev: &str) -> bool {
+    let spec = format!("{rev}^{{commit}}");
+    run(None, &["cat-file", "-e", &spec]).is_ok()
+}
+
+fn run(dir: Option<&Path>, args: &[&str]) -> Result<String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    log::debug!("running git {:?}", args);
+    let output = command.args(args).output().context("failed to spawn git")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Trimmed stdout of a successful, non-empty git command.
+fn output(args: &[&str]) -> Option<String> {
+    run(None, args).ok().filter(|stdout| !stdout.is_empty())
+}
@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use textwrap::core::display_width;
+
+use crate::keymap::KeyChord;
+
+/// Stands in for the terminal when the TUI is driven by a script: input
+/// comes from a queue of events and frames are drawn into memory. Once the
+/// script runs out the review quits.
+pub struct Headless {
+    pub width: u16,
+    pub height: u16,
+    events: VecDeque<Event>,
+    /// The latest frame as drawn, escapes included.
+    pub screen: Vec<u8>,
+}
+
+impl Headless {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            events: VecDeque::new(),
+            screen: Vec::new(),
+        }
+    }
+
+    /// Queues keys written as in the config, separated by spaces, such as
+    /// `r p ctrl-r enter`.
+    pub fn keys(mut self, script: &str) -> Result<Self> {
+        for raw in script.split_whitespace() {
+            let chord = KeyChord::parse(raw)?;
+            self.events.push_back(Event::Key(chord.to_event()));
+        }
+        Ok(self)
+    }
+
+    /// Queues `text` as typed, one key per character.
+    pub fn text(mut self, text: &str) -> Self {
+        self.events.extend(
+            text.chars()
+                .map(|ch| Event::Key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))),
+        );
+        self
+    }
+
+    /// Queues a left click, pressed and released, at `column` and `row`.
+    pub fn click(mut self, column: u16, row: u16) -> Self {
+        for kind in [
+            MouseEventKind::Down(MouseButton::Left),
+            MouseEventKind::Up(MouseButton::Left),
+        ] {
+            self.events.push_back(Event::Mouse(MouseEvent {
+                kind,
+                column,
+                row,
+                modifiers: KeyModifiers::NONE,
+            }));
+        }
+        self
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn next_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    /// The screen as text, one string per row with trailing blanks trimmed.
+    /// Cursor moves and clears are followed; other escapes are dropped.
+    pub fn rows(&self) -> Vec<String> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut grid = vec![vec![' '; width]; height];
+        let (mut row, mut column) = (0, 0);
+        let text = String::from_utf8_lossy(&self.screen);
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1b' => match chars.next() {
+                    Some('[') => {
+                        let mut params = String::new();
+                        let mut end = None;
+                        for next in chars.by_ref() {
+                            if next.is_ascii_alphabetic() || next == '~' {
+                                end = Some(next);
+                                break;
+                            }
+                            params.push(next);
+                        }
+                        match end {
+                            Some('H') => {
+                                let mut parts =
+                                    params.split(';').map(|part| part.parse().unwrap_or(1));
+                                row = parts.next().unwrap_or(1usize).saturating_sub(1);
+                                column = parts.next().unwrap_or(1usize).saturating_sub(1);
+                            }
+                            Some('J') if params == "2" => {
+                                grid.iter_mut().for_each(|line| line.fill(' '));
+                            }
+                            _ => {}
+                        }
+                    }
+                    // OSC 8 links end with BEL or ST.
+                    Some(']') => {
+                        while let Some(next) = chars.next() {
+                            if next == '\x07'
+                                || (next == '\x1b' && chars.next_if_eq(&'\\').is_some())
+                            {
+                                break;
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                '\r' => column = 0,
+                '\n' => {
+                    row += 1;
+                    column = 0;
+                }
+                ch => {
+                    if row < height && column < width {
+                        grid[row][column] = ch;
+                    }
+                    column += display_width(ch.encode_utf8(&mut [0; 4])).max(1);
+                }
+            }
+        }
+        grid.into_iter()
+            .map(|line| line.into_iter().collect::<String>().trim_end().to_string())
+            .collect()
+    }
+
+    /// Whether any row of the screen contains `needle`.
+    pub fn shows(&self, needle: &str) -> bool {
+        self.rows().iter().any(|row| row.contains(needle))
+    }
+}
+
+/// Answers `gh` calls in tests. Each call is recorded, and the first rule
+/// whose pattern appears in the command line gives its output; a call no
+/// rule matches fails.
+#[derive(Default)]
+pub struct MockGh {
+    rules: Vec<(String, Result<String, String>)>,
+    calls: std::sync::Mutex<Vec<String>>,
+}
+
+impl MockGh {
+    /// Answers calls containing `pattern` with `output`.
+    pub fn on(mut self, pattern: &str, output: &str) -> Self {
+        self.rules.push((pattern.into(), Ok(output.into())));
+        self
+    }
+
+    /// Fails calls containing `pattern` with `stderr`.
+    pub fn fail(mut self, pattern: &str, stderr: &str) -> Self {
+        self.rules.push((pattern.into(), Err(stderr.into())));
+        self
+    }
+
+    pub fn respond(&self, args: &[String]) -> Result<String> {
+        let line = args.join(" ");
+        self.calls.lock().unwrap().push(line.clone());
+        match self
+            .rules
+            .iter()
+            .find(|(pattern, _)| line.contains(pattern))
+        {
+            Some((_, Ok(output))) => Ok(output.clone()),
+            Some((_, Err(stderr))) => Err(anyhow::anyhow!("gh {args:?} failed: {stderr}")),
+            None => Err(anyhow::anyhow!("no mock answer for gh {line}")),
+        }
+    }
+
+    /// Every command line run so far, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
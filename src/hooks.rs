@@ -0,0 +1,67 @@
+use std::process::{Command, Stdio};
+
+use crate::{Repo, config::HooksConfig};
+
+#[derive(Clone, Copy)]
+pub enum Hook {
+    OpenPr,
+    CommentPosted,
+    ReviewSubmitted,
+    Approve,
+}
+
+impl Hook {
+    fn name(self) -> &'static str {
+        match self {
+            Hook::OpenPr => "open-pr",
+            Hook::CommentPosted => "comment-posted",
+            Hook::ReviewSubmitted => "review-submitted",
+            Hook::Approve => "approve",
+        }
+    }
+
+    fn command(self, hooks: &HooksConfig) -> Option<&str> {
+        match self {
+            Hook::OpenPr => hooks.on_open_pr.as_deref(),
+            Hook::CommentPosted => hooks.on_comment_posted.as_deref(),
+            Hook::ReviewSubmitted => hooks.on_review_submitted.as_deref(),
+            Hook::Approve => hooks.on_approve.as_deref(),
+        }
+    }
+}
+
+/// Starts the shell command configured for `hook`, if any, without waiting
+/// for it. The PR is described in `GH_CR_*` environment variables, along
+/// with any hook-specific `extra` ones. Output is discarded so it can't
+/// draw over the TUI.
+pub fn fire(hooks: &HooksConfig, hook: Hook, repo: &Repo, pr_number: u64, extra: &[(&str, &str)]) {
+    let Some(command) = hook
+        .command(hooks)
+        .filter(|command| !command.trim().is_empty())
+    else {
+        return;
+    };
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .env("GH_CR_EVENT", hook.name())
+        .env("GH_CR_REPO", format!("{}/{}", repo.owner, repo.name))
+        .env("GH_CR_PR", pr_number.to_string())
+        .env("GH_CR_PR_URL", format!("{}/pull/{}", repo.url, pr_number))
+        .envs(extra.iter().copied())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    log::info!("running {} hook: {command}", hook.name());
+    if let Err(err) = shell.spawn() {
+        log::warn!("failed to run {} hook: {err}", hook.name());
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    env,
+    fmt::Display,
+    io::{IsTerminal, stdout},
+};
+
+use crate::config::HyperlinkMode;
+
+/// Wraps text in OSC 8 hyperlinks when the terminal can display them, and
+/// leaves it untouched otherwise.
+#[derive(Clone, Copy)]
+pub struct Hyperlinks {
+    enabled: bool,
+}
+
+impl Hyperlinks {
+    pub fn new(mode: HyperlinkMode) -> Self {
+        let enabled = match mode {
+            HyperlinkMode::Always => true,
+            HyperlinkMode::Never => false,
+            HyperlinkMode::Auto => stdout().is_terminal() && supported(),
+        };
+        Self { enabled }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn link(&self, text: impl Display, url: &str) -> String {
+        if self.enabled {
+            format!("\x1b]8;;{url}\x07{text}\x1b]8;;\x07")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Terminals known to render OSC 8. Others may print the sequence's URL
+/// as garbage, so anything unrecognised gets plain text.
+fn supported() -> bool {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    if env::var_os("KITTY_WINDOW_ID").is_some()
+        || env::var_os("WEZTERM_PANE").is_some()
+        || env::var_os("WT_SESSION").is_some()
+        || env::var_os("KONSOLE_VERSION").is_some()
+    {
+        return true;
+    }
+    if var("VTE_VERSION")
+        .parse::<u32>()
+        .is_ok_and(|version| version >= 5000)
+    {
+        return true;
+    }
+    let program = var("TERM_PROGRAM");
+    if matches!(
+        program.as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper" | "rio"
+    ) {
+        return true;
+    }
+    let term = var("TERM");
+    [
+        "kitty",
+        "alacritty",
+        "foot",
+        "ghostty",
+        "wezterm",
+        "contour",
+    ]
+    .iter()
+    .any(|name| term.contains(name))
+}
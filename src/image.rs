@@ -0,0 +1,203 @@
+use std::{
+    env,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::clipboard::base64;
+
+/// Extensions of the image files that get a before/after preview.
+const EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "webp", "bmp", "ico"];
+
+pub fn is_image(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+/// How the terminal can draw pixels, if at all.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Graphics {
+    /// The kitty graphics protocol, also spoken by WezTerm and Ghostty.
+    Kitty,
+    /// Sixel, drawn with `img2sixel` from libsixel.
+    Sixel,
+    None,
+}
+
+impl Graphics {
+    /// Guesses from the environment, like hyperlink support: terminals
+    /// that aren't recognised get the text description only.
+    pub fn detect() -> Self {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || ["kitty", "ghostty"].iter().any(|name| term.contains(name))
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            return Self::Kitty;
+        }
+        if env::var_os("WT_SESSION").is_some()
+            || ["foot", "mlterm", "contour", "sixel"]
+                .iter()
+                .any(|name| term.contains(name))
+        {
+            return Self::Sixel;
+        }
+        Self::None
+    }
+}
+
+/// An image's format and size in pixels, when its header can be read.
+pub struct Info {
+    pub format: &'static str,
+    pub dimensions: Option<(u32, u32)>,
+}
+
+pub fn info(bytes: &[u8]) -> Info {
+    let le16 = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le32 = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le24 = |at: usize| Some(le32(at)? & 0x00ff_ffff);
+    let pair = |width: Option<u32>, height: Option<u32>| width.zip(height);
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Info {
+            format: "PNG",
+            dimensions: pair(be32(16), be32(20)),
+        }
+    } else if bytes.starts_with(b"GIF8") {
+        Info {
+            format: "GIF",
+            dimensions: pair(le16(6), le16(8)),
+        }
+    } else if bytes.starts_with(b"\xff\xd8") {
+        Info {
+            format: "JPEG",
+            dimensions: jpeg_dimensions(bytes),
+        }
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        let dimensions = match bytes.get(12..16) {
+            Some(b"VP8X") => pair(le24(24).map(|w| w + 1), le24(27).map(|h| h + 1)),
+            Some(b"VP8 ") => pair(le16(26).map(|w| w & 0x3fff), le16(28).map(|h| h & 0x3fff)),
+            Some(b"VP8L") => {
+                le32(21).map(|bits| (1 + (bits & 0x3fff), 1 + ((bits >> 14) & 0x3fff)))
+            }
+            _ => None,
+        };
+        Info {
+            format: "WebP",
+            dimensions,
+        }
+    } else if bytes.starts_with(b"BM") {
+        Info {
+            format: "BMP",
+            dimensions: pair(le32(18), le32(22).map(|h| (h as i32).unsigned_abs())),
+        }
+    } else {
+        Info {
+            format: "image",
+            dimensions: None,
+        }
+    }
+}
+
+/// Walks a JPEG's markers to the start-of-frame segment holding its size.
+fn jpeg_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    while bytes.get(at)? == &0xff {
+        let marker = *bytes.get(at + 1)?;
+        let length = u16::from_be_bytes(bytes.get(at + 2..at + 4)?.try_into().ok()?) as usize;
+        if (0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            let height = u16::from_be_bytes(bytes.get(at + 5..at + 7)?.try_into().ok()?);
+            let width = u16::from_be_bytes(bytes.get(at + 7..at + 9)?.try_into().ok()?);
+            return Some((width as u32, height as u32));
+        }
+        at += 2 + length;
+    }
+    None
+}
+
+/// `PNG 640×480, 12.3 KB`, for when the image itself can't be drawn.
+pub fn describe(bytes: &[u8]) -> String {
+    let info = info(bytes);
+    let size = match bytes.len() {
+        size if size < 1024 => format!("{size} B"),
+        size if size < 1024 * 1024 => format!("{:.1} KB", size as f64 / 1024.0),
+        size => format!("{:.1} MB", size as f64 / (1024.0 * 1024.0)),
+    };
+    match info.dimensions {
+        Some((width, height)) => format!("{} {width}×{height}, {size}", info.format),
+        None => format!("{}, {size}", info.format),
+    }
+}
+
+/// Escape sequences drawing `bytes` at most `columns` cells wide. Kitty is
+/// sent PNGs as they are, and only those; sixel images are converted with
+/// `img2sixel`.
+pub fn draw(graphics: Graphics, bytes: &[u8], columns: u16) -> Result<String> {
+    match graphics {
+        Graphics::Kitty if info(bytes).format == "PNG" => Ok(kitty(bytes, columns)),
+        Graphics::Kitty => Err(anyhow!("only PNGs are sent to kitty")),
+        Graphics::Sixel => sixel(bytes, columns),
+        Graphics::None => Err(anyhow!("the terminal can't show images")),
+    }
+}
+
+/// Transmits and displays a PNG in chunks of 4096 base64 bytes, as the
+/// kitty protocol requires.
+fn kitty(bytes: &[u8], columns: u16) -> String {
+    let encoded = base64(bytes);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(4096).collect();
+    let mut out = String::with_capacity(encoded.len() + chunks.len() * 16);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=100,c={columns},m={more};{chunk}\x1b\\"
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+        }
+    }
+    out
+}
+
+fn sixel(bytes: &[u8], columns: u16) -> Result<String> {
+    // Cells are usually around 8 pixels wide.
+    let width = (u32::from(columns) * 8).to_string();
+    let mut child = Command::new("img2sixel")
+        .args(["-w", &width])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to run img2sixel")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(bytes)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!("img2sixel exited with {}", output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images_are_described_by_format_and_size() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        assert_eq!(describe(&png), "PNG 640×480, 24 B");
+        assert_eq!(describe(b"GIF89a\x10\0\x20\0"), "GIF 16×32, 10 B");
+    }
+}
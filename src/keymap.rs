@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::ThreadView;
+use crate::config::{KeyList, KeysConfig};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    NextThread,
+    PrevThread,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    ScrollTop,
+    ScrollBottom,
+    PrevHunk,
+    NextHunk,
+    NextView,
+    ToggleSkip,
+    Reply,
+    Publish,
+    ToggleDiff,
+    Visual,
+    Yank,
+    Cancel,
+    SetMark,
+    GotoMark,
+    JumpBack,
+    JumpForward,
+    OpenBrowser,
+    Copy,
+    OpenEditor,
+    Checkout,
+    Worktree,
+    Export,
+    SavePatch,
+    ApplyPatch,
+    ToggleBlame,
+    TogglePerf,
+    SwitchPr,
+    StackDown,
+    StackUp,
+    ToggleSinceReview,
+    RangeDiff,
+    FilterFiles,
+    CycleSort,
+    SearchFiles,
+    ToggleChecklist,
+    ToggleProgress,
+    Approve,
+    RequestChanges,
+    DismissReview,
+    ToggleHidden,
+    Minimize,
+    EditDescription,
+    ToggleConversation,
+    GotoPrev,
+    GotoNext,
+    ToggleOutline,
+    MarkRead,
+    ToggleTimes,
+    TogglePin,
+    ResolveAll,
+    Undo,
+    Redo,
+    BaseFile,
+    ToggleFold,
+    ToggleReviewMap,
+    ImageDiff,
+    Commits,
+    Conflicts,
+    SyncReview,
+    FinishReview,
+    SwitchAccount,
+    RequestReviewers,
+    RecordMacro,
+    PlayMacro,
+    EditPrefs,
+    OpenIssue,
+    AddNote,
+    ToggleAnnotations,
+    Advisories,
+    ShowNotes,
+    Spelling,
+    CopyReference,
+    Todo,
+    MarkAddressed,
+    ReRequestReview,
+    ToggleMergeDiff,
+    MyReviews,
+}
+
+impl Action {
+    const ALL: [Action; 86] = [
+        Action::Quit,
+        Action::NextThread,
+        Action::PrevThread,
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::PageUp,
+        Action::PageDown,
+        Action::HalfPageUp,
+        Action::HalfPageDown,
+        Action::ScrollTop,
+        Action::ScrollBottom,
+        Action::PrevHunk,
+        Action::NextHunk,
+        Action::NextView,
+        Action::ToggleSkip,
+        Action::Reply,
+        Action::Publish,
+        Action::ToggleDiff,
+        Action::Visual,
+        Action::Yank,
+        Action::Cancel,
+        Action::SetMark,
+        Action::GotoMark,
+        Action::JumpBack,
+        Action::JumpForward,
+        Action::OpenBrowser,
+        Action::Copy,
+        Action::OpenEditor,
+        Action::Checkout,
+        Action::Worktree,
+        Action::Export,
+        Action::SavePatch,
+        Action::ApplyPatch,
+        Action::ToggleBlame,
+        Action::TogglePerf,
+        Action::SwitchPr,
+        Action::StackDown,
+        Action::StackUp,
+        Action::ToggleSinceReview,
+        Action::RangeDiff,
+        Action::FilterFiles,
+        Action::CycleSort,
+        Action::SearchFiles,
+        Action::ToggleChecklist,
+        Action::ToggleProgress,
+        Action::Approve,
+        Action::RequestChanges,
+        Action::DismissReview,
+        Action::ToggleHidden,
+        Action::Minimize,
+        Action::EditDescription,
+        Action::ToggleConversation,
+        Action::GotoPrev,
+        Action::GotoNext,
+        Action::ToggleOutline,
+        Action::MarkRead,
+        Action::ToggleTimes,
+        Action::TogglePin,
+        Action::ResolveAll,
+        Action::Undo,
+        Action::Redo,
+        Action::BaseFile,
+        Action::ToggleFold,
+        Action::ToggleReviewMap,
+        Action::ImageDiff,
+        Action::Commits,
+        Action::Conflicts,
+        Action::SyncReview,
+        Action::FinishReview,
+        Action::SwitchAccount,
+        Action::RequestReviewers,
+        Action::RecordMacro,
+        Action::PlayMacro,
+        Action::EditPrefs,
+        Action::OpenIssue,
+        Action::AddNote,
+        Action::ToggleAnnotations,
+        Action::Advisories,
+        Action::ShowNotes,
+        Action::Spelling,
+        Action::CopyReference,
+        Action::Todo,
+        Action::MarkAddressed,
+        Action::ReRequestReview,
+        Action::ToggleMergeDiff,
+        Action::MyReviews,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::NextThread => "next-thread",
+            Action::PrevThread => "prev-thread",
+            Action::ScrollUp => "scroll-up",
+            Action::ScrollDown => "scroll-down",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::HalfPageUp => "half-page-up",
+            Action::HalfPageDown => "half-page-down",
+            Action::ScrollTop => "scroll-top",
+            Action::ScrollBottom => "scroll-bottom",
+            Action::PrevHunk => "prev-hunk",
+            Action::NextHunk => "next-hunk",
+            Action::NextView => "next-view",
+            Action::ToggleSkip => "toggle-skip",
+            Action::Reply => "reply",
+            Action::Publish => "publish",
+            Action::ToggleDiff => "toggle-diff",
+            Action::Visual => "visual",
+            Action::Yank => "yank",
+            Action::Cancel => "cancel",
+            Action::SetMark => "set-mark",
+            Action::GotoMark => "goto-mark",
+            Action::JumpBack => "jump-back",
+            Action::JumpForward => "jump-forward",
+            Action::OpenBrowser => "open-browser",
+            Action::Copy => "copy",
+            Action::OpenEditor => "open-editor",
+            Action::Checkout => "checkout",
+            Action::Worktree => "worktree",
+            Action::Export => "export",
+            Action::SavePatch => "save-patch",
+            Action::ApplyPatch => "apply-patch",
+            Action::ToggleBlame => "toggle-blame",
+            Action::TogglePerf => "toggle-perf",
+            Action::SwitchPr => "switch-pr",
+            Action::StackDown => "stack-down",
+            Action::StackUp => "stack-up",
+            Action::ToggleSinceReview => "since-review",
+            Action::RangeDiff => "range-diff",
+            Action::FilterFiles => "filter-files",
+            Action::CycleSort => "cycle-sort",
+            Action::SearchFiles => "search-files",
+            Action::ToggleChecklist => "toggle-checklist",
+            Action::ToggleProgress => "progress",
+            Action::Approve => "approve",
+            Action::RequestChanges => "request-changes",
+            Action::DismissReview => "dismiss-review",
+            Action::ToggleHidden => "toggle-hidden",
+            Action::Minimize => "minimize",
+            Action::EditDescription => "edit-description",
+            Action::ToggleConversation => "conversation",
+            Action::GotoPrev => "goto-prev",
+            Action::GotoNext => "goto-next",
+            Action::ToggleOutline => "outline",
+            Action::MarkRead => "mark-read",
+            Action::ToggleTimes => "absolute-times",
+            Action::TogglePin => "pin",
+            Action::ResolveAll => "resolve-all",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::BaseFile => "base-file",
+            Action::ToggleFold => "toggle-fold",
+            Action::ToggleReviewMap => "toggle-review-map",
+            Action::ImageDiff => "image-diff",
+            Action::Commits => "commits",
+            Action::Conflicts => "conflicts",
+            Action::SyncReview => "sync-review",
+            Action::FinishReview => "finish-review",
+            Action::SwitchAccount => "switch-account",
+            Action::RequestReviewers => "request-reviewers",
+            Action::RecordMacro => "record-macro",
+            Action::PlayMacro => "play-macro",
+            Action::EditPrefs => "edit-preferences",
+            Action::OpenIssue => "open-issue",
+            Action::AddNote => "add-note",
+            Action::ToggleAnnotations => "toggle-annotations",
+            Action::Advisories => "advisories",
+            Action::ShowNotes => "notes",
+            Action::Spelling => "spelling",
+            Action::CopyReference => "copy-reference",
+            Action::Todo => "todo",
+            Action::MarkAddressed => "mark-addressed",
+            Action::ReRequestReview => "re-request-review",
+            Action::ToggleMergeDiff => "merge-diff",
+            Action::MyReviews => "my-reviews",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Whether the action writes to GitHub.
+    pub fn mutates(self) -> bool {
+        matches!(
+            self,
+            Action::Reply
+                | Action::Publish
+                | Action::Approve
+                | Action::RequestChanges
+                | Action::DismissReview
+                | Action::Minimize
+                | Action::EditDescription
+                | Action::ResolveAll
+                | Action::SyncReview
+                | Action::FinishReview
+                | Action::RequestReviewers
+                | Action::MarkAddressed
+                | Action::ReRequestReview
+        )
+    }
+
+    /// Whether a count typed before the key should repeat the action.
+    pub fn takes_count(self) -> bool {
+        matches!(
+            self,
+            Action::NextThread
+                | Action::PrevThread
+                | Action::ScrollUp
+                | Action::ScrollDown
+                | Action::PageUp
+                | Action::PageDown
+                | Action::HalfPageUp
+                | Action::HalfPageDown
+                | Action::PrevHunk
+                | Action::NextHunk
+        )
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    #[default]
+    Default,
+    Vim,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shifted characters already arrive upper-cased, so the SHIFT flag is
+        // dropped to make `G` and `shift-g` the same chord.
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+
+    /// The key press this chord matches, for feeding scripted input.
+    pub fn to_event(self) -> KeyEvent {
+        KeyEvent::new(self.code, self.modifiers)
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = raw;
+        // A lone "-" is a key in its own right, so only split on dashes that
+        // are followed by something.
+        while let Some((prefix, tail)) = rest.split_once('-') {
+            if tail.is_empty() {
+                break;
+            }
+            modifiers |= match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => return Err(anyhow!("unknown modifier `{other}` in `{raw}`")),
+            };
+            rest = tail;
+        }
+        let function = rest
+            .to_ascii_lowercase()
+            .strip_prefix('f')
+            .and_then(|n| n.parse::<u8>().ok())
+            .filter(|n| (1..=24).contains(n));
+        if let Some(n) = function {
+            return Ok(Self::new(KeyCode::F(n), modifiers));
+        }
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                        KeyCode::Char(ch.to_ascii_uppercase())
+                    }
+                    (Some(ch), None) => KeyCode::Char(ch),
+                    _ => return Err(anyhow!("unknown key `{rest}` in `{raw}`")),
+                }
+            }
+        };
+        Ok(Self::new(code, modifiers))
+    }
+
+    pub fn as_char(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(ch) if self.modifiers.is_empty() => Some(ch),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("ctrl-");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("alt-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("shift-");
+        }
+        let key = match self.code {
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::PageUp => "pageup".to_string(),
+            KeyCode::PageDown => "pagedown".to_string(),
+            KeyCode::Home => "home".to_string(),
+            KeyCode::End => "end".to_string(),
+            KeyCode::Tab => "tab".to_string(),
+            KeyCode::BackTab => "shift-tab".to_string(),
+            KeyCode::Enter => "enter".to_string(),
+            KeyCode::Esc => "esc".to_string(),
+            KeyCode::Backspace => "backspace".to_string(),
+            KeyCode::Delete => "delete".to_string(),
+            KeyCode::Char(' ') => "space".to_string(),
+            KeyCode::Char(ch) => ch.to_string(),
+            KeyCode::F(n) => format!("f{n}"),
+            other => format!("{other:?}").to_ascii_lowercase(),
+        };
+        label.push_str(&key);
+        label
+    }
+}
+
+type Bindings = Vec<(KeyChord, Action)>;
+
+pub struct KeyMap {
+    global: Bindings,
+    views: HashMap<ThreadView, Bindings>,
+    counts: bool,
+}
+
+impl KeyMap {
+    pub fn defaults(profile: Profile) -> Self {
+        let defaults: &[(&str, Action)] = &[
+            ("q", Action::Quit),
+            ("ctrl-c", Action::Quit),
+            ("right", Action::NextThread),
+            ("j", Action::NextThread),
+            ("left", Action::PrevThread),
+            ("k", Action::PrevThread),
+            ("up", Action::ScrollUp),
+            ("down", Action::ScrollDown),
+            ("pageup", Action::PageUp),
+            ("pagedown", Action::PageDown),
+            ("ctrl-u", Action::HalfPageUp),
+            ("ctrl-d", Action::HalfPageDown),
+            ("home", Action::ScrollTop),
+            ("end", Action::ScrollBottom),
+            ("tab", Action::NextView),
+            ("s", Action::ToggleSkip),
+            ("r", Action::Reply),
+            ("p", Action::Publish),
+            ("d", Action::ToggleDiff),
+            ("v", Action::Visual),
+            ("y", Action::Yank),
+            ("esc", Action::Cancel),
+            ("m", Action::SetMark),
+            ("'", Action::GotoMark),
+            ("ctrl-o", Action::JumpBack),
+            ("alt-o", Action::JumpForward),
+            ("o", Action::OpenBrowser),
+            ("c", Action::Copy),
+            ("e", Action::OpenEditor),
+            ("C", Action::Checkout),
+            ("W", Action::Worktree),
+            ("x", Action::Export),
+            ("P", Action::SavePatch),
+            ("a", Action::ApplyPatch),
+            ("b", Action::ToggleBlame),
+            ("f12", Action::TogglePerf),
+            ("ctrl-p", Action::SwitchPr),
+            ("D", Action::ToggleSinceReview),
+            ("F", Action::RangeDiff),
+            ("f", Action::FilterFiles),
+            ("S", Action::CycleSort),
+            ("/", Action::SearchFiles),
+            ("L", Action::ToggleChecklist),
+            ("ctrl-g", Action::ToggleProgress),
+            ("A", Action::Approve),
+            ("R", Action::RequestChanges),
+            ("X", Action::DismissReview),
+            ("H", Action::ToggleHidden),
+            ("M", Action::Minimize),
+            ("E", Action::EditDescription),
+            ("V", Action::ToggleConversation),
+            ("[", Action::GotoPrev),
+            ("]", Action::GotoNext),
+            ("O", Action::ToggleOutline),
+            ("U", Action::MarkRead),
+            ("T", Action::ToggleTimes),
+            ("*", Action::TogglePin),
+            ("Z", Action::ResolveAll),
+            ("u", Action::Undo),
+            ("ctrl-r", Action::Redo),
+            ("B", Action::BaseFile),
+            ("z", Action::ToggleFold),
+            ("t", Action::ToggleReviewMap),
+            ("i", Action::ImageDiff),
+            ("K", Action::Commits),
+            ("!", Action::Conflicts),
+            ("Y", Action::SyncReview),
+            ("w", Action::FinishReview),
+            ("I", Action::SwitchAccount),
+            ("N", Action::RequestReviewers),
+            ("Q", Action::RecordMacro),
+            ("@", Action::PlayMacro),
+            ("ctrl-e", Action::EditPrefs),
+            ("J", Action::OpenIssue),
+            ("n", Action::AddNote),
+            ("ctrl-a", Action::ToggleAnnotations),
+            ("ctrl-v", Action::Advisories),
+            ("ctrl-n", Action::ShowNotes),
+            ("ctrl-s", Action::Spelling),
+            ("ctrl-y", Action::CopyReference),
+            ("ctrl-t", Action::Todo),
+            ("ctrl-k", Action::MarkAddressed),
+            ("ctrl-l", Action::ToggleMergeDiff),
+            ("ctrl-w", Action::MyReviews),
+        ];
+        let vim: &[(&str, Action)] = &[
+            ("h", Action::PrevThread),
+            ("l", Action::NextThread),
+            ("j", Action::ScrollDown),
+            ("k", Action::ScrollUp),
+            ("g", Action::ScrollTop),
+            ("G", Action::ScrollBottom),
+            ("ctrl-f", Action::PageDown),
+            ("ctrl-b", Action::PageUp),
+            ("{", Action::PrevHunk),
+            ("}", Action::NextHunk),
+        ];
+        let mut global = Bindings::new();
+        let layers = match profile {
+            Profile::Default => vec![defaults],
+            Profile::Vim => vec![defaults, vim],
+        };
+        for (key, action) in layers.into_iter().flatten() {
+            let chord = KeyChord::parse(key).expect("default key bindings should parse");
+            global.retain(|(bound, _)| *bound != chord);
+            global.push((chord, *action));
+        }
+        Self {
+            global,
+            views: HashMap::new(),
+            counts: profile == Profile::Vim,
+        }
+    }
+
+    pub fn from_config(config: &KeysConfig) -> Result<Self> {
+        let mut keymap = Self::defaults(config.profile);
+        apply_overrides(&mut keymap.global, &config.global)?;
+        for (view, overrides) in [
+            (ThreadView::Unresolved, &config.unresolved),
+            (ThreadView::Active, &config.unskipped),
+            (ThreadView::Skipped, &config.skipped),
+        ] {
+            if overrides.is_empty() {
+                continue;
+            }
+            apply_overrides(keymap.views.entry(view).or_default(), overrides)?;
+        }
+        Ok(keymap)
+    }
+
+    /// Drops every binding for actions matching `remove`.
+    pub fn unbind(&mut self, remove: impl Fn(Action) -> bool) {
+        self.global.retain(|(_, action)| !remove(*action));
+        for bindings in self.views.values_mut() {
+            bindings.retain(|(_, action)| !remove(*action));
+        }
+    }
+
+    /// The action `event` runs in `view`. An action bound for the view
+    /// alone no longer answers to its global chords there.
+    pub fn action_for(&self, view: ThreadView, event: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(event);
+        let scoped = self.scoped(view);
+        lookup(scoped, &chord)
+            .or_else(|| lookup(&self.global, &chord).filter(|action| !binds(scoped, *action)))
+    }
+
+    fn scoped(&self, view: ThreadView) -> &[(KeyChord, Action)] {
+        self.views.get(&view).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Digit to append to a pending count, if `event` is one. A leading zero
+    /// is never a count so it stays available as a binding.
+    pub fn count_digit(&self, event: &KeyEvent, pending: bool) -> Option<usize> {
+        if !self.counts || !event.modifiers.is_empty() {
+            return None;
+        }
+        let digit = match event.code {
+            KeyCode::Char(ch) => ch.to_digit(10)? as usize,
+            _ => return None,
+        };
+        (digit != 0 || pending).then_some(digit)
+    }
+
+    /// Chords bound to `action` in `view`, view-specific bindings first.
+    pub fn chords_for(&self, view: ThreadView, action: Action) -> Vec<KeyChord> {
+        let scoped = self.scoped(view);
+        scoped
+            .iter()
+            .chain(
+                self.global.iter().filter(|(chord, bound)| {
+                    lookup(scoped, chord).is_none() && !binds(scoped, *bound)
+                }),
+            )
+            .filter(|(_, bound)| *bound == action)
+            .map(|(chord, _)| *chord)
+            .collect()
+    }
+
+    pub fn label(&self, view: ThreadView, action: Action) -> Option<String> {
+        self.chords_for(view, action)
+            .first()
+            .map(|chord| chord.label())
+    }
+}
+
+fn lookup(bindings: &[(KeyChord, Action)], chord: &KeyChord) -> Option<Action> {
+    bindings
+        .iter()
+        .find(|(bound, _)| bound == chord)
+        .map(|(_, action)| *action)
+}
+
+fn binds(bindings: &[(KeyChord, Action)], action: Action) -> bool {
+    bindings.iter().any(|(_, bound)| *bound == action)
+}
+
+fn apply_overrides(bindings: &mut Bindings, overrides: &HashMap<String, KeyList>) -> Result<()> {
+    for (name, keys) in overrides {
+        let action =
+            Action::from_name(name).ok_or_else(|| anyhow!("unknown action `{name}` in [keys]"))?;
+        bindings.retain(|(_, bound)| *bound != action);
+        for key in keys.iter() {
+            let chord = KeyChord::parse(key)
+                .map_err(|err| anyhow!("invalid binding for `{name}`: {err}"))?;
+            bindings.retain(|(bound, _)| *bound != chord);
+            bindings.push((chord, action));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::Local;
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Appends log records to a file, since anything written to stdout or stderr
+/// would corrupt the TUI.
+struct FileLogger {
+    level: LevelFilter,
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{} {:<5} {}: {}",
+                Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Starts logging when `--verbose` is given or `RUST_LOG` is set, returning
+/// the log file path. Each `-v` raises the level: info, debug, then trace.
+pub fn init(verbose: u8) -> Result<Option<PathBuf>> {
+    let level = match verbose {
+        0 => match env::var("RUST_LOG")
+            .ok()
+            .and_then(|value| env_level(&value))
+        {
+            Some(level) => level,
+            None => return Ok(None),
+        },
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    let path = default_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    log::set_boxed_logger(Box::new(FileLogger {
+        level,
+        file: Mutex::new(file),
+    }))?;
+    log::set_max_level(level);
+    Ok(Some(path))
+}
+
+/// The level `RUST_LOG` sets for this crate, read the way `env_logger`
+/// does: a `gh_cr=level` directive wins over a bare default level, and
+/// directives for other crates are ignored. Anything that doesn't parse is
+/// skipped rather than stopping the app from starting.
+pub fn env_level(value: &str) -> Option<LevelFilter> {
+    let mut default = None;
+    let mut own = None;
+    for directive in value.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        match directive.split_once('=') {
+            Some((target, level)) => {
+                let crate_name = target.split("::").next().unwrap_or(target);
+                if crate_name == "gh_cr" {
+                    own = level.parse().ok().or(own);
+                }
+            }
+            None => default = directive.parse().ok().or(default),
+        }
+    }
+    own.or(default)
+}
+
+fn default_path() -> Result<PathBuf> {
+    if let Ok(dir) = env::var("XDG_STATE_HOME")
+        && !dir.is_empty()
+    {
+        return Ok(Path::new(&dir).join("gh-cr").join("gh-cr.log"));
+    }
+    let mut path = dirs_next::home_dir().ok_or_else(|| anyhow!("HOME not set"))?;
+    path.push(".local");
+    path.push("state");
+    path.push("gh-cr");
+    path.push("gh-cr.log");
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_log_directives_pick_this_crates_level() {
+        use log::LevelFilter;
+        assert_eq!(env_level("debug"), Some(LevelFilter::Debug));
+        assert_eq!(env_level("gh_cr=trace"), Some(LevelFilter::Trace));
+        assert_eq!(env_level("info,hyper=warn"), Some(LevelFilter::Info));
+        assert_eq!(
+            env_level("warn,gh_cr::diff=debug"),
+            Some(LevelFilter::Debug)
+        );
+        assert_eq!(env_level("hyper=warn"), None);
+        assert_eq!(env_level("loud"), None);
+    }
+}
@@ -1,52 +1,249 @@
 #![allow(dead_code)]
 
+mod advisory;
+mod ansi;
+mod browser;
+mod buffer;
+mod clipboard;
+mod codeowners;
+mod config;
+mod diff;
+mod export;
+mod filter;
+mod git;
+#[cfg(test)]
+mod headless;
+mod hooks;
+mod hyperlink;
+mod image;
+mod keymap;
+mod logging;
+mod notify;
+mod perf;
+mod picker;
+mod prefetch;
+mod prefs;
+mod session;
+mod setup;
+mod size;
+mod spell;
+mod stack;
+mod submodule;
+mod theme;
+
 use std::{
-    collections::{HashSet, VecDeque},
+    borrow::Cow,
+    cmp::{Ordering, Reverse},
+    collections::{HashMap, HashSet, VecDeque},
     env,
     ffi::{OsStr, OsString},
     fmt::Write as _,
     fs,
-    io::{Write, stdout},
+    io::{self, BufWriter, IsTerminal, Write, stdout},
+    mem,
+    ops::{Range, RangeInclusive},
     path::{Path, PathBuf},
-    process::Command as StdCommand,
+    process::{Command as StdCommand, Stdio},
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result, anyhow};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use chrono_humanize::HumanTime;
-use clap::Parser;
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
     event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseEventKind,
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        KeyboardEnhancementFlags, MouseButton, MouseEventKind, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
     },
     execute,
     style::{Color, Stylize},
     terminal::{
         Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
-        enable_raw_mode, size,
+        enable_raw_mode, size, supports_keyboard_enhancement,
     },
 };
-use serde::Deserialize;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
 use tempfile::NamedTempFile;
-use textwrap::{Options as WrapOptions, wrap};
-use tokio::process::Command as TokioCommand;
+use textwrap::{Options as WrapOptions, core::display_width, wrap};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::{Child, ChildStdout, Command as TokioCommand},
+    task::JoinHandle,
+};
+
+use crate::{
+    buffer::{Buffer, Rect},
+    codeowners::CodeOwners,
+    config::{AuthorStyle, ChecklistConfig, Config, HooksConfig, ThreadSort},
+    filter::FileFilter,
+    hooks::Hook,
+    hyperlink::Hyperlinks,
+    keymap::{Action, KeyChord, KeyMap},
+    prefetch::{Done, Kind, Prefetcher},
+    prefs::{PrefsStore, RepoPrefs},
+    session::{Session, SessionStore},
+    theme::Theme,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Cli::parse();
-    let mode = if args.dump { Mode::Dump } else { Mode::Tui };
-    let gh = GhCli::new();
-    let repo = match gh.current_repo().await {
+    if let Some(path) = logging::init(args.verbose).context("failed to start logging")? {
+        log::info!(
+            "gh-cr {} logging to {}",
+            env!("CARGO_PKG_VERSION"),
+            path.display()
+        );
+    }
+    if args.man {
+        clap_mangen::Man::new(Cli::command()).render(&mut stdout())?;
+        return Ok(());
+    }
+    if let Some(Command::Completions { shell }) = args.command {
+        let mut command = Cli::command();
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut stdout());
+        return Ok(());
+    }
+    let mode = if args.json {
+        Mode::Json
+    } else if args.dump {
+        Mode::Dump
+    } else {
+        Mode::Tui
+    };
+    let mut gh = GhCli::new();
+    // Only required once the dashboard and inbox, which span repositories
+    // and so work from anywhere, have had their turn.
+    let repo = gh.current_repo().await;
+    if let Ok(repo) = &repo {
+        gh.host = repo.host();
+    }
+    gh.load_settings().await;
+
+    if args.wants_setup(&mode) && !gh.settings.prompts_disabled {
+        let path = Config::default_path()?;
+        if !path.exists() {
+            setup::run(&path).context("first-run setup failed")?;
+        }
+    }
+    let mut config = Config::load().context("failed to load config")?;
+    args.apply_overrides(&mut config);
+    let mode = match mode {
+        Mode::Tui if config.plain => Mode::Plain,
+        mode => mode,
+    };
+
+    // PRs in this repository open in the TUI, others on GitHub.
+    let here = repo
+        .as_ref()
+        .ok()
+        .map(|repo| format!("{}/{}", repo.owner, repo.name));
+    let is_here = |slug: &str| {
+        here.as_ref()
+            .is_some_and(|here| slug.eq_ignore_ascii_case(here))
+    };
+    // The review comment a notification pointed at, selected on opening.
+    let mut focus_comment = None;
+    let picked = if let Some(Command::Dashboard) = args.command {
+        let theme = Theme::from_config(&config).context("invalid theme")?;
+        let gh_dashboard = gh.clone();
+        let refresh = move || {
+            let gh = gh_dashboard.clone();
+            tokio::spawn(async move { gh.dashboard().await })
+        };
+        match picker::pick_dashboard(refresh, &theme)? {
+            Some(pr) if is_here(&pr.repo) => Some(pr.number),
+            Some(pr) => {
+                browser::open(&pr.url)?;
+                return Ok(());
+            }
+            None => return Ok(()),
+        }
+    } else if let Some(Command::Inbox) = args.command {
+        let notifications = gh
+            .notifications()
+            .await
+            .context("failed to load notifications")?;
+        if notifications.is_empty() {
+            eprintln!("No notifications about pull requests.");
+            return Ok(());
+        }
+        let theme = Theme::from_config(&config).context("invalid theme")?;
+        let gh_inbox = gh.clone();
+        // Marking a notification writes to GitHub, so read-only mode leaves
+        // them as they are.
+        let mark = (!config.read_only).then_some(move |id: &str, done: bool| {
+            let gh = gh_inbox.clone();
+            let id = id.to_string();
+            tokio::spawn(async move { gh.mark_notification(&id, done).await })
+        });
+        let Some(notification) = picker::pick_notification(notifications, mark, &theme)? else {
+            return Ok(());
+        };
+        if !config.read_only
+            && let Err(err) = gh.mark_notification(&notification.id, false).await
+        {
+            log::warn!("failed to mark the notification read: {err}");
+        }
+        if !is_here(&notification.repo) {
+            browser::open(&notification.html_url())?;
+            return Ok(());
+        }
+        focus_comment = notification.review_comment();
+        Some(notification.number)
+    } else {
+        None
+    };
+
+    let repo = match repo {
         Ok(repo) => repo,
         Err(err) => {
             eprintln!("Unable to determine repository: {err}");
             return Ok(());
         }
     };
+    if let Some(Command::Compare { range }) = &args.command {
+        return compare(&args, gh, repo, config, mode, range).await;
+    }
 
-    let pr_number = if let Some(num) = args.pr_number {
+    let pr_number = if let Some(num) = picked {
+        num
+    } else if let Some(Command::List) = args.command {
+        let prs = gh
+            .list_prs()
+            .await
+            .context("failed to list pull requests")?;
+        if prs.is_empty() {
+            eprintln!("No open pull requests in {}/{}.", repo.owner, repo.name);
+            return Ok(());
+        }
+        let title = format!("Open pull requests in {}/{}", repo.owner, repo.name);
+        let picked = if config.plain {
+            picker::read_pr(&title, &prs)?
+        } else {
+            let theme = Theme::from_config(&config).context("invalid theme")?;
+            picker::pick_pr(&title, prs, pr_search(&gh), &theme)?
+        };
+        match picked {
+            Some(num) => num,
+            None => return Ok(()),
+        }
+    } else if let Some(num) = args.pr_number() {
         num
+    } else if let Some(branch) = &args.branch {
+        match gh.pr_for_branch(branch).await {
+            Ok(num) => num,
+            Err(err) => {
+                eprintln!("No pull request for branch {branch}: {err}");
+                return Ok(());
+            }
+        }
     } else {
         match gh.current_pr_number().await {
             Ok(num) => num,
@@ -57,978 +254,9438 @@ async fn main() -> Result<()> {
         }
     };
 
+    match &args.command {
+        Some(Command::Checks { .. }) if args.json => {
+            let checks = gh
+                .fetch_checks(pr_number)
+                .await
+                .context("failed to fetch checks")?;
+            let checks: Vec<_> = checks.iter().map(Check::to_json).collect();
+            println!("{}", serde_json::to_string_pretty(&checks)?);
+            return Ok(());
+        }
+        Some(Command::Checks { .. }) => {
+            let links = Hyperlinks::new(config.hyperlinks);
+            return print_checks(&gh, pr_number, links).await;
+        }
+        Some(Command::Review(_)) if config.read_only => {
+            return Err(anyhow!("refusing to submit a review in read-only mode"));
+        }
+        Some(Command::Review(review)) => {
+            return submit_review(&gh, &repo, pr_number, review, &config).await;
+        }
+        _ => {}
+    }
+
     let skip_store = SkipStore::load().context("failed to load skip list")?;
     let threads = gh
         .fetch_threads(&repo, pr_number)
         .await
         .context("failed to fetch review threads")?;
 
-    let mut app = App::new(gh, repo, pr_number, skip_store, threads);
+    if let Mode::Json = mode {
+        return print_json(&gh, &repo, pr_number, &skip_store, &threads).await;
+    }
+
+    let session_store = SessionStore::new(&repo, pr_number)?;
+    let mut session = session_store.load();
+    if args.hide_diff {
+        session.show_diff = None;
+    }
+    let prefs = PrefsStore::new(&repo)?.load();
+    if session.view.is_none() {
+        session.view = prefs.view;
+    }
+
+    let mut app = App::new(gh, repo, pr_number, skip_store, config, threads)?;
+    app.apply_prefs(prefs);
+    app.restore_session(session);
+    app.load_sort().await;
+    if let Some(target) = args.file_target() {
+        app.focus_file(&target);
+    }
+    if let Some(comment) = focus_comment {
+        app.focus_comment(comment);
+    }
     match mode {
-        Mode::Tui => app.run().await?,
-        Mode::Dump => app.dump_once()?,
+        Mode::Tui => review(&args, app, session_store).await?,
+        Mode::Dump => app.dump_once().await?,
+        Mode::Plain => {
+            app.load_renames().await;
+            app.load_submodules().await;
+            hooks::fire(&app.config.hooks, Hook::OpenPr, &app.repo, pr_number, &[]);
+            let result = app.plain_review().await;
+            session_store
+                .save(&app.session())
+                .context("failed to save session")?;
+            result?;
+        }
+        Mode::Json => unreachable!(),
     }
     Ok(())
 }
 
-#[derive(Parser, Debug)]
-#[command(
-    author,
-    version,
-    about = "Review GitHub PR threads from your terminal."
-)]
-struct Cli {
-    /// Print the current thread content once and exit (no TUI)
-    #[arg(long)]
-    dump: bool,
-
-    /// Override the inferred PR number
-    #[arg(value_parser = clap::value_parser!(u64))]
-    pr_number: Option<u64>,
+/// Reviews the diff of a ref range in the same viewer as a PR, with a
+/// thread for each hunk. Nothing goes to GitHub: mutating actions are
+/// unbound and notes are kept in the session.
+async fn compare(
+    args: &Cli,
+    gh: GhCli,
+    repo: Repo,
+    mut config: Config,
+    mode: Mode,
+    range: &str,
+) -> Result<()> {
+    if let Mode::Json = mode {
+        eprintln!("--json isn't supported when comparing a range.");
+        return Ok(());
+    }
+    config.read_only = true;
+    config.refresh_interval = 0;
+    config.watch.enabled = false;
+    let (base, head) = git::range_ends(range)?;
+    let diff = git::diff(&base, &head).context("failed to diff the range")?;
+    let files = diff::split_files(&diff);
+    if files.is_empty() {
+        eprintln!("No changes in {range}.");
+        return Ok(());
+    }
+    let threads = compare_threads(range, &files);
+    let skip_store = SkipStore::load().context("failed to load skip list")?;
+    let session_store = SessionStore::for_range(&repo, range)?;
+    let mut session = session_store.load();
+    if args.hide_diff {
+        session.show_diff = None;
+    }
+    let prefs = PrefsStore::new(&repo)?.load();
+    if session.view.is_none() {
+        session.view = prefs.view;
+    }
+    let mut app = App::new(gh, repo, 0, skip_store, config, threads)?;
+    app.compare = Some(range.to_string());
+    app.base_oid = Some(base);
+    app.head_oid = Some(head);
+    app.pr_files = Some(compare_files(&files));
+    app.apply_prefs(prefs);
+    app.restore_session(session);
+    app.load_generated();
+    if let Some(target) = args.file_target() {
+        app.focus_file(&target);
+    }
+    let result = match mode {
+        Mode::Tui => {
+            let mut terminal = TerminalSession::enter()?;
+            loop {
+                match app.run(&mut terminal).await {
+                    Ok(Exit::Quit) => break Ok(()),
+                    Ok(_) => app.status_line = Some("There are no PRs open to switch to.".into()),
+                    Err(err) => break Err(err),
+                }
+            }
+        }
+        Mode::Dump => app.dump_once().await,
+        Mode::Plain => app.plain_review().await,
+        Mode::Json => unreachable!(),
+    };
+    session_store
+        .save(&app.session())
+        .context("failed to save session")?;
+    result
 }
 
-#[derive(Clone, Copy)]
-enum Mode {
-    Tui,
-    Dump,
+/// A comment-less thread for each hunk of a compared range, or for the
+/// whole file when it has no hunks, such as a binary file. Ids include the
+/// range so skipping a hunk doesn't skip it in other comparisons.
+fn compare_threads(range: &str, files: &[diff::FileDiff]) -> Vec<Thread> {
+    let now = Utc::now();
+    let thread = |id: String, path: &str, line: Option<usize>, hunk: Option<&String>| Thread {
+        id,
+        path: path.to_string(),
+        line: line.map(|line| line as i64),
+        diff_hunk: hunk.cloned(),
+        is_resolved: false,
+        is_outdated: false,
+        created_at: now,
+        comments: Vec::new(),
+    };
+    let mut threads = Vec::new();
+    for file in files {
+        if file.hunks.is_empty() {
+            threads.push(thread(
+                format!("{range}:{}", file.path),
+                &file.path,
+                None,
+                None,
+            ));
+        }
+        for (index, (start, hunk)) in file.hunks.iter().enumerate() {
+            threads.push(thread(
+                format!("{range}:{}:{index}", file.path),
+                &file.path,
+                *start,
+                Some(hunk),
+            ));
+        }
+    }
+    threads
 }
 
-const COMMENT_WRAP: usize = 80;
+/// What GitHub would list as a PR's files, counted from a local diff.
+fn compare_files(files: &[diff::FileDiff]) -> HashMap<String, PrFile> {
+    files
+        .iter()
+        .map(|file| {
+            let count = |sign: char| {
+                file.hunks
+                    .iter()
+                    .flat_map(|(_, hunk)| hunk.lines())
+                    .filter(|line| line.starts_with(sign))
+                    .count() as u64
+            };
+            let (additions, deletions) = (count('+'), count('-'));
+            let pr_file = PrFile {
+                status: file.status.to_string(),
+                changes: additions + deletions,
+                additions,
+                deletions,
+                previous: file.previous.clone(),
+            };
+            (file.path.clone(), pr_file)
+        })
+        .collect()
+}
 
-struct App {
-    gh: GhCli,
-    repo: Repo,
-    pr_number: u64,
-    skip_store: SkipStore,
-    active_threads: Vec<Thread>,
-    unresolved_threads: Vec<Thread>,
-    skipped_threads: Vec<Thread>,
-    current_unresolved: usize,
-    current_active: usize,
-    current_skipped: usize,
-    view: ThreadView,
-    status_line: Option<String>,
-    scroll_offset: usize,
-    queued_replies: VecDeque<QueuedReply>,
-    show_diff: bool,
+/// Runs the TUI, starting with `app` and opening further PRs as the user
+/// switches to them. Each PR keeps its threads, queued replies and scroll
+/// position while another is shown, and every session is saved on exit.
+async fn review(args: &Cli, app: App, session_store: SessionStore) -> Result<()> {
+    let mut terminal = TerminalSession::enter()?;
+    hooks::fire(
+        &app.config.hooks,
+        Hook::OpenPr,
+        &app.repo,
+        app.pr_number,
+        &[],
+    );
+    let mut app = app;
+    app.load_review().await;
+    let mut reviews = vec![(app, session_store)];
+    let mut current = 0;
+    let result = loop {
+        let open: Vec<u64> = reviews.iter().map(|(app, _)| app.pr_number).collect();
+        let app = &mut reviews[current].0;
+        app.open_prs = open;
+        let exit = app.run(&mut terminal).await;
+        // The active account is gh's, so a switch in one review applies to
+        // every review of the repository.
+        let account = (app.account.clone(), app.permission.clone());
+        for (review, _) in &mut reviews {
+            (review.account, review.permission) = account.clone();
+        }
+        match exit {
+            Ok(Exit::Quit) => break Ok(()),
+            Ok(Exit::Switch(index)) => current = index,
+            Ok(Exit::Pick) => match pick_review(args, &mut terminal, &mut reviews).await {
+                Ok(Some(index)) => current = index,
+                Ok(None) => {}
+                Err(err) => reviews[current]
+                    .0
+                    .report_failure(format!("Failed to open PR: {err}")),
+            },
+            Ok(Exit::Open(pr_number)) => match open_review(args, &mut reviews, pr_number).await {
+                Ok(index) => current = index,
+                Err(err) => reviews[current]
+                    .0
+                    .report_failure(format!("Failed to open PR #{pr_number}: {err}")),
+            },
+            Err(err) => break Err(err),
+        }
+        // Another review may have changed the shared skip list meanwhile.
+        let app = &mut reviews[current].0;
+        match SkipStore::load() {
+            Ok(store) => app.skip_store = store,
+            Err(err) => app.report_failure(format!("Failed to reload skip list: {err}")),
+        }
+    };
+    for (app, store) in &reviews {
+        store
+            .save(&app.session())
+            .context("failed to save session")?;
+    }
+    result
 }
 
-#[derive(Clone, Copy)]
-enum ThreadView {
-    Unresolved,
-    Active,
-    Skipped,
+/// Lets the user pick an open PR, loading it as a new review unless it is
+/// already open. Returns the index of the review to show.
+async fn pick_review(
+    args: &Cli,
+    terminal: &mut TerminalSession,
+    reviews: &mut Vec<(App, SessionStore)>,
+) -> Result<Option<usize>> {
+    let (first, _) = &reviews[0];
+    let repo = first.repo.clone();
+    let prs = first
+        .gh
+        .list_prs()
+        .await
+        .context("failed to list pull requests")?;
+    let title = format!("Open pull requests in {}/{}", repo.owner, repo.name);
+    terminal.deactivate()?;
+    let picked = picker::pick_pr(&title, prs, pr_search(&first.gh), &first.theme);
+    terminal.activate()?;
+    let Some(pr_number) = picked? else {
+        return Ok(None);
+    };
+    open_review(args, reviews, pr_number).await.map(Some)
 }
 
-impl ThreadView {
-    fn next(self) -> Self {
-        match self {
-            ThreadView::Unresolved => ThreadView::Active,
-            ThreadView::Active => ThreadView::Skipped,
-            ThreadView::Skipped => ThreadView::Unresolved,
-        }
+/// Runs the PR picker's searches in the background with `gh`.
+fn pr_search(gh: &GhCli) -> impl Fn(&str) -> JoinHandle<Result<Vec<PrSummary>>> {
+    let gh = gh.clone();
+    move |query| {
+        let gh = gh.clone();
+        let query = query.to_string();
+        tokio::spawn(async move { gh.search_prs(&query).await })
     }
+}
 
-    fn skip_action_label(self) -> &'static str {
-        match self {
-            ThreadView::Unresolved | ThreadView::Active => "skip",
-            ThreadView::Skipped => "unskip",
-        }
+/// The index of the review for `pr_number`, loading it first if it isn't
+/// open yet.
+async fn open_review(
+    args: &Cli,
+    reviews: &mut Vec<(App, SessionStore)>,
+    pr_number: u64,
+) -> Result<usize> {
+    if let Some(index) = reviews
+        .iter()
+        .position(|(app, _)| app.pr_number == pr_number)
+    {
+        return Ok(index);
     }
-
-    fn name(self) -> &'static str {
-        match self {
-            ThreadView::Unresolved => "unresolved",
-            ThreadView::Active => "unskipped",
-            ThreadView::Skipped => "skipped",
-        }
+    let repo = reviews[0].0.repo.clone();
+    let gh = reviews[0].0.gh.clone();
+    let threads = gh
+        .fetch_threads(&repo, pr_number)
+        .await
+        .context("failed to fetch review threads")?;
+    let mut config = Config::load().context("failed to load config")?;
+    args.apply_overrides(&mut config);
+    let skip_store = SkipStore::load().context("failed to load skip list")?;
+    let session_store = SessionStore::new(&repo, pr_number)?;
+    let mut session = session_store.load();
+    if args.hide_diff {
+        session.show_diff = None;
+    }
+    let prefs = PrefsStore::new(&repo)?.load();
+    if session.view.is_none() {
+        session.view = prefs.view;
     }
+    let mut app = App::new(gh, repo, pr_number, skip_store, config, threads)?;
+    app.apply_prefs(prefs);
+    app.restore_session(session);
+    app.load_sort().await;
+    app.load_review().await;
+    hooks::fire(&app.config.hooks, Hook::OpenPr, &app.repo, pr_number, &[]);
+    reviews.push((app, session_store));
+    Ok(reviews.len() - 1)
 }
 
-impl App {
-    fn new(
-        gh: GhCli,
-        repo: Repo,
-        pr_number: u64,
-        skip_store: SkipStore,
-        threads: Vec<Thread>,
-    ) -> Self {
-        let (active_threads, skipped_threads) = Self::partition_threads(&skip_store, threads);
-        let unresolved_threads = Self::build_unresolved(&active_threads);
-        Self {
-            gh,
-            repo,
-            pr_number,
-            skip_store,
-            active_threads,
-            unresolved_threads,
-            skipped_threads,
-            current_unresolved: 0,
-            current_active: 0,
-            current_skipped: 0,
-            view: ThreadView::Unresolved,
-            status_line: None,
-            scroll_offset: 0,
-            queued_replies: VecDeque::new(),
-            show_diff: true,
-        }
-    }
+/// Prints threads, check states and the viewer's pending review as a single
+/// JSON document, without touching the terminal.
+async fn print_json(
+    gh: &GhCli,
+    repo: &Repo,
+    pr_number: u64,
+    skip_store: &SkipStore,
+    threads: &[Thread],
+) -> Result<()> {
+    let checks = gh
+        .fetch_checks(pr_number)
+        .await
+        .context("failed to fetch checks")?;
+    let pending = gh
+        .fetch_pending_review(repo, pr_number)
+        .await
+        .context("failed to fetch pending review")?;
+    let unresolved = threads.iter().filter(|t| !t.is_resolved).count();
+    let skipped = threads
+        .iter()
+        .filter(|t| skip_store.contains(&t.id))
+        .count();
+    let report = serde_json::json!({
+        "repository": format!("{}/{}", repo.owner, repo.name),
+        "pr": pr_number,
+        "counts": {
+            "threads": threads.len(),
+            "unresolved": unresolved,
+            "skipped": skipped,
+            "resolved": threads.len() - unresolved,
+        },
+        "threads": threads
+            .iter()
+            .map(|thread| thread.to_json(skip_store.contains(&thread.id)))
+            .collect::<Vec<_>>(),
+        "checks": checks.iter().map(Check::to_json).collect::<Vec<_>>(),
+        "pending_review": pending.map(|review| serde_json::json!({
+            "id": review.id,
+            "body": review.body,
+            "url": review.html_url,
+        })),
+    });
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
 
-    fn partition_threads(
-        skip_store: &SkipStore,
-        threads: Vec<Thread>,
-    ) -> (Vec<Thread>, Vec<Thread>) {
-        let mut active = Vec::new();
-        let mut skipped = Vec::new();
-        for thread in threads {
-            if skip_store.contains(&thread.id) {
-                skipped.push(thread);
-            } else {
-                active.push(thread);
+async fn print_checks(gh: &GhCli, pr_number: u64, links: Hyperlinks) -> Result<()> {
+    let checks = gh
+        .fetch_checks(pr_number)
+        .await
+        .context("failed to fetch checks")?;
+    if checks.is_empty() {
+        println!("No checks reported for PR #{pr_number}.");
+        return Ok(());
+    }
+    for check in &checks {
+        let (symbol, color) = match check.state {
+            CheckState::Pass => ("✓", Color::DarkGreen),
+            CheckState::Fail => ("✗", Color::DarkRed),
+            CheckState::Pending => ("•", Color::DarkYellow),
+            CheckState::Skipped => ("-", Color::DarkGrey),
+        };
+        let name = match &check.url {
+            Some(url) => links.link(&check.name, url),
+            None => check.name.clone(),
+        };
+        match (&check.url, check.state) {
+            (Some(url), CheckState::Fail) => {
+                println!(
+                    "{} {} {}",
+                    symbol.with(color),
+                    name,
+                    url.as_str().dark_grey()
+                )
             }
+            _ => println!("{} {}", symbol.with(color), name),
         }
-        Self::sort_threads(&mut active);
-        Self::sort_threads(&mut skipped);
-        (active, skipped)
-    }
-
-    fn sort_threads(list: &mut Vec<Thread>) {
-        list.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
     }
+    let count = |state: CheckState| checks.iter().filter(|c| c.state == state).count();
+    println!(
+        "\n{} passed, {} failed, {} pending, {} skipped",
+        count(CheckState::Pass),
+        count(CheckState::Fail),
+        count(CheckState::Pending),
+        count(CheckState::Skipped)
+    );
+    Ok(())
+}
 
-    fn build_unresolved(active: &[Thread]) -> Vec<Thread> {
-        active
-            .iter()
-            .filter(|thread| !thread.is_resolved)
-            .cloned()
-            .collect()
+async fn submit_review(
+    gh: &GhCli,
+    repo: &Repo,
+    pr_number: u64,
+    review: &ReviewArgs,
+    config: &Config,
+) -> Result<()> {
+    let event = review.event();
+    let body = review.body.as_deref().unwrap_or("");
+    if event != ReviewEvent::Approve && body.trim().is_empty() {
+        return Err(anyhow!("a body (-b) is required to {}", event.describe()));
     }
+    let ticked: HashSet<String> = SessionStore::new(repo, pr_number)?
+        .load()
+        .checklist
+        .into_iter()
+        .collect();
+    let body = review_body(&config.checklist, body, &ticked);
+    gh.submit_review(repo, pr_number, event, &body)
+        .await
+        .context("failed to submit review")?;
+    println!("Submitted review on PR #{pr_number}: {}.", event.describe());
+    fire_review_hooks(&config.hooks, repo, pr_number, event, &body);
+    Ok(())
+}
 
-    fn threads_for_view(&self, view: ThreadView) -> &Vec<Thread> {
-        match view {
-            ThreadView::Unresolved => &self.unresolved_threads,
-            ThreadView::Active => &self.active_threads,
-            ThreadView::Skipped => &self.skipped_threads,
-        }
+fn fire_review_hooks(
+    hooks: &HooksConfig,
+    repo: &Repo,
+    pr_number: u64,
+    event: ReviewEvent,
+    body: &str,
+) {
+    let extra = [
+        ("GH_CR_REVIEW_EVENT", event.api_name()),
+        ("GH_CR_BODY", body),
+    ];
+    hooks::fire(hooks, Hook::ReviewSubmitted, repo, pr_number, &extra);
+    if event == ReviewEvent::Approve {
+        hooks::fire(hooks, Hook::Approve, repo, pr_number, &extra);
     }
+}
 
-    fn threads_for_view_mut(&mut self, view: ThreadView) -> &mut Vec<Thread> {
-        match view {
-            ThreadView::Unresolved => &mut self.unresolved_threads,
-            ThreadView::Active => &mut self.active_threads,
-            ThreadView::Skipped => &mut self.skipped_threads,
+/// `body` with the checklist's state appended, when configured to.
+fn review_body(checklist: &ChecklistConfig, body: &str, ticked: &HashSet<String>) -> String {
+    let mut body = body.to_string();
+    if checklist.append_to_review && !checklist.items.is_empty() {
+        if !body.trim().is_empty() {
+            body.push_str("\n\n");
         }
+        body.push_str(&checklist_summary(&checklist.items, ticked));
     }
+    body
+}
 
-    fn index_for_view(&self, view: ThreadView) -> usize {
-        match view {
-            ThreadView::Unresolved => self.current_unresolved,
-            ThreadView::Active => self.current_active,
-            ThreadView::Skipped => self.current_skipped,
-        }
-    }
+/// Marks where the summary ends in the review composer.
+const COMPOSER_MARKER: &str = "# ------------------------ >8 ------------------------";
 
-    fn index_for_view_mut(&mut self, view: ThreadView) -> &mut usize {
-        match view {
-            ThreadView::Unresolved => &mut self.current_unresolved,
-            ThreadView::Active => &mut self.current_active,
-            ThreadView::Skipped => &mut self.current_skipped,
+/// The review composer's text: room for the summary, then the verdict and
+/// a numbered recap of the queued replies.
+fn review_composer_template(pr_number: u64, recap: &[String], drafts: usize) -> String {
+    let mut buf = format!("\n\n{COMPOSER_MARKER}\n");
+    let _ = writeln!(buf, "# Finish your review of PR #{pr_number}.");
+    let _ = writeln!(
+        buf,
+        "# Write the summary above the line; it may be left empty."
+    );
+    let _ = writeln!(
+        buf,
+        "# Set the verdict to comment, approve or request-changes."
+    );
+    let _ = writeln!(buf, "verdict: comment");
+    if drafts > 0 {
+        let _ = writeln!(
+            buf,
+            "# Your pending review on GitHub also has {drafts} draft comment{}.",
+            if drafts == 1 { "" } else { "s" }
+        );
+    }
+    if !recap.is_empty() {
+        let _ = writeln!(buf, "#");
+        let _ = writeln!(
+            buf,
+            "# Queued replies, published in this order. Move lines to reorder"
+        );
+        let _ = writeln!(buf, "# them or delete a line to drop that reply.");
+        for (index, line) in recap.iter().enumerate() {
+            let _ = writeln!(buf, "{} {line}", index + 1);
         }
     }
+    buf
+}
 
-    fn current_threads(&self) -> &Vec<Thread> {
-        self.threads_for_view(self.view)
-    }
+/// What the review composer was left saying.
+#[derive(Debug, PartialEq)]
+struct ComposedReview {
+    body: String,
+    event: ReviewEvent,
+    /// Indices of the queued replies to publish, in order.
+    order: Vec<usize>,
+}
 
-    fn current_threads_mut(&mut self) -> &mut Vec<Thread> {
-        self.threads_for_view_mut(self.view)
+fn parse_review_composer(text: &str, replies: usize) -> Result<ComposedReview> {
+    let (body, rest) = text
+        .split_once(COMPOSER_MARKER)
+        .ok_or_else(|| anyhow!("the line under the summary was removed"))?;
+    let mut event = ReviewEvent::Comment;
+    let mut order = Vec::new();
+    for line in rest.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(verdict) = line.strip_prefix("verdict:") {
+            event = match verdict.trim() {
+                "comment" => ReviewEvent::Comment,
+                "approve" => ReviewEvent::Approve,
+                "request-changes" => ReviewEvent::RequestChanges,
+                other => return Err(anyhow!("unknown verdict: {other}")),
+            };
+            continue;
+        }
+        let number: usize = line
+            .split_whitespace()
+            .next()
+            .and_then(|number| number.parse().ok())
+            .filter(|number| (1..=replies).contains(number))
+            .ok_or_else(|| anyhow!("not a queued reply: {line}"))?;
+        if !order.contains(&(number - 1)) {
+            order.push(number - 1);
+        }
     }
+    Ok(ComposedReview {
+        body: body.trim().to_string(),
+        event,
+        order,
+    })
+}
 
-    fn current_index(&self) -> usize {
-        self.index_for_view(self.view)
-    }
+/// Reasons a comment can be hidden for, with GitHub's name for each.
+const MINIMIZE_REASONS: [(&str, &str); 6] = [
+    ("outdated", "OUTDATED"),
+    ("resolved", "RESOLVED"),
+    ("duplicate", "DUPLICATE"),
+    ("off-topic", "OFF_TOPIC"),
+    ("spam", "SPAM"),
+    ("abuse", "ABUSE"),
+];
 
-    fn current_index_mut(&mut self) -> &mut usize {
-        self.index_for_view_mut(self.view)
+fn minimize_label() -> String {
+    let names: Vec<&str> = MINIMIZE_REASONS.iter().map(|(name, _)| *name).collect();
+    format!("Hide comment as ({})", names.join(", "))
+}
+
+/// The checklist as a Markdown task list, which GitHub renders with
+/// checkboxes.
+fn checklist_summary(items: &[String], ticked: &HashSet<String>) -> String {
+    let mut summary = String::from("**Review checklist**\n");
+    for item in items {
+        let mark = if ticked.contains(item) { "x" } else { " " };
+        let _ = writeln!(summary, "- [{mark}] {item}");
     }
+    summary
+}
 
-    fn current_thread(&self) -> Option<&Thread> {
-        self.current_threads().get(self.current_index())
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Review GitHub PR threads from your terminal."
+)]
+struct Cli {
+    /// Print the current thread content once and exit (no TUI)
+    #[arg(long)]
+    dump: bool,
+
+    /// Print threads, counts, checks and any pending review as JSON (no TUI)
+    #[arg(long, conflicts_with = "dump")]
+    json: bool,
+
+    /// Read threads as plain text, line by line, for screen readers (no TUI)
+    #[arg(long, conflicts_with_all = ["dump", "json"])]
+    plain: bool,
+
+    /// Editor command for replies (overrides config and $EDITOR)
+    #[arg(long)]
+    editor: Option<String>,
+
+    /// Log to a file under ~/.local/state/gh-cr; repeat for more detail
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Stay on the PR and report new pushes, comments and check results
+    #[arg(long)]
+    watch: bool,
+
+    /// Disable replying, publishing and submitting reviews
+    #[arg(long)]
+    read_only: bool,
+
+    /// Start with diff hunks hidden
+    #[arg(long)]
+    hide_diff: bool,
+
+    /// Seconds between background refreshes, 0 to disable (overrides config)
+    #[arg(long, value_name = "SECONDS")]
+    refresh_interval: Option<u64>,
+
+    /// PR number, or a `path[:line]` to open at in the current branch's PR
+    #[arg(value_name = "PR|FILE[:LINE]")]
+    target: Option<String>,
+
+    /// Open the PR whose head is this branch instead of the checked-out one
+    #[arg(long, value_name = "NAME")]
+    branch: Option<String>,
+
+    /// Open at the thread nearest this file
+    #[arg(long, value_name = "PATH")]
+    file: Option<String>,
+
+    /// Line within --file to open at
+    #[arg(long, requires = "file")]
+    line: Option<i64>,
+
+    /// Print a man page to stdout
+    #[arg(long, hide = true)]
+    man: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Pick an open pull request to review
+    List,
+    /// Show your open pull requests and review requests across repositories
+    Dashboard,
+    /// Show your notifications about pull request threads and mentions
+    Inbox,
+    /// Open the review threads (the default)
+    Threads {
+        /// Override the inferred PR number
+        pr_number: Option<u64>,
+    },
+    /// Print the CI check status of a pull request
+    Checks {
+        /// Override the inferred PR number
+        pr_number: Option<u64>,
+    },
+    /// Submit a review without opening the TUI
+    Review(ReviewArgs),
+    /// Review a ref range such as `main..feature` instead of a PR, with
+    /// notes kept locally
+    Compare {
+        /// `base..head`, or `base...head` to diff from where they diverged
+        range: String,
+    },
+    /// Print shell completions to stdout
+    #[command(hide = true)]
+    Completions { shell: Shell },
+}
+
+#[derive(Args, Debug)]
+#[command(group(
+    ArgGroup::new("event")
+        .required(true)
+        .args(["approve", "request_changes", "comment"])
+))]
+struct ReviewArgs {
+    /// Override the inferred PR number
+    pr_number: Option<u64>,
+
+    /// Approve the pull request
+    #[arg(long)]
+    approve: bool,
+
+    /// Request changes (requires a body)
+    #[arg(long)]
+    request_changes: bool,
+
+    /// Leave a review comment without approving (requires a body)
+    #[arg(long)]
+    comment: bool,
+
+    /// Review body
+    #[arg(short, long)]
+    body: Option<String>,
+}
+
+impl ReviewArgs {
+    fn event(&self) -> ReviewEvent {
+        if self.approve {
+            ReviewEvent::Approve
+        } else if self.request_changes {
+            ReviewEvent::RequestChanges
+        } else {
+            ReviewEvent::Comment
+        }
     }
+}
 
-    fn clamp_index_for_view(&mut self, view: ThreadView) {
-        let len = self.threads_for_view(view).len();
-        let idx = self.index_for_view_mut(view);
-        if len == 0 {
-            *idx = 0;
-        } else if *idx >= len {
-            *idx = len - 1;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReviewEvent {
+    Approve,
+    RequestChanges,
+    Comment,
+}
+
+impl ReviewEvent {
+    fn api_name(self) -> &'static str {
+        match self {
+            ReviewEvent::Approve => "APPROVE",
+            ReviewEvent::RequestChanges => "REQUEST_CHANGES",
+            ReviewEvent::Comment => "COMMENT",
         }
     }
 
-    fn clamp_current_index(&mut self) {
-        self.clamp_index_for_view(self.view);
+    fn describe(self) -> &'static str {
+        match self {
+            ReviewEvent::Approve => "approve",
+            ReviewEvent::RequestChanges => "request changes",
+            ReviewEvent::Comment => "comment",
+        }
     }
+}
 
-    fn advance_view(&mut self) {
-        self.view = self.view.next();
-        self.clamp_current_index();
-        self.reset_scroll();
-        self.clear_status();
+impl Cli {
+    fn pr_number(&self) -> Option<u64> {
+        let nested = match &self.command {
+            Some(Command::Threads { pr_number } | Command::Checks { pr_number }) => *pr_number,
+            Some(Command::Review(review)) => review.pr_number,
+            Some(
+                Command::List
+                | Command::Dashboard
+                | Command::Inbox
+                | Command::Compare { .. }
+                | Command::Completions { .. },
+            )
+            | None => None,
+        };
+        nested.or_else(|| self.target.as_deref()?.parse().ok())
     }
 
-    fn rebuild_unresolved(&mut self, preferred: Option<String>) {
-        let fallback = self
-            .unresolved_threads
-            .get(self.current_unresolved)
-            .map(|t| t.id.clone());
-        let target = preferred.or(fallback);
-        self.unresolved_threads = Self::build_unresolved(&self.active_threads);
-        if let Some(id) = target {
-            if let Some(pos) = self.unresolved_threads.iter().position(|t| t.id == id) {
-                self.current_unresolved = pos;
-                return;
-            }
+    fn file_target(&self) -> Option<FileTarget> {
+        if let Some(path) = &self.file {
+            return Some(FileTarget::new(path, self.line));
         }
-        if self.unresolved_threads.is_empty() {
-            self.current_unresolved = 0;
-        } else if self.current_unresolved >= self.unresolved_threads.len() {
-            self.current_unresolved = self.unresolved_threads.len() - 1;
+        let target = self.target.as_deref()?;
+        if target.parse::<u64>().is_ok() {
+            return None;
         }
+        Some(match target.rsplit_once(':') {
+            Some((path, line)) if line.parse::<i64>().is_ok() => {
+                FileTarget::new(path, line.parse().ok())
+            }
+            _ => FileTarget::new(target, None),
+        })
     }
 
-    async fn run(&mut self) -> Result<()> {
-        let mut terminal = TerminalSession::enter()?;
-        let mut needs_render = true;
-        loop {
-            if needs_render {
-                self.render()?;
-                needs_render = false;
-            }
-            match event::read()? {
-                Event::Key(key) => {
-                    if key.code == KeyCode::Char('c')
-                        && key.modifiers.contains(KeyModifiers::CONTROL)
-                    {
-                        break;
-                    }
-                    match key.code {
-                        KeyCode::Char('q') if key.modifiers.is_empty() => break,
-                        KeyCode::Char('j') if key.modifiers.is_empty() => {
-                            self.next_thread();
-                            needs_render = true;
-                        }
-                        KeyCode::Char('k') if key.modifiers.is_empty() => {
-                            self.prev_thread();
-                            needs_render = true;
-                        }
-                        KeyCode::Right if key.modifiers.is_empty() => {
-                            self.next_thread();
-                            needs_render = true;
-                        }
-                        KeyCode::Left if key.modifiers.is_empty() => {
-                            self.prev_thread();
-                            needs_render = true;
-                        }
-                        KeyCode::Down if key.modifiers.is_empty() => {
-                            self.scroll_down(1);
-                            needs_render = true;
-                        }
-                        KeyCode::PageDown if key.modifiers.is_empty() => {
-                            self.scroll_page_down();
-                            needs_render = true;
-                        }
-                        KeyCode::Up if key.modifiers.is_empty() => {
-                            self.scroll_up(1);
-                            needs_render = true;
-                        }
-                        KeyCode::PageUp if key.modifiers.is_empty() => {
-                            self.scroll_page_up();
-                            needs_render = true;
-                        }
-                        KeyCode::Home if key.modifiers.is_empty() => {
-                            self.scroll_to_top();
-                            needs_render = true;
-                        }
-                        KeyCode::End if key.modifiers.is_empty() => {
-                            self.scroll_to_bottom();
-                            needs_render = true;
-                        }
-                        KeyCode::Tab if key.modifiers.is_empty() => {
-                            self.advance_view();
-                            needs_render = true;
-                        }
-                        KeyCode::Char('s') if key.modifiers.is_empty() => {
-                            let action = match self.view {
-                                ThreadView::Unresolved | ThreadView::Active => "skip",
-                                ThreadView::Skipped => "unskip",
-                            };
-                            if let Err(err) = self.skip_current() {
-                                self.status_line =
-                                    Some(format!("Failed to {action} thread: {err}"));
-                            }
-                            needs_render = true;
-                        }
-                        KeyCode::Char('r') if key.modifiers.is_empty() => {
-                            if let Err(err) = self.reply_to_current(&mut terminal).await {
-                                self.status_line = Some(format!("Failed to post reply: {err}"));
-                            }
-                            needs_render = true;
-                        }
-                        KeyCode::Char('p') if key.modifiers.is_empty() => {
-                            if let Err(err) = self.publish_queue().await {
-                                self.status_line =
-                                    Some(format!("Failed to publish replies: {err}"));
-                            }
-                            needs_render = true;
-                        }
-                        KeyCode::Char('d') if key.modifiers.is_empty() => {
-                            self.toggle_diff();
-                            needs_render = true;
-                        }
-                        _ => {}
-                    }
-                }
-                Event::Mouse(me) => match me.kind {
-                    MouseEventKind::ScrollUp => {
-                        self.scroll_up(3);
-                        needs_render = true;
-                    }
-                    MouseEventKind::ScrollDown => {
-                        self.scroll_down(3);
-                        needs_render = true;
-                    }
-                    _ => {}
-                },
-                Event::Resize(_, _) => needs_render = true,
-                _ => {}
-            }
-        }
-        Ok(())
+    /// Whether this run opens the TUI, the only place first-run setup is
+    /// offered.
+    fn wants_setup(&self, mode: &Mode) -> bool {
+        matches!(mode, Mode::Tui)
+            && !self.plain
+            && matches!(
+                self.command,
+                None | Some(
+                    Command::List
+                        | Command::Dashboard
+                        | Command::Inbox
+                        | Command::Compare { .. }
+                        | Command::Threads { .. }
+                )
+            )
+            && io::stdin().is_terminal()
+            && stdout().is_terminal()
     }
 
-    fn render(&mut self) -> Result<()> {
-        let mut out = stdout();
-        execute!(out, MoveTo(0, 0), Clear(ClearType::All))?;
-        let view = self.render_view();
-        let lines: Vec<&str> = view.lines().collect();
-        let (_, height) = size()?;
-        let viewport = height as usize;
-        if viewport == 0 {
-            return Ok(());
+    fn apply_overrides(&self, config: &mut Config) {
+        if let Some(editor) = &self.editor {
+            config.editor = Some(editor.clone());
         }
-        let max_offset = lines.len().saturating_sub(viewport);
-        if self.scroll_offset > max_offset {
-            self.scroll_offset = max_offset;
+        if self.read_only {
+            config.read_only = true;
         }
-        for (row, line) in lines
-            .iter()
-            .skip(self.scroll_offset)
-            .take(viewport)
-            .enumerate()
-        {
-            let y = row as u16;
-            execute!(out, MoveTo(0, y))?;
-            out.write_all(line.as_bytes())?;
+        if self.plain {
+            config.plain = true;
+        }
+        if self.watch {
+            config.watch.enabled = true;
+        }
+        if self.hide_diff {
+            config.diff.show = false;
+        }
+        if let Some(interval) = self.refresh_interval {
+            config.refresh_interval = interval;
         }
-        out.flush()?;
-        Ok(())
     }
+}
 
-    fn render_view(&self) -> String {
-        let mut buf = String::new();
-        self.write_view(&mut buf)
-            .expect("writing to a string should not fail");
-        buf
+/// A location given on the command line to open the review at.
+struct FileTarget {
+    path: String,
+    line: Option<i64>,
+}
+
+impl FileTarget {
+    fn new(path: &str, line: Option<i64>) -> Self {
+        Self {
+            path: path.trim_start_matches("./").to_string(),
+            line,
+        }
     }
 
-    fn write_view(&self, buf: &mut String) -> std::fmt::Result {
-        let now = Utc::now();
-        let threads = self.current_threads();
-        if threads.is_empty() {
-            writeln!(
-                buf,
-                "{}",
-                format!(
-                    "PR #{} – No {} threads to display.",
-                    self.pr_number,
-                    self.view.name()
-                )
-                .bold()
-            )?;
-            let hint = match self.view {
-                ThreadView::Unresolved => {
-                    "Press tab to view all unskipped threads or skipped threads. Press q to exit."
-                }
-                ThreadView::Active => "Press tab to view skipped threads or q to exit.",
-                ThreadView::Skipped => "Press tab to return to unresolved threads or q to exit.",
-            };
-            writeln!(buf, "{}", hint.with(Color::DarkGrey))?;
-        } else {
-            let current_index = self.current_index();
-            let thread = &threads[current_index];
-            let muted = Color::Rgb {
-                r: 110,
-                g: 110,
-                b: 110,
-            };
-            let accent = Color::Rgb {
-                r: 180,
-                g: 180,
-                b: 180,
-            };
-            writeln!(
-                buf,
-                "{}   {}",
-                format!(
-                    "Thread {}/{} ({})",
-                    current_index + 1,
-                    threads.len(),
-                    self.view.name()
-                )
-                .with(muted)
-                .bold(),
-                format!("PR #{}", self.pr_number).with(muted)
-            )?;
-            writeln!(
-                buf,
-                "{}  {}  {}",
-                thread.display_path().as_str().with(accent),
-                if thread.is_resolved {
-                    "resolved".with(Color::DarkGreen)
-                } else {
-                    "unresolved".with(Color::DarkYellow)
-                },
-                humanize_relative(now, thread.created_at).with(muted)
-            )?;
-            writeln!(buf)?;
-            if let Some(diff) = &thread.diff_hunk {
-                if self.show_diff {
-                    let diff_lines: Vec<String> = diff
-                        .lines()
-                        .map(|line| {
-                            let styled_line = match line.chars().next() {
-                                Some('+') => line.with(Color::DarkGreen),
-                                Some('-') => line.with(Color::DarkRed),
-                                Some('@') => line.with(Color::DarkGrey),
-                                _ => line.with(Color::Grey),
-                            };
-                            styled_line.to_string()
-                        })
-                        .collect();
-                    render_block(buf, &diff_lines)?;
-                    writeln!(buf)?;
+    /// Paths given relative to somewhere above the repository root, or
+    /// absolute, still match the repo-relative path of a thread.
+    fn matches(&self, path: &str) -> bool {
+        self.path == path
+            || self
+                .path
+                .strip_suffix(path)
+                .is_some_and(|prefix| prefix.ends_with('/'))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Tui,
+    Dump,
+    Json,
+    Plain,
+}
+
+const COMMENT_WRAP: usize = 80;
+/// What can be done in plain mode, where nothing is drawn: moving between
+/// threads and views, replying and reviewing, and toggles that change what
+/// is read out.
+const PLAIN_ACTIONS: [Action; 23] = [
+    Action::NextThread,
+    Action::PrevThread,
+    Action::NextView,
+    Action::ToggleSkip,
+    Action::Reply,
+    Action::Publish,
+    Action::ToggleDiff,
+    Action::OpenBrowser,
+    Action::OpenEditor,
+    Action::Checkout,
+    Action::BaseFile,
+    Action::FilterFiles,
+    Action::CycleSort,
+    Action::Approve,
+    Action::RequestChanges,
+    Action::ToggleHidden,
+    Action::EditDescription,
+    Action::MarkRead,
+    Action::ToggleTimes,
+    Action::TogglePin,
+    Action::ResolveAll,
+    Action::Undo,
+    Action::Redo,
+];
+/// How often a streaming diff download redraws its progress.
+const DIFF_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// How often the event loop checks for finished background work.
+const PREFETCH_POLL: Duration = Duration::from_millis(50);
+/// How often an idle view is redrawn so relative times stay current.
+const CLOCK_TICK: Duration = Duration::from_secs(30);
+/// The largest count a motion takes; each step is performed in turn.
+const MAX_COUNT: usize = 9999;
+
+struct App {
+    gh: GhCli,
+    repo: Repo,
+    pr_number: u64,
+    skip_store: SkipStore,
+    config: Config,
+    keymap: KeyMap,
+    theme: Theme,
+    links: Hyperlinks,
+    active_threads: Vec<Thread>,
+    unresolved_threads: Vec<Thread>,
+    skipped_threads: Vec<Thread>,
+    current_unresolved: usize,
+    current_active: usize,
+    current_skipped: usize,
+    view: ThreadView,
+    status_line: Option<String>,
+    scroll_offset: usize,
+    queued_replies: VecDeque<QueuedReply>,
+    /// Draft comments in your pending review on GitHub, published with it.
+    pending_drafts: usize,
+    /// The gh account requests are made as, and its permission on the
+    /// repository, such as `WRITE` or `READ`.
+    account: Option<String>,
+    permission: Option<String>,
+    show_diff: bool,
+    pending_count: Option<usize>,
+    sections: Vec<usize>,
+    hits: Vec<Hit>,
+    rendered_lines: usize,
+    frame: Frame,
+    crop_buf: String,
+    screen: Buffer,
+    selection: Option<Selection>,
+    pending_prefix: Option<Action>,
+    marks: HashMap<char, Location>,
+    prefs: RepoPrefs,
+    /// Changed files `.gitattributes` marks as generated, and lockfiles.
+    generated: HashSet<String>,
+    /// The PR's size without its generated files.
+    size: Option<size::Estimate>,
+    /// Issue keys found in the PR's title, branch and body, with their URLs.
+    issue_keys: Vec<(String, String)>,
+    /// Recorded actions by register.
+    macros: HashMap<char, Vec<MacroStep>>,
+    /// The register being recorded into and the actions so far.
+    recording: Option<(char, Vec<MacroStep>)>,
+    jumps: Vec<Location>,
+    jump_index: usize,
+    dragging_scrollbar: bool,
+    watch: Option<WatchState>,
+    worktree: Option<PathBuf>,
+    /// Disk cache for renderer output, when `diff.cache` is set.
+    render_cache: Option<diff::RenderCache>,
+    /// Hunks coloured by the configured diff renderer, by thread id; `None`
+    /// records a failure so the built-in colouring is used instead.
+    external_diffs: HashMap<String, Option<Vec<String>>>,
+    show_blame: bool,
+    /// Whether unchanged blocks inside hunks are folded to one line.
+    fold_unchanged: bool,
+    /// Per-line blame annotations for each thread's hunk, by thread id;
+    /// `None` when blame failed.
+    blames: HashMap<String, Option<Vec<String>>>,
+    show_perf: bool,
+    perf: perf::Stats,
+    prefetcher: Prefetcher,
+    /// Every PR open in this session, in the order they were opened.
+    open_prs: Vec<u64>,
+    /// Set when the user asks to leave this PR for another.
+    exit: Option<Exit>,
+    /// Open PRs stacked with this one, bottom first; empty when unknown.
+    stack: Vec<u64>,
+    /// Files changed since the viewer's last review, while only threads on
+    /// them are shown.
+    changed_since_review: Option<HashSet<String>>,
+    /// Threads left out of every view by that filter.
+    hidden_threads: Vec<Thread>,
+    /// The PR's most recent force-push, if it has had one.
+    force_push: Option<ForcePush>,
+    /// The PR's commits with their signature status.
+    commits: Vec<PrCommit>,
+    /// The base branch, when GitHub says the PR conflicts with it.
+    conflicting_base: Option<String>,
+    /// Only threads on files matching this are shown.
+    file_filter: Option<FileFilter>,
+    /// GitHub's change type and size for each file in the PR, once fetched.
+    pr_files: Option<HashMap<String, PrFile>>,
+    sort: ThreadSort,
+    authors: AuthorStyle,
+    /// A line being typed at the bottom of the screen.
+    prompt: Option<Prompt>,
+    /// The last code search, offered again when searching next.
+    last_search: String,
+    /// The base branch's CODEOWNERS, if it has one.
+    code_owners: Option<CodeOwners>,
+    /// Files the PR renames, keyed by their new path.
+    renames: HashMap<String, git::Rename>,
+    /// The commit the PR is based on, for showing files as they were.
+    base_oid: Option<String>,
+    /// The PR's latest commit, for showing files as they are now.
+    head_oid: Option<String>,
+    /// Submodule pointer changes that threads are on, keyed by path.
+    submodules: HashMap<String, SubmoduleBump>,
+    /// Review checklist items ticked so far.
+    checklist: HashSet<String>,
+    /// The view's threads grouped by file, while shown in place of the
+    /// selected thread.
+    outline: Option<Outline>,
+    /// Changes summed per top-level directory, while shown in place of the
+    /// selected thread.
+    review_map: Option<ReviewMap>,
+    /// The PR's top-level comments and reviews while they are shown in
+    /// place of the threads.
+    conversation: Option<Vec<ConversationEntry>>,
+    /// Show the bodies of comments hidden on GitHub.
+    show_hidden: bool,
+    /// Comment being hidden while its reason is typed.
+    minimizing: Option<String>,
+    /// Local state as it was before each undoable change, newest last.
+    undo: Vec<Edit>,
+    /// Changes undone, to be redone newest last.
+    redo: Vec<Edit>,
+    /// The thread last marked viewed, so its file is marked again only
+    /// once another thread has been shown.
+    last_shown: Option<String>,
+    /// Scripted input and an in-memory screen in place of the terminal.
+    #[cfg(test)]
+    headless: Option<headless::Headless>,
+    /// Reading the review as plain lines rather than drawing it.
+    plain: bool,
+    /// A PR comment that failed to post, offered again next time.
+    comment_draft: Option<String>,
+    /// Threads to resolve once the bulk resolve is confirmed.
+    resolving: Vec<String>,
+    /// Reviewers to ask again once the re-request is confirmed.
+    rerequesting: Vec<String>,
+    /// The PR was opened by the account, so its threads are feedback to
+    /// address rather than review to give.
+    own_pr: bool,
+    /// Show each thread's hunk from GitHub's test merge commit, what will
+    /// land on the base, instead of the head diff.
+    show_merge_diff: bool,
+    /// The merge commit's hunks by file, fetched when first shown.
+    merge_diff: Option<HashMap<String, Vec<String>>>,
+    /// Review being dismissed while its message is typed: id and reviewer.
+    dismissing: Option<(u64, String)>,
+    /// Selected checklist item while the checklist is shown.
+    checklist_cursor: Option<usize>,
+    /// Files whose threads have been shown, for the progress pane.
+    viewed_files: HashSet<String>,
+    /// Newest comment shown in each thread, by thread id.
+    seen: HashMap<String, DateTime<Utc>>,
+    /// `seen` as of the previous visit; comments after it are marked new.
+    seen_before: HashMap<String, DateTime<Utc>>,
+    /// Show comment times in the local timezone rather than relative.
+    absolute_times: bool,
+    /// Threads sorted to the top of every view, by id.
+    pinned: HashSet<String>,
+    show_progress: bool,
+    /// The ref range shown instead of a PR, by `gh cr compare`.
+    compare: Option<String>,
+    /// Notes kept to yourself, never sent to GitHub.
+    notes: Vec<Note>,
+    /// Check-run annotations on the PR's latest commit.
+    annotations: Vec<Annotation>,
+    /// Show annotations' full messages below each hunk rather than one line
+    /// each.
+    expand_annotations: bool,
+    /// Dependencies the PR changes, with the advisories that fixes or
+    /// brings in.
+    bumps: Vec<advisory::Bump>,
+    /// Set when `spell.enabled`; dropped if the checker can't run.
+    speller: Option<spell::Speller>,
+    /// Words flagged in queued replies and notes, with their suggestions.
+    misspelled: HashMap<String, Vec<String>>,
+    /// Checks still running, each marked with whether to announce what it
+    /// finds.
+    spell_checks: Vec<(bool, JoinHandle<Result<Vec<spell::Misspelling>>>)>,
+    /// When the threads were last fetched successfully.
+    refreshed_at: DateTime<Utc>,
+    /// Why the latest background refresh failed, until one succeeds.
+    refresh_error: Option<String>,
+    /// GitHub's GraphQL quota as of the last refresh.
+    rate_limit: Option<RateLimit>,
+}
+
+/// Text input for an action that needs more than a key, submitted with
+/// enter and abandoned with esc.
+struct Prompt {
+    action: Action,
+    label: String,
+    text: String,
+}
+
+/// A rewrite of the PR branch, from its timeline.
+struct ForcePush {
+    /// Branch the PR merges into.
+    base: String,
+    before: String,
+    after: String,
+    actor: Option<String>,
+    at: DateTime<Utc>,
+}
+
+/// A commit on the PR branch and whether GitHub verified its signature.
+struct PrCommit {
+    sha: String,
+    subject: String,
+    author: String,
+    verified: bool,
+    /// GitHub's reason for the verdict, such as `valid`, `unsigned` or
+    /// `unknown_key`.
+    reason: String,
+    /// `GPG` or `SSH`, for signed commits.
+    signature: Option<String>,
+    /// The combined result of the commit's checks and statuses, if it has
+    /// any.
+    checks: Option<CheckState>,
+}
+
+/// Why [`App::run`] returned.
+enum Exit {
+    Quit,
+    /// Switch to the PR at this index of the open list.
+    Switch(usize),
+    /// Choose another open PR from a picker.
+    Pick,
+    /// Open this PR, loading it if it isn't open yet.
+    Open(u64),
+}
+
+/// What watch mode last saw, compared against each poll to report changes.
+struct WatchState {
+    head: Option<String>,
+    comments: usize,
+    checks: HashMap<String, CheckState>,
+}
+
+/// A scroll position within a thread, used by marks and the jump list.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Location {
+    thread_id: String,
+    line: usize,
+}
+
+/// An action in a macro, with the letter that finished it for actions that
+/// wait for one, such as `set-mark`.
+type MacroStep = (Action, Option<char>);
+
+/// A rendered view plus the line offsets where each block starts, used for
+/// hunk jumps, and the clickable regions within it.
+#[derive(Default)]
+struct Frame {
+    text: String,
+    /// Byte range of each line of `text`, without its newline.
+    lines: Vec<Range<usize>>,
+    sections: Vec<usize>,
+    hits: Vec<Hit>,
+    heat: Vec<(usize, Heat)>,
+    /// The new-file line number shown on each content line of the hunk.
+    new_lines: Vec<(usize, usize)>,
+    /// Runs of hunk rows left out of `text` because they're out of view,
+    /// as the row each starts at and how many rows it spans. They count
+    /// towards the length and read as empty lines.
+    gaps: Vec<(usize, usize)>,
+}
+
+impl Frame {
+    /// Empties the frame, keeping its allocations for the next layout.
+    fn clear(&mut self) {
+        self.text.clear();
+        self.lines.clear();
+        self.sections.clear();
+        self.hits.clear();
+        self.heat.clear();
+        self.new_lines.clear();
+        self.gaps.clear();
+    }
+
+    fn index_lines(&mut self) {
+        let mut start = 0;
+        for (end, _) in self.text.match_indices('\n') {
+            self.lines.push(start..end);
+            start = end + 1;
+        }
+        if start < self.text.len() {
+            self.lines.push(start..self.text.len());
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.lines.len() + self.gaps.iter().map(|(_, rows)| rows).sum::<usize>()
+    }
+
+    fn line(&self, index: usize) -> &str {
+        let mut skipped = 0;
+        for &(start, rows) in &self.gaps {
+            if index < start {
+                break;
+            }
+            if index < start + rows {
+                return "";
+            }
+            skipped += rows;
+        }
+        &self.text[self.lines[index - skipped].clone()]
+    }
+}
+
+/// What a content line contributes to the minimap, in increasing priority.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Heat {
+    Addition,
+    Deletion,
+    Comment,
+}
+
+impl Heat {
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            Heat::Addition => theme.diff_added,
+            Heat::Deletion => theme.diff_removed,
+            Heat::Comment => theme.author,
+        }
+    }
+}
+
+/// Selected content lines, from a mouse drag or keyboard visual mode.
+#[derive(Clone, Copy)]
+struct Selection {
+    anchor: usize,
+    cursor: usize,
+    visual: bool,
+}
+
+impl Selection {
+    fn lines(&self) -> RangeInclusive<usize> {
+        self.anchor.min(self.cursor)..=self.anchor.max(self.cursor)
+    }
+}
+
+/// A clickable span of the rendered view, in content (not screen) lines.
+struct Hit {
+    line: usize,
+    columns: Range<usize>,
+    action: Action,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ThreadView {
+    Unresolved,
+    Active,
+    Skipped,
+}
+
+impl ThreadView {
+    fn next(self) -> Self {
+        match self {
+            ThreadView::Unresolved => ThreadView::Active,
+            ThreadView::Active => ThreadView::Skipped,
+            ThreadView::Skipped => ThreadView::Unresolved,
+        }
+    }
+
+    fn skip_action_label(self) -> &'static str {
+        match self {
+            ThreadView::Unresolved | ThreadView::Active => "skip",
+            ThreadView::Skipped => "unskip",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ThreadView::Unresolved => "unresolved",
+            ThreadView::Active => "unskipped",
+            ThreadView::Skipped => "skipped",
+        }
+    }
+}
+
+impl App {
+    fn new(
+        gh: GhCli,
+        repo: Repo,
+        pr_number: u64,
+        skip_store: SkipStore,
+        config: Config,
+        threads: Vec<Thread>,
+    ) -> Result<Self> {
+        let mut keymap = KeyMap::from_config(&config.keys).context("invalid key bindings")?;
+        if config.read_only {
+            keymap.unbind(Action::mutates);
+        }
+        let theme = Theme::from_config(&config).context("invalid theme")?;
+        let links = Hyperlinks::new(config.hyperlinks);
+        let (active_threads, skipped_threads) = Self::partition_threads(&skip_store, threads);
+        let unresolved_threads = Self::build_unresolved(&active_threads);
+        let show_diff = config.diff.show;
+        let sort = config.sort;
+        let authors = config.authors;
+        let plain = config.plain;
+        let render_cache = if config.diff.cache {
+            match diff::RenderCache::open() {
+                Ok(cache) => Some(cache),
+                Err(err) => {
+                    log::warn!("diff render cache disabled: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let speller = config
+            .spell
+            .enabled
+            .then(|| spell::Speller::new(&config.spell));
+        Ok(Self {
+            gh,
+            repo,
+            pr_number,
+            skip_store,
+            config,
+            keymap,
+            theme,
+            links,
+            active_threads,
+            unresolved_threads,
+            skipped_threads,
+            current_unresolved: 0,
+            current_active: 0,
+            current_skipped: 0,
+            view: ThreadView::Unresolved,
+            status_line: None,
+            scroll_offset: 0,
+            queued_replies: VecDeque::new(),
+            pending_drafts: 0,
+            account: None,
+            permission: None,
+            show_diff,
+            pending_count: None,
+            sections: Vec::new(),
+            hits: Vec::new(),
+            rendered_lines: 0,
+            frame: Frame::default(),
+            crop_buf: String::new(),
+            screen: Buffer::default(),
+            selection: None,
+            pending_prefix: None,
+            marks: HashMap::new(),
+            prefs: RepoPrefs::default(),
+            generated: HashSet::new(),
+            size: None,
+            issue_keys: Vec::new(),
+            macros: HashMap::new(),
+            recording: None,
+            jumps: Vec::new(),
+            jump_index: 0,
+            dragging_scrollbar: false,
+            watch: None,
+            worktree: None,
+            render_cache,
+            external_diffs: HashMap::new(),
+            show_blame: false,
+            fold_unchanged: false,
+            blames: HashMap::new(),
+            show_perf: false,
+            perf: perf::Stats::default(),
+            prefetcher: Prefetcher::new(),
+            open_prs: vec![pr_number],
+            exit: None,
+            stack: Vec::new(),
+            changed_since_review: None,
+            hidden_threads: Vec::new(),
+            force_push: None,
+            commits: Vec::new(),
+            conflicting_base: None,
+            file_filter: None,
+            pr_files: None,
+            sort,
+            authors,
+            prompt: None,
+            last_search: String::new(),
+            code_owners: None,
+            renames: HashMap::new(),
+            base_oid: None,
+            head_oid: None,
+            submodules: HashMap::new(),
+            checklist: HashSet::new(),
+            checklist_cursor: None,
+            dismissing: None,
+            show_hidden: false,
+            conversation: None,
+            outline: None,
+            review_map: None,
+            minimizing: None,
+            resolving: Vec::new(),
+            rerequesting: Vec::new(),
+            own_pr: false,
+            show_merge_diff: false,
+            merge_diff: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_shown: None,
+            #[cfg(test)]
+            headless: None,
+            plain,
+            comment_draft: None,
+            viewed_files: HashSet::new(),
+            seen: HashMap::new(),
+            seen_before: HashMap::new(),
+            absolute_times: false,
+            pinned: HashSet::new(),
+            show_progress: false,
+            compare: None,
+            notes: Vec::new(),
+            annotations: Vec::new(),
+            expand_annotations: false,
+            bumps: Vec::new(),
+            speller,
+            misspelled: HashMap::new(),
+            spell_checks: Vec::new(),
+            refreshed_at: Utc::now(),
+            refresh_error: None,
+            rate_limit: None,
+        })
+    }
+
+    /// What is being reviewed: `PR #12`, or the range being compared.
+    fn subject(&self) -> String {
+        match &self.compare {
+            Some(range) => range.clone(),
+            None => format!("PR #{}", self.pr_number),
+        }
+    }
+
+    fn partition_threads(
+        skip_store: &SkipStore,
+        threads: Vec<Thread>,
+    ) -> (Vec<Thread>, Vec<Thread>) {
+        let mut active = Vec::new();
+        let mut skipped = Vec::new();
+        for thread in threads {
+            if skip_store.contains(&thread.id) {
+                skipped.push(thread);
+            } else {
+                active.push(thread);
+            }
+        }
+        Self::sort_threads(&mut active);
+        Self::sort_threads(&mut skipped);
+        (active, skipped)
+    }
+
+    fn sort_threads(list: &mut [Thread]) {
+        list.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    }
+
+    /// Applies the chosen sort on top of [`Self::sort_threads`]. The sorts
+    /// are stable, so threads on one file keep their usual order.
+    fn order_threads(&self, list: &mut [Thread]) {
+        Self::sort_threads(list);
+        match self.sort {
+            ThreadSort::Created => {}
+            ThreadSort::Path => list.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line))),
+            ThreadSort::Changes => {
+                let changes = |thread: &Thread| {
+                    self.pr_files
+                        .as_ref()
+                        .and_then(|files| files.get(&thread.path))
+                        .map_or(0, |file| file.changes)
+                };
+                list.sort_by(|a, b| {
+                    changes(b)
+                        .cmp(&changes(a))
+                        .then_with(|| a.path.cmp(&b.path))
+                });
+            }
+            ThreadSort::Comments => {
+                let mut comments: HashMap<String, usize> = HashMap::new();
+                for thread in list.iter() {
+                    *comments.entry(thread.path.clone()).or_default() += thread.comments.len();
+                }
+                list.sort_by(|a, b| {
+                    comments[&b.path]
+                        .cmp(&comments[&a.path])
+                        .then_with(|| a.path.cmp(&b.path))
+                });
+            }
+        }
+        // Stable, so pinned threads keep the order chosen above.
+        list.sort_by_key(|thread| !self.pinned.contains(&thread.id));
+    }
+
+    fn build_unresolved(active: &[Thread]) -> Vec<Thread> {
+        active
+            .iter()
+            .filter(|thread| !thread.is_resolved)
+            .cloned()
+            .collect()
+    }
+
+    fn threads_for_view(&self, view: ThreadView) -> &Vec<Thread> {
+        match view {
+            ThreadView::Unresolved => &self.unresolved_threads,
+            ThreadView::Active => &self.active_threads,
+            ThreadView::Skipped => &self.skipped_threads,
+        }
+    }
+
+    fn threads_for_view_mut(&mut self, view: ThreadView) -> &mut Vec<Thread> {
+        match view {
+            ThreadView::Unresolved => &mut self.unresolved_threads,
+            ThreadView::Active => &mut self.active_threads,
+            ThreadView::Skipped => &mut self.skipped_threads,
+        }
+    }
+
+    fn index_for_view(&self, view: ThreadView) -> usize {
+        match view {
+            ThreadView::Unresolved => self.current_unresolved,
+            ThreadView::Active => self.current_active,
+            ThreadView::Skipped => self.current_skipped,
+        }
+    }
+
+    fn index_for_view_mut(&mut self, view: ThreadView) -> &mut usize {
+        match view {
+            ThreadView::Unresolved => &mut self.current_unresolved,
+            ThreadView::Active => &mut self.current_active,
+            ThreadView::Skipped => &mut self.current_skipped,
+        }
+    }
+
+    fn current_threads(&self) -> &Vec<Thread> {
+        self.threads_for_view(self.view)
+    }
+
+    fn current_threads_mut(&mut self) -> &mut Vec<Thread> {
+        self.threads_for_view_mut(self.view)
+    }
+
+    fn current_index(&self) -> usize {
+        self.index_for_view(self.view)
+    }
+
+    fn current_index_mut(&mut self) -> &mut usize {
+        self.index_for_view_mut(self.view)
+    }
+
+    fn current_thread(&self) -> Option<&Thread> {
+        self.current_threads().get(self.current_index())
+    }
+
+    fn clamp_index_for_view(&mut self, view: ThreadView) {
+        let len = self.threads_for_view(view).len();
+        let idx = self.index_for_view_mut(view);
+        if len == 0 {
+            *idx = 0;
+        } else if *idx >= len {
+            *idx = len - 1;
+        }
+    }
+
+    fn clamp_current_index(&mut self) {
+        self.clamp_index_for_view(self.view);
+    }
+
+    fn advance_view(&mut self) {
+        self.view = self.view.next();
+        self.clamp_current_index();
+        self.reset_scroll();
+        self.clear_status();
+    }
+
+    fn session(&self) -> Session {
+        let selected = [
+            ThreadView::Unresolved,
+            ThreadView::Active,
+            ThreadView::Skipped,
+        ]
+        .into_iter()
+        .filter_map(|view| {
+            let thread = self.threads_for_view(view).get(self.index_for_view(view))?;
+            Some((view, thread.id.clone()))
+        })
+        .collect();
+        Session {
+            view: Some(self.view),
+            selected,
+            scroll_offset: self.scroll_offset,
+            show_diff: Some(self.show_diff),
+            queued_replies: self.queued_replies.iter().cloned().collect(),
+            marks: self.marks.clone(),
+            worktree: self.worktree.clone(),
+            checklist: self.checklist.iter().cloned().collect(),
+            viewed_files: self.viewed_files.iter().cloned().collect(),
+            seen: self.seen.clone(),
+            pinned: self.pinned.iter().cloned().collect(),
+            comment_draft: self.comment_draft.clone(),
+            notes: self.notes.clone(),
+        }
+    }
+
+    /// Reselects the threads from a previous run that still exist; the
+    /// scroll position only carries over if the same thread comes back up.
+    fn restore_session(&mut self, session: Session) {
+        if !session.pinned.is_empty() {
+            self.pinned = session.pinned.into_iter().collect();
+            self.reorder_threads();
+        }
+        for (view, id) in &session.selected {
+            if let Some(pos) = self
+                .threads_for_view(*view)
+                .iter()
+                .position(|thread| &thread.id == id)
+            {
+                *self.index_for_view_mut(*view) = pos;
+            }
+        }
+        if let Some(view) = session.view {
+            self.view = view;
+        }
+        let current = self.current_thread().map(|thread| &thread.id);
+        if current.is_some() && current == session.selected.get(&self.view) {
+            self.scroll_offset = session.scroll_offset;
+        }
+        if let Some(show_diff) = session.show_diff {
+            self.show_diff = show_diff;
+        }
+        self.queued_replies = session.queued_replies.into();
+        self.marks = session.marks;
+        self.worktree = session.worktree.filter(|path| path.exists());
+        self.checklist = session.checklist.into_iter().collect();
+        self.viewed_files = session.viewed_files.into_iter().collect();
+        self.comment_draft = session.comment_draft;
+        self.seen_before = session.seen.clone();
+        self.seen = session.seen;
+        self.notes = session.notes;
+        let written: Vec<String> = self
+            .queued_replies
+            .iter()
+            .map(|reply| reply.body.clone())
+            .chain(self.notes.iter().map(|note| note.body.clone()))
+            .collect();
+        if !written.is_empty() {
+            self.check_spelling(&written.join("\n"), false);
+        }
+    }
+
+    fn rebuild_unresolved(&mut self, preferred: Option<String>) {
+        let fallback = self
+            .unresolved_threads
+            .get(self.current_unresolved)
+            .map(|t| t.id.clone());
+        let target = preferred.or(fallback);
+        self.unresolved_threads = Self::build_unresolved(&self.active_threads);
+        if let Some(id) = target
+            && let Some(pos) = self.unresolved_threads.iter().position(|t| t.id == id)
+        {
+            self.current_unresolved = pos;
+            return;
+        }
+        if self.unresolved_threads.is_empty() {
+            self.current_unresolved = 0;
+        } else if self.current_unresolved >= self.unresolved_threads.len() {
+            self.current_unresolved = self.unresolved_threads.len() - 1;
+        }
+    }
+
+    /// Runs the review until the user quits or switches to another PR.
+    async fn run(&mut self, terminal: &mut TerminalSession) -> Result<Exit> {
+        let mut needs_render = true;
+        let refresh_every = self.refresh_every();
+        if self.config.watch.enabled {
+            match self.watch_snapshot().await {
+                Ok(state) => self.watch = Some(state),
+                Err(err) => self.report_failure(format!("Failed to start watching: {err}")),
+            }
+        }
+        let mut last_refresh = Instant::now();
+        loop {
+            if let Some(exit) = self.exit.take() {
+                return Ok(exit);
+            }
+            if self.apply_prefetched() {
+                needs_render = true;
+            }
+            self.mark_viewed();
+            if needs_render {
+                self.render()?;
+                needs_render = false;
+            }
+            // Wake up regularly to show renderer and blame output as it
+            // arrives, ahead of any refresh timer.
+            if (!self.prefetcher.is_idle() || !self.spell_checks.is_empty())
+                && !self.poll_event(PREFETCH_POLL)?
+            {
+                continue;
+            }
+            let wait = refresh_every.map_or(CLOCK_TICK, |every| {
+                every.saturating_sub(last_refresh.elapsed()).min(CLOCK_TICK)
+            });
+            if !self.poll_event(wait)? {
+                if let Some(every) = refresh_every
+                    && last_refresh.elapsed() >= every
+                {
+                    if let Err(err) = self.refresh_threads().await {
+                        self.refresh_error = Some(format!("{err:#}"));
+                        self.report_failure(format!("Failed to refresh threads: {err}"));
+                    } else if let Err(err) = self.poll_changes().await {
+                        self.report_failure(format!("Failed to check for changes: {err}"));
+                    }
+                    self.load_rate_limit().await;
+                    last_refresh = Instant::now();
+                }
+                needs_render = true;
+                continue;
+            }
+            let event = self.read_event()?;
+            self.perf.record_event();
+            match event {
+                Event::Key(key) if key.kind == KeyEventKind::Release => {}
+                Event::Key(key) => {
+                    if self.prompt.is_some() {
+                        self.prompt_key(terminal, &key).await;
+                        needs_render = true;
+                        continue;
+                    }
+                    if self.review_map.is_some() && self.review_map_key(&key) {
+                        needs_render = true;
+                        continue;
+                    }
+                    if self.outline.is_some() && self.outline_key(&key) {
+                        needs_render = true;
+                        continue;
+                    }
+                    if self.checklist_cursor.is_some() && self.checklist_key(&key) {
+                        needs_render = true;
+                        continue;
+                    }
+                    if let Some(action) = self.pending_prefix.take() {
+                        if let KeyCode::Char(name) = key.code
+                            && !key.modifiers.contains(KeyModifiers::CONTROL)
+                        {
+                            if action == Action::PlayMacro {
+                                self.play_macro(name, terminal).await;
+                            } else if action == Action::AddNote {
+                                self.record_step(action, Some(name));
+                                self.note(name, terminal);
+                            } else {
+                                self.record_step(action, Some(name));
+                                self.finish_prefix(action, name);
+                            }
+                        } else {
+                            self.clear_status();
+                        }
+                        needs_render = true;
+                        continue;
+                    }
+                    if let Some(digit) = self.keymap.count_digit(&key, self.pending_count.is_some())
+                    {
+                        let count = self.pending_count.unwrap_or(0);
+                        self.pending_count = Some((count * 10 + digit).min(MAX_COUNT));
+                        continue;
+                    }
+                    let count = self.pending_count.take().unwrap_or(1);
+                    match self.keymap.action_for(self.view, &key) {
+                        Some(Action::Quit) => return Ok(Exit::Quit),
+                        Some(action) if action.takes_count() => {
+                            for _ in 0..count {
+                                self.record_step(action, None);
+                                self.perform(action, terminal).await;
+                            }
+                            needs_render = true;
+                        }
+                        Some(action) => {
+                            self.record_step(action, None);
+                            self.perform(action, terminal).await;
+                            needs_render = true;
+                        }
+                        None => {}
+                    }
+                }
+                Event::Mouse(me) => match me.kind {
+                    MouseEventKind::ScrollUp => {
+                        self.scroll_up(self.config.scroll.wheel);
+                        needs_render = true;
+                    }
+                    MouseEventKind::ScrollDown => {
+                        self.scroll_down(self.config.scroll.wheel);
+                        needs_render = true;
+                    }
+                    MouseEventKind::Down(MouseButton::Left) if self.on_scrollbar(me.column) => {
+                        self.dragging_scrollbar = true;
+                        self.scroll_to_row(me.row);
+                        needs_render = true;
+                    }
+                    MouseEventKind::Drag(MouseButton::Left) if self.dragging_scrollbar => {
+                        self.scroll_to_row(me.row);
+                        needs_render = true;
+                    }
+                    MouseEventKind::Up(MouseButton::Left) if self.dragging_scrollbar => {
+                        self.dragging_scrollbar = false;
+                    }
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        match self.hit_test(me.column, me.row) {
+                            Some(Action::Quit) => return Ok(Exit::Quit),
+                            Some(action) => self.perform(action, terminal).await,
+                            None => self.start_drag(me.row),
+                        }
+                        needs_render = true;
+                    }
+                    MouseEventKind::Drag(MouseButton::Left) => {
+                        self.extend_drag(me.row);
+                        needs_render = true;
+                    }
+                    MouseEventKind::Up(MouseButton::Left) if self.clicked_diff_line() => {
+                        self.selection = self.selection.map(|selection| Selection {
+                            visual: true,
+                            ..selection
+                        });
+                        self.perform(Action::Reply, terminal).await;
+                        self.selection = None;
+                        needs_render = true;
+                    }
+                    MouseEventKind::Up(MouseButton::Left) => {
+                        self.finish_drag();
+                        needs_render = true;
+                    }
+                    _ => {}
+                },
+                Event::Resize(_, _) => needs_render = true,
+                _ => {}
+            }
+        }
+    }
+
+    async fn perform(&mut self, action: Action, terminal: &mut TerminalSession) {
+        match action {
+            Action::Quit => {}
+            Action::NextThread => {
+                self.record_jump();
+                self.next_thread();
+            }
+            Action::PrevThread => {
+                self.record_jump();
+                self.prev_thread();
+            }
+            Action::ScrollUp if self.in_visual_mode() => self.move_selection(-1),
+            Action::ScrollDown if self.in_visual_mode() => self.move_selection(1),
+            Action::ScrollUp => self.scroll_up(self.config.scroll.step),
+            Action::ScrollDown => self.scroll_down(self.config.scroll.step),
+            Action::PageUp => self.scroll_page_up(),
+            Action::PageDown => self.scroll_page_down(),
+            Action::HalfPageUp => self.scroll_up(self.viewport_height() / 2),
+            Action::HalfPageDown => self.scroll_down(self.viewport_height() / 2),
+            Action::ScrollTop => {
+                self.record_jump();
+                self.scroll_to_top();
+            }
+            Action::ScrollBottom => {
+                self.record_jump();
+                self.scroll_to_bottom();
+            }
+            Action::PrevHunk => {
+                self.record_jump();
+                self.prev_section();
+            }
+            Action::NextHunk => {
+                self.record_jump();
+                self.next_section();
+            }
+            Action::NextView => {
+                self.record_jump();
+                self.advance_view();
+            }
+            Action::ToggleSkip => {
+                let action = self.view.skip_action_label();
+                if let Err(err) = self.skip_current() {
+                    self.report_failure(format!("Failed to {action} thread: {err}"));
+                }
+            }
+            Action::Reply if self.conversation.is_some() => {
+                if let Err(err) = self.comment_on_pr(terminal).await {
+                    self.report_failure(format!("Failed to post comment: {err}"));
+                }
+            }
+            Action::Reply if self.in_visual_mode() => {
+                if let Err(err) = self.comment_on_selection(terminal).await {
+                    self.report_failure(format!("Failed to post comment: {err}"));
+                }
+            }
+            Action::Reply => {
+                if let Err(err) = self.reply_to_current(terminal).await {
+                    self.report_failure(format!("Failed to post reply: {err}"));
+                }
+            }
+            Action::EditDescription => {
+                if let Err(err) = self.edit_description(terminal).await {
+                    self.report_failure(format!("Failed to update PR description: {err}"));
+                }
+            }
+            Action::FinishReview => {
+                if let Err(err) = self.finish_review(terminal).await {
+                    self.report_failure(format!("Failed to submit your review: {err}"));
+                }
+            }
+            Action::EditPrefs => {
+                if let Err(err) = self.edit_prefs(terminal) {
+                    self.report_failure(format!("Failed to save preferences: {err}"));
+                }
+            }
+            Action::RequestReviewers => {
+                if let Err(err) = self.request_reviewers(terminal).await {
+                    self.report_failure(format!("Failed to request reviewers: {err}"));
+                }
+            }
+            Action::MyReviews => {
+                if let Err(err) = self.show_review_history(terminal).await {
+                    self.report_failure(format!("Failed to show your reviews: {err}"));
+                }
+            }
+            Action::Todo => {
+                if let Err(err) = self.show_todo(terminal) {
+                    self.report_failure(format!("Failed to show the to-do list: {err}"));
+                }
+            }
+            Action::MarkAddressed => {
+                if let Err(err) = self.mark_addressed(terminal).await {
+                    self.report_failure(format!("Failed to mark the thread addressed: {err}"));
+                }
+            }
+            Action::ReRequestReview => {
+                if let Err(err) = self.offer_rerequest().await {
+                    self.report_failure(format!("Failed to look up reviewers: {err}"));
+                }
+            }
+            Action::SwitchAccount => {
+                if let Err(err) = self.switch_account(terminal).await {
+                    self.report_failure(format!("Failed to switch accounts: {err}"));
+                }
+            }
+            Action::SyncReview => {
+                if let Err(err) = self.sync_review().await {
+                    self.report_failure(format!("Failed to sync your review: {err}"));
+                }
+            }
+            Action::Publish => {
+                if let Err(err) = self.publish_queue().await {
+                    self.report_failure(format!("Failed to publish replies: {err}"));
+                }
+            }
+            Action::ToggleDiff => self.toggle_diff(),
+            Action::ToggleFold => self.toggle_fold(),
+            Action::ToggleAnnotations if self.annotations.is_empty() => {
+                self.status_line = Some("No check annotations on this PR.".into());
+            }
+            Action::ToggleAnnotations => self.expand_annotations = !self.expand_annotations,
+            Action::ToggleMergeDiff => {
+                if let Err(err) = self.toggle_merge_diff().await {
+                    self.report_failure(format!("Failed to load the merge diff: {err}"));
+                }
+            }
+            Action::ToggleBlame => {
+                self.show_blame = !self.show_blame;
+                self.clear_status();
+            }
+            Action::TogglePerf => self.show_perf = !self.show_perf,
+            Action::MarkRead => self.mark_all_read(),
+            Action::ToggleTimes => self.absolute_times = !self.absolute_times,
+            Action::TogglePin => self.toggle_pin(),
+            Action::Undo => self.undo_edit(false),
+            Action::Redo => self.undo_edit(true),
+            Action::ToggleOutline => {
+                if self.outline.take().is_none() {
+                    let mut outline = Outline::default();
+                    if self.prefs.collapse_generated {
+                        outline.collapsed = self.generated.clone();
+                    }
+                    let current = self.current_index();
+                    outline.cursor = self
+                        .outline_rows(&outline)
+                        .iter()
+                        .position(|row| *row == OutlineRow::Thread(current))
+                        .unwrap_or(0);
+                    self.outline = Some(outline);
+                }
+                self.reset_scroll();
+                self.follow_outline_cursor();
+            }
+            Action::ToggleReviewMap => {
+                if let Err(err) = self.toggle_review_map().await {
+                    self.report_failure(format!("Failed to list the PR's files: {err}"));
+                }
+            }
+            Action::ToggleConversation => {
+                if let Err(err) = self.toggle_conversation().await {
+                    self.report_failure(format!("Failed to load the conversation: {err}"));
+                }
+            }
+            Action::ToggleProgress => self.show_progress = !self.show_progress,
+            Action::ToggleChecklist => {
+                if self.checklist_cursor.take().is_none() {
+                    if self.config.checklist.items.is_empty() {
+                        self.status_line = Some(
+                            "No review checklist; add items under [checklist] in your config."
+                                .into(),
+                        );
+                    } else {
+                        self.checklist_cursor = Some(0);
+                    }
+                }
+            }
+            Action::Approve | Action::RequestChanges => {
+                let event = if action == Action::Approve {
+                    ReviewEvent::Approve
+                } else {
+                    ReviewEvent::RequestChanges
+                };
+                if let Err(err) = self.confirm_review(event).await {
+                    self.report_failure(format!("Failed to prepare review: {err}"));
+                }
+            }
+            Action::ToggleHidden => self.show_hidden = !self.show_hidden,
+            Action::ResolveAll => self.confirm_resolve_all(),
+            Action::Minimize => {
+                if let Err(err) = self.pick_minimize(terminal).await {
+                    self.report_failure(format!("Failed to hide comment: {err}"));
+                }
+            }
+            Action::DismissReview => {
+                if let Err(err) = self.pick_dismissal(terminal).await {
+                    self.report_failure(format!("Failed to list reviews: {err}"));
+                }
+            }
+            Action::ToggleSinceReview => {
+                if let Err(err) = self.toggle_since_review().await {
+                    self.report_failure(format!("Failed to compare with your last review: {err}"));
+                }
+            }
+            Action::CycleSort => {
+                let next = match self.sort {
+                    ThreadSort::Created => ThreadSort::Path,
+                    ThreadSort::Path => ThreadSort::Changes,
+                    ThreadSort::Changes => ThreadSort::Comments,
+                    ThreadSort::Comments => ThreadSort::Created,
+                };
+                match self.set_sort(next).await {
+                    Ok(()) => {
+                        self.status_line = Some(
+                            match next {
+                                ThreadSort::Created => "Sorted by creation time.",
+                                ThreadSort::Path => "Sorted by path.",
+                                ThreadSort::Changes => "Sorted by most changed files first.",
+                                ThreadSort::Comments => "Sorted by most discussed files first.",
+                            }
+                            .into(),
+                        );
+                    }
+                    Err(err) => self.report_failure(format!("Failed to sort threads: {err}")),
+                }
+            }
+            Action::FilterFiles => {
+                let text = self
+                    .file_filter
+                    .as_ref()
+                    .map(|filter| filter.raw.clone())
+                    .unwrap_or_default();
+                self.prompt = Some(Prompt {
+                    action,
+                    label: "Filter threads (glob, is:added|modified|deleted|renamed|outdated, has:unresolved, by:login)"
+                        .into(),
+                    text,
+                });
+            }
+            Action::SearchFiles => {
+                self.prompt = Some(Prompt {
+                    action,
+                    label: "Search PR files".into(),
+                    text: self.last_search.clone(),
+                });
+            }
+            Action::RangeDiff => {
+                if let Err(err) = self.show_range_diff(terminal) {
+                    self.report_failure(format!("Failed to compare force-push: {err}"));
+                }
+            }
+            Action::ImageDiff => {
+                if let Err(err) = self.show_image_diff(terminal) {
+                    self.report_failure(format!("Failed to show the image: {err}"));
+                }
+            }
+            Action::Commits => {
+                if let Err(err) = self.show_commits(terminal) {
+                    self.report_failure(format!("Failed to show the commits: {err}"));
+                }
+            }
+            Action::ShowNotes => {
+                if let Err(err) = self.show_notes(terminal) {
+                    self.report_failure(format!("Failed to show your notes: {err}"));
+                }
+            }
+            Action::CopyReference => {
+                if let Err(err) = self.copy_reference() {
+                    self.report_failure(format!("Failed to copy: {err}"));
+                }
+            }
+            Action::Spelling => {
+                if let Err(err) = self.fix_spelling(terminal) {
+                    self.report_failure(format!("Failed to fix spelling: {err}"));
+                }
+            }
+            Action::Advisories => {
+                if let Err(err) = self.show_advisories(terminal) {
+                    self.report_failure(format!("Failed to show advisories: {err}"));
+                }
+            }
+            Action::Conflicts => {
+                if let Err(err) = self.show_conflicts(terminal) {
+                    self.report_failure(format!("Failed to find the conflicts: {err}"));
+                }
+            }
+            Action::BaseFile => {
+                if let Err(err) = self.show_base_file(terminal) {
+                    self.report_failure(format!("Failed to show the base version: {err}"));
+                }
+            }
+            Action::StackDown | Action::StackUp => self.move_in_stack(action == Action::StackUp),
+            Action::Visual => self.toggle_visual(),
+            Action::Yank => {
+                if let Err(err) = self.copy_selection() {
+                    self.report_failure(format!("Failed to copy selection: {err}"));
+                }
+            }
+            Action::Cancel => {
+                self.selection = None;
+                self.clear_status();
+            }
+            Action::RecordMacro => {
+                if let Some((name, steps)) = self.recording.take() {
+                    self.status_line = Some(format!(
+                        "Recorded macro '{name}': {} action{}.",
+                        steps.len(),
+                        if steps.len() == 1 { "" } else { "s" }
+                    ));
+                    self.macros.insert(name, steps);
+                } else {
+                    self.pending_prefix = Some(action);
+                    self.status_line = Some("Record macro: press a letter".into());
+                }
+            }
+            Action::SetMark
+            | Action::GotoMark
+            | Action::PlayMacro
+            | Action::AddNote
+            | Action::Copy
+            | Action::SwitchPr
+            | Action::GotoPrev
+            | Action::GotoNext => {
+                self.pending_prefix = Some(action);
+                self.status_line = Some(match action {
+                    Action::GotoPrev => {
+                        "Previous: t thread, f file, u unread thread, s PR in stack".into()
+                    }
+                    Action::GotoNext => {
+                        "Next: t thread, f file, u unread thread, s PR in stack".into()
+                    }
+                    Action::SetMark => "Set mark: press a letter".into(),
+                    Action::PlayMacro => "Play macro: press a letter".into(),
+                    Action::GotoMark => "Jump to mark: press a letter".into(),
+                    Action::AddNote => "Note on: h hunk, f file, l selected line".into(),
+                    Action::SwitchPr => {
+                        let open: Vec<String> = self
+                            .open_prs
+                            .iter()
+                            .enumerate()
+                            .take(9)
+                            .map(|(i, number)| format!("{} #{number}", i + 1))
+                            .collect();
+                        format!("Switch PR: {}, p pick another", open.join(", "))
+                    }
+                    _ => "Copy: l permalink, g permalink at head, t thread link, r reference, c comment, u PR URL, f path, h hunk as patch".into(),
+                });
+            }
+            Action::Checkout => {
+                if let Err(err) = self.confirm_checkout().await {
+                    self.report_failure(format!("Failed to check out PR: {err}"));
+                }
+            }
+            Action::SavePatch => {
+                if let Err(err) = self.save_patch().await {
+                    self.report_failure(format!("Failed to save patch: {err}"));
+                }
+            }
+            Action::ApplyPatch => {
+                self.prompt = Some(Prompt {
+                    action: Action::ApplyPatch,
+                    label: format!(
+                        "Apply PR #{}'s diff to your working tree with git apply --3way? (y/n)",
+                        self.pr_number
+                    ),
+                    text: String::new(),
+                });
+            }
+            Action::Export => {
+                if let Err(err) = self.export_view() {
+                    self.report_failure(format!("Failed to export threads: {err}"));
+                }
+            }
+            Action::Worktree => {
+                if let Err(err) = self.create_worktree() {
+                    self.report_failure(format!("Failed to create worktree: {err}"));
+                }
+            }
+            Action::OpenEditor => {
+                if let Err(err) = self.open_in_editor(terminal) {
+                    self.report_failure(format!("Failed to open editor: {err}"));
+                }
+            }
+            Action::OpenIssue => {
+                if let Err(err) = self.open_issue(terminal) {
+                    self.report_failure(format!("Failed to open the issue: {err}"));
+                }
+            }
+            Action::OpenBrowser => {
+                if let Err(err) = self.open_in_browser().await {
+                    self.report_failure(format!("Failed to open browser: {err}"));
+                }
+            }
+            Action::JumpBack => self.jump_back(),
+            Action::JumpForward => self.jump_forward(),
+        }
+    }
+
+    fn current_location(&self) -> Option<Location> {
+        self.current_thread().map(|thread| Location {
+            thread_id: thread.id.clone(),
+            line: self
+                .scroll_offset
+                .min(self.rendered_lines.saturating_sub(1)),
+        })
+    }
+
+    /// Selects the thread at `location`, switching views if it moved, and
+    /// scrolls to the recorded line.
+    fn goto_location(&mut self, location: &Location) -> bool {
+        let mut views = vec![self.view];
+        let mut view = self.view.next();
+        while view != self.view {
+            views.push(view);
+            view = view.next();
+        }
+        for view in views {
+            let Some(pos) = self
+                .threads_for_view(view)
+                .iter()
+                .position(|thread| thread.id == location.thread_id)
+            else {
+                continue;
+            };
+            self.view = view;
+            *self.index_for_view_mut(view) = pos;
+            self.selection = None;
+            self.scroll_offset = location.line;
+            return true;
+        }
+        false
+    }
+
+    /// Selects the thread on `target`'s file closest to its line, preferring
+    /// the current view when several views contain a match.
+    fn focus_file(&mut self, target: &FileTarget) {
+        let distance = |thread: &Thread| match (target.line, thread.line) {
+            (Some(want), Some(line)) => (want - line).unsigned_abs(),
+            (None, _) => 0,
+            (Some(_), None) => u64::MAX,
+        };
+        let mut best: Option<(u64, &Thread)> = None;
+        let mut view = self.view;
+        loop {
+            for thread in self.threads_for_view(view) {
+                if !target.matches(&thread.path) {
+                    continue;
+                }
+                let d = distance(thread);
+                if best.is_none_or(|(best_d, _)| d < best_d) {
+                    best = Some((d, thread));
+                }
+            }
+            view = view.next();
+            if view == self.view {
+                break;
+            }
+        }
+        match best {
+            Some((_, thread)) => {
+                let location = Location {
+                    thread_id: thread.id.clone(),
+                    line: 0,
+                };
+                self.goto_location(&location);
+            }
+            None => {
+                self.status_line = Some(format!("No review threads on {}", target.path));
+            }
+        }
+    }
+
+    /// Selects the thread holding the review comment with `database_id`.
+    fn focus_comment(&mut self, database_id: u64) {
+        let thread = [
+            ThreadView::Unresolved,
+            ThreadView::Active,
+            ThreadView::Skipped,
+        ]
+        .into_iter()
+        .flat_map(|view| self.threads_for_view(view))
+        .find(|thread| {
+            thread
+                .comments
+                .iter()
+                .any(|comment| comment.database_id == database_id)
+        });
+        match thread {
+            Some(thread) => {
+                let location = Location {
+                    thread_id: thread.id.clone(),
+                    line: 0,
+                };
+                self.goto_location(&location);
+            }
+            None => {
+                self.status_line =
+                    Some("The notification's comment isn't in a review thread shown here.".into());
+            }
+        }
+    }
+
+    /// Takes on a repository's preferences, leaving out threads on the
+    /// paths they ignore.
+    fn apply_prefs(&mut self, prefs: RepoPrefs) {
+        self.prefs = prefs;
+        self.reorder_threads();
+    }
+
+    /// Opens the repository's preferences in the editor and applies them
+    /// once saved.
+    fn edit_prefs(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let store = PrefsStore::new(&self.repo)?;
+        let current = serde_json::to_string_pretty(&self.prefs)?;
+        let edited = terminal.suspend_to_edit(&self.config.editor_command(), &current)?;
+        let prefs: RepoPrefs = serde_json::from_str(&edited).context("invalid preferences")?;
+        store.save(&prefs)?;
+        self.apply_prefs(prefs);
+        self.status_line = Some(format!(
+            "Saved preferences for {}/{}.",
+            self.repo.owner, self.repo.name
+        ));
+        Ok(())
+    }
+
+    /// Finds keys of the configured issue trackers in the PR's title, branch
+    /// name and body, in that order.
+    async fn load_issue_keys(&mut self) {
+        if self.config.issues.is_empty() {
+            return;
+        }
+        let text = match self.gh.pr_text(self.pr_number).await {
+            Ok(text) => text,
+            Err(err) => {
+                log::warn!("failed to read the PR for issue keys: {err}");
+                return;
+            }
+        };
+        self.issue_keys.clear();
+        for tracker in &self.config.issues {
+            let pattern = match regex::Regex::new(&tracker.pattern) {
+                Ok(pattern) => pattern,
+                Err(err) => {
+                    self.status_line = Some(format!("Invalid issue pattern: {err}"));
+                    continue;
+                }
+            };
+            for source in [&text.title, &text.head_ref_name, &text.body] {
+                for key in pattern.find_iter(source).map(|found| found.as_str()) {
+                    if self.issue_keys.iter().all(|(known, _)| known != key) {
+                        let url = tracker.url.replace("{key}", key);
+                        self.issue_keys.push((key.to_string(), url));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Works out which changed files are generated, once the PR's files
+    /// are known, and sizes the PR without them.
+    fn load_generated(&mut self) {
+        let Some(files) = &self.pr_files else {
+            return;
+        };
+        let paths: Vec<&str> = files.keys().map(String::as_str).collect();
+        match git::generated(&paths) {
+            Ok(generated) => self.generated = generated,
+            Err(err) => log::warn!("failed to read .gitattributes: {err}"),
+        }
+        let reviewed: Vec<&PrFile> = files
+            .iter()
+            .filter(|(path, _)| !self.generated.contains(*path))
+            .map(|(_, file)| file)
+            .collect();
+        let lines = reviewed
+            .iter()
+            .map(|file| (file.additions + file.deletions) as usize)
+            .sum();
+        self.size = Some(size::estimate(lines, reviewed.len()));
+    }
+
+    /// Reads CODEOWNERS from the PR's base branch, which is the one GitHub
+    /// applies when requesting reviews.
+    async fn load_code_owners(&mut self) {
+        match self.gh.code_owners(&self.repo, self.pr_number).await {
+            Ok(owners) => self.code_owners = owners.as_deref().map(CodeOwners::parse),
+            Err(err) => log::warn!("failed to fetch CODEOWNERS: {err}"),
+        }
+    }
+
+    /// Loads everything the TUI shows alongside the threads, for the PR
+    /// reviewed at startup and for each one opened after it.
+    async fn load_review(&mut self) {
+        self.load_stack().await;
+        self.load_code_owners().await;
+        self.load_renames().await;
+        self.load_generated();
+        self.load_issue_keys().await;
+        self.load_submodules().await;
+        self.load_force_push().await;
+        self.load_commits().await;
+        self.load_merge_state().await;
+        self.load_annotations().await;
+        self.load_advisories().await;
+        self.load_pending_review().await;
+        self.load_account().await;
+        self.load_rate_limit().await;
+    }
+
+    /// Works out which open PRs this one is stacked with.
+    async fn load_stack(&mut self) {
+        match self.gh.list_prs().await {
+            Ok(prs) => self.stack = stack::chain(&prs, self.pr_number),
+            Err(err) => log::warn!("failed to look up stacked PRs: {err}"),
+        }
+    }
+
+    /// Moves through the outline, folding and unfolding files and threads.
+    /// Enter on a thread selects it and closes the outline. Returns false
+    /// for keys it doesn't use.
+    fn outline_key(&mut self, key: &event::KeyEvent) -> bool {
+        let Some(mut outline) = self.outline.take() else {
+            return false;
+        };
+        let rows = self.outline_rows(&outline);
+        let last = rows.len().saturating_sub(1);
+        let row = rows.get(outline.cursor).cloned();
+        let threads = self.current_threads();
+        let path_of = |row: &OutlineRow| match row {
+            OutlineRow::File(path) => path.clone(),
+            OutlineRow::Thread(index) | OutlineRow::Comment(index, _) => {
+                threads[*index].path.clone()
+            }
+        };
+        let mut consumed = true;
+        match (key.code, row) {
+            (KeyCode::Esc, _) => {
+                self.reset_scroll();
+                return true;
+            }
+            (KeyCode::Up | KeyCode::Char('k'), _) => {
+                outline.cursor = outline.cursor.saturating_sub(1)
+            }
+            (KeyCode::Down | KeyCode::Char('j'), _) => {
+                outline.cursor = (outline.cursor + 1).min(last)
+            }
+            (KeyCode::Enter, Some(OutlineRow::Thread(index) | OutlineRow::Comment(index, _))) => {
+                self.record_jump();
+                *self.current_index_mut() = index;
+                self.reset_scroll();
+                return true;
+            }
+            (KeyCode::Enter | KeyCode::Char(' '), Some(OutlineRow::File(path))) => {
+                if !outline.collapsed.remove(&path) {
+                    outline.collapsed.insert(path);
+                }
+            }
+            (KeyCode::Char(' '), Some(OutlineRow::Thread(index))) => {
+                let id = &threads[index].id;
+                if !outline.expanded.remove(id) {
+                    outline.expanded.insert(id.clone());
+                }
+            }
+            (KeyCode::Left | KeyCode::Char('h'), Some(row)) => {
+                let path = path_of(&row);
+                outline.cursor = rows
+                    .iter()
+                    .position(|other| *other == OutlineRow::File(path.clone()))
+                    .unwrap_or(outline.cursor);
+                outline.collapsed.insert(path);
+            }
+            (KeyCode::Right | KeyCode::Char('l'), Some(row)) => match row {
+                OutlineRow::Thread(index) => {
+                    outline.expanded.insert(threads[index].id.clone());
+                }
+                row => {
+                    outline.collapsed.remove(&path_of(&row));
+                }
+            },
+            _ => consumed = false,
+        }
+        let len = self.outline_rows(&outline).len();
+        outline.cursor = outline.cursor.min(len.saturating_sub(1));
+        self.outline = Some(outline);
+        self.follow_outline_cursor();
+        consumed
+    }
+
+    /// Scrolls so the outline's selected row is on screen.
+    fn follow_outline_cursor(&mut self) {
+        let Some(outline) = &self.outline else {
+            return;
+        };
+        // Two header lines come before the first row.
+        self.scroll_to_line(outline.cursor + 2);
+    }
+
+    /// Scrolls just far enough that `line` is on screen.
+    fn scroll_to_line(&mut self, line: usize) {
+        let viewport = self
+            .terminal_size()
+            .map_or(24, |(_, height)| height as usize)
+            .max(1);
+        if line < self.scroll_offset {
+            self.scroll_offset = line.saturating_sub(2);
+        } else if line >= self.scroll_offset + viewport {
+            self.scroll_offset = line + 1 - viewport;
+        }
+    }
+
+    /// The rows of the outline: each file in the order its first thread
+    /// appears, then its threads unless the file is folded, then each
+    /// unfolded thread's comments.
+    fn outline_rows(&self, outline: &Outline) -> Vec<OutlineRow> {
+        let threads = self.current_threads();
+        let mut files: Vec<(&str, Vec<usize>)> = Vec::new();
+        for (index, thread) in threads.iter().enumerate() {
+            match files.iter_mut().find(|(path, _)| *path == thread.path) {
+                Some((_, indices)) => indices.push(index),
+                None => files.push((&thread.path, vec![index])),
+            }
+        }
+        let mut rows = Vec::new();
+        for (path, indices) in files {
+            rows.push(OutlineRow::File(path.to_string()));
+            if outline.collapsed.contains(path) {
+                continue;
+            }
+            for index in indices {
+                rows.push(OutlineRow::Thread(index));
+                if outline.expanded.contains(&threads[index].id) {
+                    rows.extend(
+                        (0..threads[index].comments.len())
+                            .map(|comment| OutlineRow::Comment(index, comment)),
+                    );
+                }
+            }
+        }
+        rows
+    }
+
+    fn write_outline(&self, buf: &mut String, outline: &Outline) -> std::fmt::Result {
+        let threads = self.current_threads();
+        let rows = self.outline_rows(outline);
+        let files = rows
+            .iter()
+            .filter(|row| matches!(row, OutlineRow::File(_)))
+            .count();
+        writeln!(
+            buf,
+            "{}",
+            format!(
+                "{} {} threads on {files} file{}",
+                threads.len(),
+                self.view.name(),
+                if files == 1 { "" } else { "s" }
+            )
+            .bold()
+        )?;
+        writeln!(buf)?;
+        for (index, row) in rows.iter().enumerate() {
+            let text = match row {
+                OutlineRow::File(path) => {
+                    let on_file = threads.iter().filter(|thread| thread.path == *path);
+                    let total = on_file.clone().count();
+                    let unresolved = on_file.filter(|thread| !thread.is_resolved).count();
+                    let fold = if outline.collapsed.contains(path) {
+                        "▸"
+                    } else {
+                        "▾"
+                    };
+                    format!(
+                        "{fold} {}  {unresolved} unresolved of {total}",
+                        self.file_label(path)
+                    )
+                }
+                OutlineRow::Thread(thread) => {
+                    let thread = &threads[*thread];
+                    let state = if thread.is_resolved { "✓" } else { "●" };
+                    let first = thread.comments.first();
+                    format!(
+                        "    {state} {:<6} {}: {}  ({} comment{})",
+                        thread
+                            .line
+                            .map(|line| format!("L{line}"))
+                            .unwrap_or_default(),
+                        first.map_or("", |comment| comment.author.as_str()),
+                        first.map_or("", |comment| first_line(&comment.body)),
+                        thread.comments.len(),
+                        if thread.comments.len() == 1 { "" } else { "s" }
+                    )
+                }
+                OutlineRow::Comment(thread, comment) => {
+                    let comment = &threads[*thread].comments[*comment];
+                    format!(
+                        "          {}: {}",
+                        comment.author,
+                        first_line(&comment.body)
+                    )
+                }
+            };
+            if index == outline.cursor {
+                writeln!(buf, "{}", text.reverse())?;
+            } else {
+                match row {
+                    OutlineRow::File(_) => writeln!(buf, "{}", text.with(self.theme.path).bold())?,
+                    OutlineRow::Thread(_) => writeln!(buf, "{text}")?,
+                    OutlineRow::Comment(..) => writeln!(buf, "{}", text.with(self.theme.subtle))?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows or hides the review map, listing the PR's files first if they
+    /// haven't been yet.
+    async fn toggle_review_map(&mut self) -> Result<()> {
+        if self.review_map.take().is_none() {
+            if self.pr_files.is_none() {
+                self.pr_files = Some(self.gh.pr_files(&self.repo, self.pr_number).await?);
+            }
+            self.review_map = Some(ReviewMap::default());
+        }
+        self.reset_scroll();
+        Ok(())
+    }
+
+    /// The PR's changes by top-level directory, most changed first, each
+    /// with its files, most changed first.
+    fn review_map_directories(&self) -> Vec<(&str, Vec<(&str, &PrFile)>)> {
+        let mut directories: Vec<(&str, Vec<(&str, &PrFile)>)> = Vec::new();
+        for (path, file) in self.pr_files.iter().flatten() {
+            let directory = top_directory(path);
+            match directories.iter_mut().find(|(name, _)| *name == directory) {
+                Some((_, files)) => files.push((path, file)),
+                None => directories.push((directory, vec![(path, file)])),
+            }
+        }
+        let changes =
+            |files: &[(&str, &PrFile)]| -> u64 { files.iter().map(|(_, file)| file.changes).sum() };
+        for (_, files) in &mut directories {
+            files.sort_by(|a, b| b.1.changes.cmp(&a.1.changes).then_with(|| a.0.cmp(b.0)));
+        }
+        directories.sort_by(|a, b| changes(&b.1).cmp(&changes(&a.1)).then_with(|| a.0.cmp(b.0)));
+        directories
+    }
+
+    /// The review map's rows: each directory, then its files if unfolded.
+    fn review_map_rows(&self, map: &ReviewMap) -> Vec<MapRow> {
+        let mut rows = Vec::new();
+        for (directory, files) in self.review_map_directories() {
+            rows.push(MapRow::Directory(directory.to_string()));
+            if map.expanded.contains(directory) {
+                rows.extend(files.iter().map(|(path, _)| MapRow::File(path.to_string())));
+            }
+        }
+        rows
+    }
+
+    /// Moves through the review map, unfolding directories. Enter on a file
+    /// selects its first thread and closes the map. Returns false for keys
+    /// it doesn't use.
+    fn review_map_key(&mut self, key: &event::KeyEvent) -> bool {
+        let Some(mut map) = self.review_map.take() else {
+            return false;
+        };
+        let rows = self.review_map_rows(&map);
+        let last = rows.len().saturating_sub(1);
+        let row = rows.get(map.cursor).cloned();
+        let mut consumed = true;
+        match (key.code, row) {
+            (KeyCode::Esc, _) => {
+                self.reset_scroll();
+                return true;
+            }
+            (KeyCode::Up | KeyCode::Char('k'), _) => map.cursor = map.cursor.saturating_sub(1),
+            (KeyCode::Down | KeyCode::Char('j'), _) => map.cursor = (map.cursor + 1).min(last),
+            (KeyCode::Enter | KeyCode::Char(' '), Some(MapRow::Directory(directory))) => {
+                if !map.expanded.remove(&directory) {
+                    map.expanded.insert(directory);
+                }
+            }
+            (KeyCode::Right | KeyCode::Char('l'), Some(MapRow::Directory(directory))) => {
+                map.expanded.insert(directory);
+            }
+            (KeyCode::Left | KeyCode::Char('h'), Some(row)) => {
+                let directory = match row {
+                    MapRow::Directory(directory) => directory,
+                    MapRow::File(path) => top_directory(&path).to_string(),
+                };
+                map.cursor = rows
+                    .iter()
+                    .position(|other| *other == MapRow::Directory(directory.clone()))
+                    .unwrap_or(map.cursor);
+                map.expanded.remove(&directory);
+            }
+            (KeyCode::Enter, Some(MapRow::File(path))) => {
+                match self
+                    .current_threads()
+                    .iter()
+                    .position(|thread| thread.path == path)
+                {
+                    Some(index) => {
+                        self.record_jump();
+                        *self.current_index_mut() = index;
+                        self.reset_scroll();
+                        return true;
+                    }
+                    None => {
+                        self.status_line =
+                            Some(format!("No {} threads on {path}.", self.view.name()));
+                    }
+                }
+            }
+            _ => consumed = false,
+        }
+        let len = self.review_map_rows(&map).len();
+        map.cursor = map.cursor.min(len.saturating_sub(1));
+        // Two header lines come before the first row.
+        let line = map.cursor + 2;
+        self.review_map = Some(map);
+        self.scroll_to_line(line);
+        consumed
+    }
+
+    fn write_review_map(&self, buf: &mut String, map: &ReviewMap) -> std::fmt::Result {
+        const BAR_WIDTH: u64 = 20;
+        let directories = self.review_map_directories();
+        let files: Vec<&PrFile> = directories
+            .iter()
+            .flat_map(|(_, files)| files.iter().map(|(_, file)| *file))
+            .collect();
+        let sum = |files: &[&PrFile], count: fn(&PrFile) -> u64| -> u64 {
+            files.iter().map(|file| count(file)).sum()
+        };
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        writeln!(
+            buf,
+            "{}",
+            format!(
+                "{} file{} changed in {} director{}, +{} −{}",
+                files.len(),
+                plural(files.len()),
+                directories.len(),
+                if directories.len() == 1 { "y" } else { "ies" },
+                sum(&files, |file| file.additions),
+                sum(&files, |file| file.deletions)
+            )
+            .bold()
+        )?;
+        writeln!(buf)?;
+        let most = directories
+            .iter()
+            .map(|(_, files)| files.iter().map(|(_, file)| file.changes).sum::<u64>())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let threads: Vec<&Thread> = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .collect();
+        let threads_on = |on: &dyn Fn(&str) -> bool| {
+            let count = threads.iter().filter(|thread| on(&thread.path)).count();
+            match count {
+                0 => String::new(),
+                count => format!("  {count} thread{}", plural(count)),
+            }
+        };
+        let mut index = 0;
+        for (directory, files) in &directories {
+            let additions: u64 = files.iter().map(|(_, file)| file.additions).sum();
+            let deletions: u64 = files.iter().map(|(_, file)| file.deletions).sum();
+            let bar = "█".repeat(((additions + deletions) * BAR_WIDTH).div_ceil(most) as usize);
+            let fold = if map.expanded.contains(*directory) {
+                "▾"
+            } else {
+                "▸"
+            };
+            let name = if directory.is_empty() {
+                "(top level)".to_string()
+            } else {
+                format!("{directory}/")
+            };
+            let text = format!(
+                "{fold} {name:<24} {:>4} file{}  +{additions} −{deletions}{}",
+                files.len(),
+                plural(files.len()),
+                threads_on(&|path| top_directory(path) == *directory)
+            );
+            if index == map.cursor {
+                writeln!(buf, "{}  {bar}", text.reverse())?;
+            } else {
+                writeln!(
+                    buf,
+                    "{}  {}",
+                    text.with(self.theme.path).bold(),
+                    bar.with(self.theme.subtle)
+                )?;
+            }
+            index += 1;
+            if !map.expanded.contains(*directory) {
+                continue;
+            }
+            for (path, file) in files {
+                let text = format!(
+                    "    {}  {}{}",
+                    self.file_label(path),
+                    format!("+{}", file.additions).with(self.theme.diff_added),
+                    format!(" −{}", file.deletions).with(self.theme.diff_removed)
+                );
+                let threads = threads_on(&|other| other == *path);
+                if index == map.cursor {
+                    writeln!(
+                        buf,
+                        "{}",
+                        format!("{}{threads}", ansi::strip(&text)).reverse()
+                    )?;
+                } else {
+                    writeln!(buf, "{text}{}", threads.with(self.theme.subtle))?;
+                }
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves through and ticks the checklist while it is shown. Returns
+    /// false for keys it doesn't use, which then run their usual action.
+    fn checklist_key(&mut self, key: &event::KeyEvent) -> bool {
+        let Some(cursor) = &mut self.checklist_cursor else {
+            return false;
+        };
+        let last = self.config.checklist.items.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc => self.checklist_cursor = None,
+            KeyCode::Up | KeyCode::Char('k') => *cursor = cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => *cursor = (*cursor + 1).min(last),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                if let Some(item) = self.config.checklist.items.get(*cursor).cloned() {
+                    self.record_edit(Edit::Checklist(self.checklist.clone()));
+                    if !self.checklist.remove(&item) {
+                        self.checklist.insert(item);
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    async fn prompt_key(&mut self, terminal: &mut TerminalSession, key: &event::KeyEvent) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Char('c') if ctrl => self.prompt = None,
+            KeyCode::Char('u') if ctrl => prompt.text.clear(),
+            KeyCode::Backspace => {
+                prompt.text.pop();
+            }
+            KeyCode::Char(ch) if !ctrl => prompt.text.push(ch),
+            KeyCode::Enter => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.submit_prompt(terminal, prompt).await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn submit_prompt(&mut self, terminal: &mut TerminalSession, prompt: Prompt) {
+        match prompt.action {
+            Action::FilterFiles => {
+                if let Err(err) = self.filter_files(&prompt.text).await {
+                    self.report_failure(format!("Failed to filter files: {err}"));
+                }
+            }
+            Action::SearchFiles => {
+                if let Err(err) = self.search_files(terminal, &prompt.text).await {
+                    self.report_failure(format!("Failed to search files: {err}"));
+                }
+            }
+            Action::Minimize => match MINIMIZE_REASONS
+                .iter()
+                .find(|(name, _)| *name == prompt.text.trim())
+            {
+                Some((_, classifier)) => {
+                    if let Some(id) = self.minimizing.take()
+                        && let Err(err) = self.set_minimized(&id, Some(classifier)).await
+                    {
+                        self.report_failure(format!("Failed to hide comment: {err}"));
+                    }
+                }
+                None => {
+                    self.prompt = Some(Prompt {
+                        label: format!("Unknown reason; {}", minimize_label()),
+                        ..prompt
+                    });
+                }
+            },
+            Action::ReRequestReview => {
+                let logins = mem::take(&mut self.rerequesting);
+                if !matches!(prompt.text.trim(), "y" | "yes") {
+                    self.status_line = Some("Left the review requests as they were.".into());
+                } else {
+                    let reviewers: Vec<&str> = logins.iter().map(String::as_str).collect();
+                    match self.gh.request_reviewers(self.pr_number, &reviewers).await {
+                        Ok(()) => {
+                            self.status_line =
+                                Some(format!("Asked {} to review again.", mention_list(&logins)));
+                        }
+                        Err(err) => {
+                            self.report_failure(format!("Failed to re-request review: {err}"));
+                        }
+                    }
+                }
+            }
+            Action::Checkout => {
+                if !matches!(prompt.text.trim(), "y" | "yes") {
+                    self.status_line = Some("Stayed on the current branch.".into());
+                } else if let Err(err) = self.checkout_pr().await {
+                    self.report_failure(format!("Failed to check out PR: {err}"));
+                }
+            }
+            Action::ApplyPatch => {
+                if !matches!(prompt.text.trim(), "y" | "yes") {
+                    self.status_line = Some("Left the working tree as it was.".into());
+                } else if let Err(err) = self.apply_patch().await {
+                    self.report_failure(format!("Failed to apply patch: {err}"));
+                }
+            }
+            Action::ResolveAll => {
+                let ids = mem::take(&mut self.resolving);
+                if !matches!(prompt.text.trim(), "y" | "yes") {
+                    self.status_line = Some("Left the threads unresolved.".into());
+                } else if let Err(err) = self.resolve_threads(&ids).await {
+                    self.report_failure(format!("Failed to resolve threads: {err}"));
+                }
+            }
+            Action::DismissReview if prompt.text.trim().is_empty() => {
+                self.prompt = Some(Prompt {
+                    label: "A message is required to dismiss a review".into(),
+                    ..prompt
+                });
+            }
+            Action::DismissReview => {
+                if let Some((id, login)) = self.dismissing.take() {
+                    match self
+                        .gh
+                        .dismiss_review(&self.repo, self.pr_number, id, prompt.text.trim())
+                        .await
+                    {
+                        Ok(()) => self.status_line = Some(format!("Dismissed @{login}'s review.")),
+                        Err(err) => self.report_failure(format!("Failed to dismiss review: {err}")),
+                    }
+                }
+            }
+            Action::RequestChanges if prompt.text.trim().is_empty() => {
+                self.prompt = Some(Prompt {
+                    label: "A body is required to request changes".into(),
+                    ..prompt
+                });
+            }
+            Action::Approve | Action::RequestChanges => {
+                let event = if prompt.action == Action::Approve {
+                    ReviewEvent::Approve
+                } else {
+                    ReviewEvent::RequestChanges
+                };
+                if let Err(err) = self.submit_tui_review(event, &prompt.text).await {
+                    self.report_failure(format!("Failed to submit review: {err}"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Asks for a body before submitting a review, after checking GitHub
+    /// would accept it. The prompt warns about replies that haven't been
+    /// published, which the review won't include.
+    async fn confirm_review(&mut self, event: ReviewEvent) -> Result<()> {
+        let login = self.gh.viewer_login().await?;
+        let own = self.gh.pr_author(self.pr_number).await? == login;
+        let mut label = match event {
+            ReviewEvent::Approve => format!("Approve #{} (optional body)", self.pr_number),
+            _ => format!("Request changes on #{} (body required)", self.pr_number),
+        };
+        let unpublished = self.queued_replies.len();
+        if unpublished > 0 {
+            let _ = write!(
+                label,
+                " – {unpublished} unpublished repl{} left out",
+                if unpublished == 1 { "y" } else { "ies" }
+            );
+        }
+        if self
+            .gh
+            .fetch_pending_review(&self.repo, self.pr_number)
+            .await?
+            .is_some()
+        {
+            label.push_str(" – submits your pending review");
+        }
+        if own {
+            label.push_str(" – your own PR, which GitHub won't let you review");
+        }
+        let action = match event {
+            ReviewEvent::Approve => Action::Approve,
+            _ => Action::RequestChanges,
+        };
+        self.prompt = Some(Prompt {
+            action,
+            label,
+            text: String::new(),
+        });
+        Ok(())
+    }
+
+    /// Lets the user pick a comment in the current thread, then unhides it
+    /// if it is hidden and otherwise asks why it should be hidden.
+    async fn pick_minimize(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        let title = format!("Hide or unhide a comment on {}", thread.display_path());
+        terminal.deactivate()?;
+        let picked = picker::pick_comment(&title, &thread.comments, &self.theme).map(|comment| {
+            comment.map(|comment| (comment.id.clone(), comment.minimized.is_some()))
+        });
+        terminal.activate()?;
+        match picked? {
+            Some((id, true)) => self.set_minimized(&id, None).await?,
+            Some((id, false)) => {
+                self.minimizing = Some(id);
+                self.prompt = Some(Prompt {
+                    action: Action::Minimize,
+                    label: minimize_label(),
+                    text: "outdated".into(),
+                });
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Asks before resolving every unresolved thread in the view, which is
+    /// narrowed to what the file filter matches.
+    fn confirm_resolve_all(&mut self) {
+        self.resolving = self
+            .current_threads()
+            .iter()
+            .filter(|thread| !thread.is_resolved)
+            .map(|thread| thread.id.clone())
+            .collect();
+        let count = self.resolving.len();
+        if count == 0 {
+            self.status_line = Some("No unresolved threads in this view.".into());
+            return;
+        }
+        let filtered = match &self.file_filter {
+            Some(filter) => format!(" matching `{}`", filter.raw),
+            None => String::new(),
+        };
+        self.prompt = Some(Prompt {
+            action: Action::ResolveAll,
+            label: format!(
+                "Resolve {count} {} thread{}{filtered}? (y/n)",
+                self.view.name(),
+                if count == 1 { "" } else { "s" }
+            ),
+            text: String::new(),
+        });
+    }
+
+    /// Resolves `ids` one by one, stopping at the first failure, then
+    /// reloads the threads.
+    async fn resolve_threads(&mut self, ids: &[String]) -> Result<()> {
+        let mut resolved = 0;
+        let mut missing = Vec::new();
+        let mut result = Ok(());
+        for id in ids {
+            match self.gh.resolve_thread(id).await {
+                Ok(()) => resolved += 1,
+                // Perhaps deleted on GitHub since the list was loaded.
+                Err(err) if Conflict::detect(&err) == Some(Conflict::Deleted) => {
+                    missing.push((id, err));
+                }
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        self.refresh_threads().await?;
+        // Only threads the reload no longer lists were really deleted.
+        let mut deleted = 0;
+        for (id, err) in missing {
+            let listed = self
+                .active_threads
+                .iter()
+                .chain(&self.skipped_threads)
+                .chain(&self.hidden_threads)
+                .any(|thread| thread.id == *id);
+            if !listed {
+                deleted += 1;
+            } else if result.is_ok() {
+                result = Err(err);
+            }
+        }
+        result.with_context(|| format!("{resolved} of {} resolved", ids.len()))?;
+        let mut message = format!(
+            "Resolved {resolved} thread{}.",
+            if resolved == 1 { "" } else { "s" }
+        );
+        if deleted > 0 {
+            let _ = write!(
+                message,
+                " Conflict: {deleted} had been deleted on GitHub; threads refreshed."
+            );
+        }
+        self.status_line = Some(message);
+        Ok(())
+    }
+
+    /// Hides a comment with GitHub's `classifier`, or unhides it for
+    /// `None`, then reloads the threads to show the change.
+    async fn set_minimized(&mut self, comment_id: &str, classifier: Option<&str>) -> Result<()> {
+        match classifier {
+            Some(classifier) => self.gh.minimize_comment(comment_id, classifier).await?,
+            None => self.gh.unminimize_comment(comment_id).await?,
+        }
+        self.refresh_threads().await?;
+        self.status_line = Some(
+            if classifier.is_some() {
+                "Comment hidden."
+            } else {
+                "Comment unhidden."
+            }
+            .into(),
+        );
+        Ok(())
+    }
+
+    /// Lists each reviewer's standing approval or change request and asks
+    /// for a message to dismiss the chosen one with.
+    async fn pick_dismissal(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let reviews = self.gh.reviews(&self.repo, self.pr_number).await?;
+        // A reviewer's latest approval or change request is the one that
+        // counts; comments don't replace it.
+        let mut latest: Vec<SubmittedReview> = Vec::new();
+        for review in reviews {
+            if !matches!(
+                review.state.as_str(),
+                "APPROVED" | "CHANGES_REQUESTED" | "DISMISSED"
+            ) {
+                continue;
+            }
+            latest.retain(|other| other.login() != review.login());
+            latest.push(review);
+        }
+        latest.retain(|review| review.state != "DISMISSED");
+        if latest.is_empty() {
+            self.status_line = Some("No approvals or change requests to dismiss.".into());
+            return Ok(());
+        }
+        let title = format!("Dismiss a review on #{}", self.pr_number);
+        terminal.deactivate()?;
+        let picked = picker::pick_review(&title, &latest, &self.theme);
+        terminal.activate()?;
+        let Some(review) = picked? else {
+            return Ok(());
+        };
+        let login = review.login().to_string();
+        self.dismissing = Some((review.id, login.clone()));
+        self.prompt = Some(Prompt {
+            action: Action::DismissReview,
+            label: format!("Dismiss @{login}'s review (message required)"),
+            text: String::new(),
+        });
+        Ok(())
+    }
+
+    /// Lists the reviews the account has submitted on the PR, newest first,
+    /// then the picked one's inline comments: one jumps to its thread, or
+    /// the whole review opens in the pager.
+    async fn show_review_history(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        if self.compare.is_some() {
+            self.status_line = Some("Only PRs have reviews to look back on.".into());
+            return Ok(());
+        }
+        let login = match &self.account {
+            Some(login) => login.clone(),
+            None => self.gh.viewer_login().await?,
+        };
+        let mut reviews: Vec<SubmittedReview> = self
+            .gh
+            .reviews(&self.repo, self.pr_number)
+            .await?
+            .into_iter()
+            .filter(|review| {
+                review.login().eq_ignore_ascii_case(&login)
+                    && review.state != "PENDING"
+                    && review.submitted_at.is_some()
+            })
+            .collect();
+        if reviews.is_empty() {
+            self.status_line = Some(format!("You haven't reviewed #{} yet.", self.pr_number));
+            return Ok(());
+        }
+        reviews.reverse();
+        let comments = self.gh.review_comments(&self.repo, self.pr_number).await?;
+        let comments_of = |review: &SubmittedReview| -> Vec<&ReviewComment> {
+            comments
+                .iter()
+                .filter(|comment| comment.pull_request_review_id == Some(review.id))
+                .collect()
+        };
+        let now = Utc::now();
+        let options: Vec<String> = reviews
+            .iter()
+            .map(|review| {
+                let when = review
+                    .submitted_at
+                    .as_deref()
+                    .and_then(|at| parse_timestamp(at).ok())
+                    .map_or_else(String::new, |at| self.timestamp(now, at));
+                let count = comments_of(review).len();
+                let first = review.body.lines().next().unwrap_or_default();
+                format!(
+                    "{:<17} {when:<14} {}  {count} comment{}  {first}",
+                    review_state_label(&review.state),
+                    self.review_commit(review),
+                    if count == 1 { "" } else { "s" }
+                )
+            })
+            .collect();
+        let title = format!("My reviews on #{}", self.pr_number);
+        terminal.deactivate()?;
+        let picked = picker::pick_option(&title, &options, "enter: open review", &self.theme)
+            .and_then(|picked| {
+                let Some(index) = picked else {
+                    return Ok(None);
+                };
+                let review = &reviews[index];
+                let mut choices = vec!["Read the whole review".to_string()];
+                choices.extend(comments_of(review).iter().map(|comment| {
+                    let first = comment.body.lines().next().unwrap_or_default();
+                    format!("{}  {first}", comment.location())
+                }));
+                let title = format!(
+                    "{} at {}",
+                    review_state_label(&review.state),
+                    self.review_commit(review)
+                );
+                let choice =
+                    picker::pick_option(&title, &choices, "enter: go to comment", &self.theme)?;
+                Ok(choice.map(|choice| (index, choice)))
+            });
+        terminal.activate()?;
+        let Some((index, choice)) = picked? else {
+            return Ok(());
+        };
+        let review = &reviews[index];
+        let review_comments = comments_of(review);
+        if choice > 0 {
+            let database_id = review_comments[choice - 1].id;
+            let shown = self
+                .active_threads
+                .iter()
+                .chain(&self.skipped_threads)
+                .any(|thread| {
+                    thread
+                        .comments
+                        .iter()
+                        .any(|comment| comment.database_id == database_id)
+                });
+            if shown {
+                self.record_jump();
+                self.focus_comment(database_id);
+            } else {
+                self.status_line =
+                    Some("That comment's thread isn't shown here – it may be filtered out.".into());
+            }
+            return Ok(());
+        }
+        let mut text = format!(
+            "{} {}\n\n",
+            review_state_label(&review.state).bold(),
+            format!("at {}", self.review_commit(review)).with(self.theme.subtle)
+        );
+        if !review.body.is_empty() {
+            let _ = writeln!(text, "{}\n", review.body.trim_end());
+        }
+        for comment in review_comments {
+            let _ = writeln!(
+                text,
+                "{}\n{}\n",
+                comment.location().with(self.theme.path),
+                comment.body.trim_end()
+            );
+        }
+        terminal.suspend_to_page(self.gh.settings.pager.as_deref(), &text)?;
+        self.clear_status();
+        Ok(())
+    }
+
+    /// The short SHA a review was left on, flagged once the head has moved
+    /// past it.
+    fn review_commit(&self, review: &SubmittedReview) -> String {
+        let Some(sha) = &review.commit_id else {
+            return "unknown commit".into();
+        };
+        let short = &sha[..sha.len().min(7)];
+        match &self.head_oid {
+            Some(head) if head != sha => format!("{short} (head moved since)"),
+            _ => short.to_string(),
+        }
+    }
+
+    /// Lists the unresolved threads on the account's own PR, noting those
+    /// already answered, and jumps to the one picked.
+    fn show_todo(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        if self.unresolved_threads.is_empty() {
+            self.status_line = Some("Nothing left to address.".into());
+            return Ok(());
+        }
+        let account = self.account.as_deref().unwrap_or_default();
+        let options: Vec<String> = self
+            .unresolved_threads
+            .iter()
+            .map(|thread| {
+                let state = if self.queued_reply_index(thread).is_some() {
+                    "reply queued"
+                } else if thread
+                    .comments
+                    .last()
+                    .is_some_and(|comment| comment.author.eq_ignore_ascii_case(account))
+                {
+                    "answered"
+                } else {
+                    "to do"
+                };
+                let latest = thread.comments.last().map_or(String::new(), |comment| {
+                    let first = comment.body.lines().next().unwrap_or_default();
+                    format!("@{}: {first}", comment.author)
+                });
+                format!("{state:<12} {}  {latest}", thread.display_path())
+            })
+            .collect();
+        let title = format!(
+            "To do on #{} ({} unresolved)",
+            self.pr_number,
+            options.len()
+        );
+        terminal.deactivate()?;
+        let picked = picker::pick_option(&title, &options, "enter: go to thread", &self.theme);
+        terminal.activate()?;
+        let Some(index) = picked? else {
+            return Ok(());
+        };
+        let location = Location {
+            thread_id: self.unresolved_threads[index].id.clone(),
+            line: 0,
+        };
+        self.record_jump();
+        self.goto_location(&location);
+        Ok(())
+    }
+
+    /// Replies to the current thread, with its queued reply or a note of
+    /// the head commit to edit, and resolves it. Offers to re-request
+    /// review once nothing is left unresolved on the account's own PR.
+    async fn mark_addressed(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        if thread.is_resolved {
+            self.status_line = Some("This thread is already resolved.".into());
+            return Ok(());
+        }
+        let Some(comment) = thread.comments.last() else {
+            self.status_line = Some("Thread has no comments.".into());
+            return Ok(());
+        };
+        let comment_database_id = comment.database_id;
+        let thread_id = thread.id.clone();
+        let path = thread.display_path();
+        let queued = self.queued_reply_index(thread);
+        let mut template = match (queued, &self.head_oid) {
+            (Some(index), _) => self.queued_replies[index].body.clone(),
+            (None, Some(head)) => format!("Addressed in {}.", &head[..head.len().min(7)]),
+            (None, None) => "Addressed.".into(),
+        };
+        template.push_str(
+            "\n\n# Posted as a reply, then the thread is resolved.\n\
+             # Lines starting with '# ' are ignored; leave it empty to cancel.\n",
+        );
+        let Some(body) = terminal.suspend_for_editor(&self.config.editor_command(), &template)?
+        else {
+            self.status_line = Some("Left the thread open.".into());
+            return Ok(());
+        };
+        self.gh
+            .post_reply(&self.repo, self.pr_number, comment_database_id, &body)
+            .await?;
+        if let Some(index) = queued {
+            self.queued_replies.remove(index);
+        }
+        self.gh.resolve_thread(&thread_id).await?;
+        self.refresh_threads().await?;
+        self.status_line = Some(format!("Replied to {path} and resolved it."));
+        if self.own_pr && self.unresolved_threads.is_empty() {
+            self.offer_rerequest().await?;
+        }
+        Ok(())
+    }
+
+    /// Asks whether to request review again from everyone who has reviewed
+    /// the PR, other than the account itself.
+    async fn offer_rerequest(&mut self) -> Result<()> {
+        let account = self.account.clone().unwrap_or_default();
+        let mut reviewers: Vec<String> = Vec::new();
+        for review in self.gh.reviews(&self.repo, self.pr_number).await? {
+            let login = review.login();
+            if !login.eq_ignore_ascii_case(&account)
+                && !login.ends_with("[bot]")
+                && login != "ghost"
+                && !reviewers.iter().any(|seen| seen == login)
+            {
+                reviewers.push(login.to_string());
+            }
+        }
+        if reviewers.is_empty() {
+            self.status_line = Some("Nobody has reviewed this PR yet.".into());
+            return Ok(());
+        }
+        let left = self.unresolved_threads.len();
+        let state = match left {
+            0 => "All threads addressed".to_string(),
+            1 => "1 thread still unresolved".to_string(),
+            _ => format!("{left} threads still unresolved"),
+        };
+        self.prompt = Some(Prompt {
+            action: Action::ReRequestReview,
+            label: format!(
+                "{state} – re-request review from {}? (y/n)",
+                mention_list(&reviewers)
+            ),
+            text: String::new(),
+        });
+        self.rerequesting = reviewers;
+        Ok(())
+    }
+
+    /// Offers the code owners of the changed files as reviewers, ticking
+    /// those whose review hasn't been requested and who haven't approved,
+    /// and requests reviews from the ones left ticked.
+    async fn request_reviewers(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(code_owners) = &self.code_owners else {
+            self.status_line = Some("The base branch has no CODEOWNERS to suggest from.".into());
+            return Ok(());
+        };
+        if self.pr_files.is_none() {
+            self.pr_files = Some(self.gh.pr_files(&self.repo, self.pr_number).await?);
+        }
+        let author = self.gh.pr_author(self.pr_number).await?;
+        let suggested: Vec<(String, usize)> = code_owners
+            .suggest(
+                self.pr_files
+                    .iter()
+                    .flatten()
+                    .map(|(path, _)| path.as_str()),
+            )
+            .into_iter()
+            // Emails can't be requested, and authors can't review their own PR.
+            .filter(|(owner, _)| {
+                owner.starts_with('@') && !owner[1..].eq_ignore_ascii_case(&author)
+            })
+            .collect();
+        if suggested.is_empty() {
+            self.status_line = Some("CODEOWNERS doesn't name anyone for these files.".into());
+            return Ok(());
+        }
+        let requested = self
+            .gh
+            .requested_reviewers(&self.repo, self.pr_number)
+            .await?;
+        let mut approved: Vec<String> = Vec::new();
+        for review in self.gh.reviews(&self.repo, self.pr_number).await? {
+            if matches!(
+                review.state.as_str(),
+                "APPROVED" | "CHANGES_REQUESTED" | "DISMISSED"
+            ) {
+                approved.retain(|login| login != review.login());
+                if review.state == "APPROVED" {
+                    approved.push(review.login().to_string());
+                }
+            }
+        }
+        let has = |list: &[String], owner: &str| {
+            list.iter()
+                .any(|other| other.eq_ignore_ascii_case(&owner[1..]))
+        };
+        let mut options = Vec::new();
+        let mut ticked = Vec::new();
+        for (owner, count) in &suggested {
+            let state = if has(&approved, owner) {
+                "approved"
+            } else if has(&requested, owner) {
+                "requested"
+            } else if owner.contains('/') {
+                "team not covered"
+            } else {
+                "not covered"
+            };
+            options.push(format!(
+                "{owner:<30} owns {count} file{}  {state}",
+                if *count == 1 { "" } else { "s" }
+            ));
+            ticked.push(state.ends_with("not covered"));
+        }
+        let title = format!("Request reviews on #{} from code owners", self.pr_number);
+        terminal.deactivate()?;
+        let picked = picker::pick_many(&title, &options, ticked, &self.theme);
+        terminal.activate()?;
+        let Some(picked) = picked? else {
+            return Ok(());
+        };
+        let reviewers: Vec<&str> = picked
+            .into_iter()
+            .map(|index| &suggested[index].0[1..])
+            .filter(|reviewer| !has(&requested, &format!("@{reviewer}")))
+            .collect();
+        if reviewers.is_empty() {
+            self.status_line = Some("No new reviewers to request.".into());
+            return Ok(());
+        }
+        self.gh
+            .request_reviewers(self.pr_number, &reviewers)
+            .await?;
+        self.status_line = Some(format!(
+            "Requested reviews from {}.",
+            reviewers
+                .iter()
+                .map(|reviewer| format!("@{reviewer}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        Ok(())
+    }
+
+    async fn submit_tui_review(&mut self, event: ReviewEvent, body: &str) -> Result<()> {
+        let body = review_body(&self.config.checklist, body, &self.checklist);
+        self.gh
+            .submit_review(&self.repo, self.pr_number, event, &body)
+            .await?;
+        // Submitting takes the pending review and its drafts with it.
+        self.pending_drafts = 0;
+        fire_review_hooks(&self.config.hooks, &self.repo, self.pr_number, event, &body);
+        self.status_line = Some(format!(
+            "Submitted review on PR #{}: {}.",
+            self.pr_number,
+            event.describe()
+        ));
+        Ok(())
+    }
+
+    /// Greps the head version of every file the PR changes for `pattern`
+    /// and lists the matches by file. Choosing one jumps to the nearest
+    /// thread on that file.
+    async fn search_files(&mut self, terminal: &mut TerminalSession, pattern: &str) -> Result<()> {
+        if pattern.is_empty() {
+            self.clear_status();
+            return Ok(());
+        }
+        self.last_search = pattern.to_string();
+        if self.pr_files.is_none() {
+            self.pr_files = Some(self.gh.pr_files(&self.repo, self.pr_number).await?);
+        }
+        let (head, _) = self.gh.fetch_status(self.pr_number).await?;
+        let head = head.ok_or_else(|| anyhow!("PR has no head commit"))?;
+        let mut paths: Vec<&str> = self
+            .pr_files
+            .iter()
+            .flatten()
+            .filter(|(_, file)| file.status != "removed")
+            .map(|(path, _)| path.as_str())
+            .collect();
+        paths.sort_unstable();
+        let matches = git::grep(&self.config.worktree.remote, &head, &paths, pattern)?;
+        if matches.is_empty() {
+            self.status_line = Some(format!("No matches for {pattern} in the PR's files."));
+            return Ok(());
+        }
+        let mut files: Vec<&str> = matches.iter().map(|found| found.path.as_str()).collect();
+        files.dedup();
+        let title = format!(
+            "{} match{} for {pattern} in {} file{}",
+            matches.len(),
+            if matches.len() == 1 { "" } else { "es" },
+            files.len(),
+            if files.len() == 1 { "" } else { "s" }
+        );
+        terminal.deactivate()?;
+        let picked = picker::pick_match(&title, &matches, &self.theme);
+        terminal.activate()?;
+        self.clear_status();
+        if let Some(found) = picked? {
+            let target = FileTarget::new(&found.path, Some(found.line as i64));
+            self.focus_file(&target);
+        }
+        Ok(())
+    }
+
+    /// Shows only threads on files matching `raw`, or every file when it is
+    /// blank.
+    async fn filter_files(&mut self, raw: &str) -> Result<()> {
+        let filter = FileFilter::parse(raw)?;
+        if filter.as_ref().is_some_and(FileFilter::needs_statuses) && self.pr_files.is_none() {
+            self.pr_files = Some(self.gh.pr_files(&self.repo, self.pr_number).await?);
+        }
+        let all: Vec<Thread> = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .chain(&self.hidden_threads)
+            .cloned()
+            .collect();
+        self.status_line = Some(match &filter {
+            Some(filter) => format!("Showing threads on files matching {}.", filter.raw),
+            None => "Showing threads on every file.".into(),
+        });
+        self.file_filter = filter;
+        self.set_threads(all);
+        Ok(())
+    }
+
+    /// Reorders every thread list, fetching file sizes first if the sort
+    /// needs them.
+    async fn set_sort(&mut self, sort: ThreadSort) -> Result<()> {
+        if sort == ThreadSort::Changes && self.pr_files.is_none() {
+            self.pr_files = Some(self.gh.pr_files(&self.repo, self.pr_number).await?);
+        }
+        self.sort = sort;
+        self.reorder_threads();
+        Ok(())
+    }
+
+    /// Sorts every thread list again, keeping the selections.
+    fn reorder_threads(&mut self) {
+        let all: Vec<Thread> = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .chain(&self.hidden_threads)
+            .cloned()
+            .collect();
+        self.set_threads(all);
+    }
+
+    /// Remembers local state about to change so it can be undone; a new
+    /// change drops anything that was undone.
+    fn record_edit(&mut self, edit: Edit) {
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    /// Steps back through local changes, or forward again with `redo`.
+    fn undo_edit(&mut self, redo: bool) {
+        let (from, verb) = if redo {
+            (&mut self.redo, "Redid")
+        } else {
+            (&mut self.undo, "Undid")
+        };
+        let Some(edit) = from.pop() else {
+            self.status_line = Some(format!(
+                "Nothing to {}.",
+                if redo { "redo" } else { "undo" }
+            ));
+            return;
+        };
+        let what = edit.describe();
+        let replaced = match edit {
+            Edit::Replies(replies) => {
+                Edit::Replies(mem::replace(&mut self.queued_replies, replies))
+            }
+            Edit::Viewed(files) => Edit::Viewed(mem::replace(&mut self.viewed_files, files)),
+            Edit::Pinned(pinned) => {
+                let replaced = Edit::Pinned(mem::replace(&mut self.pinned, pinned));
+                self.reorder_threads();
+                replaced
+            }
+            Edit::Checklist(ticked) => Edit::Checklist(mem::replace(&mut self.checklist, ticked)),
+        };
+        if redo {
+            self.undo.push(replaced);
+        } else {
+            self.redo.push(replaced);
+        }
+        self.status_line = Some(format!("{verb} {what}."));
+    }
+
+    /// Pins the selected thread to the top of every view, or unpins it.
+    fn toggle_pin(&mut self) {
+        let Some(id) = self.current_thread().map(|thread| thread.id.clone()) else {
+            self.status_line = Some("No thread to pin.".into());
+            return;
+        };
+        self.record_edit(Edit::Pinned(self.pinned.clone()));
+        if self.pinned.remove(&id) {
+            self.status_line = Some("Unpinned the thread.".into());
+        } else {
+            self.pinned.insert(id);
+            self.status_line = Some("Pinned the thread to the top.".into());
+        }
+        self.reorder_threads();
+    }
+
+    /// Applies the configured sort; threads start out in creation order.
+    async fn load_sort(&mut self) {
+        if self.sort == ThreadSort::Created {
+            return;
+        }
+        if let Err(err) = self.set_sort(self.sort).await {
+            self.sort = ThreadSort::Created;
+            self.report_failure(format!("Failed to sort threads: {err}"));
+        }
+    }
+
+    /// Looks for a force-push in the PR's timeline and, if there was one,
+    /// offers a range-diff of it.
+    async fn load_force_push(&mut self) {
+        match self.gh.last_force_push(&self.repo, self.pr_number).await {
+            Ok(Some(push)) => {
+                if self.status_line.is_none() {
+                    self.status_line = Some(format!(
+                        "Force-pushed {}{} – press {} to see what changed",
+                        humanize_relative(Utc::now(), push.at),
+                        push.actor
+                            .as_deref()
+                            .map(|actor| format!(" by {actor}"))
+                            .unwrap_or_default(),
+                        self.key_label(Action::RangeDiff)
+                    ));
+                }
+                self.force_push = Some(push);
+            }
+            Ok(None) => {}
+            Err(err) => log::warn!("failed to look up force-pushes: {err}"),
+        }
+    }
+
+    /// Lists the PR's commits with their signature status and checks, for
+    /// the header's unverified count and the commit list.
+    async fn load_commits(&mut self) {
+        match self.gh.pr_commits(&self.repo, self.pr_number).await {
+            Ok(commits) => self.commits = commits,
+            Err(err) => {
+                log::warn!("failed to list commits: {err}");
+                return;
+            }
+        }
+        match self.gh.commit_checks(&self.repo, self.pr_number).await {
+            Ok(checks) => {
+                for commit in &mut self.commits {
+                    commit.checks = checks.get(&commit.sha).copied();
+                }
+            }
+            Err(err) => log::warn!("failed to look up commit checks: {err}"),
+        }
+    }
+
+    /// Pages the PR's commits, oldest first, each with how its checks went
+    /// and marked verified or not with GitHub's reason.
+    fn show_commits(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        if self.commits.is_empty() {
+            self.status_line = Some("The PR's commits aren't known.".into());
+            return Ok(());
+        }
+        let mut text = String::new();
+        for commit in &self.commits {
+            let short = &commit.sha[..commit.sha.len().min(7)];
+            let state = match (&commit.signature, commit.verified) {
+                (Some(kind), true) => format!("✓ {kind} verified").with(self.theme.resolved),
+                _ => format!("✗ {}", commit.reason.replace('_', " ")).with(self.theme.unresolved),
+            };
+            let checks = match commit.checks {
+                Some(CheckState::Pass) => "✓".with(self.theme.resolved),
+                Some(CheckState::Fail) => "✗".with(self.theme.diff_removed),
+                Some(CheckState::Pending) => "•".with(self.theme.subtle),
+                Some(CheckState::Skipped) | None => " ".with(self.theme.subtle),
+            };
+            writeln!(
+                text,
+                "{checks} {} {}  {} {}",
+                short.with(self.theme.subtle),
+                commit.subject,
+                format!("({})", commit.author).with(self.theme.subtle),
+                state
+            )?;
+        }
+        terminal.suspend_to_page(self.gh.settings.pager.as_deref(), &text)?;
+        self.clear_status();
+        Ok(())
+    }
+
+    async fn load_annotations(&mut self) {
+        match self.gh.annotations(&self.repo, self.pr_number).await {
+            Ok(annotations) => self.annotations = annotations,
+            Err(err) => log::warn!("failed to fetch check annotations: {err}"),
+        }
+    }
+
+    /// The annotations on lines of `thread`'s hunk, worst first.
+    fn annotations_on(&self, thread: &Thread) -> Vec<&Annotation> {
+        let Some(hunk) = &thread.diff_hunk else {
+            return Vec::new();
+        };
+        let numbers = diff::new_line_numbers(hunk);
+        let mut found: Vec<&Annotation> = self
+            .annotations
+            .iter()
+            .filter(|annotation| {
+                annotation.path == thread.path
+                    && numbers
+                        .iter()
+                        .flatten()
+                        .any(|line| annotation.covers(*line))
+            })
+            .collect();
+        found.sort_by_key(|annotation| (Reverse(annotation.severity()), annotation.start));
+        found
+    }
+
+    fn annotation_color(&self, annotation: &Annotation) -> Color {
+        match annotation.severity() {
+            2 => self.theme.diff_removed,
+            1 => self.theme.unresolved,
+            _ => self.theme.unread,
+        }
+    }
+
+    /// The annotations below a hunk, one line each until expanded.
+    fn write_annotations(&self, buf: &mut String, annotations: &[&Annotation]) -> std::fmt::Result {
+        let wrap_opts = WrapOptions::new(COMMENT_WRAP).break_words(false);
+        for annotation in annotations {
+            let lines = if annotation.end > annotation.start {
+                format!("L{}-{}", annotation.start, annotation.end)
+            } else {
+                format!("L{}", annotation.start)
+            };
+            writeln!(
+                buf,
+                "{} {} {}",
+                annotation.symbol().with(self.annotation_color(annotation)),
+                format!("{lines} {}:", annotation.check).with(self.theme.subtle),
+                annotation.summary()
+            )?;
+            if self.expand_annotations {
+                for line in annotation.message.lines() {
+                    for chunk in wrap(line, wrap_opts.clone()) {
+                        writeln!(buf, "    {}", chunk.with(self.theme.subtle))?;
+                    }
+                }
+            }
+        }
+        let more = annotations
+            .iter()
+            .any(|annotation| annotation.summary() != annotation.message.trim_end());
+        if more && !self.expand_annotations {
+            writeln!(
+                buf,
+                "{}",
+                format!(
+                    "(press {} for the full messages)",
+                    self.key_label(Action::ToggleAnnotations)
+                )
+                .with(self.theme.subtle)
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Looks up advisories for the dependencies the PR changes, when it
+    /// touches a manifest or lockfile. GitHub only answers where the
+    /// dependency graph is enabled and the account may read it.
+    async fn load_advisories(&mut self) {
+        let (Some(base), Some(head)) = (&self.base_oid, &self.head_oid) else {
+            return;
+        };
+        if !self
+            .pr_files
+            .iter()
+            .flat_map(HashMap::keys)
+            .any(|path| advisory::is_manifest(path))
+        {
+            return;
+        }
+        let changes = match self.gh.dependency_changes(&self.repo, base, head).await {
+            Ok(changes) => changes,
+            Err(err) => {
+                log::warn!("failed to compare the PR's dependencies: {err}");
+                return;
+            }
+        };
+        let mut bumps = advisory::bumps(changes);
+        for vulnerability in bumps
+            .iter_mut()
+            .flat_map(|bump| bump.fixed.iter_mut().chain(bump.introduced.iter_mut()))
+        {
+            match self.gh.advisory_cve(&vulnerability.advisory_ghsa_id).await {
+                Ok(cve) => vulnerability.cve_id = cve,
+                Err(err) => log::debug!(
+                    "failed to look up {}: {err}",
+                    vulnerability.advisory_ghsa_id
+                ),
+            }
+        }
+        self.bumps = bumps;
+    }
+
+    /// Pages the dependencies the PR changes, those with advisories first,
+    /// and the advisories each change fixes or introduces.
+    fn show_advisories(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        if self.bumps.is_empty() {
+            self.status_line =
+                Some("The PR doesn't change any dependencies GitHub knows of.".into());
+            return Ok(());
+        }
+        let mut bumps: Vec<&advisory::Bump> = self.bumps.iter().collect();
+        bumps.sort_by_key(|bump| (bump.introduced.is_empty(), bump.fixed.is_empty()));
+        let mut text = String::new();
+        for bump in bumps {
+            let versions = match (&bump.from, &bump.to) {
+                (Some(from), Some(to)) => format!("{from} → {to}"),
+                (None, Some(to)) => format!("added at {to}"),
+                (Some(from), None) => format!("removed at {from}"),
+                (None, None) => String::new(),
+            };
+            writeln!(
+                text,
+                "{} {versions}  {}",
+                bump.name.as_str().with(self.theme.path),
+                format!("({}, {})", bump.ecosystem, bump.manifest).with(self.theme.subtle)
+            )?;
+            let sides = [
+                ("fixes", self.theme.resolved, &bump.fixed),
+                ("introduces", self.theme.diff_removed, &bump.introduced),
+            ];
+            for (label, color, vulnerabilities) in sides {
+                for vulnerability in vulnerabilities {
+                    let id = vulnerability
+                        .cve_id
+                        .as_deref()
+                        .unwrap_or(&vulnerability.advisory_ghsa_id);
+                    writeln!(
+                        text,
+                        "  {} {:<8} {}  {}",
+                        label.with(color),
+                        vulnerability.severity,
+                        self.links.link(id, &vulnerability.advisory_url),
+                        vulnerability.advisory_summary
+                    )?;
+                }
+            }
+        }
+        terminal.suspend_to_page(self.gh.settings.pager.as_deref(), &text)?;
+        self.clear_status();
+        Ok(())
+    }
+
+    /// Asks GitHub whether the PR merges cleanly.
+    async fn load_merge_state(&mut self) {
+        match self.gh.merge_state(self.pr_number).await {
+            Ok(state) => {
+                self.conflicting_base = (state.mergeable == "CONFLICTING").then_some(state.base);
+            }
+            Err(err) => log::warn!("failed to look up whether the PR merges: {err}"),
+        }
+    }
+
+    /// Test-merges the PR locally and pages the files that conflict, with
+    /// their conflict regions.
+    fn show_conflicts(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(base) = &self.conflicting_base else {
+            self.status_line = Some("GitHub doesn't report any conflicts.".into());
+            return Ok(());
+        };
+        let Some(head) = &self.head_oid else {
+            self.status_line = Some("The PR's commits aren't known.".into());
+            return Ok(());
+        };
+        let conflicts = git::conflicts(&self.config.worktree.remote, base, head)?;
+        if conflicts.is_empty() {
+            self.status_line = Some(format!("The PR now merges cleanly into {base}."));
+            return Ok(());
+        }
+        let mut text = format!(
+            "{}\n\n",
+            format!(
+                "{} file{} conflict with {base}",
+                conflicts.len(),
+                if conflicts.len() == 1 { "" } else { "s" }
+            )
+            .bold()
+        );
+        for conflict in &conflicts {
+            writeln!(
+                text,
+                "{}",
+                conflict.path.as_str().with(self.theme.path).bold()
+            )?;
+            if conflict.regions.is_empty() {
+                writeln!(
+                    text,
+                    "{}",
+                    "  (deleted on one side)".with(self.theme.subtle)
+                )?;
+            }
+            for region in &conflict.regions {
+                for line in region.lines() {
+                    if ["<<<<<<<", "=======", ">>>>>>>"]
+                        .iter()
+                        .any(|marker| line.starts_with(marker))
+                    {
+                        writeln!(text, "  {}", line.with(self.theme.mark))?;
+                    } else {
+                        writeln!(text, "  {line}")?;
+                    }
+                }
+                writeln!(text)?;
+            }
+        }
+        terminal.suspend_to_page(self.gh.settings.pager.as_deref(), &text)?;
+        self.clear_status();
+        Ok(())
+    }
+
+    /// Finds the files the PR renames. GitHub says which they are; Git,
+    /// when it can reach both commits, says how similar each still is.
+    async fn load_renames(&mut self) {
+        let files = match self.gh.pr_files(&self.repo, self.pr_number).await {
+            Ok(files) => files,
+            Err(err) => {
+                log::warn!("failed to list PR files: {err}");
+                return;
+            }
+        };
+        self.renames = files
+            .iter()
+            .filter_map(|(path, file)| {
+                let from = file.previous.clone()?;
+                Some((
+                    path.clone(),
+                    git::Rename {
+                        from,
+                        similarity: None,
+                    },
+                ))
+            })
+            .collect();
+        self.pr_files = Some(files);
+        match self.gh.pr_refs(self.pr_number).await {
+            Ok((base, head)) => {
+                if !self.renames.is_empty() {
+                    match git::renames(&self.config.worktree.remote, &base, &head) {
+                        Ok(found) => {
+                            for (path, rename) in found {
+                                if let Some(known) = self.renames.get_mut(&path) {
+                                    known.similarity = rename.similarity;
+                                }
+                            }
+                        }
+                        Err(err) => log::debug!("failed to measure renames: {err}"),
+                    }
+                }
+                self.base_oid = Some(base);
+                self.head_oid = Some(head);
+            }
+            Err(err) => log::warn!("failed to look up the PR's commits: {err}"),
+        }
+    }
+
+    /// The row standing in for `count` folded unchanged lines.
+    fn fold_marker(&self, count: usize) -> String {
+        format!(
+            "⋯ {count} unchanged lines (press {} to unfold)",
+            self.key_label(Action::ToggleFold)
+        )
+        .with(self.theme.subtle)
+        .to_string()
+    }
+
+    /// Colours a line of a diff hunk by its kind, marking whitespace errors
+    /// and the missing-newline marker so they can't be missed, and expands
+    /// its tabs.
+    fn style_diff_line(&self, line: &str) -> String {
+        if diff::is_no_newline_marker(line) {
+            return line
+                .with(self.theme.diff_context)
+                .on(self.theme.diff_whitespace)
+                .to_string();
+        }
+        let color = match line.chars().next() {
+            Some('+') => self.theme.diff_added,
+            Some('-') => self.theme.diff_removed,
+            Some('@') => self.theme.diff_header,
+            _ => self.theme.diff_context,
+        };
+        let mut styled = String::new();
+        let mut at = 0;
+        for error in diff::whitespace_errors(line) {
+            if at < error.start {
+                styled.push_str(&line[at..error.start].with(color).to_string());
+            }
+            styled.push_str(
+                &line[error.clone()]
+                    .on(self.theme.diff_whitespace)
+                    .to_string(),
+            );
+            at = error.end;
+        }
+        styled.push_str(&line[at..].with(color).to_string());
+        if line.contains('\t') {
+            return diff::expand_tabs(&styled, self.config.diff.tab_width).into_owned();
+        }
+        styled
+    }
+
+    /// Looks up the commits behind any submodule pointer change that a
+    /// thread is on, so the hunk can say more than two hashes.
+    async fn load_submodules(&mut self) {
+        let mut bumps: Vec<(String, String, String)> = Vec::new();
+        for thread in self.active_threads.iter().chain(&self.skipped_threads) {
+            if bumps.iter().any(|(path, ..)| *path == thread.path) {
+                continue;
+            }
+            if let Some((old, new)) = thread.diff_hunk.as_deref().and_then(submodule::bump) {
+                bumps.push((thread.path.clone(), old, new));
+            }
+        }
+        if bumps.is_empty() {
+            return;
+        }
+        let gitmodules = match self.gh.pr_refs(self.pr_number).await {
+            Ok((_, head)) => match self.gh.file_at(&self.repo, ".gitmodules", &head).await {
+                Ok(gitmodules) => gitmodules.unwrap_or_default(),
+                Err(err) => {
+                    log::warn!("failed to read .gitmodules: {err}");
+                    String::new()
+                }
+            },
+            Err(err) => {
+                log::warn!("failed to look up the PR's commits: {err}");
+                String::new()
+            }
+        };
+        for (path, old, new) in bumps {
+            let mut bump = SubmoduleBump {
+                old,
+                new,
+                compare_url: None,
+                range: None,
+            };
+            if let Some((owner, name)) = submodule::url_for(&gitmodules, &path)
+                .and_then(|url| submodule::repo_of(&url, &self.repo.owner))
+            {
+                bump.compare_url = Some(format!(
+                    "{}/{owner}/{name}/compare/{}...{}",
+                    self.repo.web_base(),
+                    bump.old,
+                    bump.new
+                ));
+                match self.gh.compare(&owner, &name, &bump.old, &bump.new).await {
+                    Ok(range) => {
+                        bump.compare_url = Some(range.url.clone());
+                        bump.range = Some(range);
+                    }
+                    Err(err) => log::debug!("failed to compare {owner}/{name}: {err}"),
+                }
+            }
+            self.submodules.insert(path, bump);
+        }
+    }
+
+    /// `Submodule old → new` with the commits between them, linked to the
+    /// comparison on GitHub when the submodule's repository is known.
+    fn write_submodule_bump(&self, buf: &mut String, bump: &SubmoduleBump) -> std::fmt::Result {
+        let short = |sha: &str| sha[..sha.len().min(7)].to_string();
+        let mut summary = format!("Submodule {} → {}", short(&bump.old), short(&bump.new));
+        if let Some(range) = &bump.range {
+            summary.push_str(&format!(
+                ": {} commit{}",
+                range.commits,
+                if range.commits == 1 { "" } else { "s" }
+            ));
+            if range.behind > 0 {
+                summary.push_str(&format!(", back {}", range.behind));
+            }
+        }
+        let summary = summary.with(self.theme.path).to_string();
+        match &bump.compare_url {
+            Some(url) => writeln!(buf, "{}", self.links.link(summary, url))?,
+            None => writeln!(buf, "{summary}")?,
+        }
+        if let Some(range) = &bump.range {
+            match (&range.first, &range.last) {
+                (Some(first), Some(last)) if range.commits > 1 => {
+                    writeln!(buf, "  first: {first}")?;
+                    writeln!(buf, "  last:  {last}")?;
+                }
+                (_, Some(last)) => writeln!(buf, "  {last}")?,
+                _ => {}
+            }
+        }
+        if let Some(url) = &bump.compare_url
+            && !self.links.enabled()
+        {
+            writeln!(buf, "  {}", url.as_str().with(self.theme.subtle))?;
+        }
+        Ok(())
+    }
+
+    /// Pages the current thread's file as it was on the base branch, under
+    /// its old name if the PR renamed it.
+    fn show_base_file(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        let Some(base) = &self.base_oid else {
+            self.status_line = Some("The PR's base commit isn't known.".into());
+            return Ok(());
+        };
+        let path = match self.renames.get(&thread.path) {
+            Some(rename) => rename.from.clone(),
+            None => thread.path.clone(),
+        };
+        if self
+            .pr_files
+            .as_ref()
+            .and_then(|files| files.get(&thread.path))
+            .is_some_and(|file| file.status == "added")
+        {
+            self.status_line = Some(format!("{path} is new in this PR."));
+            return Ok(());
+        }
+        let text = git::show_file(&self.config.worktree.remote, base, &path)?;
+        terminal.suspend_to_page(self.gh.settings.pager.as_deref(), &text)?;
+        self.clear_status();
+        Ok(())
+    }
+
+    /// Shows the current thread's image as it was on the base branch and as
+    /// the PR leaves it, drawn when the terminal supports graphics and
+    /// described by format and size otherwise.
+    fn show_image_diff(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        if !image::is_image(&thread.path) {
+            self.status_line = Some(format!("{} isn't an image.", thread.path));
+            return Ok(());
+        }
+        let (Some(base), Some(head)) = (&self.base_oid, &self.head_oid) else {
+            self.status_line = Some("The PR's commits aren't known.".into());
+            return Ok(());
+        };
+        let status = self
+            .pr_files
+            .as_ref()
+            .and_then(|files| files.get(&thread.path))
+            .map(|file| file.status.as_str());
+        let old_path = match self.renames.get(&thread.path) {
+            Some(rename) => rename.from.as_str(),
+            None => thread.path.as_str(),
+        };
+        let remote = &self.config.worktree.remote;
+        let before = match status {
+            Some("added") => None,
+            _ => Some(git::show_bytes(remote, base, old_path)?),
+        };
+        let after = match status {
+            Some("removed") => None,
+            _ => Some(git::show_bytes(remote, head, &thread.path)?),
+        };
+        let graphics = image::Graphics::detect();
+        let columns = self.terminal_size().map_or(80, |(width, _)| width / 2);
+        let mut text = String::new();
+        for (label, bytes) in [("Before", before), ("After", after)] {
+            text.push_str(&format!("{}\r\n", label.bold()));
+            let Some(bytes) = bytes else {
+                text.push_str(&format!("{}\r\n\r\n", "(none)".with(self.theme.subtle)));
+                continue;
+            };
+            text.push_str(&format!("{}\r\n", image::describe(&bytes)));
+            if graphics != image::Graphics::None {
+                match image::draw(graphics, &bytes, columns) {
+                    Ok(drawn) => text.push_str(&drawn),
+                    Err(err) => log::debug!("failed to draw {}: {err}", thread.path),
+                }
+            }
+            text.push_str("\r\n\r\n");
+        }
+        terminal.show_until_key(&text, graphics == image::Graphics::Kitty)?;
+        self.clear_status();
+        Ok(())
+    }
+
+    /// `old → new` for a renamed file, with how similar it still is;
+    /// otherwise the path alone.
+    fn file_label(&self, path: &str) -> String {
+        match self.renames.get(path) {
+            Some(rename) => match rename.similarity {
+                Some(similarity) => format!("{} → {path} ({similarity}% similar)", rename.from),
+                None => format!("{} → {path}", rename.from),
+            },
+            None => path.to_string(),
+        }
+    }
+
+    /// Pages a `git range-diff` of the last force-push: which commits were
+    /// added, dropped or changed by the rewrite.
+    fn show_range_diff(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(push) = &self.force_push else {
+            self.status_line = Some("This PR hasn't been force-pushed.".into());
+            return Ok(());
+        };
+        let output = git::range_diff(
+            &self.config.worktree.remote,
+            &push.base,
+            &push.before,
+            &push.after,
+        )?;
+        if output.is_empty() {
+            self.status_line = Some("The force-push didn't change any commits.".into());
+            return Ok(());
+        }
+        terminal.suspend_to_page(self.gh.settings.pager.as_deref(), &output)?;
+        self.clear_status();
+        Ok(())
+    }
+
+    /// Leaves for the PR above (towards the top of the stack) or below.
+    fn move_in_stack(&mut self, up: bool) {
+        let Some(index) = self
+            .stack
+            .iter()
+            .position(|number| *number == self.pr_number)
+        else {
+            self.status_line = Some("This PR isn't part of a stack.".into());
+            return;
+        };
+        let target = if up {
+            self.stack.get(index + 1)
+        } else {
+            index.checked_sub(1).and_then(|index| self.stack.get(index))
+        };
+        match target {
+            Some(number) => self.exit = Some(Exit::Open(*number)),
+            None if self.stack.len() < 2 => {
+                self.status_line = Some("This PR isn't part of a stack.".into());
+            }
+            None if up => self.status_line = Some("Already at the top of the stack.".into()),
+            None => self.status_line = Some("Already at the bottom of the stack.".into()),
+        }
+    }
+
+    /// Adds a performed action to the macro being recorded, if any.
+    /// Starting, stopping and playing macros aren't recorded themselves.
+    fn record_step(&mut self, action: Action, name: Option<char>) {
+        if let Some((_, steps)) = &mut self.recording
+            && !matches!(action, Action::RecordMacro | Action::PlayMacro)
+        {
+            steps.push((action, name));
+        }
+    }
+
+    /// Replays the actions recorded into register `name`. Replay stops
+    /// early when a step opens a prompt or leaves the review, since the
+    /// typing that followed wasn't recorded.
+    async fn play_macro(&mut self, name: char, terminal: &mut TerminalSession) {
+        if self.recording.is_some() {
+            self.status_line = Some("Can't play a macro while recording one.".into());
+            return;
+        }
+        let Some(steps) = self.macros.get(&name).cloned() else {
+            self.status_line = Some(format!("Macro '{name}' is not recorded."));
+            return;
+        };
+        self.clear_status();
+        for (action, letter) in steps {
+            match letter {
+                Some(letter) if action == Action::AddNote => self.note(letter, terminal),
+                Some(letter) => self.finish_prefix(action, letter),
+                None => self.perform(action, terminal).await,
+            }
+            // A prefix action now waits for the letter that came with it.
+            self.pending_prefix = None;
+            if self.prompt.is_some() || self.exit.is_some() {
+                break;
+            }
+        }
+    }
+
+    fn finish_prefix(&mut self, action: Action, name: char) {
+        match action {
+            Action::RecordMacro => {
+                self.recording = Some((name, Vec::new()));
+                self.status_line = Some(format!(
+                    "Recording macro '{name}' – press {} to stop.",
+                    self.key_label(Action::RecordMacro)
+                ));
+            }
+            Action::SwitchPr if name == 'p' => self.exit = Some(Exit::Pick),
+            Action::SwitchPr => {
+                let index = name
+                    .to_digit(10)
+                    .and_then(|digit| (digit as usize).checked_sub(1))
+                    .filter(|index| *index < self.open_prs.len());
+                match index {
+                    Some(index) if self.open_prs[index] == self.pr_number => {
+                        self.status_line = Some(format!("Already on PR #{}.", self.pr_number));
+                    }
+                    Some(index) => self.exit = Some(Exit::Switch(index)),
+                    None => self.status_line = Some(format!("No open PR '{name}'.")),
+                }
+            }
+            Action::GotoPrev | Action::GotoNext => {
+                let forward = action == Action::GotoNext;
+                self.clear_status();
+                match name {
+                    't' => {
+                        self.record_jump();
+                        if forward {
+                            self.next_thread();
+                        } else {
+                            self.prev_thread();
+                        }
+                    }
+                    'f' => {
+                        self.record_jump();
+                        self.move_to_file(forward);
+                    }
+                    'u' => self.move_to_unread(forward),
+                    's' => self.move_in_stack(forward),
+                    _ => {}
+                }
+            }
+            Action::Copy => {
+                if let Err(err) = self.copy_item(name) {
+                    self.report_failure(format!("Failed to copy: {err}"));
+                }
+            }
+            Action::SetMark => {
+                let Some(location) = self.current_location() else {
+                    self.status_line = Some("No thread to mark.".into());
+                    return;
+                };
+                self.marks.insert(name, location);
+                self.status_line = Some(format!("Mark '{name}' set."));
+            }
+            _ => {
+                let Some(location) = self.marks.get(&name).cloned() else {
+                    self.status_line = Some(format!("Mark '{name}' is not set."));
+                    return;
+                };
+                self.record_jump();
+                if self.goto_location(&location) {
+                    self.clear_status();
+                } else {
+                    self.status_line = Some(format!("Mark '{name}' no longer exists."));
+                }
+            }
+        }
+    }
+
+    fn record_jump(&mut self) {
+        let Some(location) = self.current_location() else {
+            return;
+        };
+        self.jumps.truncate(self.jump_index);
+        if self.jumps.last() != Some(&location) {
+            self.jumps.push(location);
+        }
+        self.jump_index = self.jumps.len();
+    }
+
+    fn jump_back(&mut self) {
+        if self.jump_index == 0 {
+            self.status_line = Some("Already at the oldest jump.".into());
+            return;
+        }
+        if self.jump_index == self.jumps.len()
+            && let Some(location) = self.current_location()
+        {
+            self.jumps.push(location);
+        }
+        self.jump_index -= 1;
+        let location = self.jumps[self.jump_index].clone();
+        if !self.goto_location(&location) {
+            self.status_line = Some("Jump target no longer exists.".into());
+        }
+    }
+
+    fn jump_forward(&mut self) {
+        if self.jump_index + 1 >= self.jumps.len() {
+            self.status_line = Some("Already at the newest jump.".into());
+            return;
+        }
+        self.jump_index += 1;
+        let location = self.jumps[self.jump_index].clone();
+        if !self.goto_location(&location) {
+            self.status_line = Some("Jump target no longer exists.".into());
+        }
+    }
+
+    /// An author's name linked to their profile, coloured per the `authors`
+    /// setting.
+    fn author(&self, name: &str) -> String {
+        let color = match self.authors {
+            AuthorStyle::Plain => self.theme.author,
+            AuthorStyle::Color | AuthorStyle::Badge => self.theme.author_color(name),
+        };
+        let link = self.links.link(
+            name.with(color).bold(),
+            &format!("{}/{}", self.repo.web_base(), name),
+        );
+        if self.authors != AuthorStyle::Badge {
+            return link;
+        }
+        format!(
+            "{} {link}",
+            format!(" {} ", initials(name)).with(color).reverse()
+        )
+    }
+
+    /// A blame annotation with its author column in the author's colour.
+    fn blame_annotation(&self, annotation: &str) -> String {
+        let split = annotation
+            .char_indices()
+            .nth(BLAME_AUTHOR_WIDTH)
+            .map_or(annotation.len(), |(index, _)| index);
+        let (author, rest) = annotation.split_at(split);
+        let color = match author.trim() {
+            "" => self.theme.subtle,
+            _ if self.authors == AuthorStyle::Plain => self.theme.subtle,
+            name => self.theme.author_color(name),
+        };
+        format!("{}{}", author.with(color), rest.with(self.theme.subtle))
+    }
+
+    /// `then` relative to `now`, or in local time once toggled.
+    fn timestamp(&self, now: DateTime<Utc>, then: DateTime<Utc>) -> String {
+        if self.absolute_times {
+            then.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M %Z")
+                .to_string()
+        } else {
+            humanize_relative(now, then)
+        }
+    }
+
+    /// The terminal's size, or the in-memory screen's when headless.
+    fn terminal_size(&self) -> io::Result<(u16, u16)> {
+        #[cfg(test)]
+        if let Some(headless) = &self.headless {
+            return Ok((headless.width, headless.height));
+        }
+        size()
+    }
+
+    fn viewport_height(&self) -> usize {
+        self.terminal_size()
+            .map_or(0, |(_, height)| height as usize)
+    }
+
+    /// Waits up to `timeout` for input. A headless review quits once its
+    /// script has run out.
+    fn poll_event(&mut self, timeout: Duration) -> Result<bool> {
+        #[cfg(test)]
+        if let Some(headless) = &self.headless {
+            if headless.is_done() {
+                self.exit.get_or_insert(Exit::Quit);
+            }
+            return Ok(!headless.is_done());
+        }
+        Ok(event::poll(timeout)?)
+    }
+
+    fn read_event(&mut self) -> Result<Event> {
+        #[cfg(test)]
+        if let Some(headless) = &mut self.headless {
+            return headless
+                .next_event()
+                .ok_or_else(|| anyhow!("the input script has run out"));
+        }
+        Ok(event::read()?)
+    }
+
+    fn key_label(&self, action: Action) -> String {
+        self.keymap
+            .label(self.view, action)
+            .unwrap_or_else(|| "(unbound)".into())
+    }
+
+    fn render(&mut self) -> Result<()> {
+        // Plain mode reads threads out as they're visited instead.
+        if self.plain {
+            return Ok(());
+        }
+        self.mark_seen();
+        self.prepare_external_diff(true);
+        self.prepare_blame(true);
+        let (width, height) = self.terminal_size()?;
+        let started = Instant::now();
+        #[cfg(test)]
+        if let Some(mut headless) = self.headless.take() {
+            headless.screen.clear();
+            let drawn = self.draw(&mut headless.screen, width, height);
+            self.headless = Some(headless);
+            drawn?;
+            self.perf.record_frame(started.elapsed());
+            return Ok(());
+        }
+        self.draw(&mut stdout(), width, height)?;
+        self.perf.record_frame(started.elapsed());
+        Ok(())
+    }
+
+    /// Marks the current thread's file viewed once it's been moved to. Run
+    /// from the event loop rather than from render, so redrawing after an
+    /// undo doesn't record an edit and throw away what could be redone.
+    fn mark_viewed(&mut self) {
+        let Some(thread) = self.current_thread() else {
+            return;
+        };
+        if self.last_shown.as_ref() == Some(&thread.id) {
+            return;
+        }
+        let id = thread.id.clone();
+        if !self.viewed_files.contains(&thread.path) {
+            let path = thread.path.clone();
+            self.record_edit(Edit::Viewed(self.viewed_files.clone()));
+            self.viewed_files.insert(path);
+        }
+        self.last_shown = Some(id);
+    }
+
+    /// Marks the current thread's comments read.
+    fn mark_seen(&mut self) {
+        if let Some(thread) = self.current_thread()
+            && let Some(newest) = newest_comment(thread)
+        {
+            let id = thread.id.clone();
+            self.seen.insert(id, newest);
+        }
+    }
+
+    /// Draws the visible part of the view into `out`. Each part is put into
+    /// its own area of a screen buffer, which is written out at the end.
+    /// The frame, its line index, the crop buffer and the screen buffer
+    /// are kept between calls so scrolling only re-lays out the view
+    /// instead of reallocating it.
+    fn draw(&mut self, out: &mut impl Write, width: u16, height: u16) -> Result<()> {
+        execute!(out, MoveTo(0, 0), Clear(ClearType::All))?;
+        let viewport = height as usize;
+        if viewport == 0 {
+            return Ok(());
+        }
+        let mut frame = mem::take(&mut self.frame);
+        self.layout(
+            &mut frame,
+            Some(self.scroll_offset..self.scroll_offset.saturating_add(viewport)),
+        );
+        let total = frame.len();
+        let max_offset = total.saturating_sub(viewport);
+        if self.scroll_offset > max_offset {
+            // The lines now in view were left raw; lay them out again.
+            self.scroll_offset = max_offset;
+            self.layout(
+                &mut frame,
+                Some(self.scroll_offset..self.scroll_offset.saturating_add(viewport)),
+            );
+        }
+        let selected = self.selection.map(|selection| selection.lines());
+        let current_id = self.current_thread().map(|thread| thread.id.as_str());
+        let marked: HashMap<usize, char> = self
+            .marks
+            .iter()
+            .filter(|(_, location)| Some(location.thread_id.as_str()) == current_id)
+            .map(|(name, location)| (location.line, *name))
+            .collect();
+        let area = Rect::new(0, 0, width, height);
+        let mut screen = mem::take(&mut self.screen);
+        screen.clear();
+        let mut cropped = mem::take(&mut self.crop_buf);
+        for row in 0..viewport.min(total - self.scroll_offset) {
+            let index = self.scroll_offset + row;
+            let line = frame.line(index);
+            let y = area.y + row as u16;
+            cropped.clear();
+            if selected
+                .as_ref()
+                .is_some_and(|lines| lines.contains(&index))
+            {
+                let plain = ansi::strip(line);
+                ansi::crop_into(&mut cropped, &plain, area.width as usize);
+                screen.put(area.x, y, cropped.as_str().reverse())?;
+            } else {
+                ansi::crop_into(&mut cropped, line, area.width as usize);
+                screen.put(area.x, y, &cropped)?;
+            }
+            if let Some(name) = marked.get(&index) {
+                screen.put(area.x, y, name.to_string().with(self.theme.mark).bold())?;
+            }
+        }
+        let scrollbar = self.config.scroll.scrollbar && total > viewport && width > 1;
+        let beside = if scrollbar {
+            self.draw_scrollbar(area.right_columns(1), &mut screen, total)?;
+            area.without_right(1)
+        } else {
+            area
+        };
+        let minimap = beside.right_columns(1);
+        if self.config.scroll.minimap && minimap.x > 0 {
+            self.draw_minimap(minimap, &mut screen, total, &frame.heat)?;
+        }
+        // Overlays stack down from the top right, left of the minimap
+        // column, a row apart.
+        let overlays = beside.without_right(1);
+        let mut top = 0;
+        if self.show_perf {
+            let lines = self.perf.lines();
+            top += self.draw_overlay(overlays.below(top), &mut screen, &lines, false, None)? + 1;
+        }
+        if self.show_progress {
+            let lines = self.progress_lines();
+            top += self.draw_overlay(overlays.below(top), &mut screen, &lines, true, None)? + 1;
+        }
+        if let Some(cursor) = self.checklist_cursor {
+            let lines = self.checklist_lines();
+            self.draw_overlay(
+                overlays.below(top),
+                &mut screen,
+                &lines,
+                true,
+                Some(cursor + 1),
+            )?;
+        }
+        screen.flush(out)?;
+        out.flush()?;
+        self.screen = screen;
+        self.rendered_lines = total;
+        // Swap rather than move so the frame keeps last time's allocations.
+        mem::swap(&mut self.sections, &mut frame.sections);
+        mem::swap(&mut self.hits, &mut frame.hits);
+        self.frame = frame;
+        self.crop_buf = cropped;
+        Ok(())
+    }
+
+    /// Draws one column in `area` summarising where additions, deletions
+    /// and comments sit in the whole view, with the visible rows shaded.
+    fn draw_minimap(
+        &self,
+        area: Rect,
+        screen: &mut Buffer,
+        total: usize,
+        heat: &[(usize, Heat)],
+    ) -> Result<()> {
+        let viewport = area.height as usize;
+        let total = total.max(1);
+        let rows = viewport.min(total);
+        let mut cells: Vec<Option<Heat>> = vec![None; rows];
+        for (line, kind) in heat {
+            let row = line * rows / total;
+            if let Some(cell) = cells.get_mut(row) {
+                *cell = (*cell).max(Some(*kind));
+            }
+        }
+        let visible = self.scroll_offset..self.scroll_offset + viewport;
+        for (row, cell) in cells.into_iter().enumerate() {
+            let first = row * total / rows;
+            let last = ((row + 1) * total / rows).max(first + 1);
+            let in_view = first < visible.end && visible.start < last;
+            let glyph = match cell {
+                Some(kind) => "▐".with(kind.color(&self.theme)),
+                None => " ".stylize(),
+            };
+            let y = area.y + row as u16;
+            if in_view {
+                screen.put(area.x, y, glyph.on(self.theme.minimap_shade))?;
+            } else {
+                screen.put(area.x, y, glyph)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws `lines` as a box in the top right corner of `area`, a row
+    /// down, with the first line as a bold heading when `heading` is set.
+    /// Returns how many rows it took.
+    fn draw_overlay(
+        &self,
+        area: Rect,
+        screen: &mut Buffer,
+        lines: &[String],
+        heading: bool,
+        selected: Option<usize>,
+    ) -> Result<u16> {
+        let width = lines
+            .iter()
+            .map(|line| display_width(line))
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let Some(column) = (area.right() as usize).checked_sub(width) else {
+            return Ok(0);
+        };
+        let rows = lines.len().min(area.height.saturating_sub(1) as usize);
+        for (row, line) in lines.iter().enumerate().take(rows) {
+            let y = area.y + row as u16 + 1;
+            let cell = format!(" {line:<0$} ", width - 2)
+                .with(self.theme.heading)
+                .on(self.theme.minimap_shade);
+            if heading && row == 0 {
+                screen.put(column as u16, y, cell.bold())?;
+            } else if selected == Some(row) {
+                screen.put(column as u16, y, cell.reverse())?;
+            } else {
+                screen.put(column as u16, y, cell)?;
+            }
+        }
+        Ok(rows as u16)
+    }
+
+    /// The review checklist with a heading counting ticked items.
+    fn checklist_lines(&self) -> Vec<String> {
+        let items = &self.config.checklist.items;
+        let ticked = items
+            .iter()
+            .filter(|item| self.checklist.contains(*item))
+            .count();
+        let mut lines = vec![format!("Review checklist {ticked}/{}", items.len())];
+        lines.extend(items.iter().map(|item| {
+            let mark = if self.checklist.contains(item) {
+                "x"
+            } else {
+                " "
+            };
+            format!("[{mark}] {item}")
+        }));
+        lines
+    }
+
+    /// How far the review has got: files seen, threads left open, replies
+    /// not yet published and checklist items ticked.
+    fn progress_lines(&self) -> Vec<String> {
+        let files = self.review_files();
+        let viewed = files
+            .iter()
+            .filter(|path| self.viewed_files.contains(**path))
+            .count();
+        let threads = self.active_threads.len() + self.skipped_threads.len();
+        let unresolved = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .filter(|thread| !thread.is_resolved)
+            .count();
+        let drafts = self.queued_replies.len();
+        let mut lines = vec![
+            "Review progress".to_string(),
+            format!("Files viewed  {viewed}/{}", files.len()),
+            format!("Unresolved    {unresolved}/{threads} threads"),
+            format!(
+                "Drafts        {drafts} unpublished repl{}",
+                if drafts == 1 { "y" } else { "ies" }
+            ),
+        ];
+        let items = &self.config.checklist.items;
+        let ticked = items
+            .iter()
+            .filter(|item| self.checklist.contains(*item))
+            .count();
+        if !items.is_empty() {
+            lines.push(format!("Checklist     {ticked}/{}", items.len()));
+        }
+        if viewed == files.len() && unresolved == 0 && drafts == 0 && ticked == items.len() {
+            lines.push("Done – ready to submit".into());
+        }
+        lines
+    }
+
+    /// Files with threads in any view.
+    fn review_files(&self) -> HashSet<&str> {
+        self.active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .map(|thread| thread.path.as_str())
+            .collect()
+    }
+
+    /// Draws the scrollbar down the column `area` covers, with the
+    /// position indicator just left of its top.
+    fn draw_scrollbar(&self, area: Rect, screen: &mut Buffer, total: usize) -> Result<()> {
+        let column = area.x;
+        let viewport = area.height as usize;
+        let thumb = scrollbar_thumb(total, viewport, self.scroll_offset);
+        for row in 0..viewport {
+            let y = area.y + row as u16;
+            if thumb.contains(&row) {
+                screen.put(column, y, "┃".with(self.theme.scrollbar_thumb))?;
+            } else {
+                screen.put(column, y, "│".with(self.theme.scrollbar_track))?;
+            }
+        }
+        let last = (self.scroll_offset + viewport).min(total);
+        let percent = if total <= viewport {
+            100
+        } else {
+            self.scroll_offset * 100 / (total - viewport)
+        };
+        let indicator = format!(
+            " {}–{}/{} {}% ",
+            self.scroll_offset + 1,
+            last,
+            total,
+            percent
+        );
+        let indicator_width = display_width(&indicator) as u16;
+        if indicator_width < column {
+            screen.put(
+                column - indicator_width,
+                area.y,
+                indicator.with(self.theme.subtle),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn on_scrollbar(&self, column: u16) -> bool {
+        let Ok((width, height)) = self.terminal_size() else {
+            return false;
+        };
+        self.config.scroll.scrollbar && column + 1 == width && self.rendered_lines > height as usize
+    }
+
+    fn scroll_to_row(&mut self, row: u16) {
+        let viewport = self.viewport_height();
+        let max_offset = self.rendered_lines.saturating_sub(viewport);
+        let span = viewport.saturating_sub(1).max(1);
+        self.scroll_offset = (row as usize).min(span) * max_offset / span;
+    }
+
+    fn hit_test(&self, column: u16, row: u16) -> Option<Action> {
+        let line = self.scroll_offset + row as usize;
+        let column = column as usize;
+        self.hits
+            .iter()
+            .find(|hit| hit.line == line && hit.columns.contains(&column))
+            .map(|hit| hit.action)
+    }
+
+    /// Lays out the current view. Only hunk rows inside `window` (content
+    /// lines) are laid out; the rest are recorded as gaps of the right
+    /// height, which keeps the line count right for scrolling without
+    /// paying for lines that won't be drawn. `None` materialises everything.
+    fn render_view(&self, window: Option<Range<usize>>) -> Frame {
+        let mut frame = Frame::default();
+        self.layout(&mut frame, window);
+        frame
+    }
+
+    /// Clears `frame` and lays the view out into it, keeping its buffers.
+    fn layout(&self, frame: &mut Frame, window: Option<Range<usize>>) {
+        frame.clear();
+        self.write_view(frame, window)
+            .expect("writing to a string should not fail");
+        frame.index_lines();
+    }
+
+    fn write_view(&self, frame: &mut Frame, window: Option<Range<usize>>) -> std::fmt::Result {
+        let Frame {
+            text: buf,
+            sections,
+            hits,
+            heat,
+            new_lines,
+            gaps,
+            ..
+        } = frame;
+        let mut lines = LineCounter::default();
+        let now = Utc::now();
+        let threads = self.current_threads();
+        if let Some(map) = &self.review_map {
+            self.write_review_map(buf, map)?;
+        } else if let Some(outline) = &self.outline {
+            self.write_outline(buf, outline)?;
+        } else if let Some(conversation) = &self.conversation {
+            self.write_conversation(buf, sections, heat, &mut lines, conversation)?;
+        } else if threads.is_empty() {
+            let title = format!(
+                "{} – No {} threads to display.",
+                self.subject(),
+                self.view.name()
+            )
+            .bold()
+            .to_string();
+            write_clickable(buf, hits, 0, &[(title, Some(Action::NextView))])?;
+            let next = self.key_label(Action::NextView);
+            let quit = self.key_label(Action::Quit);
+            let hint = match self.view {
+                ThreadView::Unresolved => format!(
+                    "Press {next} to view all unskipped threads or skipped threads. Press {quit} to exit."
+                ),
+                ThreadView::Active => {
+                    format!("Press {next} to view skipped threads or {quit} to exit.")
+                }
+                ThreadView::Skipped => {
+                    format!("Press {next} to return to unresolved threads or {quit} to exit.")
+                }
+            };
+            writeln!(buf, "{}", hint.with(self.theme.subtle))?;
+        } else {
+            let current_index = self.current_index();
+            let thread = &threads[current_index];
+            let muted = self.theme.heading;
+            let accent = self.theme.path;
+            let title = format!(
+                "Thread {}/{} ({}{}{})",
+                current_index + 1,
+                threads.len(),
+                self.view.name(),
+                if self.changed_since_review.is_some() {
+                    ", changed since your review"
+                } else {
+                    ""
+                },
+                self.file_filter
+                    .as_ref()
+                    .map(|filter| format!(", files: {}", filter.raw))
+                    .unwrap_or_default()
+            )
+            .with(muted)
+            .bold()
+            .to_string();
+            let pr = match &self.compare {
+                Some(range) => range.as_str().with(muted).to_string(),
+                None => self.links.link(
+                    format!("PR #{}", self.pr_number).with(muted),
+                    &format!("{}/pull/{}", self.repo.url, self.pr_number),
+                ),
+            };
+            let mut header = vec![
+                (title, Some(Action::NextView)),
+                ("   ".into(), None),
+                (pr, None),
+            ];
+            if let Some(size) = &self.size {
+                header.push((
+                    format!("  {}", size.describe()).with(muted).to_string(),
+                    Some(Action::ToggleReviewMap),
+                ));
+            }
+            for (key, url) in &self.issue_keys {
+                header.push(("  ".into(), None));
+                header.push((
+                    self.links
+                        .link(format!("[{key}]").with(self.theme.key), url),
+                    Some(Action::OpenIssue),
+                ));
+            }
+            if self.open_prs.len() > 1 {
+                let position = self
+                    .open_prs
+                    .iter()
+                    .position(|number| *number == self.pr_number)
+                    .map_or(0, |index| index + 1);
+                header.push((
+                    format!(" ({position}/{} open)", self.open_prs.len())
+                        .with(muted)
+                        .to_string(),
+                    Some(Action::SwitchPr),
+                ));
+            }
+            let unverified = self
+                .commits
+                .iter()
+                .filter(|commit| !commit.verified)
+                .count();
+            if unverified > 0 {
+                header.push((
+                    format!(
+                        "  ⚠ {unverified} unverified commit{}",
+                        if unverified == 1 { "" } else { "s" }
+                    )
+                    .with(self.theme.unresolved)
+                    .to_string(),
+                    Some(Action::Commits),
+                ));
+            }
+            if let Some(base) = &self.conflicting_base {
+                header.push((
+                    format!("  ⚠ conflicts with {base}")
+                        .with(self.theme.unresolved)
+                        .to_string(),
+                    Some(Action::Conflicts),
+                ));
+            }
+            if self.show_merge_diff {
+                header.push((
+                    "  ⇄ merge diff".with(self.theme.key).to_string(),
+                    Some(Action::ToggleMergeDiff),
+                ));
+            }
+            if self.own_pr {
+                let left = self.unresolved_threads.len();
+                header.push((
+                    match left {
+                        0 => "  ✓ your PR, all addressed"
+                            .with(self.theme.resolved)
+                            .to_string(),
+                        _ => format!("  ✎ your PR, {left} to address")
+                            .with(self.theme.unresolved)
+                            .to_string(),
+                    },
+                    Some(Action::Todo),
+                ));
+            }
+            let plural = |count: usize| if count == 1 { "advisory" } else { "advisories" };
+            let fixed: usize = self.bumps.iter().map(|bump| bump.fixed.len()).sum();
+            if fixed > 0 {
+                header.push((
+                    format!("  ⛨ fixes {fixed} {}", plural(fixed))
+                        .with(self.theme.resolved)
+                        .to_string(),
+                    Some(Action::Advisories),
+                ));
+            }
+            let introduced: usize = self.bumps.iter().map(|bump| bump.introduced.len()).sum();
+            if introduced > 0 {
+                header.push((
+                    format!("  ⚠ {introduced} new {}", plural(introduced))
+                        .with(self.theme.diff_removed)
+                        .to_string(),
+                    Some(Action::Advisories),
+                ));
+            }
+            write_clickable(buf, hits, 0, &header)?;
+            if self.stack.len() > 1 {
+                let current = self
+                    .stack
+                    .iter()
+                    .position(|number| *number == self.pr_number)
+                    .unwrap_or(0);
+                let mut pieces = vec![("Stack: ".with(muted).to_string(), None)];
+                for (i, number) in self.stack.iter().enumerate() {
+                    if i > 0 {
+                        pieces.push((" › ".with(muted).to_string(), None));
+                    }
+                    let label = format!("#{number}");
+                    let (label, action) = match i.cmp(&current) {
+                        Ordering::Less => (label.with(muted).to_string(), Some(Action::StackDown)),
+                        Ordering::Equal => (label.with(accent).bold().to_string(), None),
+                        Ordering::Greater => (label.with(muted).to_string(), Some(Action::StackUp)),
+                    };
+                    pieces.push((label, action));
+                }
+                let line = lines.count(buf);
+                write_clickable(buf, hits, line, &pieces)?;
+            }
+            let path = thread.display_path().with(accent);
+            let path = match thread
+                .permalink(&self.repo.url)
+                .or_else(|| thread.comments.first().and_then(|c| c.url.clone()))
+            {
+                Some(url) => self.links.link(path, &url),
+                None => path.to_string(),
+            };
+            let owners = self
+                .code_owners
+                .as_ref()
+                .map(|code_owners| code_owners.owners(&thread.path))
+                .filter(|owners| !owners.is_empty())
+                .map(|owners| format!("  {}", owners.join(" ")).with(muted).to_string())
+                .unwrap_or_default();
+            let fresh = thread
+                .comments
+                .iter()
+                .filter(|comment| self.is_new(thread, comment))
+                .count();
+            let fresh = match fresh {
+                0 => String::new(),
+                count if count == thread.comments.len() => {
+                    format!("  {}", "new thread".with(self.theme.unread).bold())
+                }
+                count => format!(
+                    "  {}",
+                    format!("{count} new").with(self.theme.unread).bold()
+                ),
+            };
+            let pinned = if self.pinned.contains(&thread.id) {
+                format!("{}  ", "◆ pinned".with(self.theme.mark).bold())
+            } else {
+                String::new()
+            };
+            let (renamed_from, similarity) = match self.renames.get(&thread.path) {
+                Some(rename) => (
+                    format!("{} → ", rename.from).with(muted).to_string(),
+                    rename
+                        .similarity
+                        .map(|similarity| {
+                            format!("  {similarity}% similar").with(muted).to_string()
+                        })
+                        .unwrap_or_default(),
+                ),
+                None => Default::default(),
+            };
+            writeln!(
+                buf,
+                "{}{}{}{}{}  {}  {}{}",
+                pinned,
+                renamed_from,
+                path,
+                similarity,
+                owners,
+                if thread.is_resolved {
+                    "resolved".with(self.theme.resolved)
+                } else {
+                    "unresolved".with(self.theme.unresolved)
+                },
+                self.timestamp(now, thread.created_at).with(muted),
+                fresh
+            )?;
+            writeln!(buf)?;
+            let merge_hunk = self.merge_hunk(thread);
+            if let Some(diff) = merge_hunk.or(thread.diff_hunk.as_deref()) {
+                if self.show_diff {
+                    if self.show_merge_diff && merge_hunk.is_none() {
+                        writeln!(
+                            buf,
+                            "{}",
+                            "(not in the merge commit's diff – showing the head diff)".with(muted)
+                        )?;
+                    }
+                    let start = lines.count(buf);
+                    // Renderer output and blame are for the head diff's hunk.
+                    let external = self
+                        .external_diffs
+                        .get(&thread.id)
+                        .and_then(Option::as_ref)
+                        .filter(|_| merge_hunk.is_none());
+                    let raw: Vec<&str> = diff.lines().collect();
+                    // Renderers that keep one output line per input line still
+                    // get blame, minimap marks and folding; anything that
+                    // reflows the hunk doesn't.
+                    let aligned = external.is_none_or(|rendered| rendered.len() == raw.len());
+                    let rows = match external {
+                        Some(rendered) if !aligned => {
+                            (0..rendered.len()).map(diff::Row::Line).collect()
+                        }
+                        _ if self.fold_unchanged => {
+                            diff::rows(raw.len(), &diff::unchanged_blocks(diff))
+                        }
+                        _ => (0..raw.len()).map(diff::Row::Line).collect(),
+                    };
+                    let blame = match self.blames.get(&thread.id) {
+                        Some(Some(blame)) if self.show_blame && aligned && merge_hunk.is_none() => {
+                            Some(blame)
+                        }
+                        _ => None,
+                    };
+                    let annotations = self.annotations_on(thread);
+                    let numbers = diff::new_line_numbers(diff);
+                    let noted: HashSet<usize> = self
+                        .notes_on(thread)
+                        .iter()
+                        .filter_map(|note| note.line)
+                        .collect();
+                    // Only the rows in view are laid out; the rest become a
+                    // gap of the right height.
+                    let shown = match &window {
+                        Some(window) => {
+                            let clamp = |row: usize| row.saturating_sub(start).min(rows.len());
+                            clamp(window.start)..clamp(window.end).max(clamp(window.start))
+                        }
+                        None => 0..rows.len(),
+                    };
+                    let diff_lines: Vec<Cow<str>> = rows[shown.clone()]
+                        .iter()
+                        .map(|line| {
+                            let i = match line {
+                                diff::Row::Line(i) => *i,
+                                diff::Row::Folded(folded) => {
+                                    return self.fold_marker(folded.len()).into();
+                                }
+                            };
+                            let line: Cow<str> = match external {
+                                Some(rendered) => {
+                                    diff::expand_tabs(&rendered[i], self.config.diff.tab_width)
+                                }
+                                None => self.style_diff_line(raw[i]).into(),
+                            };
+                            let line = match blame.and_then(|blame| blame.get(i)) {
+                                Some(annotation) => {
+                                    format!("{}{line}", self.blame_annotation(annotation)).into()
+                                }
+                                _ => line,
+                            };
+                            // The worst annotation on the line is marked after it.
+                            let marked = numbers.get(i).copied().flatten().and_then(|number| {
+                                annotations
+                                    .iter()
+                                    .find(|annotation| annotation.covers(number))
+                            });
+                            let line = match marked {
+                                Some(annotation) if aligned => format!(
+                                    "{line}  {}",
+                                    annotation.symbol().with(self.annotation_color(annotation))
+                                )
+                                .into(),
+                                _ => line,
+                            };
+                            match numbers.get(i).copied().flatten() {
+                                Some(number) if aligned && noted.contains(&number) => {
+                                    format!("{line}  {}", "✎".with(self.theme.mark)).into()
+                                }
+                                _ => line,
+                            }
+                        })
+                        .collect();
+                    sections.push(start);
+                    if aligned {
+                        new_lines.extend(shown.clone().filter_map(|row| {
+                            let diff::Row::Line(i) = rows[row] else {
+                                return None;
+                            };
+                            Some((start + row, numbers.get(i).copied().flatten()?))
+                        }));
+                        heat.extend(rows.iter().enumerate().filter_map(|(row, line)| {
+                            let diff::Row::Line(i) = line else {
+                                return None;
+                            };
+                            match raw[*i].chars().next() {
+                                Some('+') => Some((start + row, Heat::Addition)),
+                                Some('-') => Some((start + row, Heat::Deletion)),
+                                _ => None,
+                            }
+                        }));
+                    }
+                    // A pinned thread's hunk is drawn with a gutter in the
+                    // mark colour.
+                    let gutter = if self.pinned.contains(&thread.id) {
+                        self.theme.mark
+                    } else {
+                        self.theme.subtle
+                    };
+                    if shown.start > 0 {
+                        gaps.push((start, shown.start));
+                    }
+                    render_rows(buf, rows.len(), shown.start, &diff_lines, gutter)?;
+                    lines.count(buf);
+                    lines.skip(shown.start);
+                    if shown.end < rows.len() {
+                        gaps.push((start + shown.end, rows.len() - shown.end));
+                        lines.skip(rows.len() - shown.end);
+                    }
+                    if let Some(bump) = self.submodules.get(&thread.path) {
+                        self.write_submodule_bump(buf, bump)?;
+                    }
+                    if !annotations.is_empty() {
+                        self.write_annotations(buf, &annotations)?;
+                    }
+                    writeln!(buf)?;
+                } else {
+                    writeln!(
+                        buf,
+                        "{}",
+                        format!(
+                            "(diff hunk hidden – press {} to show)",
+                            self.key_label(Action::ToggleDiff)
+                        )
+                        .with(self.theme.subtle)
+                    )?;
+                    writeln!(buf)?;
+                }
+            } else if image::is_image(&thread.path) {
+                writeln!(
+                    buf,
+                    "{}",
+                    format!(
+                        "(image – press {} to compare before and after)",
+                        self.key_label(Action::ImageDiff)
+                    )
+                    .with(self.theme.subtle)
+                )?;
+                writeln!(buf)?;
+            }
+            let wrap_opts = WrapOptions::new(COMMENT_WRAP).break_words(false);
+            for (idx, comment) in thread.comments.iter().enumerate() {
+                if idx > 0 {
+                    // writeln!(buf)?;
+                }
+                // writeln!(
+                //     buf,
+                //     "{} {} {}",
+                //     "╭".dark_grey(),
+                //     comment
+                //         .author
+                //         .as_str()
+                //         .with(Color::Rgb {
+                //             r: 120,
+                //             g: 200,
+                //             b: 220
+                //         })
+                //         .bold(),
+                //     humanize_relative(now, comment.created_at).with(muted)
+                // )?;
+                let mut body_lines = Vec::new();
+                body_lines.push(format!(
+                    "{} {}",
+                    self.author(&comment.author),
+                    self.timestamp(now, comment.created_at).with(muted)
+                ));
+                if self.is_new(thread, comment) {
+                    body_lines[0].push_str(&format!("  {}", "new".with(self.theme.unread).bold()));
+                }
+                if let Some(reason) = &comment.minimized {
+                    let note = if self.show_hidden {
+                        format!("Hidden as {reason}")
+                    } else {
+                        format!(
+                            "Hidden as {reason} – press {} to show",
+                            self.key_label(Action::ToggleHidden)
+                        )
+                    };
+                    body_lines.push(note.with(muted).italic().to_string());
+                }
+                let body = match comment.minimized {
+                    Some(_) if !self.show_hidden => "",
+                    _ => comment.body.as_str(),
+                };
+                for line in body.lines() {
+                    if line.trim().is_empty() {
+                        body_lines.push(String::new());
+                    } else {
+                        for chunk in wrap(line, wrap_opts.clone()) {
+                            body_lines.push(chunk.into_owned());
+                        }
+                    }
+                }
+                let start = lines.count(buf);
+                sections.push(start);
+                heat.extend((start..start + body_lines.len()).map(|line| (line, Heat::Comment)));
+                render_block(buf, &body_lines, self.theme.subtle)?;
+                writeln!(buf)?;
+            }
+            if let Some(index) = self.queued_reply_index(thread) {
+                let mut body_lines = vec![
+                    format!(
+                        "Your reply, queued – press {} to edit",
+                        self.key_label(Action::Reply)
+                    )
+                    .with(self.theme.queued)
+                    .bold()
+                    .to_string(),
+                ];
+                for line in self.queued_replies[index].body.lines() {
+                    if line.trim().is_empty() {
+                        body_lines.push(String::new());
+                    } else {
+                        for chunk in wrap(line, wrap_opts.clone()) {
+                            body_lines.push(self.mark_misspellings(&chunk));
+                        }
+                    }
+                }
+                render_block(buf, &body_lines, self.theme.queued)?;
+                writeln!(buf)?;
+            }
+            for note in self.notes_on(thread) {
+                let target = match note.line {
+                    Some(line) => format!("line {line}"),
+                    None if note.thread_id.is_empty() => "this file".into(),
+                    None => "this hunk".into(),
+                };
+                let mut body_lines = vec![format!(
+                    "{} {}",
+                    format!("✎ Your note on {target}, local only")
+                        .with(self.theme.mark)
+                        .bold(),
+                    self.timestamp(now, note.created_at).with(muted)
+                )];
+                for line in note.body.lines() {
+                    if line.trim().is_empty() {
+                        body_lines.push(String::new());
+                    } else {
+                        for chunk in wrap(line, wrap_opts.clone()) {
+                            body_lines.push(self.mark_misspellings(&chunk));
+                        }
+                    }
+                }
+                let start = lines.count(buf);
+                sections.push(start);
+                render_block(buf, &body_lines, self.theme.mark)?;
+                writeln!(buf)?;
+            }
+        }
+        if !self.queued_replies.is_empty() {
+            writeln!(
+                buf,
+                "{}",
+                format!(
+                    "{} replies queued – press {} to publish",
+                    self.queued_replies.len(),
+                    self.key_label(Action::Publish)
+                )
+                .with(self.theme.queued)
+            )?;
+        }
+        let highlight_first = |word: &str| -> String {
+            let mut chars = word.chars();
+            let Some(first) = chars.next() else {
+                return String::new();
+            };
+            let rest: String = chars.collect();
+            let lead = format!("{}", first.to_string().with(self.theme.key));
+            if rest.is_empty() {
+                lead
+            } else {
+                format!("{}{}", lead, rest.with(self.theme.subtle))
+            }
+        };
+        let highlight_with_key = |word: &str, key: char| -> Option<String> {
+            let mut rendered = String::new();
+            let mut used = false;
+            for ch in word.chars() {
+                if !used && ch == key {
+                    rendered.push_str(&format!("{}", ch.to_string().with(self.theme.key)));
+                    used = true;
+                } else {
+                    rendered.push_str(&format!("{}", ch.to_string().with(self.theme.subtle)));
+                }
+            }
+            used.then_some(rendered)
+        };
+        let hint = |word: &str, action: Action| -> String {
+            let chords = self.keymap.chords_for(self.view, action);
+            if let Some(rendered) = chords
+                .iter()
+                .filter_map(|chord| chord.as_char())
+                .find_map(|key| highlight_with_key(word, key))
+            {
+                return rendered;
+            }
+            match chords.first() {
+                Some(chord) => format!(
+                    "{} {}",
+                    chord.label().with(self.theme.key),
+                    word.with(self.theme.subtle)
+                ),
+                None => highlight_first(word),
+            }
+        };
+        let muted = |text: String| text.with(self.theme.subtle).to_string();
+        let mut footer = vec![
+            (
+                muted(self.key_label(Action::PrevThread)),
+                Some(Action::PrevThread),
+            ),
+            (muted("/".into()), None),
+            (
+                muted(format!("{} thread", self.key_label(Action::NextThread))),
+                Some(Action::NextThread),
+            ),
+            ("  ".into(), None),
+            (hint("diff", Action::ToggleDiff), Some(Action::ToggleDiff)),
+            ("  ".into(), None),
+            (hint("open", Action::OpenBrowser), Some(Action::OpenBrowser)),
+            ("  ".into(), None),
+            (hint("reply", Action::Reply), Some(Action::Reply)),
+            ("  ".into(), None),
+            (hint("publish", Action::Publish), Some(Action::Publish)),
+            ("  ".into(), None),
+            (
+                hint(self.view.skip_action_label(), Action::ToggleSkip),
+                Some(Action::ToggleSkip),
+            ),
+            ("  ".into(), None),
+            (hint("quit", Action::Quit), Some(Action::Quit)),
+        ];
+        if self.config.read_only {
+            // Drop each hidden hint together with the spacer before it.
+            while let Some(pos) = footer
+                .iter()
+                .position(|(_, action)| action.is_some_and(Action::mutates))
+            {
+                footer.drain(pos - 1..=pos);
+            }
+            footer.push(("  ".into(), None));
+            footer.push((muted("read-only".into()), None));
+        }
+        if let Some((name, _)) = &self.recording {
+            footer.push(("  ".into(), None));
+            footer.push((
+                format!("recording @{name}")
+                    .with(self.theme.key)
+                    .to_string(),
+                Some(Action::RecordMacro),
+            ));
+        }
+        if let Some(account) = &self.account {
+            let label = match self.read_access() {
+                Some(level) => format!("@{account} ({level})"),
+                None => format!("@{account}"),
+            };
+            footer.push(("  ".into(), None));
+            footer.push((muted(label), Some(Action::SwitchAccount)));
+        }
+        if self.compare.is_none() {
+            let now = Utc::now();
+            footer.push(("  ".into(), None));
+            footer.push((
+                muted(format!(
+                    "⟳ synced {}",
+                    self.timestamp(now, self.refreshed_at)
+                )),
+                None,
+            ));
+            if let Some(limit) = &self.rate_limit {
+                let text = format!("API {}/{}", limit.remaining, limit.limit);
+                footer.push(("  ".into(), None));
+                footer.push((
+                    if limit.is_low() {
+                        format!("{text}, resets {}", self.timestamp(now, limit.resets_at()))
+                            .with(self.theme.unresolved)
+                            .to_string()
+                    } else {
+                        muted(text)
+                    },
+                    None,
+                ));
+            }
+            if let Some(reason) = self.staleness(now) {
+                footer.push(("  ".into(), None));
+                footer.push((
+                    format!("⚠ stale – {reason}")
+                        .with(self.theme.unresolved)
+                        .to_string(),
+                    None,
+                ));
+            }
+        }
+        let footer_line = lines.count(buf);
+        write_clickable(buf, hits, footer_line, &footer)?;
+        if let Some(prompt) = &self.prompt {
+            writeln!(
+                buf,
+                "{} {}{}",
+                format!("{}:", prompt.label).with(self.theme.subtle),
+                prompt.text,
+                "█".with(self.theme.key)
+            )?;
+        } else if let Some(message) = &self.status_line {
+            writeln!(buf, "{}", message.as_str().with(self.theme.subtle))?;
+        }
+        Ok(())
+    }
+
+    /// Current thread first, then every other thread in any view, so work
+    /// queued in this order finishes soonest for what's on screen.
+    fn prefetch_order(&self, others: bool) -> Vec<&Thread> {
+        let current = self.current_thread();
+        let rest = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .filter(|thread| others && current.is_none_or(|current| current.id != thread.id));
+        current.into_iter().chain(rest).collect()
+    }
+
+    /// Queues hunks for the configured diff renderer in the background: the
+    /// current thread's, plus every other thread's when `others` is set.
+    /// The built-in colouring shows until a result arrives.
+    fn prepare_external_diff(&mut self, others: bool) {
+        let Some(command) = self.config.diff.renderer.clone() else {
+            return;
+        };
+        if !self.show_diff {
+            return;
+        }
+        if let Some(thread) = self.current_thread() {
+            self.perf
+                .record_cache(self.external_diffs.contains_key(&thread.id));
+        }
+        let jobs: Vec<(String, String, String)> = self
+            .prefetch_order(others)
+            .into_iter()
+            .filter(|thread| !self.external_diffs.contains_key(&thread.id))
+            .filter_map(|thread| {
+                let hunk = thread.diff_hunk.clone()?;
+                Some((thread.id.clone(), thread.path.clone(), hunk))
+            })
+            .collect();
+        if jobs.is_empty() {
+            return;
+        }
+        let width = self
+            .terminal_size()
+            .map(|(width, _)| width)
+            .unwrap_or(80)
+            .saturating_sub(2);
+        let cache = self.render_cache.clone();
+        for (id, path, hunk) in jobs {
+            let command = command.clone();
+            let cache = cache.clone();
+            self.prefetcher.spawn(Kind::ExternalDiff, id, move || {
+                match cache {
+                    Some(cache) => cache.render(&command, &path, &hunk, width),
+                    None => diff::render_external(&command, &path, &hunk, width),
+                }
+                .map(Some)
+            });
+        }
+    }
+
+    /// Queues blame for the lines around each hunk in the local checkout, at
+    /// the commit the thread was left on when that is available.
+    fn prepare_blame(&mut self, others: bool) {
+        if !self.show_blame || !self.show_diff {
+            return;
+        }
+        if let Some(thread) = self.current_thread() {
+            self.perf.record_cache(self.blames.contains_key(&thread.id));
+        }
+        let jobs: Vec<(String, String, String, Option<String>)> = self
+            .prefetch_order(others)
+            .into_iter()
+            .filter(|thread| !self.blames.contains_key(&thread.id))
+            .filter_map(|thread| {
+                let hunk = thread.diff_hunk.clone()?;
+                let commit = thread
+                    .comments
+                    .iter()
+                    .find_map(|comment| comment.commit_oid.clone());
+                Some((thread.id.clone(), thread.path.clone(), hunk, commit))
+            })
+            .collect();
+        if jobs.is_empty() {
+            return;
+        }
+        let root = self.worktree.clone().or_else(git::toplevel);
+        for (id, path, hunk, commit) in jobs {
+            let root = root.clone();
+            self.prefetcher.spawn(Kind::Blame, id, move || {
+                blame_annotations(root, &path, commit, &hunk).with_context(|| path)
+            });
+        }
+    }
+
+    /// Stores finished background work; true when anything arrived.
+    fn apply_prefetched(&mut self) -> bool {
+        let mut changed = false;
+        while let Some(done) = self.prefetcher.try_next() {
+            self.store_prefetched(done);
+            changed = true;
+        }
+        let finished: Vec<_> = self
+            .spell_checks
+            .extract_if(.., |(_, handle)| handle.is_finished())
+            .collect();
+        for (announce, done) in finished {
+            match done.now_or_never() {
+                Some(Ok(result)) => self.store_misspellings(result, announce),
+                Some(Err(err)) => self.store_misspellings(Err(err.into()), announce),
+                None => {}
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    fn store_prefetched(&mut self, done: Done) {
+        let lines = match done.result {
+            Ok(lines) => lines,
+            Err(err) => {
+                self.report_failure(match done.kind {
+                    Kind::ExternalDiff => format!("Diff renderer failed: {err}"),
+                    Kind::Blame => format!("Failed to blame {err:#}"),
+                });
+                None
+            }
+        };
+        match done.kind {
+            Kind::ExternalDiff => self.external_diffs.insert(done.thread_id, lines),
+            Kind::Blame => self.blames.insert(done.thread_id, lines),
+        };
+    }
+
+    /// Prints the current thread once, waiting for its renderer and blame
+    /// output rather than falling back.
+    async fn dump_once(&mut self) -> Result<()> {
+        self.prepare_external_diff(false);
+        self.prepare_blame(false);
+        while let Some(done) = self.prefetcher.next().await {
+            self.store_prefetched(done);
+        }
+        print!("{}", self.render_view(None).text);
+        Ok(())
+    }
+
+    /// Reads the review out line by line for screen readers: no alternate
+    /// screen, colour or box drawing, and commands typed on a line of their
+    /// own. Each thread is announced before it is read.
+    async fn plain_review(&mut self) -> Result<()> {
+        let mut terminal = TerminalSession::detached();
+        let stdin = io::stdin();
+        let mut lines = self.plain_lines();
+        let mut cursor = 0;
+        let mut shown = self.plain_position();
+        self.mark_viewed();
+        self.mark_seen();
+        println!("{}. {}", self.subject(), self.plain_help());
+        println!("{}", lines[0]);
+        loop {
+            print!("> ");
+            stdout().flush()?;
+            let mut input = String::new();
+            if stdin.read_line(&mut input)? == 0 {
+                break;
+            }
+            match input.trim() {
+                "" | "j" => match lines.get(cursor + 1) {
+                    Some(line) => {
+                        cursor += 1;
+                        println!("{line}");
+                    }
+                    None => println!(
+                        "End of thread. {} moves to the next one.",
+                        self.key_label(Action::NextThread)
+                    ),
+                },
+                "k" => match cursor.checked_sub(1) {
+                    Some(previous) => {
+                        cursor = previous;
+                        println!("{}", lines[cursor]);
+                    }
+                    None => println!("Start of thread."),
+                },
+                "a" => {
+                    lines.iter().for_each(|line| println!("{line}"));
+                    cursor = lines.len() - 1;
+                }
+                "h" | "?" => println!("{}", self.plain_help()),
+                "q" => break,
+                command => match self.plain_action(command) {
+                    Some(action) => {
+                        self.perform(action, &mut terminal).await;
+                        if let Some(prompt) = self.prompt.take() {
+                            self.plain_prompt(&mut terminal, prompt).await?;
+                        }
+                    }
+                    None => println!("Unknown command `{command}`. Type h for help."),
+                },
+            }
+            if let Some(status) = self.status_line.take() {
+                println!("{status}");
+            }
+            lines = self.plain_lines();
+            cursor = cursor.min(lines.len() - 1);
+            if self.plain_position() != shown {
+                shown = self.plain_position();
+                cursor = 0;
+                self.mark_viewed();
+                self.mark_seen();
+                println!("{}", lines[0]);
+            }
+        }
+        Ok(())
+    }
+
+    /// The view and thread shown, to tell when plain mode should announce
+    /// another.
+    fn plain_position(&self) -> (ThreadView, Option<String>) {
+        (
+            self.view,
+            self.current_thread().map(|thread| thread.id.clone()),
+        )
+    }
+
+    /// The action a plain-mode command stands for: a key bound in the TUI
+    /// or an action name such as `next-thread`. Actions that only make
+    /// sense on screen, like scrolling, aren't available.
+    fn plain_action(&self, command: &str) -> Option<Action> {
+        let action = match KeyChord::parse(command) {
+            Ok(chord) => self.keymap.action_for(self.view, &chord.to_event()),
+            Err(_) => None,
+        }
+        .or_else(|| Action::from_name(command))?;
+        if self.config.read_only && action.mutates() {
+            return None;
+        }
+        PLAIN_ACTIONS.contains(&action).then_some(action)
+    }
+
+    /// Asks for a prompt's answer on a line of its own.
+    async fn plain_prompt(
+        &mut self,
+        terminal: &mut TerminalSession,
+        mut prompt: Prompt,
+    ) -> Result<()> {
+        if prompt.text.is_empty() {
+            println!("{}", prompt.label);
+        } else {
+            println!("{} Currently: {}", prompt.label, prompt.text);
+        }
+        print!("> ");
+        stdout().flush()?;
+        prompt.text.clear();
+        io::stdin().read_line(&mut prompt.text)?;
+        prompt.text.truncate(prompt.text.trim_end().len());
+        self.submit_prompt(terminal, prompt).await;
+        Ok(())
+    }
+
+    fn plain_help(&self) -> String {
+        let key = |action| self.key_label(action);
+        format!(
+            "Press enter or j to read the next line, k the previous one, a the whole thread, \
+             h for help and q to quit. {} and {} move between threads, {} switches view, \
+             {} replies and {} publishes. Other keys and action names work as in the full \
+             interface.",
+            key(Action::NextThread),
+            key(Action::PrevThread),
+            key(Action::NextView),
+            key(Action::Reply),
+            key(Action::Publish),
+        )
+    }
+
+    /// The current thread as plain lines, starting with an announcement of
+    /// where it is and what state it's in. Diff lines are labelled rather
+    /// than coloured.
+    fn plain_lines(&self) -> Vec<String> {
+        let threads = self.current_threads();
+        let Some(thread) = self.current_thread() else {
+            return vec![format!(
+                "No {} threads. {} switches view.",
+                self.view.name(),
+                self.key_label(Action::NextView)
+            )];
+        };
+        let now = Utc::now();
+        let mut state = vec![if thread.is_resolved {
+            "resolved"
+        } else {
+            "unresolved"
+        }];
+        if thread.is_outdated {
+            state.push("outdated");
+        }
+        if self.pinned.contains(&thread.id) {
+            state.push("pinned");
+        }
+        if self.queued_reply_index(thread).is_some() {
+            state.push("reply queued");
+        }
+        let new = thread
+            .comments
+            .iter()
+            .filter(|comment| self.is_new(thread, comment))
+            .count();
+        let location = match thread.line {
+            Some(line) => format!("{} line {line}", self.file_label(&thread.path)),
+            None => self.file_label(&thread.path),
+        };
+        let mut lines = vec![format!(
+            "Thread {} of {} {}: {location}, {}. {} comment{}{}.",
+            self.current_index() + 1,
+            threads.len(),
+            self.view.name(),
+            state.join(", "),
+            thread.comments.len(),
+            if thread.comments.len() == 1 { "" } else { "s" },
+            if new > 0 {
+                format!(", {new} new")
+            } else {
+                String::new()
+            },
+        )];
+        if self.show_diff
+            && let Some(hunk) = &thread.diff_hunk
+        {
+            lines.push(format!("Diff, {} lines.", hunk.lines().count()));
+            for line in hunk.lines() {
+                let (label, text) = match line.chars().next() {
+                    Some('+') => ("added", &line[1..]),
+                    Some('-') => ("removed", &line[1..]),
+                    Some(' ') => ("unchanged", &line[1..]),
+                    _ if line.starts_with("@@") => ("hunk", line),
+                    _ => continue,
+                };
+                lines.push(format!("{label}: {}", text.trim_end()));
+            }
+        }
+        for (index, comment) in thread.comments.iter().enumerate() {
+            let new = if self.is_new(thread, comment) {
+                ", new"
+            } else {
+                ""
+            };
+            lines.push(format!(
+                "Comment {} of {} by {}, {}{new}.",
+                index + 1,
+                thread.comments.len(),
+                comment.author,
+                self.timestamp(now, comment.created_at)
+            ));
+            if let Some(reason) = &comment.minimized {
+                lines.push(format!("Hidden as {reason}."));
+                if !self.show_hidden {
+                    continue;
+                }
+            }
+            lines.extend(plain_body(&comment.body));
+        }
+        if let Some(index) = self.queued_reply_index(thread) {
+            lines.push("Your queued reply.".into());
+            lines.extend(plain_body(&self.queued_replies[index].body));
+        }
+        lines.push(format!("End of thread {}.", self.current_index() + 1));
+        lines
+    }
+
+    fn next_thread(&mut self) {
+        let len = self.current_threads().len();
+        if len == 0 {
+            return;
+        }
+        let index = self.current_index_mut();
+        *index = (*index + 1) % len;
+        self.reset_scroll();
+    }
+
+    /// Selects the first thread on the next file in the view's order, or
+    /// the first thread on the previous one, wrapping around.
+    fn move_to_file(&mut self, forward: bool) {
+        let threads = self.current_threads();
+        let Some(current) = threads.get(self.current_index()) else {
+            return;
+        };
+        let path = current.path.clone();
+        let len = threads.len();
+        let start = self.current_index();
+        let step = |i: usize| {
+            if forward {
+                (i + 1) % len
+            } else {
+                (i + len - 1) % len
+            }
+        };
+        let mut index = step(start);
+        while index != start && threads[index].path == path {
+            index = step(index);
+        }
+        if index == start {
+            self.status_line = Some("Every thread is on this file.".into());
+            return;
+        }
+        if !forward {
+            // Back up to the first thread of that file.
+            let target = &threads[index].path;
+            while index > 0 && threads[index - 1].path == *target {
+                index -= 1;
+            }
+        }
+        *self.current_index_mut() = index;
+        self.reset_scroll();
+    }
+
+    /// Selects the next or previous thread with comments not yet shown,
+    /// wrapping around.
+    fn move_to_unread(&mut self, forward: bool) {
+        let threads = self.current_threads();
+        let len = threads.len();
+        let start = self.current_index();
+        let found = (1..len)
+            .map(|step| {
+                if forward {
+                    (start + step) % len
+                } else {
+                    (start + len - step) % len
+                }
+            })
+            .find(|index| self.is_unread(&threads[*index]));
+        match found {
+            Some(index) => {
+                self.record_jump();
+                *self.current_index_mut() = index;
+                self.reset_scroll();
+            }
+            None => self.status_line = Some("No unread threads in this view.".into()),
+        }
+    }
+
+    /// Whether `thread` has comments newer than any shown of it so far.
+    fn is_unread(&self, thread: &Thread) -> bool {
+        match (newest_comment(thread), self.seen.get(&thread.id)) {
+            (Some(newest), Some(seen)) => newest > *seen,
+            (newest, None) => newest.is_some(),
+            (None, Some(_)) => false,
+        }
+    }
+
+    /// Whether `comment` arrived since the previous visit. Nothing is new
+    /// on a first visit, when no thread has been seen before.
+    fn is_new(&self, thread: &Thread, comment: &Comment) -> bool {
+        !self.seen_before.is_empty()
+            && self
+                .seen_before
+                .get(&thread.id)
+                .is_none_or(|seen| comment.created_at > *seen)
+    }
+
+    /// Marks every thread in every view as read and drops the new markers.
+    fn mark_all_read(&mut self) {
+        let newest: Vec<(String, DateTime<Utc>)> = [
+            ThreadView::Unresolved,
+            ThreadView::Active,
+            ThreadView::Skipped,
+        ]
+        .into_iter()
+        .flat_map(|view| self.threads_for_view(view))
+        .filter_map(|thread| Some((thread.id.clone(), newest_comment(thread)?)))
+        .collect();
+        self.seen.extend(newest);
+        self.seen_before = self.seen.clone();
+        self.status_line = Some("Marked every thread as read.".into());
+    }
+
+    fn prev_thread(&mut self) {
+        let len = self.current_threads().len();
+        if len == 0 {
+            return;
+        }
+        let index = self.current_index_mut();
+        if *index == 0 {
+            *index = len - 1;
+        } else {
+            *index -= 1;
+        }
+        self.reset_scroll();
+    }
+
+    fn scroll_up(&mut self, amount: usize) {
+        if amount == 0 {
+            return;
+        }
+        self.scroll_offset = self.scroll_offset.saturating_sub(amount);
+    }
+
+    fn scroll_down(&mut self, amount: usize) {
+        if amount == 0 {
+            return;
+        }
+        self.scroll_offset = self.scroll_offset.saturating_add(amount);
+    }
+
+    fn page_step(&self) -> usize {
+        let height = self.viewport_height();
+        if height == 0 {
+            return 0;
+        }
+        height
+            .saturating_sub(self.config.scroll.page_overlap)
+            .max(1)
+    }
+
+    fn scroll_page_down(&mut self) {
+        let step = self.page_step();
+        if step == 0 {
+            return;
+        }
+        self.scroll_down(step);
+    }
+
+    fn scroll_page_up(&mut self) {
+        let step = self.page_step();
+        if step == 0 {
+            return;
+        }
+        self.scroll_up(step);
+    }
+
+    fn scroll_to_top(&mut self) {
+        self.scroll_offset = 0;
+    }
+
+    fn scroll_to_bottom(&mut self) {
+        // As of the last frame; drawing clamps it again if the view has
+        // changed length since.
+        self.scroll_offset = self.rendered_lines.saturating_sub(self.viewport_height());
+    }
+
+    fn next_section(&mut self) {
+        if let Some(&start) = self
+            .sections
+            .iter()
+            .find(|&&start| start > self.scroll_offset)
+        {
+            self.scroll_offset = start;
+        }
+    }
+
+    fn prev_section(&mut self) {
+        let start = self
+            .sections
+            .iter()
+            .rev()
+            .find(|&&start| start < self.scroll_offset)
+            .copied()
+            .unwrap_or(0);
+        self.scroll_offset = start;
+    }
+
+    /// Top-level comments and review summaries, oldest first, each in a
+    /// block headed by its author.
+    fn write_conversation(
+        &self,
+        buf: &mut String,
+        sections: &mut Vec<usize>,
+        heat: &mut Vec<(usize, Heat)>,
+        lines: &mut LineCounter,
+        conversation: &[ConversationEntry],
+    ) -> std::fmt::Result {
+        let now = Utc::now();
+        let muted = self.theme.heading;
+        writeln!(
+            buf,
+            "{}   {}",
+            format!("Conversation ({})", conversation.len()).bold(),
+            self.links.link(
+                format!("PR #{}", self.pr_number).with(muted),
+                &format!("{}/pull/{}", self.repo.url, self.pr_number),
+            )
+        )?;
+        writeln!(buf)?;
+        if conversation.is_empty() {
+            writeln!(
+                buf,
+                "{}",
+                format!(
+                    "No comments yet – press {} to add one.",
+                    self.key_label(Action::Reply)
+                )
+                .with(self.theme.subtle)
+            )?;
+        }
+        let wrap_opts = WrapOptions::new(COMMENT_WRAP).break_words(false);
+        for entry in conversation {
+            let author = self.author(&entry.author);
+            let mut body_lines = vec![match &entry.review_state {
+                Some(state) => format!(
+                    "{author} {} {}",
+                    state.as_str().with(self.theme.path),
+                    self.timestamp(now, entry.created_at).with(muted)
+                ),
+                None => format!(
+                    "{author} {}",
+                    self.timestamp(now, entry.created_at).with(muted)
+                ),
+            }];
+            for line in entry.body.lines() {
+                if line.trim().is_empty() {
+                    body_lines.push(String::new());
+                } else {
+                    for chunk in wrap(line, wrap_opts.clone()) {
+                        body_lines.push(chunk.into_owned());
+                    }
+                }
+            }
+            let start = lines.count(buf);
+            sections.push(start);
+            heat.extend((start..start + body_lines.len()).map(|line| (line, Heat::Comment)));
+            render_block(buf, &body_lines, self.theme.subtle)?;
+            writeln!(buf)?;
+        }
+        Ok(())
+    }
+
+    /// Shows the PR's conversation in place of the threads, or goes back.
+    async fn toggle_conversation(&mut self) -> Result<()> {
+        if self.conversation.take().is_none() {
+            self.conversation = Some(self.gh.conversation(&self.repo, self.pr_number).await?);
+        }
+        self.reset_scroll();
+        self.clear_status();
+        Ok(())
+    }
+
+    /// Writes a top-level comment in the editor and posts it straight away.
+    async fn comment_on_pr(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        // A comment that failed to post comes back as the starting text.
+        let template = format!(
+            "{}\n\n# Comment on PR #{}.\n# Lines starting with '# ' are ignored when posting.\n",
+            self.comment_draft.take().unwrap_or_default(),
+            self.pr_number
+        );
+        let editor = self.config.editor_command();
+        let Some(body) = terminal.suspend_for_editor(&editor, &template)? else {
+            self.status_line = Some("Comment cancelled.".into());
+            return Ok(());
+        };
+        if let Err(err) = self
+            .gh
+            .post_comment(&self.repo, self.pr_number, &body)
+            .await
+        {
+            self.comment_draft = Some(body);
+            return Err(err.context("the draft is kept for next time"));
+        }
+        hooks::fire(
+            &self.config.hooks,
+            Hook::CommentPosted,
+            &self.repo,
+            self.pr_number,
+            &[("GH_CR_BODY", &body)],
+        );
+        self.conversation = Some(self.gh.conversation(&self.repo, self.pr_number).await?);
+        self.status_line = Some("Comment posted.".into());
+        Ok(())
+    }
+
+    fn reset_scroll(&mut self) {
+        self.scroll_offset = 0;
+        self.selection = None;
+    }
+
+    fn in_visual_mode(&self) -> bool {
+        self.selection.is_some_and(|selection| selection.visual)
+    }
+
+    fn toggle_visual(&mut self) {
+        if self.in_visual_mode() {
+            self.selection = None;
+            return;
+        }
+        let line = self
+            .scroll_offset
+            .min(self.rendered_lines.saturating_sub(1));
+        self.selection = Some(Selection {
+            anchor: line,
+            cursor: line,
+            visual: true,
+        });
+        self.status_line = Some(format!(
+            "Visual line – {} to copy, {} to comment, {} l to note, {} to cancel",
+            self.key_label(Action::Yank),
+            self.key_label(Action::Reply),
+            self.key_label(Action::AddNote),
+            self.key_label(Action::Cancel)
+        ));
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let Some(selection) = self.selection.as_mut() else {
+            return;
+        };
+        let last = self.rendered_lines.saturating_sub(1);
+        selection.cursor = selection.cursor.saturating_add_signed(delta).min(last);
+        let cursor = selection.cursor;
+        let viewport = self.viewport_height().max(1);
+        if cursor < self.scroll_offset {
+            self.scroll_offset = cursor;
+        } else if cursor >= self.scroll_offset + viewport {
+            self.scroll_offset = cursor + 1 - viewport;
+        }
+    }
+
+    fn start_drag(&mut self, row: u16) {
+        let line = self.scroll_offset + row as usize;
+        self.selection = Some(Selection {
+            anchor: line,
+            cursor: line,
+            visual: false,
+        });
+    }
+
+    fn extend_drag(&mut self, row: u16) {
+        let line = (self.scroll_offset + row as usize).min(self.rendered_lines.saturating_sub(1));
+        if let Some(selection) = self.selection.as_mut() {
+            selection.cursor = line;
+        }
+    }
+
+    /// Whether the mouse was pressed and released on the same diff line,
+    /// which starts a comment on it.
+    fn clicked_diff_line(&self) -> bool {
+        !self.config.read_only
+            && self.selection.is_some_and(|selection| {
+                !selection.visual
+                    && selection.anchor == selection.cursor
+                    && self
+                        .frame
+                        .new_lines
+                        .iter()
+                        .any(|(row, _)| *row == selection.cursor)
+            })
+    }
+
+    fn finish_drag(&mut self) {
+        match self.selection {
+            Some(selection) if !selection.visual && selection.anchor != selection.cursor => {
+                if let Err(err) = self.copy_selection() {
+                    self.report_failure(format!("Failed to copy selection: {err}"));
+                }
+            }
+            Some(selection) if !selection.visual => self.selection = None,
+            _ => {}
+        }
+    }
+
+    /// Copies the part of the current thread named by `key`, as listed in the
+    /// copy prompt.
+    fn copy_item(&mut self, key: char) -> Result<()> {
+        let pr_url = format!("{}/pull/{}", self.repo.url, self.pr_number);
+        if key == 'u' {
+            clipboard::copy(&pr_url)?;
+            self.status_line = Some("Copied the PR URL.".into());
+            return Ok(());
+        }
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread to copy from.".into());
+            return Ok(());
+        };
+        let (text, what) = match key {
+            'l' => (
+                thread.permalink(&self.repo.url).unwrap_or(pr_url),
+                "permalink",
+            ),
+            't' => (thread.discussion_url().unwrap_or(pr_url), "thread link"),
+            'r' => {
+                let author = thread
+                    .comments
+                    .first()
+                    .map_or("unknown", |comment| comment.author.as_str());
+                (
+                    format!(
+                        "{} (thread by @{author}): {}",
+                        thread.display_path(),
+                        thread.discussion_url().unwrap_or(pr_url)
+                    ),
+                    "thread reference",
+                )
+            }
+            'c' => match thread.comments.last() {
+                Some(comment) => (comment.body.clone(), "latest comment"),
+                None => return Ok(()),
+            },
+            'f' => (thread.path.clone(), "file path"),
+            'g' => (self.head_permalink(thread), "permalink at the head commit"),
+            'h' => match &thread.diff_hunk {
+                Some(hunk) => (
+                    format!(
+                        "diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n{hunk}\n",
+                        path = thread.path
+                    ),
+                    "hunk",
+                ),
+                None => {
+                    self.status_line = Some("This thread has no diff hunk.".into());
+                    return Ok(());
+                }
+            },
+            _ => {
+                self.clear_status();
+                return Ok(());
+            }
+        };
+        clipboard::copy(&text)?;
+        self.status_line = Some(format!("Copied the {what}."));
+        Ok(())
+    }
+
+    /// A link to the thread's line, or the selected one, in the PR's head
+    /// commit, so it still points at the same code once more is pushed.
+    fn head_permalink(&self, thread: &Thread) -> String {
+        let line = self.selected_line().map(|line| line as i64).or(thread.line);
+        let commit = self.head_oid.as_deref().or_else(|| {
+            thread
+                .comments
+                .iter()
+                .find_map(|comment| comment.commit_oid.as_deref())
+        });
+        let Some(commit) = commit else {
+            return format!("{}/pull/{}/files", self.repo.url, self.pr_number);
+        };
+        let anchor = line.map(|line| format!("#L{line}")).unwrap_or_default();
+        format!("{}/blob/{commit}/{}{anchor}", self.repo.url, thread.path)
+    }
+
+    /// Copies the current thread's file and line, or the selected line, in
+    /// the `copy.reference` format.
+    fn copy_reference(&mut self) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread to copy from.".into());
+            return Ok(());
+        };
+        let line = self.selected_line().map(|line| line as i64).or(thread.line);
+        let text = format_reference(
+            &self.config.copy.reference,
+            &thread.path,
+            line,
+            self.head_oid.as_deref().unwrap_or_default(),
+            &self.head_permalink(thread),
+            self.pr_number,
+        );
+        clipboard::copy(&text)?;
+        self.status_line = Some(format!("Copied {text}"));
+        Ok(())
+    }
+
+    fn copy_selection(&mut self) -> Result<()> {
+        let Some(selection) = self.selection.take() else {
+            self.status_line = Some("Nothing selected.".into());
+            return Ok(());
+        };
+        let frame = self.render_view(None);
+        let range = selection.lines();
+        let count = range.end() - range.start() + 1;
+        let text = frame
+            .text
+            .lines()
+            .skip(*range.start())
+            .take(count)
+            .map(ansi::strip)
+            .collect::<Vec<_>>()
+            .join("\n");
+        clipboard::copy(&text)?;
+        self.status_line = Some(format!(
+            "Copied {count} line{} to the clipboard.",
+            if count == 1 { "" } else { "s" }
+        ));
+        Ok(())
+    }
+
+    fn report_failure(&mut self, message: String) {
+        log::error!("{message}");
+        self.status_line = Some(message);
+    }
+
+    fn clear_status(&mut self) {
+        self.status_line = None;
+    }
+
+    fn skip_current(&mut self) -> Result<()> {
+        match self.view {
+            ThreadView::Unresolved | ThreadView::Active => self.skip_selected_thread(),
+            ThreadView::Skipped => self.unskip_selected_thread(),
+        }
+    }
+
+    fn skip_selected_thread(&mut self) -> Result<()> {
+        let thread = match self.view {
+            ThreadView::Active => {
+                self.clamp_index_for_view(ThreadView::Active);
+                if self.active_threads.is_empty() {
+                    self.status_line = Some("No thread to skip.".into());
+                    return Ok(());
+                }
+                self.active_threads.remove(self.current_active)
+            }
+            ThreadView::Unresolved => {
+                self.clamp_index_for_view(ThreadView::Unresolved);
+                if self.unresolved_threads.is_empty() {
+                    self.status_line = Some("No unresolved thread to skip.".into());
+                    return Ok(());
+                }
+                let thread = self.unresolved_threads.remove(self.current_unresolved);
+                if let Some(pos) = self
+                    .active_threads
+                    .iter()
+                    .position(|candidate| candidate.id == thread.id)
+                {
+                    self.active_threads.remove(pos);
+                    self.clamp_index_for_view(ThreadView::Active);
+                }
+                thread
+            }
+            ThreadView::Skipped => {
+                self.status_line = Some("Cannot skip thread in skipped view.".into());
+                return Ok(());
+            }
+        };
+        self.skip_store
+            .add(thread.id.clone())
+            .context("failed to persist skip state")?;
+        self.skipped_threads.push(thread);
+        let mut skipped = mem::take(&mut self.skipped_threads);
+        self.order_threads(&mut skipped);
+        self.skipped_threads = skipped;
+        self.rebuild_unresolved(None);
+        self.clamp_index_for_view(self.view);
+        self.reset_scroll();
+        self.clear_status();
+        Ok(())
+    }
+
+    fn unskip_selected_thread(&mut self) -> Result<()> {
+        self.clamp_index_for_view(ThreadView::Skipped);
+        if self.skipped_threads.is_empty() {
+            self.status_line = Some("No skipped thread to unskip.".into());
+            return Ok(());
+        }
+        let thread = self.skipped_threads.remove(self.current_skipped);
+        self.skip_store
+            .remove(&thread.id)
+            .context("failed to persist skip state")?;
+        self.active_threads.push(thread.clone());
+        let mut active = mem::take(&mut self.active_threads);
+        self.order_threads(&mut active);
+        self.active_threads = active;
+        let preferred = if thread.is_resolved {
+            None
+        } else {
+            Some(thread.id.clone())
+        };
+        self.rebuild_unresolved(preferred);
+        self.clamp_index_for_view(ThreadView::Skipped);
+        self.reset_scroll();
+        self.clear_status();
+        Ok(())
+    }
+
+    async fn reply_to_current(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        let Some(target_comment) = thread.comments.last() else {
+            self.status_line = Some("Thread has no comments.".into());
+            return Ok(());
+        };
+
+        // A reply already queued for the thread is its draft: reopen it
+        // rather than starting another.
+        let queued = self.queued_reply_index(thread);
+        let mut editor_template = build_reply_editor_template(thread);
+        if let Some(index) = queued {
+            editor_template.insert_str(0, &self.queued_replies[index].body);
+        }
+        let comment_database_id = target_comment.database_id;
+        let thread_id = thread.id.clone();
+        let editor = self.config.editor_command();
+        let reply_body = terminal.suspend_for_editor(&editor, &editor_template)?;
+        if reply_body.is_some() || queued.is_some() {
+            self.record_edit(Edit::Replies(self.queued_replies.clone()));
+        }
+        let written = reply_body.clone();
+        match (reply_body, queued) {
+            (Some(body), Some(index)) => {
+                self.queued_replies[index].body = body;
+                self.status_line = Some("Queued reply updated.".into());
+            }
+            (Some(body), None) => {
+                self.queued_replies.push_back(QueuedReply {
+                    comment_database_id,
+                    thread_id,
+                    body,
+                });
+                self.clear_status();
+            }
+            (None, Some(index)) => {
+                self.queued_replies.remove(index);
+                self.status_line = Some("Queued reply discarded.".into());
+            }
+            (None, None) => self.status_line = Some("Reply cancelled.".into()),
+        }
+        if let Some(body) = written {
+            self.flag_misspellings(&body);
+        }
+        if self.config.sync_review && !self.queued_replies.is_empty() {
+            self.sync_review().await?;
+        }
+        Ok(())
+    }
+
+    /// Starts a review thread on the diff lines under the visual selection,
+    /// spanning all of them when there are several.
+    async fn comment_on_selection(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        let lines = self.selected_lines();
+        let (Some(&start), Some(&end)) = (lines.first(), lines.last()) else {
+            self.status_line = Some(format!(
+                "Select lines of the diff first with {}.",
+                self.key_label(Action::Visual)
+            ));
+            return Ok(());
+        };
+        let path = thread.path.clone();
+        let span = if start == end {
+            format!("line {end}")
+        } else {
+            format!("lines {start}-{end}")
+        };
+        let template = format!(
+            "\n\n# Comment on {} {span}.\n\
+             # Lines starting with '# ' are ignored; leave it empty to cancel.\n",
+            thread.display_path()
+        );
+        let editor = self.config.editor_command();
+        let Some(body) = terminal.suspend_for_editor(&editor, &template)? else {
+            self.status_line = Some("Comment cancelled.".into());
+            return Ok(());
+        };
+        let commit = match &self.head_oid {
+            Some(commit) => commit.clone(),
+            None => self.gh.pr_refs(self.pr_number).await?.1,
+        };
+        self.gh
+            .post_review_comment(
+                &self.repo,
+                self.pr_number,
+                &commit,
+                &path,
+                start..=end,
+                &body,
+            )
+            .await?;
+        self.selection = None;
+        self.refresh_threads().await?;
+        self.status_line = Some(format!("Commented on {path} {span}."));
+        self.flag_misspellings(&body);
+        Ok(())
+    }
+
+    /// Writes or edits a note on the current hunk, its file or the line
+    /// under the selection, as picked by `key`.
+    fn note(&mut self, key: char, terminal: &mut TerminalSession) {
+        if let Err(err) = self.edit_note(key, terminal) {
+            self.report_failure(format!("Failed to save note: {err}"));
+        }
+    }
+
+    /// Opens the note for `key`'s target in the editor; saving it empty
+    /// deletes it.
+    fn edit_note(&mut self, key: char, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        let path = thread.path.clone();
+        let (thread_id, line) = match key {
+            'h' => (thread.id.clone(), None),
+            'f' => (String::new(), None),
+            'l' => {
+                let Some(line) = self.selected_line() else {
+                    self.status_line = Some(format!(
+                        "Select a line of the diff first with {}.",
+                        self.key_label(Action::Visual)
+                    ));
+                    return Ok(());
+                };
+                (String::new(), Some(line))
+            }
+            _ => {
+                self.clear_status();
+                return Ok(());
+            }
+        };
+        let target = Note {
+            thread_id,
+            path,
+            line,
+            body: String::new(),
+            created_at: Utc::now(),
+        };
+        let existing = self.notes.iter().position(|note| note.same_target(&target));
+        let mut template = existing
+            .map(|index| self.notes[index].body.clone())
+            .unwrap_or_default();
+        template.push_str(&format!(
+            "\n\n# Note on {}, kept on this machine and never sent to GitHub.\n\
+             # Lines starting with '# ' are ignored; leave it empty to delete it.\n",
+            target.describe()
+        ));
+        let body = terminal.suspend_for_editor(&self.config.editor_command(), &template)?;
+        self.selection = None;
+        let written = body.clone();
+        match (body, existing) {
+            (Some(body), Some(index)) => {
+                self.notes[index].body = body;
+                self.status_line = Some("Note updated.".into());
+            }
+            (Some(body), None) => {
+                self.notes.push(Note { body, ..target });
+                self.status_line = Some("Note saved.".into());
+            }
+            (None, Some(index)) => {
+                self.notes.remove(index);
+                self.status_line = Some("Note deleted.".into());
+            }
+            (None, None) => self.clear_status(),
+        }
+        if let Some(body) = written {
+            self.flag_misspellings(&body);
+        }
+        Ok(())
+    }
+
+    /// The new-side line numbers of the diff lines under the selection, in
+    /// order.
+    fn selected_lines(&self) -> Vec<usize> {
+        let Some(selection) = self.selection else {
+            return Vec::new();
+        };
+        let rows = selection.lines();
+        let mut lines: Vec<usize> = self
+            .frame
+            .new_lines
+            .iter()
+            .filter(|(content, _)| rows.contains(content))
+            .map(|(_, line)| *line)
+            .collect();
+        lines.sort_unstable();
+        lines
+    }
+
+    /// The new-side line number under the visual selection's cursor.
+    fn selected_line(&self) -> Option<usize> {
+        let cursor = self.selection?.cursor;
+        self.frame
+            .new_lines
+            .iter()
+            .find(|(content, _)| *content == cursor)
+            .map(|(_, line)| *line)
+    }
+
+    /// The notes shown with `thread`: its own, its file's and those on
+    /// lines of its hunk.
+    fn notes_on(&self, thread: &Thread) -> Vec<&Note> {
+        let numbers = thread
+            .diff_hunk
+            .as_deref()
+            .map(diff::new_line_numbers)
+            .unwrap_or_default();
+        self.notes
+            .iter()
+            .filter(|note| Self::note_is_on(note, thread, &numbers))
+            .collect()
+    }
+
+    /// Whether `note` belongs with `thread`, whose hunk has the new-side
+    /// line `numbers`.
+    fn note_is_on(note: &Note, thread: &Thread, numbers: &[Option<usize>]) -> bool {
+        if !note.thread_id.is_empty() {
+            return note.thread_id == thread.id;
+        }
+        note.path == thread.path && note.line.is_none_or(|line| numbers.contains(&Some(line)))
+    }
+
+    /// Runs the spell checker over `text`, remembering what it flags, and
+    /// returns how many words that was. A checker that fails is turned off
+    /// for the session.
+    /// Starts checking `text` on a blocking thread; what it flags is picked
+    /// up by [`App::apply_prefetched`].
+    fn check_spelling(&mut self, text: &str, announce: bool) {
+        let Some(speller) = self.speller.clone() else {
+            return;
+        };
+        let text = text.to_string();
+        let check = tokio::task::spawn_blocking(move || speller.check(&text));
+        self.spell_checks.push((announce, check));
+    }
+
+    fn store_misspellings(&mut self, result: Result<Vec<spell::Misspelling>>, announce: bool) {
+        if self.speller.is_none() {
+            return;
+        }
+        match result {
+            Ok(found) => {
+                let count = found.len();
+                for misspelling in found {
+                    self.misspelled
+                        .insert(misspelling.word, misspelling.suggestions);
+                }
+                if announce && count > 0 {
+                    self.status_line = Some(format!(
+                        "{count} possible misspelling{} – press {} for suggestions.",
+                        if count == 1 { "" } else { "s" },
+                        self.key_label(Action::Spelling)
+                    ));
+                }
+            }
+            Err(err) => {
+                log::warn!("spell checking disabled: {err}");
+                self.speller = None;
+                self.report_failure(format!("Failed to check spelling: {err}"));
+            }
+        }
+    }
+
+    /// Checks freshly written text and says how to fix what it flags once
+    /// the check finishes.
+    fn flag_misspellings(&mut self, text: &str) {
+        self.check_spelling(text, true);
+    }
+
+    /// `line` with its flagged words underlined.
+    fn mark_misspellings(&self, line: &str) -> String {
+        if self.misspelled.is_empty() {
+            return line.to_string();
+        }
+        let mut marked = String::with_capacity(line.len());
+        let mut last = 0;
+        for range in spell::words(line) {
+            let word = &line[range.clone()];
+            if self.misspelled.contains_key(word) {
+                marked.push_str(&line[last..range.start]);
+                marked.push_str(&word.underlined().to_string());
+                last = range.end;
+            }
+        }
+        marked.push_str(&line[last..]);
+        marked
+    }
+
+    /// Offers the flagged words in the current thread's queued reply and
+    /// notes, then swaps the one picked for a suggestion everywhere in
+    /// them, or stops flagging it.
+    fn fix_spelling(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        if self.speller.is_none() {
+            self.status_line =
+                Some("Spell checking is off – set spell.enabled to turn it on.".into());
+            return Ok(());
+        }
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        let reply = self.queued_reply_index(thread);
+        let numbers = thread
+            .diff_hunk
+            .as_deref()
+            .map(diff::new_line_numbers)
+            .unwrap_or_default();
+        let notes: Vec<usize> = (0..self.notes.len())
+            .filter(|index| Self::note_is_on(&self.notes[*index], thread, &numbers))
+            .collect();
+        let texts: Vec<&str> = reply
+            .map(|index| self.queued_replies[index].body.as_str())
+            .into_iter()
+            .chain(notes.iter().map(|index| self.notes[*index].body.as_str()))
+            .collect();
+        let mut flagged: Vec<String> = Vec::new();
+        for text in &texts {
+            for range in spell::words(text) {
+                let word = &text[range];
+                if self.misspelled.contains_key(word) && !flagged.iter().any(|seen| seen == word) {
+                    flagged.push(word.to_string());
+                }
+            }
+        }
+        if flagged.is_empty() {
+            self.status_line = Some("No misspellings in your reply or notes here.".into());
+            return Ok(());
+        }
+        let options: Vec<String> = flagged
+            .iter()
+            .map(|word| match self.misspelled[word].as_slice() {
+                [] => format!("{word}  (no suggestions)"),
+                suggestions => format!("{word}  → {}", suggestions.join(", ")),
+            })
+            .collect();
+        terminal.deactivate()?;
+        let picked = picker::pick_option(
+            "Misspelled words",
+            &options,
+            "enter: pick a replacement",
+            &self.theme,
+        )
+        .and_then(|picked| {
+            let Some(index) = picked else {
+                return Ok(None);
+            };
+            let word = &flagged[index];
+            let mut choices = self.misspelled[word].clone();
+            choices.push("Ignore this word".into());
+            let choice = picker::pick_option(
+                &format!("Replace “{word}”"),
+                &choices,
+                "enter: replace everywhere here",
+                &self.theme,
+            )?;
+            Ok(choice.map(|choice| (word.clone(), choice, choices)))
+        });
+        terminal.activate()?;
+        let Some((word, choice, choices)) = picked? else {
+            return Ok(());
+        };
+        if choice == choices.len() - 1 {
+            if let Some(speller) = &mut self.speller {
+                speller.ignore(&word);
+            }
+            self.misspelled.remove(&word);
+            self.status_line = Some(format!(
+                "Ignoring “{word}” until you quit – add it to spell.words to keep it."
+            ));
+            return Ok(());
+        }
+        let with = &choices[choice];
+        if let Some(index) = reply {
+            self.record_edit(Edit::Replies(self.queued_replies.clone()));
+            let body = &mut self.queued_replies[index].body;
+            *body = spell::replace_word(body, &word, with);
+        }
+        for index in notes {
+            let body = &mut self.notes[index].body;
+            *body = spell::replace_word(body, &word, with);
+        }
+        self.status_line = Some(format!("Replaced “{word}” with “{with}”."));
+        Ok(())
+    }
+
+    /// Lists every note and jumps to the one picked: to its thread, or the
+    /// thread nearest its file and line.
+    fn show_notes(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        if self.notes.is_empty() {
+            self.status_line = Some(format!(
+                "No notes yet – press {} to write one.",
+                self.key_label(Action::AddNote)
+            ));
+            return Ok(());
+        }
+        let options: Vec<String> = self
+            .notes
+            .iter()
+            .map(|note| {
+                let first = note.body.lines().next().unwrap_or_default();
+                format!("{}  {first}", note.describe())
+            })
+            .collect();
+        terminal.deactivate()?;
+        let picked = picker::pick_option("My notes", &options, "enter: go to note", &self.theme);
+        terminal.activate()?;
+        let Some(index) = picked? else {
+            return Ok(());
+        };
+        let note = &self.notes[index];
+        let exists = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .any(|thread| thread.id == note.thread_id);
+        if exists {
+            let location = Location {
+                thread_id: note.thread_id.clone(),
+                line: 0,
+            };
+            self.goto_location(&location);
+        } else {
+            let target = FileTarget::new(&note.path, note.line.map(|line| line as i64));
+            self.focus_file(&target);
+        }
+        Ok(())
+    }
+
+    /// Opens the review composer: a summary to write, a verdict to pick and
+    /// the queued replies to reorder or drop. Then publishes the replies in
+    /// the chosen order and submits the review.
+    async fn finish_review(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let threads: Vec<&Thread> = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .chain(&self.hidden_threads)
+            .collect();
+        let recap: Vec<String> = self
+            .queued_replies
+            .iter()
+            .map(|reply| {
+                let location = threads
+                    .iter()
+                    .find(|thread| {
+                        thread.id == reply.thread_id
+                            || thread
+                                .comments
+                                .iter()
+                                .any(|comment| comment.database_id == reply.comment_database_id)
+                    })
+                    .map_or_else(
+                        || "(thread gone)".to_string(),
+                        |thread| thread.display_path(),
+                    );
+                format!("{location}  {}", first_line(&reply.body))
+            })
+            .collect();
+        let template = review_composer_template(self.pr_number, &recap, self.pending_drafts);
+        let editor = self.config.editor_command();
+        let text = terminal.suspend_to_edit(&editor, &template)?;
+        let composed = parse_review_composer(&text, recap.len())?;
+        if composed.body.is_empty()
+            && composed.event != ReviewEvent::Approve
+            && composed.order.is_empty()
+            && self.pending_drafts == 0
+        {
+            self.status_line = Some("Review cancelled.".into());
+            return Ok(());
+        }
+        self.record_edit(Edit::Replies(self.queued_replies.clone()));
+        let queued = mem::take(&mut self.queued_replies);
+        self.queued_replies = composed
+            .order
+            .iter()
+            .filter_map(|&index| queued.get(index).cloned())
+            .collect();
+        // The pending review's drafts go out with the verdict below, not as
+        // a separate comment review.
+        let drafts = mem::take(&mut self.pending_drafts);
+        let published = self.publish_queue().await;
+        if published.is_err() || !self.queued_replies.is_empty() {
+            // Publishing stopped at a failure or a conflict, which says why.
+            self.pending_drafts = drafts;
+            return published;
+        }
+        // A comment review needs a body or drafts; without either the
+        // replies alone were the review.
+        if composed.event == ReviewEvent::Comment && composed.body.is_empty() && drafts == 0 {
+            self.status_line = Some(format!(
+                "Published {} repl{}.",
+                composed.order.len(),
+                if composed.order.len() == 1 {
+                    "y"
                 } else {
-                    writeln!(
-                        buf,
-                        "{}",
-                        "(diff hunk hidden – press d to show)".with(Color::DarkGrey)
-                    )?;
-                    writeln!(buf)?;
+                    "ies"
+                }
+            ));
+            return Ok(());
+        }
+        self.submit_tui_review(composed.event, &composed.body).await
+    }
+
+    /// Counts the draft comments in your pending review on GitHub, which
+    /// may have been written on another machine.
+    async fn load_pending_review(&mut self) {
+        match self
+            .gh
+            .pending_draft_count(&self.repo, self.pr_number)
+            .await
+        {
+            Ok(count) => {
+                self.pending_drafts = count;
+                if count > 0 && self.status_line.is_none() {
+                    self.status_line = Some(format!(
+                        "Your pending review on GitHub has {count} draft comment{} – press {} to publish {}",
+                        if count == 1 { "" } else { "s" },
+                        self.key_label(Action::Publish),
+                        if count == 1 { "it" } else { "them" }
+                    ));
+                }
+            }
+            Err(err) => log::warn!("failed to look up your pending review: {err}"),
+        }
+    }
+
+    /// Looks up which gh account is active and what it may do here.
+    async fn load_account(&mut self) {
+        match self.gh.viewer_login().await {
+            Ok(login) => self.account = Some(login),
+            Err(err) => log::warn!("failed to look up the active account: {err}"),
+        }
+        match self.gh.viewer_permission(&self.repo).await {
+            Ok(permission) => self.permission = Some(permission),
+            Err(err) => log::warn!("failed to look up your permission on the repository: {err}"),
+        }
+        let Some(login) = self.account.clone().filter(|_| self.compare.is_none()) else {
+            return;
+        };
+        match self.gh.pr_author(self.pr_number).await {
+            Ok(author) => self.own_pr = author.eq_ignore_ascii_case(&login),
+            Err(err) => log::warn!("failed to look up the PR's author: {err}"),
+        }
+        let left = self.unresolved_threads.len();
+        if self.own_pr && left > 0 && self.status_line.is_none() {
+            self.status_line = Some(format!(
+                "Your PR: {left} thread{} to address – {} lists them, {} replies and resolves one.",
+                if left == 1 { "" } else { "s" },
+                self.key_label(Action::Todo),
+                self.key_label(Action::MarkAddressed)
+            ));
+        }
+    }
+
+    /// The account's access level, lowercased, when it can't push to the
+    /// repository or resolve threads on it.
+    fn read_access(&self) -> Option<String> {
+        self.permission
+            .as_deref()
+            .filter(|permission| !matches!(*permission, "ADMIN" | "MAINTAIN" | "WRITE"))
+            .map(str::to_lowercase)
+    }
+
+    /// Lets the user pick another account gh is logged in to for the
+    /// repository's host and switches to it, as `gh auth switch` does, then
+    /// reloads the threads as that account sees them.
+    async fn switch_account(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let host = self.gh.host.clone().unwrap_or_else(|| "github.com".into());
+        let accounts = self.gh.auth_accounts(&host).await?;
+        if accounts.len() < 2 {
+            self.status_line = Some(format!(
+                "Only one account is logged in to {host} – add another with `gh auth login`."
+            ));
+            return Ok(());
+        }
+        let options: Vec<String> = accounts
+            .iter()
+            .map(|(login, active)| {
+                if *active {
+                    format!("{login} (active)")
+                } else {
+                    login.clone()
+                }
+            })
+            .collect();
+        terminal.deactivate()?;
+        let picked = picker::pick_option(
+            &format!("Switch account on {host}"),
+            &options,
+            "↑/↓ select  enter switch  q close",
+            &self.theme,
+        );
+        terminal.activate()?;
+        let Some(index) = picked? else {
+            return Ok(());
+        };
+        let (login, active) = &accounts[index];
+        if *active {
+            return Ok(());
+        }
+        self.gh.switch_account(&host, login).await?;
+        self.account = None;
+        self.permission = None;
+        self.load_account().await;
+        self.refresh_threads().await?;
+        self.status_line = Some(match self.read_access() {
+            Some(level) => format!(
+                "Switched to @{login}, who only has {level} access to {}/{} – resolving threads and reviewing may fail.",
+                self.repo.owner, self.repo.name
+            ),
+            None => format!("Switched to @{login}."),
+        });
+        Ok(())
+    }
+
+    /// Moves the queued replies into your pending review on GitHub,
+    /// starting one if needed, so the review can be carried on from
+    /// anywhere. Replies saved before threads were recorded stay queued.
+    async fn sync_review(&mut self) -> Result<()> {
+        let syncable = self
+            .queued_replies
+            .iter()
+            .filter(|reply| !reply.thread_id.is_empty())
+            .count();
+        if syncable > 0 {
+            // Synced replies are on GitHub now, out of undo's reach.
+            self.undo.retain(|edit| !matches!(edit, Edit::Replies(_)));
+            self.redo.retain(|edit| !matches!(edit, Edit::Replies(_)));
+            let review = self
+                .gh
+                .start_pending_review(&self.repo, self.pr_number)
+                .await?;
+            let mut kept = VecDeque::new();
+            while let Some(reply) = self.queued_replies.pop_front() {
+                if reply.thread_id.is_empty() {
+                    kept.push_back(reply);
+                    continue;
+                }
+                if let Err(err) = self
+                    .gh
+                    .add_draft_reply(&review, &reply.thread_id, &reply.body)
+                    .await
+                {
+                    kept.push_back(reply);
+                    kept.extend(self.queued_replies.drain(..));
+                    self.queued_replies = kept;
+                    return Err(err);
+                }
+            }
+            self.queued_replies = kept;
+            self.refresh_threads().await?;
+        }
+        self.pending_drafts = self
+            .gh
+            .pending_draft_count(&self.repo, self.pr_number)
+            .await?;
+        self.status_line = Some(format!(
+            "Synced {syncable} repl{} – your pending review on GitHub has {} draft comment{}.",
+            if syncable == 1 { "y" } else { "ies" },
+            self.pending_drafts,
+            if self.pending_drafts == 1 { "" } else { "s" }
+        ));
+        Ok(())
+    }
+
+    /// Where the reply queued for `thread` is in the queue, if there is one.
+    fn queued_reply_index(&self, thread: &Thread) -> Option<usize> {
+        self.queued_replies.iter().position(|reply| {
+            reply.thread_id == thread.id
+                || thread
+                    .comments
+                    .iter()
+                    .any(|comment| comment.database_id == reply.comment_database_id)
+        })
+    }
+
+    /// Edits the PR's title and description in the user's editor: the first
+    /// line is the title and everything after the blank line below it is
+    /// the body. Only the author and maintainers may save changes.
+    async fn edit_description(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let (title, body) = self.gh.pr_description(self.pr_number).await?;
+        let editor = self.config.editor_command();
+        let edited = terminal.suspend_to_edit(&editor, &format!("{title}\n\n{body}\n"))?;
+        let (new_title, new_body) = edited.split_once('\n').unwrap_or((&edited, ""));
+        let (new_title, new_body) = (new_title.trim(), new_body.trim());
+        if new_title.is_empty() {
+            self.status_line = Some("Edit cancelled: the title can't be empty.".into());
+            return Ok(());
+        }
+        if new_title == title.trim() && new_body == body.trim() {
+            self.status_line = Some("Description unchanged.".into());
+            return Ok(());
+        }
+        self.gh
+            .update_description(&self.repo, self.pr_number, new_title, new_body)
+            .await?;
+        self.status_line = Some(format!("Updated PR #{}: {new_title}", self.pr_number));
+        Ok(())
+    }
+
+    async fn publish_queue(&mut self) -> Result<()> {
+        if self.queued_replies.is_empty() && self.pending_drafts == 0 {
+            self.status_line = None;
+            return Ok(());
+        }
+        // Published replies can't come back, so neither can queue states
+        // that still hold them.
+        self.undo.retain(|edit| !matches!(edit, Edit::Replies(_)));
+        self.redo.retain(|edit| !matches!(edit, Edit::Replies(_)));
+        let total = self.queued_replies.len();
+        let mut index = 0;
+        while let Some(reply) = self.queued_replies.front().cloned() {
+            self.status_line = Some(format!("Publishing reply {}/{}", index + 1, total,));
+
+            self.render()?;
+
+            if let Err(err) = self
+                .gh
+                .post_reply(
+                    &self.repo,
+                    self.pr_number,
+                    reply.comment_database_id,
+                    &reply.body,
+                )
+                .await
+            {
+                let Some(conflict) = Conflict::detect(&err) else {
+                    return Err(err);
+                };
+                return self.reply_conflict(conflict, index, err).await;
+            }
+            self.queued_replies.pop_front();
+            index += 1;
+            hooks::fire(
+                &self.config.hooks,
+                Hook::CommentPosted,
+                &self.repo,
+                self.pr_number,
+                &[("GH_CR_BODY", &reply.body)],
+            );
+        }
+        if self.pending_drafts > 0 {
+            self.status_line = Some("Submitting your pending review".into());
+            self.render()?;
+            self.gh
+                .submit_review(&self.repo, self.pr_number, ReviewEvent::Comment, "")
+                .await?;
+            self.pending_drafts = 0;
+        }
+        self.clear_status();
+        self.refresh_threads().await?;
+        Ok(())
+    }
+
+    /// Reloads the threads after the reply at the front of the queue hit a
+    /// conflict, and says what happened to it. A reply whose comment was
+    /// deleted moves to the thread's latest comment, or to the PR comment
+    /// draft if the whole thread is gone. A not-found error is only taken
+    /// as a deletion once the reload confirms it; otherwise `err` is
+    /// returned as it was.
+    async fn reply_conflict(
+        &mut self,
+        conflict: Conflict,
+        published: usize,
+        err: anyhow::Error,
+    ) -> Result<()> {
+        self.refresh_threads().await?;
+        let message = match conflict {
+            Conflict::Locked => "the PR's conversation was locked".to_string(),
+            Conflict::Closed => "the PR was closed".to_string(),
+            Conflict::Deleted => {
+                let Some(reply) = self.queued_replies.front() else {
+                    return Ok(());
+                };
+                let thread = self
+                    .active_threads
+                    .iter()
+                    .chain(&self.skipped_threads)
+                    .chain(&self.hidden_threads)
+                    .find(|thread| thread.id == reply.thread_id);
+                if thread.is_some_and(|thread| {
+                    thread
+                        .comments
+                        .iter()
+                        .any(|comment| comment.database_id == reply.comment_database_id)
+                }) {
+                    return Err(err);
+                }
+                let latest = thread
+                    .and_then(|thread| thread.comments.last())
+                    .map(|comment| comment.database_id);
+                match latest {
+                    Some(id) => {
+                        if let Some(reply) = self.queued_replies.front_mut() {
+                            reply.comment_database_id = id;
+                        }
+                        format!(
+                            "the comment replied to was deleted; the reply now answers the thread's latest comment – press {} to publish",
+                            self.key_label(Action::Publish)
+                        )
+                    }
+                    None => {
+                        let reply = self.queued_replies.pop_front();
+                        if let Some(reply) = reply {
+                            let draft = self.comment_draft.take().unwrap_or_default();
+                            self.comment_draft =
+                                Some(format!("{draft}\n\n{}", reply.body).trim().to_string());
+                        }
+                        "the thread was deleted; the reply is kept as your PR comment draft"
+                            .to_string()
+                    }
+                }
+            }
+        };
+        self.report_failure(format!(
+            "Conflict after publishing {published} repl{}: {message}. Threads refreshed, {left} still queued.",
+            if published == 1 { "y" } else { "ies" },
+            left = self.queued_replies.len(),
+        ));
+        Ok(())
+    }
+
+    /// Asks before switching the local checkout to the PR, naming the
+    /// branch it switches to.
+    async fn confirm_checkout(&mut self) -> Result<()> {
+        let branch = self.gh.pr_text(self.pr_number).await?.head_ref_name;
+        let current = git::head_name().unwrap_or_else(|| "a detached HEAD".into());
+        let target = if self.config.checkout.detach {
+            format!("{branch}'s head, detached")
+        } else {
+            format!("branch {branch}")
+        };
+        self.prompt = Some(Prompt {
+            action: Action::Checkout,
+            label: format!(
+                "Switch from {current} to {target} for PR #{}? (y/n)",
+                self.pr_number
+            ),
+            text: String::new(),
+        });
+        Ok(())
+    }
+
+    async fn checkout_pr(&mut self) -> Result<()> {
+        let detach = self.config.checkout.detach;
+        self.gh.checkout(self.pr_number, detach).await?;
+        let head = git::head_name().unwrap_or_else(|| "the PR head".into());
+        self.status_line = Some(if detach {
+            format!("Checked out PR #{} at {head} (detached).", self.pr_number)
+        } else {
+            format!("Checked out PR #{} on branch {head}.", self.pr_number)
+        });
+        Ok(())
+    }
+
+    /// Saves the PR's commits as a `git am`-able patch in the working directory.
+    async fn save_patch(&mut self) -> Result<()> {
+        let path = PathBuf::from(format!("{}-pr-{}.patch", self.repo.name, self.pr_number));
+        let file = fs::File::create(&path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        let files = self
+            .stream_diff(true, |line| {
+                out.write_all(line)?;
+                Ok(())
+            })
+            .await?;
+        out.flush()
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        self.status_line = Some(format!(
+            "Saved PR #{} ({files} file{}) to {}.",
+            self.pr_number,
+            if files == 1 { "" } else { "s" },
+            path.display()
+        ));
+        Ok(())
+    }
+
+    /// Applies the PR's combined diff to the working tree without switching
+    /// branches.
+    async fn apply_patch(&mut self) -> Result<()> {
+        let mut diff = Vec::new();
+        self.stream_diff(false, |line| {
+            diff.extend_from_slice(line);
+            Ok(())
+        })
+        .await?;
+        git::apply_3way(&diff)?;
+        self.status_line = Some(format!(
+            "Applied PR #{} to the working tree.",
+            self.pr_number
+        ));
+        Ok(())
+    }
+
+    /// Feeds the PR's diff to `sink` line by line as `gh` produces it,
+    /// redrawing the status line with the files seen so far so a large diff
+    /// shows progress while it downloads. Lines are passed on byte for byte,
+    /// line endings included, so CRLF files survive. Returns the number of
+    /// files.
+    async fn stream_diff(
+        &mut self,
+        patch: bool,
+        mut sink: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<usize> {
+        let mut stream = self.gh.pr_diff(self.pr_number, patch)?;
+        let mut files = 0;
+        let mut last_draw = Instant::now();
+        while let Some(line) = stream.next_line().await? {
+            sink(&line)?;
+            let text = String::from_utf8_lossy(&line);
+            let Some(path) = diff::file_header_path(text.trim_end()) else {
+                continue;
+            };
+            files += 1;
+            if files == 1 || last_draw.elapsed() >= DIFF_PROGRESS_INTERVAL {
+                self.status_line = Some(format!(
+                    "Downloading PR #{} diff: {files} file{}, now {path}…",
+                    self.pr_number,
+                    if files == 1 { "" } else { "s" }
+                ));
+                self.render()?;
+                last_draw = Instant::now();
+            }
+        }
+        stream.finish().await?;
+        Ok(files)
+    }
+
+    /// Writes the threads in the current view, plus unpublished replies, to
+    /// a Markdown file in the working directory.
+    fn export_view(&mut self) -> Result<()> {
+        let heading = format!("{} threads", self.view.name());
+        let queued: Vec<QueuedReply> = self.queued_replies.iter().cloned().collect();
+        let doc = export::markdown(
+            &self.repo,
+            self.pr_number,
+            &heading,
+            self.current_threads(),
+            &queued,
+        );
+        let path = PathBuf::from(format!(
+            "{}-pr-{}-{}.md",
+            self.repo.name,
+            self.pr_number,
+            self.view.name()
+        ));
+        fs::write(&path, doc).with_context(|| format!("failed to write {}", path.display()))?;
+        self.status_line = Some(format!(
+            "Exported {} threads to {}.",
+            self.current_threads().len(),
+            path.display()
+        ));
+        Ok(())
+    }
+
+    /// Checks the PR head out into its own worktree, leaving the current
+    /// branch alone, and remembers it for the editor action.
+    fn create_worktree(&mut self) -> Result<()> {
+        let path = match (&self.worktree, &self.config.worktree.dir) {
+            (Some(path), _) => path.clone(),
+            (None, Some(dir)) => dir.join(format!("{}-pr-{}", self.repo.name, self.pr_number)),
+            (None, None) => {
+                let root = git::toplevel().ok_or_else(|| anyhow!("not inside a git checkout"))?;
+                let name = format!("{}-pr-{}", self.repo.name, self.pr_number);
+                root.parent().unwrap_or(&root).join(name)
+            }
+        };
+        git::worktree_for_pr(&self.config.worktree.remote, self.pr_number, &path)?;
+        self.status_line = Some(format!(
+            "PR #{} checked out in {}.",
+            self.pr_number,
+            path.display()
+        ));
+        self.worktree = Some(path);
+        Ok(())
+    }
+
+    /// Opens the current thread's file at the line under discussion, in the
+    /// PR's worktree if one was created, otherwise in the local checkout.
+    fn open_in_editor(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let Some(thread) = self.current_thread() else {
+            self.status_line = Some("No thread selected.".into());
+            return Ok(());
+        };
+        let root = match &self.worktree {
+            Some(worktree) => worktree.clone(),
+            None => git::toplevel().ok_or_else(|| anyhow!("not inside a git checkout"))?,
+        };
+        let path = root.join(&thread.path);
+        if !path.exists() {
+            self.status_line = Some(format!("{} isn't in the local checkout.", thread.path));
+            return Ok(());
+        }
+        let line = thread.line;
+        let editor = self.config.editor_command();
+        terminal.suspend_to_open(&editor, &path, line)?;
+        self.clear_status();
+        Ok(())
+    }
+
+    /// Opens the issue the PR refers to, asking which when it names several.
+    fn open_issue(&mut self, terminal: &mut TerminalSession) -> Result<()> {
+        let url = match self.issue_keys.as_slice() {
+            [] => {
+                self.status_line = Some("No issue keys in the PR's title, branch or body.".into());
+                return Ok(());
+            }
+            [(_, url)] => url.clone(),
+            keys => {
+                let options: Vec<String> = keys.iter().map(|(key, _)| key.clone()).collect();
+                terminal.deactivate()?;
+                let picked = picker::pick_option(
+                    "Open an issue",
+                    &options,
+                    "↑/↓ select  enter open  q close",
+                    &self.theme,
+                );
+                terminal.activate()?;
+                let Some(index) = picked? else {
+                    return Ok(());
+                };
+                keys[index].1.clone()
+            }
+        };
+        browser::open(&url)?;
+        self.status_line = Some(format!("Opened {url}"));
+        Ok(())
+    }
+
+    /// Opens the selected thread on GitHub, or the PR itself when no thread
+    /// is selected.
+    async fn open_in_browser(&mut self) -> Result<()> {
+        let url = self
+            .current_thread()
+            .and_then(|thread| thread.comments.first())
+            .and_then(|comment| comment.url.clone());
+        match url {
+            Some(url) => {
+                browser::open(&url)?;
+                self.status_line = Some(format!("Opened {url}"));
+            }
+            None => {
+                self.gh.open_pr(self.pr_number).await?;
+                self.status_line = Some(format!("Opened PR #{}", self.pr_number));
+            }
+        }
+        Ok(())
+    }
+
+    fn toggle_diff(&mut self) {
+        self.show_diff = !self.show_diff;
+        self.reset_scroll();
+        self.clear_status();
+    }
+
+    fn toggle_fold(&mut self) {
+        self.fold_unchanged = !self.fold_unchanged;
+        self.reset_scroll();
+        self.status_line = Some(if self.fold_unchanged {
+            "Folding unchanged blocks in hunks.".into()
+        } else {
+            "Showing hunks in full.".into()
+        });
+    }
+
+    async fn watch_snapshot(&self) -> Result<WatchState> {
+        let (head, checks) = self.gh.fetch_status(self.pr_number).await?;
+        let comments = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .chain(&self.hidden_threads)
+            .map(|thread| thread.comments.len())
+            .sum();
+        Ok(WatchState {
+            head,
+            comments,
+            checks: checks
+                .into_iter()
+                .map(|check| (check.name, check.state))
+                .collect(),
+        })
+    }
+
+    /// Compares the PR against the last watch snapshot and announces what
+    /// changed in the status line and, if enabled, as a desktop notification.
+    async fn poll_changes(&mut self) -> Result<()> {
+        let Some(previous) = &self.watch else {
+            return Ok(());
+        };
+        let current = self.watch_snapshot().await?;
+        let mut changes = Vec::new();
+        if previous.head.is_some() && current.head != previous.head {
+            changes.push("new commits pushed".to_string());
+        }
+        if current.comments > previous.comments {
+            let added = current.comments - previous.comments;
+            let noun = if added == 1 { "comment" } else { "comments" };
+            changes.push(format!("{added} new {noun}"));
+        }
+        let mut finished: Vec<String> = current
+            .checks
+            .iter()
+            .filter(|(name, state)| previous.checks.get(*name) != Some(state))
+            .filter_map(|(name, state)| match state {
+                CheckState::Pass => Some(format!("{name} passed")),
+                CheckState::Fail => Some(format!("{name} failed")),
+                CheckState::Pending | CheckState::Skipped => None,
+            })
+            .collect();
+        finished.sort();
+        changes.extend(finished);
+        self.watch = Some(current);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let summary = changes.join(", ");
+        log::info!("watch: {summary}");
+        if self.config.watch.notify {
+            notify::desktop(&format!("PR #{}", self.pr_number), &summary);
+        }
+        self.status_line = Some(format!("PR #{}: {summary}", self.pr_number));
+        Ok(())
+    }
+
+    async fn refresh_threads(&mut self) -> Result<()> {
+        let updated = self
+            .gh
+            .fetch_threads(&self.repo, self.pr_number)
+            .await
+            .context("failed to refresh threads")?;
+        self.external_diffs.clear();
+        self.blames.clear();
+        // Files may have been added or removed since; refetch only if the
+        // filter or sort depends on them.
+        self.pr_files = None;
+        if self.sort == ThreadSort::Changes
+            || self
+                .file_filter
+                .as_ref()
+                .is_some_and(FileFilter::needs_statuses)
+        {
+            self.pr_files = Some(self.gh.pr_files(&self.repo, self.pr_number).await?);
+        }
+        self.set_threads(updated);
+        self.refreshed_at = Utc::now();
+        self.refresh_error = None;
+        self.load_annotations().await;
+        // The merge commit changes with every push to the head or base.
+        self.merge_diff = None;
+        if self.show_merge_diff
+            && let Err(err) = self.load_merge_diff().await
+        {
+            log::warn!("failed to reload the merge diff: {err}");
+        }
+        Ok(())
+    }
+
+    /// Switches between the head diff and the merge commit's, fetching the
+    /// latter the first time.
+    async fn toggle_merge_diff(&mut self) -> Result<()> {
+        if self.compare.is_some() {
+            self.status_line = Some("Only PRs have a merge commit to diff.".into());
+            return Ok(());
+        }
+        if self.show_merge_diff {
+            self.show_merge_diff = false;
+            self.status_line = Some("Showing the head diff.".into());
+            return Ok(());
+        }
+        if self.merge_diff.is_none() && !self.load_merge_diff().await? {
+            self.status_line = Some(
+                "GitHub has no merge commit for this PR – it may conflict with the base.".into(),
+            );
+            return Ok(());
+        }
+        self.show_merge_diff = true;
+        self.status_line = Some(format!(
+            "Showing the merge commit's diff: what lands on the base. {} shows the head diff.",
+            self.key_label(Action::ToggleMergeDiff)
+        ));
+        Ok(())
+    }
+
+    /// Fetches the test merge commit's diff, returning whether GitHub had
+    /// one.
+    async fn load_merge_diff(&mut self) -> Result<bool> {
+        let Some(diff) = self
+            .gh
+            .merge_commit_diff(&self.repo, self.pr_number)
+            .await?
+        else {
+            return Ok(false);
+        };
+        let files = diff::split_files(&diff)
+            .into_iter()
+            .map(|file| {
+                let hunks = file.hunks.into_iter().map(|(_, hunk)| hunk).collect();
+                (file.path, hunks)
+            })
+            .collect();
+        self.merge_diff = Some(files);
+        Ok(true)
+    }
+
+    /// The merge commit's hunk around the thread's line, while the merge
+    /// diff is shown.
+    fn merge_hunk(&self, thread: &Thread) -> Option<&str> {
+        if !self.show_merge_diff {
+            return None;
+        }
+        let hunks = self.merge_diff.as_ref()?.get(&thread.path)?;
+        let Some(line) = thread.line else {
+            return hunks.first().map(String::as_str);
+        };
+        hunks
+            .iter()
+            .find(|hunk| diff::new_line_numbers(hunk).contains(&Some(line as usize)))
+            .map(String::as_str)
+    }
+
+    /// How often threads are refetched in the background, if at all.
+    fn refresh_every(&self) -> Option<Duration> {
+        let interval = match (self.config.watch.enabled, self.config.refresh_interval) {
+            (true, 0) => self.config.watch.interval,
+            (_, interval) => interval,
+        };
+        (interval > 0).then(|| Duration::from_secs(interval))
+    }
+
+    /// Why what's on screen may be out of date: the last background
+    /// refresh failed, or none has finished for two intervals.
+    fn staleness(&self, now: DateTime<Utc>) -> Option<String> {
+        if let Some(err) = &self.refresh_error {
+            return Some(format!("refresh failed: {err}"));
+        }
+        let every = chrono::Duration::from_std(self.refresh_every()?).ok()?;
+        (now - self.refreshed_at > every * 2).then(|| "background refresh overdue".into())
+    }
+
+    async fn load_rate_limit(&mut self) {
+        match self.gh.rate_limit().await {
+            Ok(limit) => self.rate_limit = Some(limit),
+            Err(err) => log::warn!("failed to look up the API rate limit: {err}"),
+        }
+    }
+
+    /// Replaces every thread list, applying the skip list and any
+    /// since-review filter, and keeps each view's selection where it can.
+    fn set_threads(&mut self, threads: Vec<Thread>) {
+        let current_id = self.current_thread().map(|t| t.id.clone());
+        let current_unresolved_id = self
+            .unresolved_threads
+            .get(self.current_unresolved)
+            .map(|t| t.id.clone());
+        let current_active_id = self
+            .active_threads
+            .get(self.current_active)
+            .map(|t| t.id.clone());
+        let current_skipped_id = self
+            .skipped_threads
+            .get(self.current_skipped)
+            .map(|t| t.id.clone());
+        let unresolved: HashSet<String> = threads
+            .iter()
+            .filter(|thread| !thread.is_resolved)
+            .map(|thread| thread.path.clone())
+            .collect();
+        let (threads, hidden) = threads.into_iter().partition(|thread| {
+            let changed = self
+                .changed_since_review
+                .as_ref()
+                .is_none_or(|paths| paths.contains(&thread.path));
+            let status = self
+                .pr_files
+                .as_ref()
+                .and_then(|files| files.get(&thread.path))
+                .map(|file| file.status.as_str());
+            let matched = self.file_filter.as_ref().is_none_or(|filter| {
+                filter.matches(
+                    &thread.path,
+                    status,
+                    unresolved.contains(&thread.path),
+                    thread.is_outdated,
+                    thread
+                        .comments
+                        .first()
+                        .map_or("", |comment| &comment.author),
+                )
+            });
+            let ignored = self
+                .prefs
+                .ignore
+                .iter()
+                .any(|glob| filter::glob_match(glob, &thread.path));
+            changed && matched && !ignored
+        });
+        self.hidden_threads = hidden;
+        let (mut active, mut skipped) = Self::partition_threads(&self.skip_store, threads);
+        self.order_threads(&mut active);
+        self.order_threads(&mut skipped);
+        self.active_threads = active;
+        self.skipped_threads = skipped;
+        self.restore_selection(ThreadView::Active, current_active_id);
+        self.restore_selection(ThreadView::Skipped, current_skipped_id);
+        self.rebuild_unresolved(current_unresolved_id);
+        self.clamp_current_index();
+        if self.current_thread().map(|t| t.id.clone()) != current_id {
+            self.reset_scroll();
+        }
+    }
+
+    /// Limits every view to threads on files changed since the viewer's
+    /// last submitted review, or lifts that limit.
+    async fn toggle_since_review(&mut self) -> Result<()> {
+        let all: Vec<Thread> = self
+            .active_threads
+            .iter()
+            .chain(&self.skipped_threads)
+            .chain(&self.hidden_threads)
+            .cloned()
+            .collect();
+        if self.changed_since_review.take().is_some() {
+            self.set_threads(all);
+            self.status_line = Some("Showing threads on every file.".into());
+            return Ok(());
+        }
+        let login = self.gh.viewer_login().await?;
+        let Some(reviewed) = self
+            .gh
+            .last_review_commit(&self.repo, self.pr_number, &login)
+            .await?
+        else {
+            self.status_line = Some("You haven't reviewed this PR yet.".into());
+            return Ok(());
+        };
+        let (head, _) = self.gh.fetch_status(self.pr_number).await?;
+        let head = head.ok_or_else(|| anyhow!("PR has no head commit"))?;
+        let short = &reviewed[..reviewed.len().min(7)];
+        if head == reviewed {
+            self.status_line = Some(format!("Nothing has changed since your review at {short}."));
+            return Ok(());
+        }
+        let paths = self.gh.changed_files(&self.repo, &reviewed, &head).await?;
+        let files = paths.len();
+        self.changed_since_review = Some(paths);
+        self.set_threads(all);
+        self.status_line = Some(format!(
+            "Showing threads on {files} file{} changed since your review at {short}.",
+            if files == 1 { "" } else { "s" }
+        ));
+        Ok(())
+    }
+
+    fn restore_selection(&mut self, view: ThreadView, target: Option<String>) {
+        let target_pos =
+            target.and_then(|id| self.threads_for_view(view).iter().position(|t| t.id == id));
+        let len = self.threads_for_view(view).len();
+        let index = self.index_for_view_mut(view);
+        if let Some(found) = target_pos {
+            *index = found;
+        } else if len == 0 {
+            *index = 0;
+        } else if *index >= len {
+            *index = len - 1;
+        }
+    }
+}
+
+fn build_reply_editor_template(thread: &Thread) -> String {
+    let mut buf = String::from("\n\n");
+    let now = Utc::now();
+    let status = if thread.is_resolved {
+        "resolved"
+    } else {
+        "open"
+    };
+    let _ = writeln!(
+        buf,
+        "# Reply to the thread on {} ({} status).",
+        thread.display_path(),
+        status
+    );
+    let _ = writeln!(
+        buf,
+        "# Lines starting with '# ' are ignored when submitting the reply."
+    );
+    let _ = writeln!(buf, "#");
+    let _ = writeln!(buf, "# --- Thread comments ---");
+    let context_width = COMMENT_WRAP.saturating_sub(2).max(10);
+    let context_wrap = WrapOptions::new(context_width).break_words(false);
+    for comment in &thread.comments {
+        let _ = writeln!(
+            buf,
+            "# {} ({})",
+            comment.author,
+            humanize_relative(now, comment.created_at)
+        );
+        if comment.body.trim().is_empty() {
+            let _ = writeln!(buf, "#");
+            continue;
+        }
+        for line in comment.body.lines() {
+            if line.trim().is_empty() {
+                let _ = writeln!(buf, "#");
+            } else {
+                for chunk in wrap(line, context_wrap.clone()) {
+                    let _ = writeln!(buf, "# {}", chunk);
                 }
             }
-            let wrap_opts = WrapOptions::new(COMMENT_WRAP).break_words(false);
-            for (idx, comment) in thread.comments.iter().enumerate() {
-                if idx > 0 {
-                    // writeln!(buf)?;
-                }
-                // writeln!(
-                //     buf,
-                //     "{} {} {}",
-                //     "╭".dark_grey(),
-                //     comment
-                //         .author
-                //         .as_str()
-                //         .with(Color::Rgb {
-                //             r: 120,
-                //             g: 200,
-                //             b: 220
-                //         })
-                //         .bold(),
-                //     humanize_relative(now, comment.created_at).with(muted)
-                // )?;
-                let mut body_lines = Vec::new();
-                body_lines.push(format!(
-                    "{} {}",
-                    comment
-                        .author
-                        .as_str()
-                        .with(Color::Rgb {
-                            r: 120,
-                            g: 200,
-                            b: 220
-                        })
-                        .bold(),
-                    humanize_relative(now, comment.created_at).with(muted)
-                ));
-                for line in comment.body.lines() {
-                    if line.trim().is_empty() {
-                        body_lines.push(String::new());
-                    } else {
-                        for chunk in wrap(line, wrap_opts.clone()) {
-                            body_lines.push(chunk.into_owned());
+        }
+        let _ = writeln!(buf, "#");
+    }
+    buf
+}
+
+/// One file in a PR, as listed by GitHub.
+struct PrFile {
+    /// `added`, `modified`, `removed` or `renamed`.
+    status: String,
+    /// Lines added plus lines removed.
+    changes: u64,
+    additions: u64,
+    deletions: u64,
+    /// The path before a rename.
+    previous: Option<String>,
+}
+
+#[derive(Clone)]
+struct Thread {
+    id: String,
+    path: String,
+    line: Option<i64>,
+    diff_hunk: Option<String>,
+    is_resolved: bool,
+    /// The commented line is gone from the PR's latest diff.
+    is_outdated: bool,
+    created_at: DateTime<Utc>,
+    comments: Vec<Comment>,
+}
+
+impl Thread {
+    fn sort_key(&self) -> (&bool, &DateTime<Utc>) {
+        (&self.is_resolved, &self.created_at)
+    }
+
+    fn display_path(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.path, line),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Link to the thread's conversation on the PR, from its first comment.
+    fn discussion_url(&self) -> Option<String> {
+        self.comments.first()?.url.clone()
+    }
+
+    /// Link to the commented line at the commit the thread was left on.
+    fn permalink(&self, repo_url: &str) -> Option<String> {
+        let commit = self
+            .comments
+            .iter()
+            .find_map(|comment| comment.commit_oid.as_deref())?;
+        let anchor = self
+            .line
+            .map(|line| format!("#L{line}"))
+            .unwrap_or_default();
+        Some(format!("{repo_url}/blob/{commit}/{}{anchor}", self.path))
+    }
+
+    fn to_json(&self, skipped: bool) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "path": self.path,
+            "line": self.line,
+            "resolved": self.is_resolved,
+            "skipped": skipped,
+            "created_at": self.created_at.to_rfc3339(),
+            "diff_hunk": self.diff_hunk,
+            "comments": self
+                .comments
+                .iter()
+                .map(|comment| serde_json::json!({
+                    "id": comment.database_id,
+                    "author": comment.author,
+                    "body": comment.body,
+                    "created_at": comment.created_at.to_rfc3339(),
+                    "url": comment.url,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+#[derive(Clone)]
+struct Comment {
+    id: String,
+    database_id: u64,
+    author: String,
+    body: String,
+    line: Option<i64>,
+    original_line: Option<i64>,
+    diff_hunk: Option<String>,
+    created_at: DateTime<Utc>,
+    url: Option<String>,
+    commit_oid: Option<String>,
+    /// Why the comment was hidden on GitHub, such as `off-topic`.
+    minimized: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct QueuedReply {
+    comment_database_id: u64,
+    /// Thread replied to, so the reply can follow it if the comment is
+    /// deleted; empty in sessions saved before it was recorded.
+    #[serde(default)]
+    thread_id: String,
+    body: String,
+}
+
+/// A note that stays on this machine, on a hunk, a file or one line of a
+/// file.
+#[derive(Clone, Serialize, Deserialize)]
+struct Note {
+    /// The thread whose hunk the note is on; empty for notes on a file or
+    /// line.
+    #[serde(default)]
+    thread_id: String,
+    #[serde(default)]
+    path: String,
+    /// The new-file line the note is on.
+    #[serde(default)]
+    line: Option<usize>,
+    body: String,
+    created_at: DateTime<Utc>,
+}
+
+impl Note {
+    fn same_target(&self, other: &Note) -> bool {
+        (&self.thread_id, &self.path, self.line) == (&other.thread_id, &other.path, other.line)
+    }
+
+    /// `the hunk in src/lib.rs`, `src/lib.rs` or `src/lib.rs:40`.
+    fn describe(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{line}", self.path),
+            None if self.thread_id.is_empty() => self.path.clone(),
+            None => format!("the hunk in {}", self.path),
+        }
+    }
+}
+
+/// Undo steps kept; older ones are dropped.
+const UNDO_LIMIT: usize = 100;
+
+/// Local review state from before a change, swapped back in to undo it.
+/// Nothing here has reached GitHub yet.
+enum Edit {
+    Replies(VecDeque<QueuedReply>),
+    Viewed(HashSet<String>),
+    Pinned(HashSet<String>),
+    Checklist(HashSet<String>),
+}
+
+impl Edit {
+    fn describe(&self) -> &'static str {
+        match self {
+            Edit::Replies(_) => "the queued reply change",
+            Edit::Viewed(_) => "marking the file viewed",
+            Edit::Pinned(_) => "the pin",
+            Edit::Checklist(_) => "the checklist tick",
+        }
+    }
+}
+
+/// Why a write to GitHub no longer applies because something changed there.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Conflict {
+    Deleted,
+    Locked,
+    Closed,
+}
+
+impl Conflict {
+    /// Recognises a conflict from a failed `gh` call's error output. Not
+    /// found reads as `Deleted`, which callers confirm by refetching, as
+    /// GitHub also answers 404 for missing access or a wrong id.
+    fn detect(err: &anyhow::Error) -> Option<Self> {
+        let message = format!("{err:#}");
+        if message.contains("HTTP 404") || message.contains("Could not resolve to") {
+            return Some(Conflict::Deleted);
+        }
+        if !message.contains("HTTP 422") && !message.contains("HTTP 403") {
+            return None;
+        }
+        if message.contains("locked") {
+            Some(Conflict::Locked)
+        } else if message.contains("closed") || message.contains("merged") {
+            Some(Conflict::Closed)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GhCli {
+    /// The repository's host, so API calls reach GitHub Enterprise too.
+    /// `$GH_HOST` wins when it's set.
+    host: Option<String>,
+    settings: GhSettings,
+    /// Answers calls in place of `gh` in tests.
+    #[cfg(test)]
+    mock: Option<std::sync::Arc<headless::MockGh>>,
+}
+
+/// What the user set with `gh config set`, followed so `gh cr` behaves like
+/// the rest of gh whether it runs as an extension or on its own.
+#[derive(Clone, Default)]
+struct GhSettings {
+    pager: Option<String>,
+    /// `prompt disabled`: never stop to ask questions, as in scripts.
+    prompts_disabled: bool,
+}
+
+impl GhCli {
+    fn new() -> Self {
+        Self {
+            host: None,
+            settings: GhSettings::default(),
+            #[cfg(test)]
+            mock: None,
+        }
+    }
+
+    /// Reads gh's settings for the host. Unset or unreadable ones keep
+    /// their defaults.
+    async fn load_settings(&mut self) {
+        let pager = self.setting("pager").await;
+        let prompt = self.setting("prompt").await;
+        self.settings = GhSettings {
+            pager,
+            prompts_disabled: prompt.as_deref() == Some("disabled"),
+        };
+    }
+
+    async fn setting(&self, key: &str) -> Option<String> {
+        let mut args = vec!["config", "get", key];
+        if let Some(host) = &self.host {
+            args.extend(["--host", host]);
+        }
+        match self.run(args).await {
+            Ok(value) => Some(value.trim().to_string()).filter(|value| !value.is_empty()),
+            Err(err) => {
+                log::debug!("failed to read gh's {key} setting: {err}");
+                None
+            }
+        }
+    }
+
+    /// A `gh` process for the repository's host.
+    fn command(&self) -> TokioCommand {
+        let mut command = TokioCommand::new("gh");
+        if let Some(host) = &self.host
+            && env::var_os("GH_HOST").is_none()
+        {
+            command.env("GH_HOST", host);
+        }
+        command
+    }
+
+    async fn current_repo(&self) -> Result<Repo> {
+        let output = self
+            .run(["repo", "view", "--json", "name,owner,url"])
+            .await
+            .context("gh repo view failed")?;
+        let repo: RepoResponse =
+            serde_json::from_str(&output).context("failed to parse repo info")?;
+        Ok(Repo {
+            owner: repo.owner.login,
+            name: repo.name,
+            url: repo.url,
+        })
+    }
+
+    async fn current_pr_number(&self) -> Result<u64> {
+        let output = self
+            .run(["pr", "view", "--json", "number"])
+            .await
+            .context("gh pr view failed")?;
+        let pr: PrResponse = serde_json::from_str(&output).context("failed to parse PR info")?;
+        Ok(pr.number)
+    }
+
+    /// The newest PR whose head is `branch`, open or not, so it works from
+    /// a detached HEAD or a CI checkout.
+    async fn pr_for_branch(&self, branch: &str) -> Result<u64> {
+        let output = self
+            .run([
+                "pr", "list", "--head", branch, "--state", "all", "--limit", "1", "--json",
+                "number",
+            ])
+            .await
+            .context("gh pr list failed")?;
+        let prs: Vec<PrResponse> =
+            serde_json::from_str(&output).context("failed to parse PR list")?;
+        prs.first()
+            .map(|pr| pr.number)
+            .ok_or_else(|| anyhow!("no pull request has {branch} as its head"))
+    }
+
+    async fn fetch_threads(&self, repo: &Repo, pr_number: u64) -> Result<Vec<Thread>> {
+        let query = r#"query($owner: String!, $name: String!, $number: Int!) {
+            repository(owner: $owner, name: $name) {
+                pullRequest(number: $number) {
+                    reviewThreads(first: 100) {
+                        nodes {
+                            id
+                            isResolved
+                            isOutdated
+                            path
+                            comments(first: 100) {
+                                nodes {
+                            id
+                            databaseId
+                            line
+                            originalLine
+                            body
+                            diffHunk
+                            createdAt
+                            url
+                            isMinimized
+                            minimizedReason
+                            commit {
+                                oid
+                            }
+                                    author {
+                                        login
+                                    }
+                                }
+                            }
                         }
                     }
                 }
-                render_block(buf, &body_lines)?;
-                writeln!(buf)?;
             }
+        }"#;
+        let args = vec![
+            "api".to_string(),
+            "graphql".to_string(),
+            "-f".to_string(),
+            format!("query={}", query),
+            "-F".to_string(),
+            format!("owner={}", repo.owner),
+            "-F".to_string(),
+            format!("name={}", repo.name),
+            "-F".to_string(),
+            format!("number={}", pr_number),
+        ];
+        let output = self
+            .run(args)
+            .await
+            .context("failed to query review threads")?;
+        let response: ThreadsResponse =
+            serde_json::from_str(&output).context("failed to parse thread response")?;
+        let raw_threads = response
+            .data
+            .repository
+            .ok_or_else(|| anyhow!("repository missing from response"))?
+            .pull_request
+            .ok_or_else(|| anyhow!("pull request missing from response"))?
+            .review_threads
+            .nodes;
+        let mut threads: Vec<Thread> = raw_threads
+            .into_iter()
+            .map(Thread::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        threads.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+        Ok(threads)
+    }
+
+    /// The PR's combined diff, or with `patch` its commits in mbox form.
+    /// Starts `gh pr diff` and returns its output as a stream of lines, so
+    /// callers can act on the first files before the rest has arrived.
+    /// Only saving and applying the patch read it; the viewer's hunks come
+    /// from the review threads, not from this diff.
+    fn pr_diff(&self, pr_number: u64, patch: bool) -> Result<DiffStream> {
+        let mut args = vec!["pr".to_string(), "diff".to_string(), pr_number.to_string()];
+        if patch {
+            args.push("--patch".to_string());
         }
-        if !self.queued_replies.is_empty() {
-            writeln!(
-                buf,
-                "{}",
-                format!(
-                    "{} replies queued – press p to publish",
-                    self.queued_replies.len()
-                )
-                .with(Color::DarkMagenta)
-            )?;
+        log::debug!("running gh {:?}", args);
+        let call = perf::GhCall::start();
+        let mut child = self
+            .command()
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to spawn gh")?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("gh pr diff has no stdout"))?;
+        Ok(DiffStream {
+            child,
+            reader: BufReader::new(stdout),
+            args,
+            started: Instant::now(),
+            _call: call,
+        })
+    }
+
+    async fn checkout(&self, pr_number: u64, detach: bool) -> Result<()> {
+        let mut args = vec![
+            "pr".to_string(),
+            "checkout".to_string(),
+            pr_number.to_string(),
+        ];
+        if detach {
+            args.push("--detach".to_string());
         }
-        let highlight_first = |word: &str| -> String {
-            let mut chars = word.chars();
-            let Some(first) = chars.next() else {
-                return String::new();
-            };
-            let rest: String = chars.collect();
-            let lead = format!("{}", first.to_string().with(Color::White));
-            if rest.is_empty() {
-                lead
-            } else {
-                format!("{}{}", lead, rest.with(Color::DarkGrey))
-            }
-        };
-        let highlight_with_key = |word: &str, key: char| -> String {
-            let mut rendered = String::new();
-            let mut used = false;
-            for ch in word.chars() {
-                if !used && ch.eq_ignore_ascii_case(&key) {
-                    rendered.push_str(&format!("{}", ch.to_string().with(Color::White)));
-                    used = true;
-                } else {
-                    rendered.push_str(&format!("{}", ch.to_string().with(Color::DarkGrey)));
-                }
+        self.run(args).await.context("gh pr checkout failed")?;
+        Ok(())
+    }
+
+    async fn open_pr(&self, pr_number: u64) -> Result<()> {
+        self.run([
+            "pr".to_string(),
+            "view".to_string(),
+            pr_number.to_string(),
+            "--web".to_string(),
+        ])
+        .await?;
+        Ok(())
+    }
+
+    async fn list_prs(&self) -> Result<Vec<PrSummary>> {
+        self.search_prs("").await
+    }
+
+    /// Open PRs matching GitHub search syntax, such as
+    /// `author:octocat review:required`.
+    async fn search_prs(&self, query: &str) -> Result<Vec<PrSummary>> {
+        let mut args = vec![
+            "pr",
+            "list",
+            "--limit",
+            "100",
+            "--json",
+            "number,title,author,headRefName,baseRefName,isDraft,additions,deletions,changedFiles",
+        ];
+        if !query.is_empty() {
+            args.extend(["--search", query]);
+        }
+        let output = self.run(args).await.context("gh pr list failed")?;
+        serde_json::from_str(&output).context("failed to parse PR list")
+    }
+
+    /// Your open PRs and the open PRs requesting your review, across every
+    /// repository on the host.
+    async fn dashboard(&self) -> Result<Dashboard> {
+        let query = r#"query {
+            authored: search(query: "is:open is:pr author:@me archived:false", type: ISSUE, first: 50) {
+                nodes { ...row }
             }
-            if used {
-                rendered
-            } else {
-                highlight_first(word)
+            requested: search(query: "is:open is:pr review-requested:@me archived:false", type: ISSUE, first: 50) {
+                nodes { ...row }
             }
-        };
-        writeln!(
-            buf,
-            "{}",
-            format!(
-                "{}  {}  {}  {}  {}  {}",
-                "←/→ thread".with(Color::DarkGrey),
-                highlight_first("diff"),
-                highlight_first("reply"),
-                highlight_first("publish"),
-                highlight_with_key(self.view.skip_action_label(), 's'),
-                highlight_first("quit"),
-            )
-        )?;
-        if let Some(message) = &self.status_line {
-            writeln!(buf, "{}", message.as_str().with(Color::DarkGrey))?;
         }
-        Ok(())
+        fragment row on PullRequest {
+            number
+            title
+            url
+            isDraft
+            repository { nameWithOwner }
+            author { login }
+            reviewDecision
+            mergeable
+            reviewThreads(first: 100) { nodes { isResolved } }
+            commits(last: 1) { nodes { commit { statusCheckRollup { state } } } }
+        }"#;
+        let jq = r#"def row: {
+                number,
+                title,
+                url,
+                draft: .isDraft,
+                repo: .repository.nameWithOwner,
+                author: (.author.login // "ghost"),
+                checks: (.commits.nodes[0].commit.statusCheckRollup.state // ""),
+                decision: (.reviewDecision // ""),
+                mergeable,
+                unresolved: ([.reviewThreads.nodes[] | select(.isResolved | not)] | length)
+            };
+            {
+                authored: [.data.authored.nodes[] | row],
+                requested: [.data.requested.nodes[] | row]
+            }"#;
+        let output = self
+            .run([
+                "api".to_string(),
+                "graphql".to_string(),
+                "-f".to_string(),
+                format!("query={query}"),
+                "--jq".to_string(),
+                jq.to_string(),
+            ])
+            .await
+            .context("failed to load your pull requests")?;
+        serde_json::from_str(&output).context("failed to parse your pull requests")
     }
 
-    fn dump_once(&self) -> Result<()> {
-        print!("{}", self.render_view());
+    /// Unread and read notifications about PRs you review, authored, commented
+    /// on or were mentioned in, newest first.
+    async fn notifications(&self) -> Result<Vec<Notification>> {
+        let jq = r#"[.[]
+            | select(.subject.type == "PullRequest")
+            | select(.reason | IN("review_requested", "mention", "team_mention", "comment", "author"))
+            | {
+                id,
+                reason,
+                unread,
+                updated_at,
+                title: .subject.title,
+                repo: .repository.full_name,
+                repo_url: .repository.html_url,
+                number: (.subject.url | split("/") | last | tonumber),
+                comment_url: (.subject.latest_comment_url // "")
+            }]"#;
+        let output = self
+            .run(["api", "notifications?all=true&per_page=50", "--jq", jq])
+            .await
+            .context("failed to list notifications")?;
+        serde_json::from_str(&output).context("failed to parse notifications")
+    }
+
+    /// Marks a notification thread read, or done, which also removes it
+    /// from the inbox on GitHub.
+    async fn mark_notification(&self, id: &str, done: bool) -> Result<()> {
+        let endpoint = format!("notifications/threads/{id}");
+        let method = if done { "DELETE" } else { "PATCH" };
+        self.run(["api", "--method", method, endpoint.as_str()])
+            .await
+            .context("failed to update the notification")?;
         Ok(())
     }
 
-    fn next_thread(&mut self) {
-        let len = self.current_threads().len();
-        if len == 0 {
-            return;
+    async fn fetch_checks(&self, pr_number: u64) -> Result<Vec<Check>> {
+        let (_, checks) = self.fetch_status(pr_number).await?;
+        Ok(checks)
+    }
+
+    /// The PR's head commit and the checks reported against it.
+    async fn fetch_status(&self, pr_number: u64) -> Result<(Option<String>, Vec<Check>)> {
+        let output = self
+            .run([
+                "pr".to_string(),
+                "view".to_string(),
+                pr_number.to_string(),
+                "--json".to_string(),
+                "headRefOid,statusCheckRollup".to_string(),
+            ])
+            .await
+            .context("gh pr view failed")?;
+        let rollup: CheckRollupResponse =
+            serde_json::from_str(&output).context("failed to parse check status")?;
+        let checks = rollup
+            .status_check_rollup
+            .into_iter()
+            .map(Check::from)
+            .collect();
+        Ok((rollup.head_ref_oid, checks))
+    }
+
+    /// The most recent force-push in the PR's timeline.
+    async fn last_force_push(&self, repo: &Repo, pr_number: u64) -> Result<Option<ForcePush>> {
+        let query = r#"query($owner: String!, $name: String!, $number: Int!) {
+            repository(owner: $owner, name: $name) {
+                pullRequest(number: $number) {
+                    baseRefName
+                    timelineItems(last: 1, itemTypes: [HEAD_REF_FORCE_PUSHED_EVENT]) {
+                        nodes {
+                            ... on HeadRefForcePushedEvent {
+                                createdAt
+                                actor {
+                                    login
+                                }
+                                beforeCommit {
+                                    oid
+                                }
+                                afterCommit {
+                                    oid
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }"#;
+        let args = vec![
+            "api".to_string(),
+            "graphql".to_string(),
+            "-f".to_string(),
+            format!("query={}", query),
+            "-F".to_string(),
+            format!("owner={}", repo.owner),
+            "-F".to_string(),
+            format!("name={}", repo.name),
+            "-F".to_string(),
+            format!("number={}", pr_number),
+        ];
+        let output = self
+            .run(args)
+            .await
+            .context("failed to query force-pushes")?;
+        let response: serde_json::Value =
+            serde_json::from_str(&output).context("failed to parse timeline response")?;
+        let nodes = response
+            .pointer("/data/repository/pullRequest/timelineItems/nodes")
+            .cloned()
+            .ok_or_else(|| anyhow!("pull request timeline missing from response"))?;
+        let events: Vec<RawForcePush> =
+            serde_json::from_value(nodes).context("failed to parse force-push")?;
+        let base = response
+            .pointer("/data/repository/pullRequest/baseRefName")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| anyhow!("base branch missing from response"))?
+            .to_string();
+        let Some(event) = events.into_iter().next() else {
+            return Ok(None);
+        };
+        let (Some(before), Some(after)) = (event.before_commit, event.after_commit) else {
+            return Ok(None);
+        };
+        Ok(Some(ForcePush {
+            base,
+            before: before.oid,
+            after: after.oid,
+            actor: event.actor.map(|actor| actor.login),
+            at: parse_timestamp(&event.created_at)?,
+        }))
+    }
+
+    /// Contents of the CODEOWNERS file on the PR's base branch, from the
+    /// first place GitHub looks that has one.
+    async fn code_owners(&self, repo: &Repo, pr_number: u64) -> Result<Option<String>> {
+        let base = self
+            .run([
+                "pr".to_string(),
+                "view".to_string(),
+                pr_number.to_string(),
+                "--json".to_string(),
+                "baseRefName".to_string(),
+                "--jq".to_string(),
+                ".baseRefName".to_string(),
+            ])
+            .await
+            .context("failed to look up the base branch")?;
+        for location in codeowners::LOCATIONS {
+            if let Some(contents) = self.file_at(repo, location, base.trim()).await? {
+                return Ok(Some(contents));
+            }
         }
-        let index = self.current_index_mut();
-        *index = (*index + 1) % len;
-        self.reset_scroll();
+        Ok(None)
     }
 
-    fn prev_thread(&mut self) {
-        let len = self.current_threads().len();
-        if len == 0 {
-            return;
-        }
-        let index = self.current_index_mut();
-        if *index == 0 {
-            *index = len - 1;
-        } else {
-            *index -= 1;
+    /// A file's contents at `rev`, or `None` if it doesn't exist there.
+    async fn file_at(&self, repo: &Repo, path: &str, rev: &str) -> Result<Option<String>> {
+        let endpoint = format!(
+            "repos/{}/{}/contents/{path}?ref={rev}",
+            repo.owner, repo.name
+        );
+        match self
+            .run([
+                "api",
+                "-H",
+                "Accept: application/vnd.github.raw+json",
+                endpoint.as_str(),
+            ])
+            .await
+        {
+            Ok(contents) => Ok(Some(contents)),
+            Err(err) if err.to_string().contains("HTTP 404") => Ok(None),
+            Err(err) => Err(err),
         }
-        self.reset_scroll();
     }
 
-    fn scroll_up(&mut self, amount: usize) {
-        if amount == 0 {
-            return;
-        }
-        self.scroll_offset = self.scroll_offset.saturating_sub(amount);
+    /// The commits between two revisions of another repository, such as a
+    /// submodule's.
+    async fn compare(
+        &self,
+        owner: &str,
+        name: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<CommitRange> {
+        let endpoint = format!("repos/{owner}/{name}/compare/{base}...{head}");
+        let output = self
+            .run([
+                "api",
+                endpoint.as_str(),
+                "--jq",
+                "{commits: .ahead_by, behind: .behind_by, url: .html_url, \
+                 first: (.commits | first | .commit.message), \
+                 last: (.commits | last | .commit.message)}",
+            ])
+            .await
+            .context("failed to compare commits")?;
+        let mut range: CommitRange =
+            serde_json::from_str(&output).context("failed to parse the comparison")?;
+        range.first = range
+            .first
+            .as_deref()
+            .map(|message| first_line(message).to_string());
+        range.last = range
+            .last
+            .as_deref()
+            .map(|message| first_line(message).to_string());
+        Ok(range)
     }
 
-    fn scroll_down(&mut self, amount: usize) {
-        if amount == 0 {
-            return;
-        }
-        self.scroll_offset = self.scroll_offset.saturating_add(amount);
+    /// The PR's title and description.
+    async fn pr_description(&self, pr_number: u64) -> Result<(String, String)> {
+        let output = self
+            .run([
+                "pr".to_string(),
+                "view".to_string(),
+                pr_number.to_string(),
+                "--json".to_string(),
+                "title,body".to_string(),
+            ])
+            .await
+            .context("failed to fetch the PR description")?;
+        let description: PrDescription =
+            serde_json::from_str(&output).context("failed to parse the PR description")?;
+        Ok((description.title, description.body))
     }
 
-    fn scroll_page_down(&mut self) {
-        let step = page_step();
-        if step == 0 {
-            return;
-        }
-        self.scroll_down(step);
+    /// The PR's title, body and head branch.
+    async fn pr_text(&self, pr_number: u64) -> Result<PrDescription> {
+        let output = self
+            .run([
+                "pr".to_string(),
+                "view".to_string(),
+                pr_number.to_string(),
+                "--json".to_string(),
+                "title,body,headRefName".to_string(),
+            ])
+            .await
+            .context("failed to fetch the PR description")?;
+        serde_json::from_str(&output).context("failed to parse the PR description")
     }
 
-    fn scroll_page_up(&mut self) {
-        let step = page_step();
-        if step == 0 {
-            return;
-        }
-        self.scroll_up(step);
+    async fn update_description(
+        &self,
+        repo: &Repo,
+        pr_number: u64,
+        title: &str,
+        body: &str,
+    ) -> Result<()> {
+        let endpoint = format!("repos/{}/{}/pulls/{}", repo.owner, repo.name, pr_number);
+        self.run([
+            "api".to_string(),
+            endpoint,
+            "-X".to_string(),
+            "PATCH".to_string(),
+            "-f".to_string(),
+            format!("title={title}"),
+            "-f".to_string(),
+            format!("body={body}"),
+        ])
+        .await?;
+        Ok(())
     }
 
-    fn scroll_to_top(&mut self) {
-        self.scroll_offset = 0;
+    async fn pr_author(&self, pr_number: u64) -> Result<String> {
+        let output = self
+            .run([
+                "pr".to_string(),
+                "view".to_string(),
+                pr_number.to_string(),
+                "--json".to_string(),
+                "author".to_string(),
+                "--jq".to_string(),
+                ".author.login".to_string(),
+            ])
+            .await
+            .context("failed to look up the PR author")?;
+        Ok(output.trim().to_string())
     }
 
-    fn scroll_to_bottom(&mut self) {
-        // Clamp happens during render based on actual viewport size.
-        self.scroll_offset = usize::MAX;
+    /// Users and teams whose review is requested and still outstanding, as
+    /// `login` or `org/team-slug`.
+    async fn requested_reviewers(&self, repo: &Repo, pr_number: u64) -> Result<Vec<String>> {
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{pr_number}/requested_reviewers",
+            repo.owner, repo.name
+        );
+        let jq = format!(
+            r#"(.users[] | .login), (.teams[] | "{}/" + .slug)"#,
+            repo.owner
+        );
+        let output = self
+            .run(["api", endpoint.as_str(), "--jq", jq.as_str()])
+            .await
+            .context("failed to list requested reviewers")?;
+        Ok(output.lines().map(str::to_string).collect())
     }
 
-    fn reset_scroll(&mut self) {
-        self.scroll_offset = 0;
+    /// Requests reviews from users and `org/team` teams.
+    async fn request_reviewers(&self, pr_number: u64, reviewers: &[&str]) -> Result<()> {
+        self.run([
+            "pr".to_string(),
+            "edit".to_string(),
+            pr_number.to_string(),
+            "--add-reviewer".to_string(),
+            reviewers.join(","),
+        ])
+        .await
+        .context("gh pr edit failed")?;
+        Ok(())
     }
 
-    fn clear_status(&mut self) {
-        self.status_line = None;
+    async fn viewer_login(&self) -> Result<String> {
+        let output = self
+            .run(["api", "user", "--jq", ".login"])
+            .await
+            .context("failed to look up your GitHub login")?;
+        Ok(output.trim().to_string())
     }
 
-    fn skip_current(&mut self) -> Result<()> {
-        match self.view {
-            ThreadView::Unresolved | ThreadView::Active => self.skip_selected_thread(),
-            ThreadView::Skipped => self.unskip_selected_thread(),
-        }
+    /// Your permission on `repo`, such as `ADMIN`, `WRITE` or `READ`.
+    async fn viewer_permission(&self, repo: &Repo) -> Result<String> {
+        let output = self
+            .run([
+                "repo".to_string(),
+                "view".to_string(),
+                format!("{}/{}", repo.owner, repo.name),
+                "--json".to_string(),
+                "viewerPermission".to_string(),
+                "--jq".to_string(),
+                ".viewerPermission".to_string(),
+            ])
+            .await
+            .context("failed to look up your permission on the repository")?;
+        Ok(output.trim().to_string())
     }
 
-    fn skip_selected_thread(&mut self) -> Result<()> {
-        let thread = match self.view {
-            ThreadView::Active => {
-                self.clamp_index_for_view(ThreadView::Active);
-                if self.active_threads.is_empty() {
-                    self.status_line = Some("No thread to skip.".into());
-                    return Ok(());
-                }
-                self.active_threads.remove(self.current_active)
-            }
-            ThreadView::Unresolved => {
-                self.clamp_index_for_view(ThreadView::Unresolved);
-                if self.unresolved_threads.is_empty() {
-                    self.status_line = Some("No unresolved thread to skip.".into());
-                    return Ok(());
-                }
-                let thread = self.unresolved_threads.remove(self.current_unresolved);
-                if let Some(pos) = self
-                    .active_threads
-                    .iter()
-                    .position(|candidate| candidate.id == thread.id)
-                {
-                    self.active_threads.remove(pos);
-                    self.clamp_index_for_view(ThreadView::Active);
-                }
-                thread
-            }
-            ThreadView::Skipped => {
-                self.status_line = Some("Cannot skip thread in skipped view.".into());
-                return Ok(());
-            }
-        };
-        self.skip_store
-            .add(thread.id.clone())
-            .context("failed to persist skip state")?;
-        self.skipped_threads.push(thread);
-        Self::sort_threads(&mut self.skipped_threads);
-        self.rebuild_unresolved(None);
-        self.clamp_index_for_view(self.view);
-        self.reset_scroll();
-        self.clear_status();
-        Ok(())
+    /// Every account gh is logged in to on `host`, and whether it's the
+    /// active one.
+    async fn auth_accounts(&self, host: &str) -> Result<Vec<(String, bool)>> {
+        let output = self
+            .run([
+                "auth",
+                "status",
+                "--hostname",
+                host,
+                "--json",
+                "hosts",
+                "--jq",
+                r#".hosts[][] | [.login, (.active | tostring)] | @tsv"#,
+            ])
+            .await
+            .context("failed to list your gh accounts")?;
+        Ok(output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(login, active)| (login.to_string(), active == "true"))
+            .collect())
     }
 
-    fn unskip_selected_thread(&mut self) -> Result<()> {
-        self.clamp_index_for_view(ThreadView::Skipped);
-        if self.skipped_threads.is_empty() {
-            self.status_line = Some("No skipped thread to unskip.".into());
-            return Ok(());
-        }
-        let thread = self.skipped_threads.remove(self.current_skipped);
-        self.skip_store
-            .remove(&thread.id)
-            .context("failed to persist skip state")?;
-        self.active_threads.push(thread.clone());
-        Self::sort_threads(&mut self.active_threads);
-        let preferred = if thread.is_resolved {
-            None
-        } else {
-            Some(thread.id.clone())
-        };
-        self.rebuild_unresolved(preferred);
-        self.clamp_index_for_view(ThreadView::Skipped);
-        self.reset_scroll();
-        self.clear_status();
+    async fn switch_account(&self, host: &str, login: &str) -> Result<()> {
+        self.run(["auth", "switch", "--hostname", host, "--user", login])
+            .await
+            .with_context(|| format!("failed to switch to {login}"))?;
         Ok(())
     }
 
-    async fn reply_to_current(&mut self, terminal: &mut TerminalSession) -> Result<()> {
-        let Some(thread) = self.current_thread() else {
-            self.status_line = Some("No thread selected.".into());
-            return Ok(());
-        };
-        let Some(target_comment) = thread.comments.last() else {
-            self.status_line = Some("Thread has no comments.".into());
-            return Ok(());
-        };
+    async fn minimize_comment(&self, comment_id: &str, classifier: &str) -> Result<()> {
+        let query = r#"mutation($id: ID!, $classifier: ReportedContentClassifiers!) {
+            minimizeComment(input: {subjectId: $id, classifier: $classifier}) {
+                minimizedComment { isMinimized }
+            }
+        }"#;
+        self.run([
+            "api".to_string(),
+            "graphql".to_string(),
+            "-f".to_string(),
+            format!("query={query}"),
+            "-F".to_string(),
+            format!("id={comment_id}"),
+            "-F".to_string(),
+            format!("classifier={classifier}"),
+        ])
+        .await?;
+        Ok(())
+    }
 
-        let editor_template = build_reply_editor_template(thread);
-        let reply_body = match terminal.suspend_for_editor(&editor_template)? {
-            Some(body) => body,
-            None => {
-                self.status_line = Some("Reply cancelled.".into());
-                return Ok(());
+    async fn resolve_thread(&self, thread_id: &str) -> Result<()> {
+        let query = r#"mutation($id: ID!) {
+            resolveReviewThread(input: {threadId: $id}) {
+                thread { isResolved }
             }
-        };
+        }"#;
+        self.run([
+            "api".to_string(),
+            "graphql".to_string(),
+            "-f".to_string(),
+            format!("query={query}"),
+            "-F".to_string(),
+            format!("id={thread_id}"),
+        ])
+        .await?;
+        Ok(())
+    }
 
-        self.queued_replies.push_back(QueuedReply {
-            comment_database_id: target_comment.database_id,
-            body: reply_body,
-        });
-        self.clear_status();
+    async fn unminimize_comment(&self, comment_id: &str) -> Result<()> {
+        let query = r#"mutation($id: ID!) {
+            unminimizeComment(input: {subjectId: $id}) {
+                unminimizedComment { isMinimized }
+            }
+        }"#;
+        self.run([
+            "api".to_string(),
+            "graphql".to_string(),
+            "-f".to_string(),
+            format!("query={query}"),
+            "-F".to_string(),
+            format!("id={comment_id}"),
+        ])
+        .await?;
         Ok(())
     }
 
-    async fn publish_queue(&mut self) -> Result<()> {
-        if self.queued_replies.is_empty() {
-            self.status_line = None;
-            return Ok(());
+    /// Top-level comments and submitted reviews, oldest first. Reviews
+    /// that only left inline comments are skipped; their threads show them.
+    async fn conversation(&self, repo: &Repo, pr_number: u64) -> Result<Vec<ConversationEntry>> {
+        let endpoint = format!(
+            "repos/{}/{}/issues/{}/comments?per_page=100",
+            repo.owner, repo.name, pr_number
+        );
+        let output = self
+            .run(["api", "--paginate", endpoint.as_str(), "--jq", ".[]"])
+            .await
+            .context("failed to fetch PR comments")?;
+        let mut entries = Vec::new();
+        for line in output.lines().filter(|line| !line.trim().is_empty()) {
+            let comment: IssueComment =
+                serde_json::from_str(line).context("failed to parse PR comment")?;
+            entries.push(ConversationEntry {
+                author: comment
+                    .user
+                    .map_or_else(|| "ghost".into(), |user| user.login),
+                created_at: parse_timestamp(&comment.created_at)?,
+                body: comment.body,
+                review_state: None,
+            });
         }
-        let total = self.queued_replies.len();
-        let mut index = 0;
-        while let Some(reply) = self.queued_replies.front().cloned() {
-            self.status_line = Some(format!("Publishing reply {}/{}", index + 1, total,));
-
-            self.render()?;
-
-            self.gh
-                .post_reply(
-                    &self.repo,
-                    self.pr_number,
-                    reply.comment_database_id,
-                    &reply.body,
-                )
-                .await?;
-            self.queued_replies.pop_front();
-            index += 1;
+        for review in self.reviews(repo, pr_number).await? {
+            let Some(submitted_at) = &review.submitted_at else {
+                continue;
+            };
+            if review.state == "PENDING" || (review.state == "COMMENTED" && review.body.is_empty())
+            {
+                continue;
+            }
+            entries.push(ConversationEntry {
+                author: review.login().to_string(),
+                created_at: parse_timestamp(submitted_at)?,
+                review_state: Some(review.state.to_lowercase().replace('_', " ")),
+                body: review.body,
+            });
         }
-        self.clear_status();
-        self.refresh_threads().await?;
-        Ok(())
+        entries.sort_by_key(|entry| entry.created_at);
+        Ok(entries)
     }
 
-    fn toggle_diff(&mut self) {
-        self.show_diff = !self.show_diff;
-        self.reset_scroll();
-        self.clear_status();
+    async fn post_comment(&self, repo: &Repo, pr_number: u64, body: &str) -> Result<()> {
+        let endpoint = format!(
+            "repos/{}/{}/issues/{}/comments",
+            repo.owner, repo.name, pr_number
+        );
+        self.run([
+            "api".to_string(),
+            endpoint,
+            "-X".to_string(),
+            "POST".to_string(),
+            "-f".to_string(),
+            format!("body={body}"),
+        ])
+        .await?;
+        Ok(())
     }
 
-    async fn refresh_threads(&mut self) -> Result<()> {
-        let current_unresolved_id = self
-            .unresolved_threads
-            .get(self.current_unresolved)
-            .map(|t| t.id.clone());
-        let current_active_id = self
-            .active_threads
-            .get(self.current_active)
-            .map(|t| t.id.clone());
-        let current_skipped_id = self
-            .skipped_threads
-            .get(self.current_skipped)
-            .map(|t| t.id.clone());
-        let updated = self
-            .gh
-            .fetch_threads(&self.repo, self.pr_number)
+    /// Every inline review comment on the PR, oldest first.
+    async fn review_comments(&self, repo: &Repo, pr_number: u64) -> Result<Vec<ReviewComment>> {
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/comments?per_page=100",
+            repo.owner, repo.name, pr_number
+        );
+        let output = self
+            .run(["api", "--paginate", endpoint.as_str(), "--jq", ".[]"])
             .await
-            .context("failed to refresh threads")?;
-        let (active, skipped) = Self::partition_threads(&self.skip_store, updated);
-        self.active_threads = active;
-        self.skipped_threads = skipped;
-        self.restore_selection(ThreadView::Active, current_active_id);
-        self.restore_selection(ThreadView::Skipped, current_skipped_id);
-        self.rebuild_unresolved(current_unresolved_id);
-        self.clamp_current_index();
-        self.reset_scroll();
+            .context("failed to fetch review comments")?;
+        output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("failed to parse review comment"))
+            .collect()
+    }
+
+    /// Every review on the PR, oldest first.
+    async fn reviews(&self, repo: &Repo, pr_number: u64) -> Result<Vec<SubmittedReview>> {
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/reviews?per_page=100",
+            repo.owner, repo.name, pr_number
+        );
+        let output = self
+            .run(["api", "--paginate", endpoint.as_str(), "--jq", ".[]"])
+            .await?;
+        output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("failed to parse review"))
+            .collect()
+    }
+
+    /// Dismisses a submitted approval or change request. Needs write access
+    /// unless the review is the viewer's own.
+    async fn dismiss_review(
+        &self,
+        repo: &Repo,
+        pr_number: u64,
+        review_id: u64,
+        message: &str,
+    ) -> Result<()> {
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/reviews/{}/dismissals",
+            repo.owner, repo.name, pr_number, review_id
+        );
+        self.run([
+            "api".to_string(),
+            endpoint,
+            "-X".to_string(),
+            "PUT".to_string(),
+            "-f".to_string(),
+            format!("message={message}"),
+            "-f".to_string(),
+            "event=DISMISS".to_string(),
+        ])
+        .await?;
         Ok(())
     }
 
-    fn restore_selection(&mut self, view: ThreadView, target: Option<String>) {
-        let target_pos =
-            target.and_then(|id| self.threads_for_view(view).iter().position(|t| t.id == id));
-        let len = self.threads_for_view(view).len();
-        let index = self.index_for_view_mut(view);
-        if let Some(found) = target_pos {
-            *index = found;
-        } else if len == 0 {
-            *index = 0;
-        } else if *index >= len {
-            *index = len - 1;
-        }
+    /// The commit `login` last submitted a review against, if any.
+    async fn last_review_commit(
+        &self,
+        repo: &Repo,
+        pr_number: u64,
+        login: &str,
+    ) -> Result<Option<String>> {
+        let reviews = self.reviews(repo, pr_number).await?;
+        Ok(reviews
+            .into_iter()
+            .filter(|review| review.state != "PENDING")
+            .filter(|review| review.user.as_ref().is_some_and(|user| user.login == login))
+            .filter_map(|review| review.commit_id)
+            .next_back())
     }
-}
 
-fn build_reply_editor_template(thread: &Thread) -> String {
-    let mut buf = String::from("\n\n");
-    let now = Utc::now();
-    let status = if thread.is_resolved {
-        "resolved"
-    } else {
-        "open"
-    };
-    let _ = writeln!(
-        buf,
-        "# Reply to the thread on {} ({} status).",
-        thread.display_path(),
-        status
-    );
-    let _ = writeln!(
-        buf,
-        "# Lines starting with '# ' are ignored when submitting the reply."
-    );
-    let _ = writeln!(buf, "#");
-    let _ = writeln!(buf, "# --- Thread comments ---");
-    let context_width = COMMENT_WRAP.saturating_sub(2).max(10);
-    let context_wrap = WrapOptions::new(context_width).break_words(false);
-    for comment in &thread.comments {
-        let _ = writeln!(
-            buf,
-            "# {} ({})",
-            comment.author,
-            humanize_relative(now, comment.created_at)
-        );
-        if comment.body.trim().is_empty() {
-            let _ = writeln!(buf, "#");
-            continue;
-        }
-        for line in comment.body.lines() {
-            if line.trim().is_empty() {
-                let _ = writeln!(buf, "#");
-            } else {
-                for chunk in wrap(line, context_wrap.clone()) {
-                    let _ = writeln!(buf, "# {}", chunk);
+    /// Annotations the check runs on the PR's latest commit left on its
+    /// files, such as linter warnings and compiler errors.
+    async fn annotations(&self, repo: &Repo, pr_number: u64) -> Result<Vec<Annotation>> {
+        let query = r#"query($owner: String!, $name: String!, $number: Int!) {
+            repository(owner: $owner, name: $name) {
+                pullRequest(number: $number) {
+                    commits(last: 1) {
+                        nodes {
+                            commit {
+                                checkSuites(first: 50) {
+                                    nodes {
+                                        checkRuns(first: 50) {
+                                            nodes {
+                                                name
+                                                annotations(first: 100) {
+                                                    nodes {
+                                                        path
+                                                        annotationLevel
+                                                        title
+                                                        message
+                                                        location {
+                                                            start { line }
+                                                            end { line }
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
             }
-        }
-        let _ = writeln!(buf, "#");
+        }"#;
+        let jq = r#"[.data.repository.pullRequest.commits.nodes[].commit.checkSuites.nodes[]
+            | .checkRuns.nodes[]
+            | .name as $check
+            | .annotations.nodes[]
+            | {
+                check: $check,
+                path,
+                level: .annotationLevel,
+                title,
+                message,
+                start: .location.start.line,
+                end: .location.end.line
+            }]"#;
+        let output = self
+            .run([
+                "api".to_string(),
+                "graphql".to_string(),
+                "-f".to_string(),
+                format!("query={query}"),
+                "-F".to_string(),
+                format!("owner={}", repo.owner),
+                "-F".to_string(),
+                format!("name={}", repo.name),
+                "-F".to_string(),
+                format!("number={pr_number}"),
+                "--jq".to_string(),
+                jq.to_string(),
+            ])
+            .await
+            .context("failed to query check annotations")?;
+        serde_json::from_str(&output).context("failed to parse check annotations")
     }
-    buf
-}
-
-#[derive(Clone)]
-struct Thread {
-    id: String,
-    path: String,
-    line: Option<i64>,
-    diff_hunk: Option<String>,
-    is_resolved: bool,
-    created_at: DateTime<Utc>,
-    comments: Vec<Comment>,
-}
 
-impl Thread {
-    fn sort_key(&self) -> (&bool, &DateTime<Utc>) {
-        (&self.is_resolved, &self.created_at)
+    /// Dependencies added and removed between two commits, from the
+    /// dependency graph, with the advisories affecting each version.
+    async fn dependency_changes(
+        &self,
+        repo: &Repo,
+        base: &str,
+        head: &str,
+    ) -> Result<Vec<advisory::Change>> {
+        let endpoint = format!(
+            "repos/{}/{}/dependency-graph/compare/{base}...{head}",
+            repo.owner, repo.name
+        );
+        let output = self
+            .run(["api", endpoint.as_str()])
+            .await
+            .context("failed to compare dependencies")?;
+        serde_json::from_str(&output).context("failed to parse dependency changes")
     }
 
-    fn display_path(&self) -> String {
-        match self.line {
-            Some(line) => format!("{}:{}", self.path, line),
-            None => self.path.clone(),
-        }
+    /// The CVE an advisory was assigned, if any.
+    async fn advisory_cve(&self, ghsa_id: &str) -> Result<Option<String>> {
+        let endpoint = format!("advisories/{ghsa_id}");
+        let output = self
+            .run(["api", endpoint.as_str(), "--jq", ".cve_id // empty"])
+            .await
+            .context("failed to look up the advisory")?;
+        Ok(Some(output.trim().to_string()).filter(|cve| !cve.is_empty()))
     }
-}
 
-#[derive(Clone)]
-struct Comment {
-    id: String,
-    database_id: u64,
-    author: String,
-    body: String,
-    line: Option<i64>,
-    original_line: Option<i64>,
-    diff_hunk: Option<String>,
-    created_at: DateTime<Utc>,
-}
+    /// The GraphQL quota left, which nearly every request here draws on.
+    async fn rate_limit(&self) -> Result<RateLimit> {
+        let output = self
+            .run(["api", "rate_limit", "--jq", ".resources.graphql"])
+            .await
+            .context("failed to look up the rate limit")?;
+        serde_json::from_str(&output).context("failed to parse the rate limit")
+    }
 
-#[derive(Clone)]
-struct QueuedReply {
-    comment_database_id: u64,
-    body: String,
-}
+    /// Whether the PR merges cleanly, and the branch it merges into.
+    async fn merge_state(&self, pr_number: u64) -> Result<MergeState> {
+        let output = self
+            .run([
+                "pr",
+                "view",
+                &pr_number.to_string(),
+                "--json",
+                "mergeable,baseRefName",
+            ])
+            .await
+            .context("gh pr view failed")?;
+        serde_json::from_str(&output).context("failed to parse merge state")
+    }
 
-struct GhCli;
+    /// The PR's base and head commits.
+    async fn pr_refs(&self, pr_number: u64) -> Result<(String, String)> {
+        let output = self
+            .run([
+                "pr",
+                "view",
+                &pr_number.to_string(),
+                "--json",
+                "baseRefOid,headRefOid",
+            ])
+            .await
+            .context("gh pr view failed")?;
+        let refs: PrRefs = serde_json::from_str(&output).context("failed to parse PR commits")?;
+        Ok((refs.base, refs.head))
+    }
 
-impl GhCli {
-    fn new() -> Self {
-        Self
+    /// The diff of GitHub's test merge of the PR into its base against the
+    /// base, or `None` while GitHub has no merge commit, as when the PR
+    /// conflicts.
+    async fn merge_commit_diff(&self, repo: &Repo, pr_number: u64) -> Result<Option<String>> {
+        let endpoint = format!("repos/{}/{}/pulls/{}", repo.owner, repo.name, pr_number);
+        let sha = self
+            .run([
+                "api",
+                endpoint.as_str(),
+                "--jq",
+                "if .mergeable == false then empty else .merge_commit_sha // empty end",
+            ])
+            .await
+            .context("failed to look up the merge commit")?;
+        let sha = sha.trim();
+        if sha.is_empty() {
+            return Ok(None);
+        }
+        let endpoint = format!("repos/{}/{}/commits/{sha}", repo.owner, repo.name);
+        let diff = self
+            .run([
+                "api",
+                "-H",
+                "Accept: application/vnd.github.diff",
+                endpoint.as_str(),
+            ])
+            .await
+            .context("failed to fetch the merge commit's diff")?;
+        Ok(Some(diff))
     }
 
-    async fn current_repo(&self) -> Result<Repo> {
+    /// Each file in the PR with its change type, size and any old name.
+    async fn pr_files(&self, repo: &Repo, pr_number: u64) -> Result<HashMap<String, PrFile>> {
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/files?per_page=100",
+            repo.owner, repo.name, pr_number
+        );
         let output = self
-            .run(["repo", "view", "--json", "name,owner"])
+            .run([
+                "api",
+                "--paginate",
+                endpoint.as_str(),
+                "--jq",
+                ".[] | [.filename, .status, .changes, .additions, .deletions, .previous_filename // \"\"] | @tsv",
+            ])
             .await
-            .context("gh repo view failed")?;
-        let repo: RepoResponse =
-            serde_json::from_str(&output).context("failed to parse repo info")?;
-        Ok(Repo {
-            owner: repo.owner.login,
-            name: repo.name,
-        })
+            .context("failed to list PR files")?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let path = fields.next()?.to_string();
+                let status = fields.next()?.to_string();
+                let changes = fields.next()?.parse().ok()?;
+                let additions = fields.next()?.parse().ok()?;
+                let deletions = fields.next()?.parse().ok()?;
+                let previous = fields
+                    .next()
+                    .filter(|previous| !previous.is_empty())
+                    .map(str::to_string);
+                Some((
+                    path,
+                    PrFile {
+                        status,
+                        changes,
+                        additions,
+                        deletions,
+                        previous,
+                    },
+                ))
+            })
+            .collect())
     }
 
-    async fn current_pr_number(&self) -> Result<u64> {
+    /// The PR's commits, oldest first, with their signature verification.
+    async fn pr_commits(&self, repo: &Repo, pr_number: u64) -> Result<Vec<PrCommit>> {
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/commits?per_page=100",
+            repo.owner, repo.name, pr_number
+        );
         let output = self
-            .run(["pr", "view", "--json", "number"])
+            .run([
+                "api",
+                "--paginate",
+                endpoint.as_str(),
+                "--jq",
+                ".[] | [.sha, (.commit.message | split(\"\\n\") | first), \
+                 (.author.login // .commit.author.name), .commit.verification.verified, \
+                 .commit.verification.reason, (.commit.verification.signature // \"\" \
+                 | if contains(\"SSH SIGNATURE\") then \"SSH\" elif . != \"\" then \"GPG\" \
+                 else \"\" end)] | @tsv",
+            ])
             .await
-            .context("gh pr view failed")?;
-        let pr: PrResponse = serde_json::from_str(&output).context("failed to parse PR info")?;
-        Ok(pr.number)
+            .context("failed to list PR commits")?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let sha = fields.next()?.to_string();
+                let subject = fields.next()?.to_string();
+                let author = fields.next()?.to_string();
+                let verified = fields.next()? == "true";
+                let reason = fields.next()?.to_string();
+                let signature = fields
+                    .next()
+                    .filter(|kind| !kind.is_empty())
+                    .map(str::to_string);
+                Some(PrCommit {
+                    sha,
+                    subject,
+                    author,
+                    verified,
+                    reason,
+                    signature,
+                    checks: None,
+                })
+            })
+            .collect())
     }
 
-    async fn fetch_threads(&self, repo: &Repo, pr_number: u64) -> Result<Vec<Thread>> {
+    /// The combined check state of each of the PR's commits, by SHA, for
+    /// commits that have any checks or statuses.
+    async fn commit_checks(
+        &self,
+        repo: &Repo,
+        pr_number: u64,
+    ) -> Result<HashMap<String, CheckState>> {
         let query = r#"query($owner: String!, $name: String!, $number: Int!) {
             repository(owner: $owner, name: $name) {
                 pullRequest(number: $number) {
-                    reviewThreads(first: 100) {
+                    commits(last: 100) {
                         nodes {
-                            id
-                            isResolved
-                            path
-                            comments(first: 100) {
-                                nodes {
-                            id
-                            databaseId
-                            line
-                            originalLine
-                            body
-                            diffHunk
-                            createdAt
-                                    author {
-                                        login
-                                    }
+                            commit {
+                                oid
+                                statusCheckRollup {
+                                    state
                                 }
                             }
                         }
@@ -1036,38 +9693,175 @@ impl GhCli {
                 }
             }
         }"#;
-        let args = vec![
+        let output = self
+            .run([
+                "api".to_string(),
+                "graphql".to_string(),
+                "-f".to_string(),
+                format!("query={query}"),
+                "-F".to_string(),
+                format!("owner={}", repo.owner),
+                "-F".to_string(),
+                format!("name={}", repo.name),
+                "-F".to_string(),
+                format!("number={pr_number}"),
+                "--jq".to_string(),
+                ".data.repository.pullRequest.commits.nodes[].commit \
+                 | [.oid, (.statusCheckRollup.state // \"\")] | @tsv"
+                    .to_string(),
+            ])
+            .await
+            .context("failed to query commit checks")?;
+        Ok(output
+            .lines()
+            .filter_map(|line| {
+                let (sha, state) = line.split_once('\t')?;
+                let state = match state {
+                    "SUCCESS" => CheckState::Pass,
+                    "FAILURE" | "ERROR" => CheckState::Fail,
+                    "PENDING" | "EXPECTED" => CheckState::Pending,
+                    _ => return None,
+                };
+                Some((sha.to_string(), state))
+            })
+            .collect())
+    }
+
+    /// Paths changed between two commits.
+    async fn changed_files(&self, repo: &Repo, base: &str, head: &str) -> Result<HashSet<String>> {
+        let endpoint = format!("repos/{}/{}/compare/{base}...{head}", repo.owner, repo.name);
+        let output = self
+            .run(["api", endpoint.as_str(), "--jq", ".files[].filename"])
+            .await
+            .context("failed to compare commits")?;
+        Ok(output.lines().map(str::to_string).collect())
+    }
+
+    /// The authenticated user's unsubmitted review, which the reviews
+    /// endpoint only ever returns to its author.
+    async fn fetch_pending_review(
+        &self,
+        repo: &Repo,
+        pr_number: u64,
+    ) -> Result<Option<PendingReview>> {
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/reviews?per_page=100",
+            repo.owner, repo.name, pr_number
+        );
+        // Every page, or a busy PR hides the pending review past the first.
+        let output = self
+            .run([
+                "api",
+                "--paginate",
+                endpoint.as_str(),
+                "--jq",
+                r#".[] | select(.state == "PENDING")"#,
+            ])
+            .await?;
+        output
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("failed to parse the pending review"))
+            .transpose()
+    }
+
+    /// Your pending review, or a new one if you have none.
+    async fn start_pending_review(&self, repo: &Repo, pr_number: u64) -> Result<PendingReview> {
+        if let Some(pending) = self.fetch_pending_review(repo, pr_number).await? {
+            return Ok(pending);
+        }
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/reviews",
+            repo.owner, repo.name, pr_number
+        );
+        // Without an event the review stays pending.
+        let output = self
+            .run(["api", endpoint.as_str(), "-X", "POST"])
+            .await
+            .context("failed to start a review")?;
+        serde_json::from_str(&output).context("failed to parse the new review")
+    }
+
+    /// How many draft comments your pending review holds, or 0 without one.
+    async fn pending_draft_count(&self, repo: &Repo, pr_number: u64) -> Result<usize> {
+        let Some(pending) = self.fetch_pending_review(repo, pr_number).await? else {
+            return Ok(0);
+        };
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/reviews/{}/comments?per_page=100",
+            repo.owner, repo.name, pr_number, pending.id
+        );
+        let output = self
+            .run(["api", "--paginate", endpoint.as_str(), "--jq", ".[].id"])
+            .await
+            .context("failed to list pending review comments")?;
+        Ok(output.lines().count())
+    }
+
+    /// Adds a reply to a thread as a draft in `review`, unpublished until
+    /// the review is submitted.
+    async fn add_draft_reply(
+        &self,
+        review: &PendingReview,
+        thread_id: &str,
+        body: &str,
+    ) -> Result<()> {
+        let query = r#"mutation($review: ID!, $thread: ID!, $body: String!) {
+            addPullRequestReviewThreadReply(input: {
+                pullRequestReviewId: $review,
+                pullRequestReviewThreadId: $thread,
+                body: $body
+            }) {
+                comment {
+                    id
+                }
+            }
+        }"#;
+        self.run([
             "api".to_string(),
             "graphql".to_string(),
             "-f".to_string(),
-            format!("query={}", query),
-            "-F".to_string(),
-            format!("owner={}", repo.owner),
-            "-F".to_string(),
-            format!("name={}", repo.name),
-            "-F".to_string(),
-            format!("number={}", pr_number),
-        ];
-        let output = self
-            .run(args)
-            .await
-            .context("failed to query review threads")?;
-        let response: ThreadsResponse =
-            serde_json::from_str(&output).context("failed to parse thread response")?;
-        let raw_threads = response
-            .data
-            .repository
-            .ok_or_else(|| anyhow!("repository missing from response"))?
-            .pull_request
-            .ok_or_else(|| anyhow!("pull request missing from response"))?
-            .review_threads
-            .nodes;
-        let mut threads: Vec<Thread> = raw_threads
-            .into_iter()
-            .map(Thread::try_from)
-            .collect::<Result<Vec<_>>>()?;
-        threads.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
-        Ok(threads)
+            format!("query={query}"),
+            "-f".to_string(),
+            format!("review={}", review.node_id),
+            "-f".to_string(),
+            format!("thread={thread_id}"),
+            "-f".to_string(),
+            format!("body={body}"),
+        ])
+        .await
+        .context("failed to add the reply to your pending review")?;
+        Ok(())
+    }
+
+    /// Submits a review, or the viewer's pending review when there is one;
+    /// GitHub refuses to start a second review alongside it.
+    async fn submit_review(
+        &self,
+        repo: &Repo,
+        pr_number: u64,
+        event: ReviewEvent,
+        body: &str,
+    ) -> Result<()> {
+        let mut endpoint = format!(
+            "repos/{}/{}/pulls/{}/reviews",
+            repo.owner, repo.name, pr_number
+        );
+        if let Some(pending) = self.fetch_pending_review(repo, pr_number).await? {
+            endpoint = format!("{endpoint}/{}/events", pending.id);
+        }
+        let args = vec![
+            "api".to_string(),
+            endpoint,
+            "-X".to_string(),
+            "POST".to_string(),
+            "-f".to_string(),
+            format!("event={}", event.api_name()),
+            "-f".to_string(),
+            format!("body={}", body),
+        ];
+        self.run(args).await?;
+        Ok(())
     }
 
     async fn post_reply(
@@ -1093,6 +9887,49 @@ impl GhCli {
         Ok(())
     }
 
+    /// Starts a review thread on `lines` of `path` as of `commit`, on the
+    /// new side of the diff.
+    async fn post_review_comment(
+        &self,
+        repo: &Repo,
+        pr_number: u64,
+        commit: &str,
+        path: &str,
+        lines: RangeInclusive<usize>,
+        body: &str,
+    ) -> Result<()> {
+        let endpoint = format!(
+            "repos/{}/{}/pulls/{}/comments",
+            repo.owner, repo.name, pr_number
+        );
+        let mut args = vec![
+            "api".to_string(),
+            endpoint,
+            "-X".to_string(),
+            "POST".to_string(),
+            "-f".to_string(),
+            format!("body={body}"),
+            "-f".to_string(),
+            format!("commit_id={commit}"),
+            "-f".to_string(),
+            format!("path={path}"),
+            "-F".to_string(),
+            format!("line={}", lines.end()),
+            "-f".to_string(),
+            "side=RIGHT".to_string(),
+        ];
+        if lines.start() < lines.end() {
+            args.extend([
+                "-F".to_string(),
+                format!("start_line={}", lines.start()),
+                "-f".to_string(),
+                "start_side=RIGHT".to_string(),
+            ]);
+        }
+        self.run(args).await?;
+        Ok(())
+    }
+
     async fn run<I, S>(&self, args: I) -> Result<String>
     where
         I: IntoIterator<Item = S>,
@@ -1102,16 +9939,35 @@ impl GhCli {
             .into_iter()
             .map(|arg| arg.as_ref().to_os_string())
             .collect();
-        let output = TokioCommand::new("gh")
+        let rendered: Vec<String> = args_vec
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        log::debug!("running gh {:?}", rendered);
+        #[cfg(test)]
+        if let Some(mock) = &self.mock {
+            return mock.respond(&rendered);
+        }
+        let _call = perf::GhCall::start();
+        let started = Instant::now();
+        let output = self
+            .command()
             .args(&args_vec)
             .output()
             .await
             .context("failed to spawn gh")?;
+        log::info!(
+            "gh {} exited with {} in {:?}",
+            rendered.first().map_or("", String::as_str),
+            output.status,
+            started.elapsed()
+        );
         if !output.status.success() {
-            let rendered: Vec<String> = args_vec
-                .iter()
-                .map(|arg| arg.to_string_lossy().into_owned())
-                .collect();
+            log::warn!(
+                "gh {:?} stderr: {}",
+                rendered,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
             return Err(anyhow!(
                 "gh {:?} failed: {}",
                 rendered,
@@ -1123,15 +9979,77 @@ impl GhCli {
     }
 }
 
+/// A running `gh pr diff`, read a line at a time.
+struct DiffStream {
+    child: Child,
+    reader: BufReader<ChildStdout>,
+    args: Vec<String>,
+    started: Instant,
+    _call: perf::GhCall,
+}
+
+impl DiffStream {
+    /// The next line with its line ending, or `None` at the end.
+    async fn next_line(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut line = Vec::new();
+        let read = self
+            .reader
+            .read_until(b'\n', &mut line)
+            .await
+            .context("failed to read gh pr diff output")?;
+        Ok((read > 0).then_some(line))
+    }
+
+    /// Waits for `gh` to exit once its output has been read, turning a
+    /// failure exit into an error.
+    async fn finish(self) -> Result<()> {
+        let output = self
+            .child
+            .wait_with_output()
+            .await
+            .context("gh pr diff failed")?;
+        log::info!(
+            "gh pr diff exited with {} in {:?}",
+            output.status,
+            self.started.elapsed()
+        );
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            log::warn!("gh {:?} stderr: {}", self.args, stderr.trim());
+            return Err(anyhow!("gh {:?} failed: {}", self.args, stderr));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
 struct Repo {
     owner: String,
     name: String,
+    url: String,
+}
+
+impl Repo {
+    /// Scheme and host the repository is served from, e.g. for user pages.
+    fn web_base(&self) -> &str {
+        self.url
+            .strip_suffix(&format!("/{}/{}", self.owner, self.name))
+            .unwrap_or("https://github.com")
+    }
+
+    /// The host alone, such as `github.com` or a GitHub Enterprise server.
+    fn host(&self) -> Option<String> {
+        let base = self.web_base();
+        let host = base.split_once("://").map_or(base, |(_, host)| host);
+        Some(host.to_string()).filter(|host| !host.is_empty())
+    }
 }
 
 #[derive(Deserialize)]
 struct RepoResponse {
     name: String,
     owner: RepoOwner,
+    url: String,
 }
 
 #[derive(Deserialize)]
@@ -1144,6 +10062,459 @@ struct PrResponse {
     number: u64,
 }
 
+/// A submodule pointer change and, when its repository could be reached,
+/// the commits it brings in.
+struct SubmoduleBump {
+    old: String,
+    new: String,
+    compare_url: Option<String>,
+    range: Option<CommitRange>,
+}
+
+#[derive(Deserialize)]
+struct CommitRange {
+    commits: u64,
+    behind: u64,
+    url: String,
+    first: Option<String>,
+    last: Option<String>,
+}
+
+/// `@a, @b and @c`.
+fn mention_list(logins: &[String]) -> String {
+    let mentions: Vec<String> = logins.iter().map(|login| format!("@{login}")).collect();
+    match mentions.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {last}", rest.join(", ")),
+        _ => mentions.concat(),
+    }
+}
+
+/// Fills in a `copy.reference` template. Without a line, `{line}` is
+/// dropped along with a `:` or `#L` just before it.
+fn format_reference(
+    template: &str,
+    path: &str,
+    line: Option<i64>,
+    sha: &str,
+    permalink: &str,
+    pr_number: u64,
+) -> String {
+    let template = match line {
+        Some(_) => template.to_string(),
+        None => template.replace(":{line}", "").replace("#L{line}", ""),
+    };
+    template
+        .replace("{path}", path)
+        .replace(
+            "{line}",
+            &line.map(|line| line.to_string()).unwrap_or_default(),
+        )
+        .replace("{sha}", sha)
+        .replace("{short_sha}", &sha[..sha.len().min(7)])
+        .replace("{permalink}", permalink)
+        .replace("{pr}", &pr_number.to_string())
+}
+
+/// One of GitHub's API quotas, from `gh api rate_limit`, which doesn't
+/// count against it.
+#[derive(Deserialize)]
+struct RateLimit {
+    limit: u64,
+    remaining: u64,
+    /// Unix time the quota refills.
+    reset: i64,
+}
+
+impl RateLimit {
+    /// A tenth or less of the quota left.
+    fn is_low(&self) -> bool {
+        self.remaining * 10 <= self.limit
+    }
+
+    fn resets_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.reset, 0).unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize)]
+struct MergeState {
+    /// `MERGEABLE`, `CONFLICTING` or `UNKNOWN`.
+    mergeable: String,
+    #[serde(rename = "baseRefName")]
+    base: String,
+}
+
+#[derive(Deserialize)]
+struct PrRefs {
+    #[serde(rename = "baseRefOid")]
+    base: String,
+    #[serde(rename = "headRefOid")]
+    head: String,
+}
+
+#[derive(Deserialize)]
+struct PrSummary {
+    number: u64,
+    title: String,
+    author: Option<RawAuthor>,
+    #[serde(rename = "headRefName")]
+    head_ref_name: String,
+    #[serde(rename = "baseRefName", default)]
+    base_ref_name: String,
+    #[serde(rename = "isDraft")]
+    is_draft: bool,
+    #[serde(default)]
+    additions: usize,
+    #[serde(default)]
+    deletions: usize,
+    #[serde(rename = "changedFiles", default)]
+    changed_files: usize,
+}
+
+impl PrSummary {
+    fn author_login(&self) -> &str {
+        self.author.as_ref().map_or("unknown", |a| a.login.as_str())
+    }
+
+    /// Sized from GitHub's totals, which count generated files too.
+    fn size(&self) -> size::Estimate {
+        size::estimate(self.additions + self.deletions, self.changed_files)
+    }
+}
+
+/// The PRs on the dashboard, each group in GitHub's search order.
+#[derive(Deserialize)]
+struct Dashboard {
+    authored: Vec<DashboardPr>,
+    requested: Vec<DashboardPr>,
+}
+
+#[derive(Clone, Deserialize)]
+struct DashboardPr {
+    number: u64,
+    title: String,
+    url: String,
+    draft: bool,
+    /// `owner/name`.
+    repo: String,
+    author: String,
+    /// The head commit's check rollup, such as `SUCCESS` or `FAILURE`, or
+    /// empty without checks.
+    checks: String,
+    /// `APPROVED`, `CHANGES_REQUESTED`, `REVIEW_REQUIRED`, or empty when
+    /// the repository doesn't require reviews.
+    decision: String,
+    /// `MERGEABLE`, `CONFLICTING` or `UNKNOWN`.
+    mergeable: String,
+    unresolved: usize,
+}
+
+#[derive(Clone, Deserialize)]
+struct Notification {
+    id: String,
+    /// Why it was sent, such as `mention` or `review_requested`.
+    reason: String,
+    unread: bool,
+    updated_at: DateTime<Utc>,
+    title: String,
+    /// `owner/name`.
+    repo: String,
+    repo_url: String,
+    number: u64,
+    /// The API URL of the latest comment, or empty.
+    comment_url: String,
+}
+
+impl Notification {
+    /// The review comment the notification is about, if it is one.
+    fn review_comment(&self) -> Option<u64> {
+        let (_, id) = self.comment_url.rsplit_once("/pulls/comments/")?;
+        id.parse().ok()
+    }
+
+    /// The PR on GitHub, at the comment the notification is about.
+    fn html_url(&self) -> String {
+        let pr = format!("{}/pull/{}", self.repo_url, self.number);
+        if let Some(id) = self.review_comment() {
+            return format!("{pr}#discussion_r{id}");
+        }
+        match self.comment_url.rsplit_once("/issues/comments/") {
+            Some((_, id)) => format!("{pr}#issuecomment-{id}"),
+            None => pr,
+        }
+    }
+
+    fn reason_label(&self) -> &str {
+        match self.reason.as_str() {
+            "review_requested" => "review requested",
+            "mention" => "mentioned",
+            "team_mention" => "team mentioned",
+            "comment" => "commented",
+            "author" => "your PR",
+            reason => reason,
+        }
+    }
+}
+
+/// A finding a check run reported against lines of a file.
+#[derive(Deserialize)]
+struct Annotation {
+    check: String,
+    path: String,
+    /// `NOTICE`, `WARNING` or `FAILURE`.
+    level: String,
+    title: Option<String>,
+    message: String,
+    start: usize,
+    end: usize,
+}
+
+impl Annotation {
+    fn severity(&self) -> u8 {
+        match self.level.as_str() {
+            "FAILURE" => 2,
+            "WARNING" => 1,
+            _ => 0,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self.severity() {
+            2 => "✗",
+            1 => "⚠",
+            _ => "ℹ",
+        }
+    }
+
+    fn covers(&self, line: usize) -> bool {
+        (self.start..=self.end.max(self.start)).contains(&line)
+    }
+
+    /// The title, or the message's first line when there isn't one.
+    fn summary(&self) -> &str {
+        match self.title.as_deref() {
+            Some(title) if !title.trim().is_empty() => title,
+            _ => self.message.lines().next().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CheckRollupResponse {
+    #[serde(rename = "headRefOid")]
+    head_ref_oid: Option<String>,
+    #[serde(rename = "statusCheckRollup", default)]
+    status_check_rollup: Vec<RawCheck>,
+}
+
+/// A check run or a commit status context, which report state differently.
+#[derive(Deserialize)]
+struct RawCheck {
+    name: Option<String>,
+    context: Option<String>,
+    status: Option<String>,
+    conclusion: Option<String>,
+    state: Option<String>,
+    #[serde(rename = "detailsUrl")]
+    details_url: Option<String>,
+    #[serde(rename = "targetUrl")]
+    target_url: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CheckState {
+    Pass,
+    Fail,
+    Pending,
+    Skipped,
+}
+
+impl CheckState {
+    fn as_str(self) -> &'static str {
+        match self {
+            CheckState::Pass => "pass",
+            CheckState::Fail => "fail",
+            CheckState::Pending => "pending",
+            CheckState::Skipped => "skipped",
+        }
+    }
+}
+
+struct Check {
+    name: String,
+    state: CheckState,
+    url: Option<String>,
+}
+
+impl Check {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "state": self.state.as_str(),
+            "url": self.url,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawForcePush {
+    created_at: String,
+    actor: Option<RawAuthor>,
+    before_commit: Option<RawCommit>,
+    after_commit: Option<RawCommit>,
+}
+
+#[derive(Default)]
+struct Outline {
+    /// Index into the outline's rows.
+    cursor: usize,
+    /// Files whose threads are folded away.
+    collapsed: HashSet<String>,
+    /// Threads whose comments are listed.
+    expanded: HashSet<String>,
+}
+
+/// One line of the outline; threads are indices into the current view.
+#[derive(Clone, PartialEq, Eq)]
+enum OutlineRow {
+    File(String),
+    Thread(usize),
+    Comment(usize, usize),
+}
+
+#[derive(Default)]
+struct ReviewMap {
+    /// Index into the map's rows.
+    cursor: usize,
+    /// Directories whose files are listed.
+    expanded: HashSet<String>,
+}
+
+/// One line of the review map.
+#[derive(Clone, PartialEq, Eq)]
+enum MapRow {
+    Directory(String),
+    File(String),
+}
+
+/// The first component of a path, or nothing for files at the top level.
+fn top_directory(path: &str) -> &str {
+    path.split_once('/').map_or("", |(directory, _)| directory)
+}
+
+/// A top-level PR comment, or a review with its summary.
+struct ConversationEntry {
+    author: String,
+    created_at: DateTime<Utc>,
+    body: String,
+    /// `approved`, `changes requested` and so on for reviews.
+    review_state: Option<String>,
+}
+
+/// An inline comment as listed by the pulls API.
+#[derive(Deserialize)]
+struct ReviewComment {
+    id: u64,
+    pull_request_review_id: Option<u64>,
+    path: String,
+    /// `None` once the line is outdated; `original_line` still has it.
+    line: Option<i64>,
+    original_line: Option<i64>,
+    #[serde(default)]
+    body: String,
+}
+
+impl ReviewComment {
+    fn location(&self) -> String {
+        match self.line.or(self.original_line) {
+            Some(line) => format!("{}:{line}", self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// A review state as GitHub names it, in words.
+fn review_state_label(state: &str) -> &'static str {
+    match state {
+        "APPROVED" => "approved",
+        "CHANGES_REQUESTED" => "changes requested",
+        "DISMISSED" => "dismissed",
+        _ => "commented",
+    }
+}
+
+/// A PR comment as listed by the issues API.
+#[derive(Deserialize)]
+struct IssueComment {
+    user: Option<RawAuthor>,
+    #[serde(default)]
+    body: String,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct PrDescription {
+    title: String,
+    #[serde(default)]
+    body: String,
+    #[serde(rename = "headRefName", default)]
+    head_ref_name: String,
+}
+
+/// A review as listed by the REST API.
+#[derive(Deserialize)]
+struct SubmittedReview {
+    id: u64,
+    user: Option<RawAuthor>,
+    state: String,
+    commit_id: Option<String>,
+    #[serde(default)]
+    body: String,
+    submitted_at: Option<String>,
+}
+
+impl SubmittedReview {
+    fn login(&self) -> &str {
+        self.user
+            .as_ref()
+            .map_or("ghost", |user| user.login.as_str())
+    }
+}
+
+#[derive(Deserialize)]
+struct PendingReview {
+    id: u64,
+    #[serde(default)]
+    node_id: String,
+    state: String,
+    body: Option<String>,
+    html_url: Option<String>,
+}
+
+impl From<RawCheck> for Check {
+    fn from(raw: RawCheck) -> Self {
+        let state = match (
+            raw.status.as_deref(),
+            raw.conclusion.as_deref(),
+            raw.state.as_deref(),
+        ) {
+            (Some("COMPLETED"), Some("SUCCESS" | "NEUTRAL"), _) => CheckState::Pass,
+            (Some("COMPLETED"), Some("SKIPPED"), _) => CheckState::Skipped,
+            (Some("COMPLETED"), _, _) => CheckState::Fail,
+            (Some(_), _, _) => CheckState::Pending,
+            (None, _, Some("SUCCESS")) => CheckState::Pass,
+            (None, _, Some("FAILURE" | "ERROR")) => CheckState::Fail,
+            (None, _, _) => CheckState::Pending,
+        };
+        Self {
+            name: raw.name.or(raw.context).unwrap_or_else(|| "unknown".into()),
+            state,
+            url: raw.details_url.or(raw.target_url),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct ThreadsResponse {
     data: ThreadData,
@@ -1176,6 +10547,8 @@ struct RawThread {
     id: String,
     #[serde(rename = "isResolved")]
     is_resolved: bool,
+    #[serde(rename = "isOutdated", default)]
+    is_outdated: bool,
     path: Option<String>,
     comments: RawCommentConnection,
 }
@@ -1198,9 +10571,20 @@ struct RawComment {
     diff_hunk: Option<String>,
     #[serde(rename = "createdAt")]
     created_at: String,
+    url: Option<String>,
+    #[serde(rename = "isMinimized", default)]
+    is_minimized: bool,
+    #[serde(rename = "minimizedReason")]
+    minimized_reason: Option<String>,
+    commit: Option<RawCommit>,
     author: Option<RawAuthor>,
 }
 
+#[derive(Deserialize)]
+struct RawCommit {
+    oid: String,
+}
+
 #[derive(Deserialize)]
 struct RawAuthor {
     login: String,
@@ -1221,18 +10605,17 @@ impl TryFrom<RawThread> for Thread {
             .collect::<Result<_>>()?;
         let created_at = comments
             .first()
-            .map(|c| c.created_at.clone())
+            .map(|c| c.created_at)
             .ok_or_else(|| anyhow!("thread missing creation time"))?;
         let diff_hunk = comments.iter().find_map(|c| c.diff_hunk.clone());
-        let line = comments
-            .iter()
-            .find_map(|c| c.line.or(c.original_line));
+        let line = comments.iter().find_map(|c| c.line.or(c.original_line));
         Ok(Thread {
             id: raw.id,
             path: raw.path.unwrap_or_else(|| "unknown".into()),
             line,
             diff_hunk,
             is_resolved: raw.is_resolved,
+            is_outdated: raw.is_outdated,
             created_at,
             comments,
         })
@@ -1259,53 +10642,218 @@ impl TryFrom<RawComment> for Comment {
             original_line: raw.original_line,
             diff_hunk: raw.diff_hunk,
             created_at,
+            url: raw.url,
+            commit_oid: raw.commit.map(|commit| commit.oid),
+            minimized: raw.is_minimized.then(|| {
+                raw.minimized_reason
+                    .map(|reason| reason.to_lowercase().replace('_', "-"))
+                    .unwrap_or_else(|| "hidden".into())
+            }),
+        })
+    }
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    let dt = DateTime::parse_from_rfc3339(value)
+        .with_context(|| format!("invalid timestamp: {value}"))?;
+    Ok(dt.with_timezone(&Utc))
+}
+
+fn humanize_relative(now: DateTime<Utc>, then: DateTime<Utc>) -> String {
+    let delta = now.signed_duration_since(then);
+    let text = HumanTime::from(delta).to_string();
+    if let Some(rest) = text.strip_prefix("in ") {
+        format!("{} ago", rest.trim())
+    } else {
+        text
+    }
+}
+
+/// Characters of a blame annotation given to the author's name.
+const BLAME_AUTHOR_WIDTH: usize = 12;
+
+/// One `author  age │ ` annotation per line of `hunk`, from blaming the
+/// new-file lines it covers under `root`. `commit` is used when it exists
+/// locally; otherwise the working tree is blamed. `None` when the hunk has
+/// no new-file lines.
+fn blame_annotations(
+    root: Option<PathBuf>,
+    path: &str,
+    commit: Option<String>,
+    hunk: &str,
+) -> Result<Option<Vec<String>>> {
+    let numbers = diff::new_line_numbers(hunk);
+    let (Some(first), Some(last)) = (
+        numbers.iter().flatten().min().copied(),
+        numbers.iter().flatten().max().copied(),
+    ) else {
+        return Ok(None);
+    };
+    let rev = commit.filter(|oid| git::has_object(oid));
+    let root = root.ok_or_else(|| anyhow!("not inside a git checkout"))?;
+    let blame = git::blame(&root, path, rev.as_deref(), first, last - first + 1)?;
+    let now = Utc::now();
+    let annotations = numbers
+        .iter()
+        .map(|number| {
+            let line = number.and_then(|number| blame.get(&number));
+            let (author, age) = match line {
+                Some(line) => (
+                    line.author.as_str(),
+                    DateTime::from_timestamp(line.time, 0)
+                        .map(|then| humanize_relative(now, then))
+                        .unwrap_or_default(),
+                ),
+                None => ("", String::new()),
+            };
+            format!("{author:<BLAME_AUTHOR_WIDTH$.BLAME_AUTHOR_WIDTH$} {age:>14} │ ")
         })
+        .collect();
+    Ok(Some(annotations))
+}
+
+fn render_block(buf: &mut String, lines: &[impl AsRef<str>], border: Color) -> std::fmt::Result {
+    render_rows(buf, lines.len(), 0, lines, border)
+}
+
+/// Writes `lines` as rows `first..` of a block `total` rows tall, so a
+/// block can be drawn a window at a time with the right border glyphs.
+fn render_rows(
+    buf: &mut String,
+    total: usize,
+    first: usize,
+    lines: &[impl AsRef<str>],
+    border: Color,
+) -> std::fmt::Result {
+    if total == 0 {
+        writeln!(buf, "{}", "│".with(border))?;
+        return Ok(());
+    }
+    // Style each glyph once; a long hunk would otherwise re-format the
+    // border escape for every line.
+    let [top, middle, bottom] = ["╭", "│", "╰"].map(|glyph| glyph.with(border).to_string());
+    let single = "".with(border).to_string();
+    for (offset, line) in lines.iter().enumerate() {
+        let line = line.as_ref();
+        let i = first + offset;
+        let block = match i {
+            0 if total == 1 => &single,
+            0 => &top,
+            _ if i + 1 == total => &bottom,
+            _ => &middle,
+        };
+        buf.push_str(block);
+        if !line.is_empty() {
+            buf.push(' ');
+            buf.push_str(line);
+        }
+        buf.push('\n');
+    }
+    Ok(())
+}
+
+/// Up to two initials from a login or name, such as `JK` for
+/// `james-kingsley`.
+fn initials(name: &str) -> String {
+    let words = name
+        .split(|c: char| c == '-' || c == '_' || c == '.' || c.is_whitespace())
+        .filter(|word| !word.is_empty());
+    let mut initials: String = words
+        .take(2)
+        .filter_map(|word| word.chars().next())
+        .collect();
+    if initials.chars().count() < 2 {
+        initials = name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .take(2)
+            .collect();
     }
+    initials.to_uppercase()
 }
 
-fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
-    let dt = DateTime::parse_from_rfc3339(value)
-        .with_context(|| format!("invalid timestamp: {value}"))?;
-    Ok(dt.with_timezone(&Utc))
+/// A comment's non-blank lines, trimmed, as plain mode reads them.
+fn plain_body(body: &str) -> impl Iterator<Item = String> + '_ {
+    body.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
 }
 
-fn humanize_relative(now: DateTime<Utc>, then: DateTime<Utc>) -> String {
-    let delta = now.signed_duration_since(then);
-    let text = HumanTime::from(delta).to_string();
-    if let Some(rest) = text.strip_prefix("in ") {
-        format!("{} ago", rest.trim())
-    } else {
-        text
-    }
+fn newest_comment(thread: &Thread) -> Option<DateTime<Utc>> {
+    thread
+        .comments
+        .iter()
+        .map(|comment| comment.created_at)
+        .max()
 }
 
-fn render_block(buf: &mut String, lines: &[String]) -> std::fmt::Result {
-    if lines.is_empty() {
-        writeln!(buf, "{}", "│".with(Color::DarkGrey))?;
-        return Ok(());
+/// The first non-blank line of a comment, for one-line summaries.
+fn first_line(body: &str) -> &str {
+    body.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+}
+
+fn line_count(buf: &str) -> usize {
+    buf.matches('\n').count()
+}
+
+/// Counts the lines of a growing buffer, scanning only what was appended
+/// since the last call so laying out a long view stays linear. Rows left
+/// out of the buffer as gaps are added with `skip`.
+#[derive(Default)]
+struct LineCounter {
+    scanned: usize,
+    lines: usize,
+}
+
+impl LineCounter {
+    fn count(&mut self, buf: &str) -> usize {
+        self.lines += line_count(&buf[self.scanned..]);
+        self.scanned = buf.len();
+        self.lines
     }
-    for (i, line) in lines.iter().enumerate() {
-        let block = match i {
-            0 if lines.len() == 1 => "",
-            0 if lines.len() > 1 => "╭",
-            _ if i + 1 == lines.len() => "╰",
-            _ => "│",
-        };
 
-        if line.is_empty() {
-            writeln!(buf, "{}", block.with(Color::DarkGrey))?;
-        } else {
-            writeln!(buf, "{} {}", block.with(Color::DarkGrey), line)?;
+    fn skip(&mut self, rows: usize) {
+        self.lines += rows;
+    }
+}
+
+/// Writes `pieces` as `line`, recording a hit region for each piece that
+/// carries an action.
+fn write_clickable(
+    buf: &mut String,
+    hits: &mut Vec<Hit>,
+    line: usize,
+    pieces: &[(String, Option<Action>)],
+) -> std::fmt::Result {
+    let mut column = 0;
+    for (text, action) in pieces {
+        let width = display_width(text);
+        if let Some(action) = action {
+            hits.push(Hit {
+                line,
+                columns: column..column + width,
+                action: *action,
+            });
         }
+        column += width;
+        buf.push_str(text);
     }
-    Ok(())
+    writeln!(buf)
 }
 
-fn page_step() -> usize {
-    match size() {
-        Ok((_, height)) => height.saturating_sub(1) as usize,
-        Err(_) => 0,
+/// Rows of a `viewport`-high scrollbar covered by the thumb.
+fn scrollbar_thumb(total: usize, viewport: usize, offset: usize) -> Range<usize> {
+    if total <= viewport || viewport == 0 {
+        return 0..viewport;
     }
+    let size = (viewport * viewport / total).clamp(1, viewport);
+    let max_offset = total - viewport;
+    let start = offset.min(max_offset) * (viewport - size) / max_offset;
+    start..start + size
 }
 
 struct SkipStore {
@@ -1355,10 +10903,10 @@ impl SkipStore {
     }
 
     fn default_path() -> Result<PathBuf> {
-        if let Ok(dir) = env::var("XDG_STATE_HOME") {
-            if !dir.is_empty() {
-                return Ok(Path::new(&dir).join("gh-cr").join("skipped.json"));
-            }
+        if let Ok(dir) = env::var("XDG_STATE_HOME")
+            && !dir.is_empty()
+        {
+            return Ok(Path::new(&dir).join("gh-cr").join("skipped.json"));
         }
         let mut path = dirs_next::home_dir().ok_or_else(|| anyhow!("HOME not set"))?;
         path.push(".local");
@@ -1371,9 +10919,21 @@ impl SkipStore {
 
 struct TerminalSession {
     active: bool,
+    keyboard_enhanced: bool,
+    /// Leaves the real terminal alone, for reviews driven by a script or
+    /// read out in plain mode.
+    detached: bool,
 }
 
 impl TerminalSession {
+    fn detached() -> Self {
+        Self {
+            active: false,
+            keyboard_enhanced: false,
+            detached: true,
+        }
+    }
+
     fn enter() -> Result<Self> {
         let mut out = stdout();
         enable_raw_mode().context("enable raw mode failed")?;
@@ -1385,18 +10945,92 @@ impl TerminalSession {
             EnableMouseCapture
         )
         .context("failed to configure terminal")?;
-        Ok(Self { active: true })
+        let mut session = Self {
+            active: true,
+            keyboard_enhanced: false,
+            detached: false,
+        };
+        session.enhance_keyboard()?;
+        Ok(session)
+    }
+
+    /// Opts into the kitty keyboard protocol where available so chords such as
+    /// ctrl-enter and shift-enter can be told apart from plain enter.
+    fn enhance_keyboard(&mut self) -> Result<()> {
+        if !supports_keyboard_enhancement().unwrap_or(false) {
+            return Ok(());
+        }
+        execute!(
+            stdout(),
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                    | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+            )
+        )?;
+        self.keyboard_enhanced = true;
+        Ok(())
+    }
+
+    fn suspend_for_editor(
+        &mut self,
+        editor: &str,
+        initial_contents: &str,
+    ) -> Result<Option<String>> {
+        self.deactivate()?;
+        let result = launch_editor(editor, initial_contents);
+        self.activate()?;
+        result
+    }
+
+    fn suspend_to_edit(&mut self, editor: &str, text: &str) -> Result<String> {
+        self.deactivate()?;
+        let result = edit_text(editor, text);
+        self.activate()?;
+        result
+    }
+
+    fn suspend_to_page(&mut self, pager: Option<&str>, text: &str) -> Result<()> {
+        self.deactivate()?;
+        let result = page(pager, text);
+        self.activate()?;
+        result
+    }
+
+    /// Replaces the screen with `text`, which may draw images, until a key
+    /// is pressed. Kitty images are deleted afterwards, as clearing the
+    /// screen leaves them.
+    fn show_until_key(&mut self, text: &str, kitty: bool) -> Result<()> {
+        if !self.active {
+            return Ok(());
+        }
+        let mut out = stdout();
+        execute!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+        write!(out, "{text}{}", "Press any key to return.".dark_grey())?;
+        out.flush()?;
+        loop {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                break;
+            }
+        }
+        if kitty {
+            write!(out, "\x1b_Ga=d\x1b\\")?;
+        }
+        execute!(out, Clear(ClearType::All))?;
+        Ok(())
     }
 
-    fn suspend_for_editor(&mut self, initial_contents: &str) -> Result<Option<String>> {
+    fn suspend_to_open(&mut self, editor: &str, path: &Path, line: Option<i64>) -> Result<()> {
         self.deactivate()?;
-        let result = launch_editor(initial_contents);
+        let result = open_in_editor(editor, path, line);
         self.activate()?;
         result
     }
 
     fn activate(&mut self) -> Result<()> {
-        if self.active {
+        if self.active || self.detached {
             return Ok(());
         }
         let mut out = stdout();
@@ -1409,7 +11043,7 @@ impl TerminalSession {
             EnableMouseCapture
         )?;
         self.active = true;
-        Ok(())
+        self.enhance_keyboard()
     }
 
     fn deactivate(&mut self) -> Result<()> {
@@ -1417,6 +11051,10 @@ impl TerminalSession {
             return Ok(());
         }
         let mut out = stdout();
+        if self.keyboard_enhanced {
+            execute!(out, PopKeyboardEnhancementFlags).ok();
+            self.keyboard_enhanced = false;
+        }
         disable_raw_mode().ok();
         execute!(out, DisableMouseCapture, Show, LeaveAlternateScreen)?;
         self.active = false;
@@ -1430,23 +11068,100 @@ impl Drop for TerminalSession {
     }
 }
 
-fn launch_editor(initial_contents: &str) -> Result<Option<String>> {
-    let editor = env::var("EDITOR").unwrap_or_else(|_| "vim".into());
+fn launch_editor(editor: &str, initial_contents: &str) -> Result<Option<String>> {
+    let body = edit_text(editor, initial_contents)?;
+    Ok(sanitize_editor_contents(&body))
+}
+
+/// Lets the user edit `initial_contents` in `editor` and returns the result
+/// as saved, `#` lines included.
+fn edit_text(editor: &str, initial_contents: &str) -> Result<String> {
     println!("Opening editor: {editor}");
     let mut tempfile = NamedTempFile::new().context("unable to create temp file")?;
     tempfile
         .write_all(initial_contents.as_bytes())
         .context("failed to prime editor template")?;
     tempfile.flush()?;
-    let status = StdCommand::new(&editor)
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow!("editor command is empty"))?;
+    let status = StdCommand::new(program)
+        .args(parts)
         .arg(tempfile.path())
         .status()
         .with_context(|| format!("failed to launch editor: {editor}"))?;
     if !status.success() {
         return Err(anyhow!("editor exited with {}", status));
     }
-    let body = fs::read_to_string(tempfile.path()).context("failed to read editor contents")?;
-    Ok(sanitize_editor_contents(&body))
+    fs::read_to_string(tempfile.path()).context("failed to read editor contents")
+}
+
+/// Shows `text` in a pager, chosen the way gh chooses its own: `$GH_PAGER`,
+/// then gh's `pager` setting, then `$PAGER`, and `less -R` so colours come
+/// through.
+fn page(gh_pager: Option<&str>, text: &str) -> Result<()> {
+    let set = |pager: &str| !pager.trim().is_empty();
+    let pager = env::var("GH_PAGER")
+        .ok()
+        .filter(|pager| set(pager))
+        .or_else(|| gh_pager.filter(|pager| set(pager)).map(str::to_string))
+        .or_else(|| env::var("PAGER").ok().filter(|pager| set(pager)))
+        .unwrap_or_else(|| "less -R".into());
+    let mut parts = pager.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow!("pager command is empty"))?;
+    let mut child = StdCommand::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to launch pager: {pager}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager closing early (quitting before the end) is fine.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Runs `editor` on `path`, passing `line` in whichever form the editor
+/// understands: `--goto file:line` for VS Code and its forks, `file:line` for
+/// editors that parse it themselves and `+line file` for everything else.
+fn open_in_editor(editor: &str, path: &Path, line: Option<i64>) -> Result<()> {
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow!("editor command is empty"))?;
+    let name = Path::new(program)
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or(program);
+    let mut command = StdCommand::new(program);
+    command.args(parts);
+    match (line, name) {
+        (Some(line), "code" | "code-insiders" | "codium" | "cursor" | "windsurf") => {
+            command
+                .arg("--goto")
+                .arg(format!("{}:{line}", path.display()));
+        }
+        (Some(line), "subl" | "zed" | "hx" | "helix" | "micro") => {
+            command.arg(format!("{}:{line}", path.display()));
+        }
+        (Some(line), _) => {
+            command.arg(format!("+{line}")).arg(path);
+        }
+        (None, _) => {
+            command.arg(path);
+        }
+    }
+    let status = command
+        .status()
+        .with_context(|| format!("failed to launch editor: {editor}"))?;
+    if !status.success() {
+        return Err(anyhow!("editor exited with {}", status));
+    }
+    Ok(())
 }
 
 fn sanitize_editor_contents(raw: &str) -> Option<String> {
@@ -1462,3 +11177,744 @@ fn sanitize_editor_contents(raw: &str) -> Option<String> {
         Some(trimmed.to_owned())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{io::sink, sync::Arc};
+
+    use super::*;
+    use crate::headless::{Headless, MockGh};
+
+    const HUNK_LINES: usize = 20_000;
+    const WIDTH: u16 = 120;
+    const HEIGHT: u16 = 50;
+
+    fn large_diff_app() -> App {
+        let mut hunk = format!("@@ -1,{HUNK_LINES} +1,{HUNK_LINES} @@");
+        for i in 0..HUNK_LINES {
+            let sign = ['+', '-', ' '][i % 3];
+            hunk.push_str(&format!(
+                "\n{sign}    let value_{i} = compute({i}, \"{i:x}\");"
+            ));
+        }
+        let thread = fixture_thread(
+            "thread",
+            "src/lib.rs",
+            1,
+            &hunk,
+            &[("reviewer", "This function is getting long.")],
+        );
+        fixture_app(GhCli::new(), Config::default(), vec![thread])
+    }
+
+    /// A thread on `line` of `path` whose comments, given as author and
+    /// body, were posted three hours ago.
+    fn fixture_thread(
+        id: &str,
+        path: &str,
+        line: i64,
+        hunk: &str,
+        comments: &[(&str, &str)],
+    ) -> Thread {
+        let posted = Utc::now() - chrono::Duration::hours(3);
+        Thread {
+            id: id.into(),
+            path: path.into(),
+            line: Some(line),
+            diff_hunk: Some(hunk.into()),
+            is_resolved: false,
+            is_outdated: false,
+            created_at: posted,
+            comments: comments
+                .iter()
+                .enumerate()
+                .map(|(index, (author, body))| Comment {
+                    id: format!("{id}-{index}"),
+                    database_id: index as u64 + 1,
+                    author: (*author).into(),
+                    body: (*body).into(),
+                    line: Some(line),
+                    original_line: Some(line),
+                    diff_hunk: None,
+                    created_at: posted,
+                    url: None,
+                    commit_oid: None,
+                    minimized: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn fixture_app(gh: GhCli, config: Config, threads: Vec<Thread>) -> App {
+        let repo = Repo {
+            owner: "owner".into(),
+            name: "repo".into(),
+            url: "https://github.com/owner/repo".into(),
+        };
+        let skip_store = SkipStore {
+            path: PathBuf::new(),
+            skipped: HashSet::new(),
+        };
+        App::new(gh, repo, 1, skip_store, config, threads).expect("config should be valid")
+    }
+
+    /// A timing benchmark, kept out of the default run because wall-clock
+    /// budgets flake on loaded machines; run it with
+    /// `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore = "timing benchmark"]
+    fn scrolling_a_large_diff_stays_fast() {
+        let mut app = large_diff_app();
+        let mut out = sink();
+        app.draw(&mut out, WIDTH, HEIGHT).unwrap();
+        assert!(app.rendered_lines > HUNK_LINES);
+
+        let frames = 200;
+        let step = app.rendered_lines / frames;
+        let started = Instant::now();
+        for frame in 0..frames {
+            app.scroll_offset = frame * step;
+            app.draw(&mut out, WIDTH, HEIGHT).unwrap();
+        }
+        let per_frame = started.elapsed() / frames as u32;
+        // Debug builds are several times slower than release.
+        let budget = if cfg!(debug_assertions) {
+            Duration::from_millis(50)
+        } else {
+            Duration::from_millis(5)
+        };
+        assert!(
+            per_frame < budget,
+            "scrolling took {per_frame:?} per frame, over {budget:?}"
+        );
+    }
+
+    #[test]
+    fn frame_buffers_are_reused_between_draws() {
+        let mut app = large_diff_app();
+        let mut out = sink();
+        app.draw(&mut out, WIDTH, HEIGHT).unwrap();
+        let text = app.frame.text.as_ptr();
+        let lines = app.frame.lines.as_ptr();
+        app.scroll_offset = 1_000;
+        app.draw(&mut out, WIDTH, HEIGHT).unwrap();
+        assert_eq!(app.frame.text.as_ptr(), text);
+        assert_eq!(app.frame.lines.as_ptr(), lines);
+    }
+
+    fn thread_json(comments: &[(u64, &str, &str)]) -> String {
+        let nodes: Vec<serde_json::Value> = comments
+            .iter()
+            .map(|(id, author, body)| {
+                serde_json::json!({
+                    "id": format!("comment-{id}"),
+                    "databaseId": id,
+                    "body": body,
+                    "line": 3,
+                    "originalLine": 3,
+                    "diffHunk": "@@ -1,3 +1,3 @@\n fn main() {\n-    old();\n+    new();",
+                    "createdAt": "2026-01-01T00:00:00Z",
+                    "url": null,
+                    "commit": null,
+                    "author": { "login": author },
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "data": { "repository": { "pullRequest": { "reviewThreads": { "nodes": [{
+                "id": "thread",
+                "isResolved": false,
+                "isOutdated": false,
+                "path": "src/main.rs",
+                "comments": { "nodes": nodes },
+            }]}}}}
+        })
+        .to_string()
+    }
+
+    /// A review of one thread driven by `script`, answering `gh` from `mock`.
+    fn scripted_app(mock: &Arc<MockGh>, script: Headless, config: Config) -> App {
+        let response: ThreadsResponse =
+            serde_json::from_str(&thread_json(&[(1, "octocat", "Please rename this.")])).unwrap();
+        let threads = response
+            .data
+            .repository
+            .and_then(|repo| repo.pull_request)
+            .unwrap()
+            .review_threads
+            .nodes
+            .into_iter()
+            .map(Thread::try_from)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        let mut gh = GhCli::new();
+        gh.mock = Some(Arc::clone(mock));
+        let mut app = fixture_app(gh, config, threads);
+        app.headless = Some(script);
+        app
+    }
+
+    #[test]
+    fn view_bindings_replace_the_global_keys() {
+        let keys = config::KeysConfig {
+            skipped: HashMap::from([("toggle-skip".into(), config::KeyList::One("u".into()))]),
+            ..config::KeysConfig::default()
+        };
+        let keymap = KeyMap::from_config(&keys).unwrap();
+        let key = |chord: &str| KeyChord::parse(chord).unwrap().to_event();
+        assert_eq!(
+            keymap.action_for(ThreadView::Skipped, &key("u")),
+            Some(Action::ToggleSkip)
+        );
+        assert_eq!(keymap.action_for(ThreadView::Skipped, &key("s")), None);
+        assert_eq!(
+            keymap.action_for(ThreadView::Active, &key("s")),
+            Some(Action::ToggleSkip)
+        );
+        assert_eq!(
+            keymap
+                .label(ThreadView::Skipped, Action::ToggleSkip)
+                .as_deref(),
+            Some("u")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn visual_selection_starts_a_multi_line_thread() {
+        let mock = Arc::new(MockGh::default().on("pulls/1/comments -X POST", "{}").on(
+            "api graphql",
+            &thread_json(&[(1, "octocat", "Please rename this.")]),
+        ));
+        let config = Config {
+            editor: Some("sed -i 1s/^/Inline/".into()),
+            ..Config::default()
+        };
+        let mut app = scripted_app(&mock, Headless::new(100, 30), config);
+        app.head_oid = Some("abc123".into());
+        app.draw(&mut sink(), WIDTH, HEIGHT).unwrap();
+        let rows: Vec<usize> = app.frame.new_lines.iter().map(|(row, _)| *row).collect();
+        app.selection = Some(Selection {
+            anchor: rows[0],
+            cursor: rows[rows.len() - 1],
+            visual: true,
+        });
+
+        app.perform(Action::Reply, &mut TerminalSession::detached())
+            .await;
+
+        let calls = mock.calls();
+        assert!(
+            calls.iter().any(|call| call.contains(
+                "pulls/1/comments -X POST -f body=Inline -f commit_id=abc123 -f path=src/main.rs \
+                 -F line=2 -f side=RIGHT -F start_line=1 -f start_side=RIGHT"
+            )),
+            "{calls:#?}"
+        );
+        assert!(app.selection.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn reply_publish_and_approve() {
+        let mock = Arc::new(
+            MockGh::default()
+                .on("comments/1/replies", "{}")
+                .on(
+                    "api graphql",
+                    &thread_json(&[
+                        (1, "octocat", "Please rename this."),
+                        (2, "reviewer", "Fixed"),
+                    ]),
+                )
+                .on("api user", "reviewer\n")
+                .on("--json author", "octocat\n")
+                .on("reviews -X POST", "{}")
+                .on("pulls/1/reviews", ""),
+        );
+        let script = Headless::new(100, 30)
+            .keys("r p A")
+            .unwrap()
+            .text("LGTM")
+            .keys("enter")
+            .unwrap();
+        // The editor puts the reply in front of the template.
+        let config = Config {
+            editor: Some("sed -i 1s/^/Fixed/".into()),
+            ..Config::default()
+        };
+        let mut app = scripted_app(&mock, script, config);
+
+        let exit = app.run(&mut TerminalSession::detached()).await.unwrap();
+
+        assert!(matches!(exit, Exit::Quit));
+        let calls = mock.calls();
+        assert!(
+            calls
+                .iter()
+                .any(|call| call.contains("comments/1/replies -X POST -f body=Fixed")),
+            "{calls:#?}"
+        );
+        assert!(
+            calls
+                .iter()
+                .any(|call| call.contains("-f event=APPROVE -f body=LGTM")),
+            "{calls:#?}"
+        );
+        assert!(app.queued_replies.is_empty());
+        app.render().unwrap();
+        let screen = app.headless.as_ref().unwrap();
+        assert!(screen.shows("Submitted review on PR #1: approve."));
+        assert!(screen.shows("Fixed"));
+    }
+
+    #[tokio::test]
+    async fn approving_your_own_pr_warns_before_asking() {
+        let mock = Arc::new(
+            MockGh::default()
+                .on("api user", "octocat\n")
+                .on("--json author", "octocat\n")
+                .on("pulls/1/reviews", ""),
+        );
+        let script = Headless::new(100, 30).keys("A").unwrap();
+        let mut app = scripted_app(&mock, script, Config::default());
+
+        app.run(&mut TerminalSession::detached()).await.unwrap();
+
+        let prompt = app.prompt.as_ref().expect("approval should still be asked");
+        assert!(prompt.label.contains("your own PR"), "{}", prompt.label);
+    }
+
+    #[tokio::test]
+    async fn a_deleted_comment_moves_the_reply_to_the_latest_one() {
+        let mock = Arc::new(
+            MockGh::default()
+                .fail("comments/1/replies", "HTTP 404: Not Found")
+                .on(
+                    "api graphql",
+                    &thread_json(&[(3, "octocat", "Reworded my comment.")]),
+                ),
+        );
+        let script = Headless::new(100, 30).keys("p").unwrap();
+        let mut app = scripted_app(&mock, script, Config::default());
+        app.queued_replies.push_back(QueuedReply {
+            comment_database_id: 1,
+            thread_id: "thread".into(),
+            body: "Done.".into(),
+        });
+
+        app.run(&mut TerminalSession::detached()).await.unwrap();
+
+        assert_eq!(app.queued_replies.len(), 1);
+        assert_eq!(app.queued_replies[0].comment_database_id, 3);
+        let status = app.status_line.clone().unwrap_or_default();
+        assert!(status.starts_with("Conflict"), "{status}");
+    }
+
+    #[tokio::test]
+    async fn a_not_found_reply_to_a_listed_comment_is_an_error() {
+        let mock = Arc::new(
+            MockGh::default()
+                .fail("comments/1/replies", "HTTP 404: Not Found")
+                .on(
+                    "api graphql",
+                    &thread_json(&[(1, "octocat", "Please rename this.")]),
+                ),
+        );
+        let script = Headless::new(100, 30).keys("p").unwrap();
+        let mut app = scripted_app(&mock, script, Config::default());
+        app.queued_replies.push_back(QueuedReply {
+            comment_database_id: 1,
+            thread_id: "thread".into(),
+            body: "Done.".into(),
+        });
+
+        app.run(&mut TerminalSession::detached()).await.unwrap();
+
+        assert_eq!(app.queued_replies[0].comment_database_id, 1);
+        let status = app.status_line.clone().unwrap_or_default();
+        assert!(status.starts_with("Failed to publish replies"), "{status}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn clicking_a_diff_line_starts_a_thread_on_it() {
+        let mock = Arc::new(MockGh::default().on("pulls/1/comments -X POST", "{}").on(
+            "api graphql",
+            &thread_json(&[(1, "octocat", "Please rename this.")]),
+        ));
+        let config = Config {
+            editor: Some("sed -i 1s/^/Inline/".into()),
+            ..Config::default()
+        };
+        let mut app = scripted_app(&mock, Headless::new(100, 30), config);
+        app.head_oid = Some("abc123".into());
+        app.draw(&mut sink(), 100, 30).unwrap();
+        let (row, _) = app.frame.new_lines[0];
+        app.headless = Some(Headless::new(100, 30).click(10, row as u16));
+
+        app.run(&mut TerminalSession::detached()).await.unwrap();
+
+        let calls = mock.calls();
+        assert!(
+            calls.iter().any(|call| call.contains(
+                "pulls/1/comments -X POST -f body=Inline -f commit_id=abc123 -f path=src/main.rs \
+                 -F line=1 -f side=RIGHT"
+            ) && !call.contains("start_line")),
+            "{calls:#?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn end_scrolls_to_the_last_line() {
+        let mock = Arc::new(MockGh::default());
+        let script = Headless::new(100, 5).keys("end").unwrap();
+        let mut app = scripted_app(&mock, script, Config::default());
+
+        app.run(&mut TerminalSession::detached()).await.unwrap();
+
+        app.render().unwrap();
+        assert_eq!(app.scroll_offset, app.rendered_lines - 5);
+    }
+
+    #[tokio::test]
+    async fn redrawing_after_an_undo_keeps_the_redo() {
+        let mock = Arc::new(MockGh::default());
+        let script = Headless::new(100, 30).keys("u j ctrl-r").unwrap();
+        let mut app = scripted_app(&mock, script, Config::default());
+
+        app.run(&mut TerminalSession::detached()).await.unwrap();
+
+        assert_eq!(
+            app.status_line.as_deref(),
+            Some("Redid marking the file viewed.")
+        );
+        assert!(app.viewed_files.contains("src/main.rs"));
+    }
+
+    #[tokio::test]
+    async fn macros_replay_recorded_actions() {
+        let mock = Arc::new(MockGh::default());
+        let script = Headless::new(100, 30).keys("Q a T m x Q @ a").unwrap();
+        let mut app = scripted_app(&mock, script, Config::default());
+
+        app.run(&mut TerminalSession::detached()).await.unwrap();
+
+        assert_eq!(
+            app.macros[&'a'],
+            [
+                (Action::ToggleTimes, None),
+                (Action::SetMark, None),
+                (Action::SetMark, Some('x'))
+            ]
+        );
+        // Toggled once while recording and again by the replay.
+        assert!(!app.absolute_times);
+        assert!(app.marks.contains_key(&'x'));
+    }
+
+    #[test]
+    fn notes_show_on_their_hunk_file_and_lines() {
+        let hunk = "@@ -1,3 +1,3 @@\n fn main() {\n-    old();\n+    new();";
+        let thread = fixture_thread("thread", "src/main.rs", 2, hunk, &[]);
+        let mut app = fixture_app(GhCli::new(), Config::default(), vec![thread]);
+        let note = |thread_id: &str, path: &str, line: Option<usize>| Note {
+            thread_id: thread_id.into(),
+            path: path.into(),
+            line,
+            body: format!("about {path}"),
+            created_at: Utc::now(),
+        };
+        app.notes = vec![
+            note("thread", "src/main.rs", None),
+            note("", "src/main.rs", None),
+            note("", "src/main.rs", Some(2)),
+            note("", "src/main.rs", Some(40)),
+            note("", "src/lib.rs", None),
+        ];
+        let shown = app.notes_on(&app.active_threads[0]).len();
+        assert_eq!(shown, 3);
+
+        app.draw(&mut sink(), WIDTH, HEIGHT).unwrap();
+        assert!(app.frame.text.contains("✎ Your note on line 2"));
+        assert!(app.frame.text.contains("✎ Your note on this file"));
+        let rows: Vec<usize> = app.frame.new_lines.iter().map(|(_, line)| *line).collect();
+        assert_eq!(rows, [1, 2]);
+    }
+
+    #[test]
+    fn footer_warns_when_refreshes_stop() {
+        let config = Config {
+            refresh_interval: 60,
+            ..Config::default()
+        };
+        let mut app = fixture_app(GhCli::new(), config, Vec::new());
+        let now = Utc::now();
+        assert_eq!(app.staleness(now), None);
+        assert!(app.staleness(now + chrono::Duration::minutes(3)).is_some());
+
+        app.refresh_error = Some("gh: HTTP 502".into());
+        app.draw(&mut sink(), WIDTH, HEIGHT).unwrap();
+        assert!(
+            app.frame
+                .text
+                .contains("⚠ stale – refresh failed: gh: HTTP 502")
+        );
+    }
+
+    #[test]
+    fn references_follow_the_template() {
+        let sha = "0123456789abcdef";
+        let link = "https://github.com/o/r/blob/0123456789abcdef/src/gh.rs#L87";
+        let format =
+            |template: &str, line| format_reference(template, "src/gh.rs", line, sha, link, 12);
+        assert_eq!(format("{path}:{line}", Some(87)), "src/gh.rs:87");
+        assert_eq!(format("{path}:{line}", None), "src/gh.rs");
+        assert_eq!(
+            format(
+                "[{path}:{line}]({permalink}) in #{pr} @ {short_sha}",
+                Some(87)
+            ),
+            format!("[src/gh.rs:87]({link}) in #12 @ 0123456")
+        );
+    }
+
+    #[test]
+    fn own_pr_header_counts_threads_to_address() {
+        let hunk = "@@ -1 +1 @@\n-a\n+b";
+        let threads = vec![
+            fixture_thread("one", "src/a.rs", 1, hunk, &[]),
+            fixture_thread("two", "src/b.rs", 1, hunk, &[]),
+        ];
+        let mut app = fixture_app(GhCli::new(), Config::default(), threads);
+        app.own_pr = true;
+        app.draw(&mut sink(), WIDTH, HEIGHT).unwrap();
+        assert!(app.frame.text.contains("✎ your PR, 2 to address"));
+    }
+
+    #[test]
+    fn mentions_join_with_commas_and_a_final_and() {
+        let logins = ["alice".to_string(), "bob".into(), "carol".into()];
+        assert_eq!(mention_list(&logins[..1]), "@alice");
+        assert_eq!(mention_list(&logins), "@alice, @bob and @carol");
+    }
+
+    #[test]
+    fn merge_diff_replaces_the_hunk_around_the_line() {
+        let hunk = "@@ -10,2 +10,2 @@\n a\n-b\n+c";
+        let threads = vec![
+            fixture_thread("one", "src/a.rs", 11, hunk, &[]),
+            fixture_thread("two", "src/b.rs", 11, hunk, &[]),
+        ];
+        let mut app = fixture_app(GhCli::new(), Config::default(), threads);
+        let merged = "@@ -1,2 +1,2 @@\n-x\n+y\n@@ -10,2 +10,2 @@\n a\n-b\n+merged";
+        let hunks = diff::split_files(&format!(
+            "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n{merged}\n"
+        ));
+        app.merge_diff = Some(
+            hunks
+                .into_iter()
+                .map(|file| {
+                    (
+                        file.path,
+                        file.hunks.into_iter().map(|(_, hunk)| hunk).collect(),
+                    )
+                })
+                .collect(),
+        );
+        assert_eq!(app.merge_hunk(&app.active_threads[0]), None);
+
+        app.show_merge_diff = true;
+        let shown = app.merge_hunk(&app.active_threads[0]).unwrap();
+        assert!(shown.starts_with("@@ -10,2 +10,2 @@") && shown.contains("+merged"));
+        assert_eq!(app.merge_hunk(&app.active_threads[1]), None);
+    }
+
+    #[test]
+    fn review_comments_parse_with_their_location() {
+        let comment: ReviewComment = serde_json::from_str(
+            r#"{"id": 7, "pull_request_review_id": 3, "path": "src/gh.rs", "line": null, "original_line": 87, "body": "Why?"}"#,
+        )
+        .unwrap();
+        assert_eq!(comment.pull_request_review_id, Some(3));
+        assert_eq!(comment.location(), "src/gh.rs:87");
+    }
+
+    #[test]
+    fn review_states_read_as_words() {
+        assert_eq!(review_state_label("CHANGES_REQUESTED"), "changes requested");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn renderers_stream_large_hunks_and_take_quoted_arguments() {
+        let hunk: String = std::iter::once("@@ -1,5000 +1,5000 @@".to_string())
+            .chain((0..5000).map(|i| format!("+line {i} of a hunk larger than a pipe buffer")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let lines = diff::render_external("cat", "src/big.rs", &hunk, 80).unwrap();
+        assert_eq!(lines.len(), 5001);
+        assert_eq!(
+            lines[5000],
+            "+line 4999 of a hunk larger than a pipe buffer"
+        );
+
+        let lines =
+            diff::render_external("sed -e 's/^/> /' | cat", "a.rs", "@@ -1 +1 @@\n+x", 80).unwrap();
+        assert_eq!(lines, ["> @@ -1 +1 @@", "> +x"]);
+    }
+
+    #[test]
+    fn compare_makes_a_thread_per_hunk() {
+        let raw = "diff --git a/src/old.rs b/src/new.rs\n\
+                   similarity index 90%\n\
+                   rename from src/old.rs\n\
+                   rename to src/new.rs\n\
+                   --- a/src/old.rs\n\
+                   +++ b/src/new.rs\n\
+                   @@ -1,2 +1,2 @@\n\
+                   -a\n\
+                   +b\n\
+                   \x20c\n\
+                   @@ -10 +10,2 @@\n\
+                   \x20d\n\
+                   +e\n\
+                   diff --git a/logo.png b/logo.png\n\
+                   new file mode 100644\n\
+                   Binary files /dev/null and b/logo.png differ\n";
+        let files = diff::split_files(raw);
+        let threads = compare_threads("main..topic", &files);
+        let places: Vec<_> = threads
+            .iter()
+            .map(|thread| (thread.id.as_str(), thread.line))
+            .collect();
+        assert_eq!(
+            places,
+            [
+                ("main..topic:src/new.rs:0", Some(1)),
+                ("main..topic:src/new.rs:1", Some(10)),
+                ("main..topic:logo.png", None),
+            ]
+        );
+        assert_eq!(
+            threads[1].diff_hunk.as_deref(),
+            Some("@@ -10 +10,2 @@\n d\n+e\n")
+        );
+        let counts = compare_files(&files);
+        let renamed = &counts["src/new.rs"];
+        assert_eq!(renamed.status, "renamed");
+        assert_eq!(renamed.previous.as_deref(), Some("src/old.rs"));
+        assert_eq!((renamed.additions, renamed.deletions), (2, 1));
+        assert_eq!(counts["logo.png"].status, "added");
+    }
+
+    const SNAPSHOT_HUNK: &str = "@@ -10,7 +10,8 @@ impl Parser {
+     fn next_token(&mut self) -> Option<Token> {
+-        let ch = self.chars.next()?;
++        let ch = self.chars.next().filter(|ch| !ch.is_control())?;
++        self.column += 1;
+         match ch {
+             '(' => Some(Token::Open),
+             ')' => Some(Token::Close),";
+
+    /// The frame `app` draws on a `width` by `height` terminal, as text.
+    fn frame(app: &mut App, width: u16, height: u16) -> String {
+        let mut screen = Headless::new(width, height);
+        app.draw(&mut screen.screen, width, height).unwrap();
+        screen.rows().join("\n")
+    }
+
+    #[test]
+    fn diff_layout_snapshot() {
+        let thread = fixture_thread(
+            "thread",
+            "src/parser.rs",
+            12,
+            SNAPSHOT_HUNK,
+            &[
+                ("reviewer", "Should control characters be skipped here?"),
+                ("author", "Yes, the lexer rejects them later anyway."),
+            ],
+        );
+        let mut app = fixture_app(GhCli::new(), Config::default(), vec![thread]);
+        insta::assert_snapshot!("diff_layout", frame(&mut app, 100, 30));
+    }
+
+    #[test]
+    fn long_comments_wrap_snapshot() {
+        let body = "The column is counted in chars, but the editor reports \
+                    columns in UTF-16 units, see \
+                    https://microsoft.github.io/language-server-protocol/specifications/specification-current";
+        let thread = fixture_thread(
+            "thread",
+            "src/parser.rs",
+            12,
+            SNAPSHOT_HUNK,
+            &[("reviewer", body)],
+        );
+        let mut app = fixture_app(GhCli::new(), Config::default(), vec![thread]);
+        insta::assert_snapshot!("long_comment", frame(&mut app, 100, 30));
+    }
+
+    #[test]
+    fn pinned_thread_gutter_snapshot() {
+        let threads = vec![
+            fixture_thread(
+                "first",
+                "src/lexer.rs",
+                4,
+                "@@ -1,4 +1,4 @@\n use std::str::Chars;\n-pub struct Lexer;\n+pub struct Lexer<'a>(Chars<'a>);",
+                &[("reviewer", "Does this need to be public?")],
+            ),
+            fixture_thread(
+                "second",
+                "src/parser.rs",
+                12,
+                SNAPSHOT_HUNK,
+                &[("reviewer", "Should control characters be skipped here?")],
+            ),
+        ];
+        let mut app = fixture_app(GhCli::new(), Config::default(), threads);
+        app.next_thread();
+        app.toggle_pin();
+        insta::assert_snapshot!("pinned_gutter", frame(&mut app, 80, 24));
+    }
+
+    #[test]
+    fn only_lines_in_view_are_laid_out() {
+        let mut app = large_diff_app();
+        app.scroll_offset = 10_000;
+        app.draw(&mut sink(), WIDTH, HEIGHT).unwrap();
+        let full = app.render_view(None);
+        assert_eq!(app.frame.len(), full.len());
+        assert!(app.frame.text.len() * 100 < full.text.len());
+        assert_eq!(app.frame.line(5_000), "");
+        assert_eq!(app.frame.line(10_000), full.line(10_000));
+        let last = full.len() - 1;
+        assert_eq!(app.frame.line(last), full.line(last));
+    }
+
+    #[test]
+    fn review_composer_reorders_and_drops_replies() {
+        let recap = [
+            "a.rs:1  one".to_string(),
+            "b.rs:2  two".into(),
+            "c.rs:3  three".into(),
+        ];
+        let template = review_composer_template(7, &recap, 0);
+        let edited = template
+            .replacen("\n\n", "Looks good overall.\n\n", 1)
+            .replace("verdict: comment", "verdict: approve")
+            .replace("1 a.rs:1  one\n", "")
+            .replace("3 c.rs:3  three\n", "")
+            .replace("2 b.rs:2  two\n", "3 c.rs:3  three\n2 b.rs:2  two\n");
+        assert_eq!(
+            parse_review_composer(&edited, recap.len()).unwrap(),
+            ComposedReview {
+                body: "Looks good overall.".into(),
+                event: ReviewEvent::Approve,
+                order: vec![2, 1],
+            }
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use std::process::{Command, Stdio};
+
+/// Shows a desktop notification with the platform's own tool. Failures are
+/// only logged; a missing notifier shouldn't interrupt the review.
+pub fn desktop(title: &str, body: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            quote(body),
+            quote(title)
+        );
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args([title, body]);
+        command
+    };
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => log::warn!("notifier exited with {status}"),
+        Err(err) => log::warn!("failed to run notifier: {err}"),
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,94 @@
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+static GH_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static GH_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Marks a `gh` invocation as running until dropped.
+pub struct GhCall;
+
+impl GhCall {
+    pub fn start() -> Self {
+        GH_IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+        GH_TOTAL.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for GhCall {
+    fn drop(&mut self) {
+        GH_IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Timings and counters shown by the performance overlay.
+#[derive(Default)]
+pub struct Stats {
+    frame: Option<Duration>,
+    /// Exponentially weighted average, so one slow frame stands out
+    /// against it rather than disappearing into a long-run mean.
+    frame_average: Option<Duration>,
+    event_at: Option<Instant>,
+    latency: Option<Duration>,
+    // Cells so lookups can be counted while the thread being looked up is
+    // still borrowed from the app.
+    cache_hits: Cell<u64>,
+    cache_misses: Cell<u64>,
+}
+
+impl Stats {
+    pub fn record_frame(&mut self, elapsed: Duration) {
+        self.frame = Some(elapsed);
+        self.frame_average = Some(match self.frame_average {
+            Some(average) => (average * 7 + elapsed) / 8,
+            None => elapsed,
+        });
+        if let Some(at) = self.event_at.take() {
+            self.latency = Some(at.elapsed());
+        }
+    }
+
+    /// Notes when an input event arrived; the next frame completes it.
+    pub fn record_event(&mut self) {
+        self.event_at = Some(Instant::now());
+    }
+
+    pub fn record_cache(&self, hit: bool) {
+        let counter = if hit {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.set(counter.get() + 1);
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let millis = |duration: Option<Duration>| match duration {
+            Some(duration) => format!("{:.2}ms", duration.as_secs_f64() * 1000.0),
+            None => "–".into(),
+        };
+        let hits = self.cache_hits.get();
+        let lookups = hits + self.cache_misses.get();
+        let cache = match (hits * 100).checked_div(lookups) {
+            Some(rate) => format!("{rate}% of {lookups} lookups"),
+            None => "no lookups".into(),
+        };
+        vec![
+            format!(
+                "frame  {} (avg {})",
+                millis(self.frame),
+                millis(self.frame_average)
+            ),
+            format!("input  {}", millis(self.latency)),
+            format!(
+                "gh     {} in flight, {} total",
+                GH_IN_FLIGHT.load(Ordering::Relaxed),
+                GH_TOTAL.load(Ordering::Relaxed)
+            ),
+            format!("cache  {cache}"),
+        ]
+    }
+}
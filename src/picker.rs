@@ -0,0 +1,715 @@
+use std::{
+    io::{Write, stdin, stdout},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use chrono::Utc;
+use crossterm::{
+    cursor::MoveTo,
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    style::Stylize,
+    terminal::{Clear, ClearType, size},
+};
+use futures::FutureExt;
+use tokio::task::JoinHandle;
+
+use crate::{
+    Comment, Dashboard, DashboardPr, Notification, PrSummary, SubmittedReview, TerminalSession,
+    git::GrepMatch, humanize_relative, theme::Theme,
+};
+
+/// A line of a picker list. Headings group the items below them and can't
+/// be selected.
+enum Row {
+    Heading(String),
+    Item(String),
+}
+
+/// How long typing has to pause before the PR search runs.
+const SEARCH_DELAY: Duration = Duration::from_millis(300);
+
+/// Lets the user choose a pull request, starting from `prs`, returning its
+/// number, or `None` if the picker was dismissed. `/` opens a search line
+/// whose qualifiers, such as `author:octocat label:bug review:required
+/// draft:false`, are handed to `search`; the list follows the results as
+/// the query is typed.
+pub fn pick_pr<S>(
+    title: &str,
+    mut prs: Vec<PrSummary>,
+    search: S,
+    theme: &Theme,
+) -> Result<Option<u64>>
+where
+    S: Fn(&str) -> JoinHandle<Result<Vec<PrSummary>>>,
+{
+    let _terminal = TerminalSession::enter()?;
+    let mut query = String::new();
+    let mut editing = false;
+    // When the query last changed without being searched for yet.
+    let mut changed: Option<Instant> = None;
+    let mut pending: Option<JoinHandle<Result<Vec<PrSummary>>>> = None;
+    let mut error: Option<String> = None;
+    let mut selected = 0;
+    let mut offset = 0;
+    loop {
+        if changed.is_some_and(|at| at.elapsed() >= SEARCH_DELAY) {
+            changed = None;
+            if let Some(previous) = pending.take() {
+                previous.abort();
+            }
+            pending = Some(search(&search_query(&query)));
+        }
+        if let Some(done) = pending.take_if(|handle| handle.is_finished()) {
+            match done.now_or_never() {
+                Some(Ok(Ok(found))) => {
+                    prs = found;
+                    error = None;
+                    selected = 0;
+                    offset = 0;
+                }
+                Some(Ok(Err(err))) => error = Some(format!("Search failed: {err}")),
+                Some(Err(err)) => error = Some(format!("Search failed: {err}")),
+                None => {}
+            }
+        }
+
+        let (_, height) = size()?;
+        let visible = (height as usize).saturating_sub(2).max(1);
+        let last = prs.len().saturating_sub(1);
+        selected = selected.min(last);
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + visible {
+            offset = selected + 1 - visible;
+        }
+        let rows: Vec<Row> = prs
+            .iter()
+            .map(|pr| {
+                let draft = if pr.is_draft { " draft" } else { "" };
+                Row::Item(format!(
+                    "#{:<6} {:<3} {}  {} · {}{}",
+                    pr.number,
+                    pr.size().label,
+                    pr.title,
+                    pr.author_login(),
+                    pr.head_ref_name,
+                    draft
+                ))
+            })
+            .collect();
+        let status = if pending.is_some() || changed.is_some() {
+            " searching…"
+        } else if prs.is_empty() {
+            " no matches"
+        } else {
+            ""
+        };
+        let hint = match &error {
+            Some(error) => error.clone(),
+            None if editing => format!("/{query}▏{status}"),
+            None if !query.is_empty() => {
+                format!("/{query}{status}  ↑/↓ select  enter open  / search  q quit")
+            }
+            None => "↑/↓ select  enter open  / search  q quit".to_string(),
+        };
+        render(title, &rows, &hint, theme, selected, offset, visible)?;
+
+        // Wake up to run or collect a search even without input.
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl && key.code == KeyCode::Char('c') {
+            return Ok(None);
+        }
+        if editing {
+            match key.code {
+                KeyCode::Esc | KeyCode::Enter => editing = false,
+                KeyCode::Backspace => {
+                    query.pop();
+                    changed = Some(Instant::now());
+                }
+                KeyCode::Char('u') if ctrl => {
+                    query.clear();
+                    changed = Some(Instant::now());
+                }
+                KeyCode::Char(ch) if !ctrl => {
+                    query.push(ch);
+                    changed = Some(Instant::now());
+                }
+                KeyCode::Down => selected = (selected + 1).min(last),
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                _ => {}
+            }
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Char('/') => {
+                editing = true;
+                error = None;
+            }
+            KeyCode::Enter if !prs.is_empty() => return Ok(Some(prs[selected].number)),
+            KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+            KeyCode::PageDown => selected = (selected + visible).min(last),
+            KeyCode::PageUp => selected = selected.saturating_sub(visible),
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = last,
+            _ => {}
+        }
+    }
+}
+
+/// How often the dashboard reloads while it's open.
+const DASHBOARD_REFRESH: Duration = Duration::from_secs(60);
+
+/// Shows your open PRs and those requesting your review, with columns for
+/// checks, review decision, unresolved threads and mergeability, reloading
+/// them with `refresh` in the background every minute or on `r`. Returns
+/// the chosen PR, or `None` if the dashboard was closed.
+pub fn pick_dashboard<R>(refresh: R, theme: &Theme) -> Result<Option<DashboardPr>>
+where
+    R: Fn() -> JoinHandle<Result<Dashboard>>,
+{
+    let _terminal = TerminalSession::enter()?;
+    let mut prs: Vec<DashboardPr> = Vec::new();
+    let mut rows: Vec<Row> = Vec::new();
+    // The row of each PR in `prs`.
+    let mut items: Vec<usize> = Vec::new();
+    let mut loaded: Option<Instant> = None;
+    let mut pending = Some(refresh());
+    let mut error: Option<String> = None;
+    let mut selected = 0;
+    let mut offset = 0;
+    loop {
+        if pending.is_none() && loaded.is_some_and(|at| at.elapsed() >= DASHBOARD_REFRESH) {
+            pending = Some(refresh());
+        }
+        if let Some(done) = pending.take_if(|handle| handle.is_finished()) {
+            loaded = Some(Instant::now());
+            match done.now_or_never() {
+                Some(Ok(Ok(dashboard))) => {
+                    // Keep the same PR selected across reloads.
+                    let current = prs.get(selected).map(|pr| (pr.repo.clone(), pr.number));
+                    (prs, rows, items) = dashboard_rows(dashboard);
+                    selected = current
+                        .and_then(|(repo, number)| {
+                            prs.iter()
+                                .position(|pr| pr.repo == repo && pr.number == number)
+                        })
+                        .unwrap_or(0);
+                    error = None;
+                }
+                Some(Ok(Err(err))) => error = Some(format!("Refresh failed: {err}")),
+                Some(Err(err)) => error = Some(format!("Refresh failed: {err}")),
+                None => {}
+            }
+        }
+
+        let (_, height) = size()?;
+        let visible = (height as usize).saturating_sub(2).max(1);
+        let last = prs.len().saturating_sub(1);
+        selected = selected.min(last);
+        let row = items.get(selected).copied().unwrap_or(usize::MAX);
+        // Scrolling up to the first PR of a group shows its heading too.
+        let top = match row.checked_sub(1).and_then(|above| rows.get(above)) {
+            Some(Row::Heading(_)) => row - 1,
+            _ => row,
+        };
+        if top < offset {
+            offset = top;
+        } else if row != usize::MAX && row >= offset + visible {
+            offset = row + 1 - visible;
+        }
+        let status = match (&pending, loaded) {
+            (Some(_), None) => " loading…",
+            (Some(_), Some(_)) => " refreshing…",
+            _ => "",
+        };
+        let hint = match &error {
+            Some(error) => error.clone(),
+            None => format!("↑/↓ select  enter open  r refresh  q quit{status}"),
+        };
+        render(
+            "Your pull requests",
+            &rows,
+            &hint,
+            theme,
+            row,
+            offset,
+            visible,
+        )?;
+
+        // Wake up to collect a refresh even without input.
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(None);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Char('r') if pending.is_none() => pending = Some(refresh()),
+            KeyCode::Enter if !prs.is_empty() => return Ok(Some(prs.swap_remove(selected))),
+            KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+            KeyCode::PageDown => selected = (selected + visible).min(last),
+            KeyCode::PageUp => selected = selected.saturating_sub(visible),
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = last,
+            _ => {}
+        }
+    }
+}
+
+/// Lists `notifications` until one is chosen, returning it. `m` marks the
+/// selected one read and `d` marks it done, removing it from the list;
+/// both are sent with `mark` in the background, and neither is offered
+/// without it.
+pub fn pick_notification<M>(
+    mut notifications: Vec<Notification>,
+    mark: Option<M>,
+    theme: &Theme,
+) -> Result<Option<Notification>>
+where
+    M: Fn(&str, bool) -> JoinHandle<Result<()>>,
+{
+    let _terminal = TerminalSession::enter()?;
+    let mut pending: Vec<JoinHandle<Result<()>>> = Vec::new();
+    let mut error: Option<String> = None;
+    let mut selected = 0;
+    let mut offset = 0;
+    loop {
+        for done in pending.extract_if(.., |handle| handle.is_finished()) {
+            match done.now_or_never() {
+                Some(Ok(Err(err))) => error = Some(format!("Failed to update: {err}")),
+                Some(Err(err)) => error = Some(format!("Failed to update: {err}")),
+                _ => {}
+            }
+        }
+
+        let (_, height) = size()?;
+        let visible = (height as usize).saturating_sub(2).max(1);
+        let last = notifications.len().saturating_sub(1);
+        selected = selected.min(last);
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + visible {
+            offset = selected + 1 - visible;
+        }
+        let rows: Vec<Row> = notifications
+            .iter()
+            .map(|notification| {
+                Row::Item(format!(
+                    "{} {:<16}  {}#{}  {}  {}",
+                    if notification.unread { "●" } else { " " },
+                    notification.reason_label(),
+                    notification.repo,
+                    notification.number,
+                    notification.title,
+                    humanize_relative(Utc::now(), notification.updated_at)
+                ))
+            })
+            .collect();
+        let unread = notifications.iter().filter(|n| n.unread).count();
+        let title = format!("Inbox: {unread} unread");
+        let hint = match &error {
+            Some(error) => error.clone(),
+            None if notifications.is_empty() => "Inbox zero  q quit".to_string(),
+            None if mark.is_none() => "↑/↓ select  enter open  q quit".to_string(),
+            None => "↑/↓ select  enter open  m mark read  d done  q quit".to_string(),
+        };
+        render(&title, &rows, &hint, theme, selected, offset, visible)?;
+
+        // Wake up to collect finished updates even without input.
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(None);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Enter if !notifications.is_empty() => {
+                return Ok(Some(notifications.remove(selected)));
+            }
+            KeyCode::Char('m') if notifications.get(selected).is_some_and(|n| n.unread) => {
+                if let Some(mark) = &mark {
+                    notifications[selected].unread = false;
+                    pending.push(mark(&notifications[selected].id, false));
+                }
+            }
+            KeyCode::Char('d') if !notifications.is_empty() => {
+                if let Some(mark) = &mark {
+                    let notification = notifications.remove(selected);
+                    pending.push(mark(&notification.id, true));
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+            KeyCode::PageDown => selected = (selected + visible).min(last),
+            KeyCode::PageUp => selected = selected.saturating_sub(visible),
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = last,
+            _ => {}
+        }
+    }
+}
+
+/// The dashboard's PRs in display order, its rows, and the row of each PR.
+/// Columns are padded to line up across both groups.
+fn dashboard_rows(dashboard: Dashboard) -> (Vec<DashboardPr>, Vec<Row>, Vec<usize>) {
+    let width = dashboard
+        .authored
+        .iter()
+        .chain(&dashboard.requested)
+        .map(|pr| pr.repo.chars().count() + pr.number.to_string().len() + 1)
+        .max()
+        .unwrap_or(0);
+    let mut prs = Vec::new();
+    let mut rows = Vec::new();
+    let mut items = Vec::new();
+    let groups = [
+        ("Authored by you", dashboard.authored),
+        ("Requesting your review", dashboard.requested),
+    ];
+    for (heading, group) in groups {
+        rows.push(Row::Heading(format!(
+            "{:<2} {:<17} {:>7}  {:<9}  {heading} ({})",
+            "CI",
+            "review",
+            "threads",
+            "merge",
+            group.len()
+        )));
+        for pr in group {
+            let checks = match pr.checks.as_str() {
+                "SUCCESS" => "✓",
+                "FAILURE" | "ERROR" => "✗",
+                "PENDING" | "EXPECTED" => "•",
+                _ => "",
+            };
+            let review = match pr.decision.as_str() {
+                "APPROVED" => "approved",
+                "CHANGES_REQUESTED" => "changes requested",
+                "REVIEW_REQUIRED" => "review required",
+                _ => "",
+            };
+            let unresolved = match pr.unresolved {
+                0 => String::new(),
+                count => count.to_string(),
+            };
+            let merge = match pr.mergeable.as_str() {
+                "CONFLICTING" => "conflicts",
+                "MERGEABLE" => "clean",
+                _ => "",
+            };
+            let reference = format!("{}#{}", pr.repo, pr.number);
+            let draft = if pr.draft { " draft" } else { "" };
+            items.push(rows.len());
+            rows.push(Row::Item(format!(
+                "{checks:<2} {review:<17} {unresolved:>7}  {merge:<9}  {reference:<width$}  {}  {}{draft}",
+                pr.title, pr.author
+            )));
+            prs.push(pr);
+        }
+    }
+    (prs, rows, items)
+}
+
+/// GitHub search syntax for what was typed in the PR picker. Qualifiers
+/// pass through as they are, apart from `by:login`, which matches the
+/// thread filter's spelling of `author:`.
+fn search_query(typed: &str) -> String {
+    typed
+        .split_whitespace()
+        .map(|term| match term.strip_prefix("by:") {
+            Some(login) => format!("author:{}", login.trim_start_matches('@')),
+            None => term.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Lists `prs` as plain lines and reads the number of the one to open, for
+/// screen readers. `None` if nothing was typed.
+pub fn read_pr(title: &str, prs: &[PrSummary]) -> Result<Option<u64>> {
+    println!("{title}: {} open.", prs.len());
+    for pr in prs {
+        let draft = if pr.is_draft { ", draft" } else { "" };
+        println!(
+            "{}: {}, by {} from {}, size {}{draft}.",
+            pr.number,
+            pr.title,
+            pr.author_login(),
+            pr.head_ref_name,
+            pr.size().describe()
+        );
+    }
+    loop {
+        print!("Type a number to open, or nothing to quit: ");
+        stdout().flush()?;
+        let mut input = String::new();
+        if stdin().read_line(&mut input)? == 0 {
+            return Ok(None);
+        }
+        let input = input.trim().trim_start_matches('#');
+        if input.is_empty() {
+            return Ok(None);
+        }
+        match input.parse() {
+            Ok(number) if prs.iter().any(|pr| pr.number == number) => return Ok(Some(number)),
+            _ => println!("No open pull request numbered {input}."),
+        }
+    }
+}
+
+/// Lists search matches under a heading per file, in the order given, and
+/// returns the chosen one.
+pub fn pick_match<'a>(
+    title: &str,
+    matches: &'a [GrepMatch],
+    theme: &Theme,
+) -> Result<Option<&'a GrepMatch>> {
+    let mut rows = Vec::new();
+    for (index, found) in matches.iter().enumerate() {
+        if index == 0 || matches[index - 1].path != found.path {
+            let count = matches[index..]
+                .iter()
+                .take_while(|other| other.path == found.path)
+                .count();
+            rows.push(Row::Heading(format!("{} ({count})", found.path)));
+        }
+        rows.push(Row::Item(format!(
+            "{:>6}  {}",
+            found.line,
+            found.text.trim()
+        )));
+    }
+    let picked = pick(title, &rows, "↑/↓ select  enter jump  q close", theme)?;
+    Ok(picked.map(|index| &matches[index]))
+}
+
+/// Lets the user choose one of `reviews`, listed by reviewer and state.
+pub fn pick_review<'a>(
+    title: &str,
+    reviews: &'a [SubmittedReview],
+    theme: &Theme,
+) -> Result<Option<&'a SubmittedReview>> {
+    let rows: Vec<Row> = reviews
+        .iter()
+        .map(|review| {
+            let commit = review.commit_id.as_deref().unwrap_or_default();
+            Row::Item(format!(
+                "@{:<20} {:<18} {}",
+                review.login(),
+                review.state.to_lowercase().replace('_', " "),
+                &commit[..commit.len().min(7)]
+            ))
+        })
+        .collect();
+    let picked = pick(title, &rows, "↑/↓ select  enter dismiss  q close", theme)?;
+    Ok(picked.map(|index| &reviews[index]))
+}
+
+/// Lets the user choose one of a thread's comments, shown by author and
+/// first line.
+pub fn pick_comment<'a>(
+    title: &str,
+    comments: &'a [Comment],
+    theme: &Theme,
+) -> Result<Option<&'a Comment>> {
+    let rows: Vec<Row> = comments
+        .iter()
+        .map(|comment| {
+            let first = comment.body.lines().next().unwrap_or_default().trim();
+            let hidden = match &comment.minimized {
+                Some(reason) => format!("  [hidden as {reason}]"),
+                None => String::new(),
+            };
+            Row::Item(format!("@{:<20} {first}{hidden}", comment.author))
+        })
+        .collect();
+    let picked = pick(title, &rows, "↑/↓ select  enter choose  q close", theme)?;
+    Ok(picked.map(|index| &comments[index]))
+}
+
+/// Lets the user tick any of `options`, starting from `ticked`, and
+/// returns the indices ticked when enter is pressed, or `None` if the
+/// picker was dismissed.
+pub fn pick_many(
+    title: &str,
+    options: &[String],
+    mut ticked: Vec<bool>,
+    theme: &Theme,
+) -> Result<Option<Vec<usize>>> {
+    if options.is_empty() {
+        return Ok(None);
+    }
+    let _terminal = TerminalSession::enter()?;
+    let last = options.len() - 1;
+    let mut selected = 0;
+    let mut offset = 0;
+    loop {
+        let (_, height) = size()?;
+        let visible = (height as usize).saturating_sub(2).max(1);
+        if selected < offset {
+            offset = selected;
+        } else if selected >= offset + visible {
+            offset = selected + 1 - visible;
+        }
+        let rows: Vec<Row> = options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| {
+                let mark = if ticked[index] { "[x]" } else { "[ ]" };
+                Row::Item(format!("{mark} {option}"))
+            })
+            .collect();
+        let hint = "↑/↓ select  space tick  enter confirm  q close";
+        render(title, &rows, hint, theme, selected, offset, visible)?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(None);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Char(' ') => ticked[selected] = !ticked[selected],
+            KeyCode::Enter => {
+                return Ok(Some(
+                    (0..options.len()).filter(|index| ticked[*index]).collect(),
+                ));
+            }
+            KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+            KeyCode::PageDown => selected = (selected + visible).min(last),
+            KeyCode::PageUp => selected = selected.saturating_sub(visible),
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = last,
+            _ => {}
+        }
+    }
+}
+
+/// Lets the user choose one of `options`, returning its index.
+pub fn pick_option(
+    title: &str,
+    options: &[String],
+    hint: &str,
+    theme: &Theme,
+) -> Result<Option<usize>> {
+    let rows: Vec<Row> = options.iter().cloned().map(Row::Item).collect();
+    pick(title, &rows, hint, theme)
+}
+
+/// Shows `rows` until an item is chosen, returning its index among the
+/// items alone, or `None` if the picker was dismissed.
+fn pick(title: &str, rows: &[Row], hint: &str, theme: &Theme) -> Result<Option<usize>> {
+    let items: Vec<usize> = rows
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| matches!(row, Row::Item(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if items.is_empty() {
+        return Ok(None);
+    }
+    let _terminal = TerminalSession::enter()?;
+    let last = items.len() - 1;
+    let mut selected = 0;
+    let mut offset = 0;
+    loop {
+        let (_, height) = size()?;
+        let visible = (height as usize).saturating_sub(2).max(1);
+        // Scrolling up to the first item of a group shows its heading too.
+        let row = items[selected];
+        let top = match row.checked_sub(1).map(|above| &rows[above]) {
+            Some(Row::Heading(_)) => row - 1,
+            _ => row,
+        };
+        if top < offset {
+            offset = top;
+        } else if row >= offset + visible {
+            offset = row + 1 - visible;
+        }
+        render(title, rows, hint, theme, row, offset, visible)?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(None);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+            KeyCode::Enter => return Ok(Some(selected)),
+            KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+            KeyCode::PageDown => selected = (selected + visible).min(last),
+            KeyCode::PageUp => selected = selected.saturating_sub(visible),
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = last,
+            _ => {}
+        }
+    }
+}
+
+fn render(
+    title: &str,
+    rows: &[Row],
+    hint: &str,
+    theme: &Theme,
+    selected: usize,
+    offset: usize,
+    visible: usize,
+) -> Result<()> {
+    let mut out = stdout();
+    execute!(out, MoveTo(0, 0), Clear(ClearType::All))?;
+    write!(out, "{}", title.with(theme.heading).bold())?;
+    for (line, (index, row)) in rows
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+        .enumerate()
+    {
+        execute!(out, MoveTo(0, line as u16 + 1))?;
+        match row {
+            Row::Heading(text) => write!(out, "{}", text.as_str().with(theme.heading).bold())?,
+            Row::Item(text) if index == selected => write!(out, "{}", text.as_str().reverse())?,
+            Row::Item(text) => write!(out, "{}", text.as_str().with(theme.path))?,
+        }
+    }
+    execute!(out, MoveTo(0, visible as u16 + 1))?;
+    write!(out, "{}", hint.with(theme.subtle))?;
+    out.flush()?;
+    Ok(())
+}
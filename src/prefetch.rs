@@ -0,0 +1,97 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use tokio::sync::{
+    Semaphore,
+    mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+};
+
+/// At most this many renderer or blame processes run at once.
+const JOBS: usize = 4;
+
+/// What a job computes for a thread's hunk.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    ExternalDiff,
+    Blame,
+}
+
+/// Lines computed for one thread; `Ok(None)` means there was nothing to
+/// compute, such as a hunk with no lines left in the new file.
+pub struct Done {
+    pub kind: Kind,
+    pub thread_id: String,
+    pub result: Result<Option<Vec<String>>>,
+}
+
+/// Runs per-thread work (external diff rendering, blame) on blocking
+/// threads with bounded concurrency, so moving between threads never waits
+/// on a subprocess. Results are collected with [`Prefetcher::try_next`].
+pub struct Prefetcher {
+    limit: Arc<Semaphore>,
+    tx: UnboundedSender<Done>,
+    rx: UnboundedReceiver<Done>,
+    pending: HashSet<(Kind, String)>,
+}
+
+impl Prefetcher {
+    pub fn new() -> Self {
+        let (tx, rx) = unbounded_channel();
+        Self {
+            limit: Arc::new(Semaphore::new(JOBS)),
+            tx,
+            rx,
+            pending: HashSet::new(),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn is_pending(&self, kind: Kind, thread_id: &str) -> bool {
+        self.pending.contains(&(kind, thread_id.to_string()))
+    }
+
+    /// Queues `work` for a thread unless the same job is already queued.
+    pub fn spawn<F>(&mut self, kind: Kind, thread_id: String, work: F)
+    where
+        F: FnOnce() -> Result<Option<Vec<String>>> + Send + 'static,
+    {
+        if !self.pending.insert((kind, thread_id.clone())) {
+            return;
+        }
+        let limit = Arc::clone(&self.limit);
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let result = match limit.acquire_owned().await {
+                Ok(_permit) => tokio::task::spawn_blocking(work)
+                    .await
+                    .unwrap_or_else(|err| Err(anyhow!("prefetch job failed: {err}"))),
+                Err(err) => Err(anyhow!("prefetch queue closed: {err}")),
+            };
+            let _ = tx.send(Done {
+                kind,
+                thread_id,
+                result,
+            });
+        });
+    }
+
+    /// A finished job, if any, without waiting.
+    pub fn try_next(&mut self) -> Option<Done> {
+        let done = self.rx.try_recv().ok()?;
+        self.pending.remove(&(done.kind, done.thread_id.clone()));
+        Some(done)
+    }
+
+    /// Waits for the next finished job; `None` once nothing is queued.
+    pub async fn next(&mut self) -> Option<Done> {
+        if self.is_idle() {
+            return None;
+        }
+        let done = self.rx.recv().await?;
+        self.pending.remove(&(done.kind, done.thread_id.clone()));
+        Some(done)
+    }
+}
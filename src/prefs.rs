@@ -0,0 +1,66 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::{Repo, ThreadView};
+
+/// Preferences for one repository, kept in the state directory rather than
+/// the config so they can be changed from the TUI.
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RepoPrefs {
+    /// View a PR opens in when it has no session yet.
+    pub view: Option<ThreadView>,
+    /// Globs of paths whose threads are left out, such as `vendor/**`.
+    pub ignore: Vec<String>,
+    /// Start generated files collapsed in the outline.
+    pub collapse_generated: bool,
+}
+
+pub struct PrefsStore {
+    path: PathBuf,
+}
+
+impl PrefsStore {
+    pub fn new(repo: &Repo) -> Result<Self> {
+        let name = format!("{}-{}.json", repo.owner, repo.name);
+        Ok(Self {
+            path: Self::dir()?.join(name),
+        })
+    }
+
+    /// Missing or unreadable preferences fall back to the defaults.
+    pub fn load(&self) -> RepoPrefs {
+        fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, prefs: &RepoPrefs) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let data = serde_json::to_vec_pretty(prefs)?;
+        fs::write(&self.path, data)?;
+        Ok(())
+    }
+
+    fn dir() -> Result<PathBuf> {
+        if let Ok(dir) = env::var("XDG_STATE_HOME")
+            && !dir.is_empty()
+        {
+            return Ok(Path::new(&dir).join("gh-cr").join("repos"));
+        }
+        let mut path = dirs_next::home_dir().ok_or_else(|| anyhow!("HOME not set"))?;
+        path.push(".local");
+        path.push("state");
+        path.push("gh-cr");
+        path.push("repos");
+        Ok(path)
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Location, Note, QueuedReply, Repo, ThreadView};
+
+/// Where a review was left: restored on the next launch for the same PR.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Session {
+    pub view: Option<ThreadView>,
+    /// Selected thread id in each view.
+    pub selected: HashMap<ThreadView, String>,
+    pub scroll_offset: usize,
+    pub show_diff: Option<bool>,
+    /// Replies written but not yet published.
+    pub queued_replies: Vec<QueuedReply>,
+    pub marks: HashMap<char, Location>,
+    /// Worktree created for the PR head, used by editor actions.
+    pub worktree: Option<PathBuf>,
+    /// Review checklist items ticked so far.
+    pub checklist: Vec<String>,
+    /// Files whose threads have been shown.
+    pub viewed_files: Vec<String>,
+    /// Newest comment shown in each thread, by thread id.
+    pub seen: HashMap<String, DateTime<Utc>>,
+    /// Threads pinned to the top of the lists.
+    pub pinned: Vec<String>,
+    /// A PR comment that failed to post.
+    pub comment_draft: Option<String>,
+    /// Notes kept to yourself, never sent to GitHub.
+    pub notes: Vec<Note>,
+}
+
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(repo: &Repo, pr_number: u64) -> Result<Self> {
+        let name = format!("{}-{}-{}.json", repo.owner, repo.name, pr_number);
+        Ok(Self {
+            path: Self::dir()?.join(name),
+        })
+    }
+
+    /// The session for comparing a ref range rather than reviewing a PR.
+    pub fn for_range(repo: &Repo, range: &str) -> Result<Self> {
+        let range: String = range
+            .chars()
+            .map(|ch| match ch {
+                '/' | '\\' | ':' => '_',
+                ch => ch,
+            })
+            .collect();
+        let name = format!("{}-{}-{}.json", repo.owner, repo.name, range);
+        Ok(Self {
+            path: Self::dir()?.join(name),
+        })
+    }
+
+    /// A missing or unreadable session starts the review afresh.
+    pub fn load(&self) -> Session {
+        fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, session: &Session) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let data = serde_json::to_vec_pretty(session)?;
+        fs::write(&self.path, data)?;
+        Ok(())
+    }
+
+    fn dir() -> Result<PathBuf> {
+        if let Ok(dir) = env::var("XDG_STATE_HOME")
+            && !dir.is_empty()
+        {
+            return Ok(Path::new(&dir).join("gh-cr").join("sessions"));
+        }
+        let mut path = dirs_next::home_dir().ok_or_else(|| anyhow!("HOME not set"))?;
+        path.push(".local");
+        path.push("state");
+        path.push("gh-cr");
+        path.push("sessions");
+        Ok(path)
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{picker, theme::Theme};
+
+const THEMES: [&str; 5] = ["dark", "light", "solarized", "gruvbox", "high-contrast"];
+/// Editors offered when installed, with the flags they need to wait for the
+/// reply to be saved.
+const EDITORS: [&str; 6] = ["vim", "nvim", "hx", "nano", "emacs -nw", "code --wait"];
+
+/// Asks for the settings people most often change on first launch and
+/// writes them to the user config at `path`. Dismissing a step leaves its
+/// default; the file is written either way, so setup only runs once.
+pub fn run(path: &Path) -> Result<()> {
+    let theme = Theme::dark();
+    let hint = "↑/↓ select  enter choose  q keep default";
+
+    let profiles = ["default".to_string(), "vim".to_string()];
+    let profile = picker::pick_option(
+        "Setup 1/3: key bindings (vim adds h/l, j/k, g/G and counts)",
+        &profiles,
+        hint,
+        &theme,
+    )?
+    .filter(|index| *index > 0)
+    .map(|index| profiles[index].clone());
+
+    let themes: Vec<String> = THEMES.iter().map(|name| name.to_string()).collect();
+    let picked_theme = picker::pick_option("Setup 2/3: theme", &themes, hint, &theme)?
+        .filter(|index| *index > 0)
+        .map(|index| themes[index].clone());
+
+    let current = env::var("EDITOR").ok().filter(|editor| !editor.is_empty());
+    let mut editors = vec![match &current {
+        Some(editor) => format!("$EDITOR ({editor})"),
+        None => "$EDITOR (unset, falls back to vim)".into(),
+    }];
+    editors.extend(
+        EDITORS
+            .iter()
+            .filter(|command| command.split_whitespace().next().is_some_and(on_path))
+            .map(|command| command.to_string()),
+    );
+    let editor = picker::pick_option("Setup 3/3: reply editor", &editors, hint, &theme)?
+        .filter(|index| *index > 0)
+        .map(|index| editors[index].clone());
+
+    let mut config =
+        String::from("# Written by gh-cr's first-run setup. See the README for every setting.\n");
+    if let Some(editor) = editor {
+        config.push_str(&format!("editor = {}\n", quote(&editor)));
+    }
+    if let Some(name) = picked_theme {
+        config.push_str(&format!("theme = {}\n", quote(&name)));
+    }
+    if let Some(profile) = profile {
+        config.push_str(&format!("\n[keys]\nprofile = {}\n", quote(&profile)));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    fs::write(path, config).with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Saved your settings to {}.", path.display());
+    Ok(())
+}
+
+/// A TOML basic string; JSON's escapes are a subset of TOML's.
+fn quote(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir: PathBuf| dir.join(program).is_file())
+    })
+}
@@ -0,0 +1,59 @@
+/// Lines changed and files touched at which a PR moves up a size, from XS
+/// to XL; anything past the last is XXL.
+const LINES: [usize; 5] = [10, 100, 500, 1000, 2000];
+const FILES: [usize; 5] = [2, 5, 15, 30, 60];
+const LABELS: [&str; 6] = ["XS", "S", "M", "L", "XL", "XXL"];
+
+/// How big a PR is and roughly how long it takes to review.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Estimate {
+    pub label: &'static str,
+    pub minutes: usize,
+}
+
+/// Sizes a PR by whichever of its changed lines and files puts it higher,
+/// and estimates review time at around 400 lines an hour plus a couple of
+/// minutes to get oriented in each file.
+pub fn estimate(lines: usize, files: usize) -> Estimate {
+    let class = |value: usize, limits: &[usize; 5]| {
+        limits
+            .iter()
+            .position(|limit| value < *limit)
+            .unwrap_or(limits.len())
+    };
+    let size = class(lines, &LINES).max(class(files, &FILES));
+    let minutes = (lines * 60 / 400 + files * 2).max(5);
+    Estimate {
+        label: LABELS[size],
+        // Nobody can tell 23 minutes from 25 ahead of time.
+        minutes: minutes.div_ceil(5) * 5,
+    }
+}
+
+impl Estimate {
+    /// `M · ~25 min` or `XL · ~3.5 h`.
+    pub fn describe(&self) -> String {
+        if self.minutes < 60 {
+            format!("{} · ~{} min", self.label, self.minutes)
+        } else {
+            // To the nearest half hour.
+            let hours = (self.minutes as f64 / 30.0).round() / 2.0;
+            let precision = usize::from(hours.fract() != 0.0);
+            format!("{} · ~{hours:.precision$} h", self.label)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_follows_the_larger_of_lines_and_files() {
+        assert_eq!(estimate(4, 1).label, "XS");
+        assert_eq!(estimate(40, 20).label, "L");
+        assert_eq!(estimate(2500, 3).label, "XXL");
+        assert_eq!(estimate(200, 4).describe(), "M · ~40 min");
+        assert_eq!(estimate(1200, 10).describe(), "XL · ~3.5 h");
+    }
+}
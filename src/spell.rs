@@ -0,0 +1,228 @@
+use std::{
+    collections::HashSet,
+    io::Write,
+    ops::Range,
+    process::{Command, Stdio},
+    thread,
+};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::config::SpellConfig;
+
+/// A word the dictionary doesn't know, with its suggested replacements.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Misspelling {
+    pub word: String,
+    pub suggestions: Vec<String>,
+}
+
+/// Checks prose with hunspell or aspell through the ispell pipe protocol
+/// both speak with `-a`.
+#[derive(Clone)]
+pub struct Speller {
+    command: String,
+    language: String,
+    /// Words never flagged: the configured list and any ignored since.
+    known: HashSet<String>,
+}
+
+impl Speller {
+    pub fn new(config: &SpellConfig) -> Self {
+        Self {
+            command: config.command.clone(),
+            language: config.language.clone(),
+            known: config.words.iter().cloned().collect(),
+        }
+    }
+
+    /// Stops flagging `word` for the rest of the session.
+    pub fn ignore(&mut self, word: &str) {
+        self.known.insert(word.to_string());
+    }
+
+    /// The words in `text` the dictionary doesn't know, each once, in the
+    /// order they first appear. Code, links and mentions aren't checked.
+    pub fn check(&self, text: &str) -> Result<Vec<Misspelling>> {
+        let args = if self.command.ends_with("aspell") {
+            vec!["-a".to_string(), format!("--lang={}", self.language)]
+        } else {
+            vec!["-a".to_string(), "-d".to_string(), self.language.clone()]
+        };
+        log::debug!("running {} {}", self.command, args.join(" "));
+        let mut child = Command::new(&self.command)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to spawn {}", self.command))?;
+        // Written from its own thread so a checker that answers as it reads
+        // can't fill stdout while we're still blocked on stdin.
+        let writer = child.stdin.take().map(|mut stdin| {
+            let prose = prose(text);
+            thread::spawn(move || -> std::io::Result<()> {
+                // `^` keeps a line starting with a command character from
+                // being read as one.
+                for line in prose.lines() {
+                    writeln!(stdin, "^{line}")?;
+                }
+                Ok(())
+            })
+        });
+        let output = child.wait_with_output()?;
+        if let Some(writer) = writer {
+            writer
+                .join()
+                .map_err(|_| anyhow!("{} input thread panicked", self.command))??;
+        }
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} failed: {}",
+                self.command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let mut found: Vec<Misspelling> = Vec::new();
+        for misspelling in parse(&String::from_utf8_lossy(&output.stdout)) {
+            if !self.known.contains(&misspelling.word)
+                && !found.iter().any(|seen| seen.word == misspelling.word)
+            {
+                found.push(misspelling);
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Reads `& word count offset: one, two` and `# word offset` answers; the
+/// rest mean a word was found.
+pub fn parse(output: &str) -> Vec<Misspelling> {
+    output
+        .lines()
+        .filter_map(|line| {
+            if let Some(rest) = line.strip_prefix("& ") {
+                let (head, suggestions) = rest.split_once(": ")?;
+                let word = head.split_whitespace().next()?;
+                Some(Misspelling {
+                    word: word.to_string(),
+                    suggestions: suggestions.split(", ").map(str::to_string).collect(),
+                })
+            } else {
+                let word = line.strip_prefix("# ")?.split_whitespace().next()?;
+                Some(Misspelling {
+                    word: word.to_string(),
+                    suggestions: Vec::new(),
+                })
+            }
+        })
+        .collect()
+}
+
+/// `text` with fenced code blocks, code spans, URLs and `@mentions`
+/// blanked out, keeping its lines.
+pub fn prose(text: &str) -> String {
+    let mut fenced = false;
+    let mut kept = Vec::new();
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            fenced = !fenced;
+            kept.push(String::new());
+            continue;
+        }
+        if fenced {
+            kept.push(String::new());
+            continue;
+        }
+        let mut in_code = false;
+        let unquoted: String = line
+            .chars()
+            .map(|ch| {
+                if ch == '`' {
+                    in_code = !in_code;
+                    ' '
+                } else if in_code {
+                    ' '
+                } else {
+                    ch
+                }
+            })
+            .collect();
+        let words: Vec<&str> = unquoted
+            .split(' ')
+            .map(|word| {
+                if word.contains("://") || word.starts_with('@') {
+                    ""
+                } else {
+                    word
+                }
+            })
+            .collect();
+        kept.push(words.join(" "));
+    }
+    kept.join("\n")
+}
+
+/// Byte ranges of the words in `line`: letters with any apostrophes
+/// inside them.
+pub fn words(line: &str) -> Vec<Range<usize>> {
+    let mut found = Vec::new();
+    let mut start = None;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, ch)) = chars.next() {
+        let inner_apostrophe = ch == '\''
+            && start.is_some()
+            && chars.peek().is_some_and(|(_, next)| next.is_alphabetic());
+        if ch.is_alphabetic() || inner_apostrophe {
+            start.get_or_insert(index);
+        } else if let Some(from) = start.take() {
+            found.push(from..index);
+        }
+    }
+    if let Some(from) = start {
+        found.push(from..line.len());
+    }
+    found
+}
+
+/// `text` with every whole-word `word` replaced by `with`.
+pub fn replace_word(text: &str, word: &str, with: &str) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    let mut last = 0;
+    for range in words(text) {
+        if &text[range.clone()] == word {
+            replaced.push_str(&text[last..range.start]);
+            replaced.push_str(with);
+            last = range.end;
+        }
+    }
+    replaced.push_str(&text[last..]);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spelling_reads_ispell_answers_and_skips_code() {
+        let found = parse("@(#) International Ispell\n*\n& teh 2 4: the, tech\n# frobz 10\n");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].word, "teh");
+        assert_eq!(found[0].suggestions, ["the", "tech"]);
+        assert!(found[1].suggestions.is_empty());
+
+        let prose = prose("See `fooBar` at https://x.io, @octocat\n```\nlet zz = 1;\n```\nok");
+        assert!(!prose.contains("fooBar") && !prose.contains("x.io") && !prose.contains("octocat"));
+        assert!(!prose.contains("zz"));
+        assert_eq!(prose.lines().count(), 5);
+
+        let line = "don't teh 'quoted' teh2";
+        let words: Vec<&str> = words(line).into_iter().map(|range| &line[range]).collect();
+        assert_eq!(words, ["don't", "teh", "quoted", "teh"]);
+        assert_eq!(
+            replace_word("teh tehran teh", "teh", "the"),
+            "the tehran the"
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use crate::PrSummary;
+
+/// The chain of open PRs that `pr_number` belongs to, from the PR nearest
+/// the default branch up to the top of the stack. A PR is stacked on
+/// another when its base branch is that PR's head branch. Where several
+/// PRs build on the same one, the lowest-numbered is followed. Returns just
+/// `pr_number` when it isn't part of a stack.
+pub fn chain(prs: &[PrSummary], pr_number: u64) -> Vec<u64> {
+    let Some(current) = prs.iter().find(|pr| pr.number == pr_number) else {
+        return vec![pr_number];
+    };
+    let mut below = Vec::new();
+    let mut base = current.base_ref_name.as_str();
+    while let Some(parent) = prs
+        .iter()
+        .find(|pr| pr.head_ref_name == base && !below.contains(&pr.number))
+    {
+        if parent.number == pr_number {
+            break;
+        }
+        below.push(parent.number);
+        base = &parent.base_ref_name;
+    }
+    below.reverse();
+    let mut stack = below;
+    stack.push(pr_number);
+    let mut head = current.head_ref_name.as_str();
+    while let Some(child) = prs
+        .iter()
+        .filter(|pr| pr.base_ref_name == head && !stack.contains(&pr.number))
+        .min_by_key(|pr| pr.number)
+    {
+        stack.push(child.number);
+        head = &child.head_ref_name;
+    }
+    stack
+}
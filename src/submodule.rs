@@ -0,0 +1,61 @@
+/// The old and new commits of a submodule pointer change in a diff hunk,
+/// from its `-Subproject commit` and `+Subproject commit` lines.
+pub fn bump(hunk: &str) -> Option<(String, String)> {
+    let commit = |sign: char| {
+        hunk.lines().find_map(|line| {
+            line.strip_prefix(sign)?
+                .strip_prefix("Subproject commit ")
+                .map(|sha| sha.trim().trim_end_matches("-dirty").to_string())
+        })
+    };
+    Some((commit('-')?, commit('+')?))
+}
+
+/// The URL `.gitmodules` gives for the submodule checked out at `path`.
+pub fn url_for(gitmodules: &str, path: &str) -> Option<String> {
+    let mut section_path = None;
+    let mut section_url = None;
+    for line in gitmodules.lines().map(str::trim) {
+        if line.starts_with('[') {
+            if section_path.as_deref() == Some(path) {
+                return section_url;
+            }
+            section_path = None;
+            section_url = None;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key.trim() {
+            "path" => section_path = Some(value.trim().to_string()),
+            "url" => section_url = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    if section_path.as_deref() == Some(path) {
+        return section_url;
+    }
+    None
+}
+
+/// Owner and name of the GitHub repository a submodule URL points at.
+/// Relative URLs such as `../lib.git` are resolved against the owner of the
+/// superproject, `owner`.
+pub fn repo_of(url: &str, owner: &str) -> Option<(String, String)> {
+    let url = url.trim_end_matches('/').trim_end_matches(".git");
+    let path = if let Some(sibling) = url.strip_prefix("../") {
+        format!("{owner}/{sibling}")
+    } else if let Some((_, rest)) = url.split_once("://") {
+        rest.split_once('/')?.1.to_string()
+    } else if let Some((_, rest)) = url.split_once(':') {
+        // scp-like `git@github.com:owner/name`.
+        rest.to_string()
+    } else {
+        return None;
+    };
+    let mut parts = path.rsplitn(2, '/');
+    let name = parts.next()?;
+    let owner = parts.next()?.rsplit('/').next()?;
+    Some((owner.to_string(), name.to_string()))
+}
@@ -0,0 +1,396 @@
+use std::env;
+
+use anyhow::{Result, anyhow};
+use crossterm::style::{Color, Colored};
+
+use crate::config::{ColorMode, Config};
+
+macro_rules! theme_roles {
+    ($($role:ident),* $(,)?) => {
+        #[derive(Clone)]
+        pub struct Theme {
+            $(pub $role: Color,)*
+            /// Colours told apart by author, picked by [`Theme::author_color`].
+            pub authors: [Color; 8],
+        }
+
+        impl Theme {
+            fn set(&mut self, role: &str, color: Color) -> bool {
+                match role {
+                    $(stringify!($role) => self.$role = color,)*
+                    _ => return false,
+                }
+                true
+            }
+
+            fn map_colors(&mut self, convert: impl Fn(Color) -> Color) {
+                $(self.$role = convert(self.$role);)*
+                for color in &mut self.authors {
+                    *color = convert(*color);
+                }
+            }
+        }
+    };
+}
+
+theme_roles!(
+    heading,
+    path,
+    subtle,
+    key,
+    author,
+    resolved,
+    unresolved,
+    diff_added,
+    diff_removed,
+    diff_header,
+    diff_context,
+    diff_whitespace,
+    queued,
+    mark,
+    unread,
+    scrollbar_thumb,
+    scrollbar_track,
+    minimap_shade,
+);
+
+const fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            heading: rgb(110, 110, 110),
+            path: rgb(180, 180, 180),
+            subtle: Color::DarkGrey,
+            key: Color::White,
+            author: rgb(120, 200, 220),
+            resolved: Color::DarkGreen,
+            unresolved: Color::DarkYellow,
+            diff_added: Color::DarkGreen,
+            diff_removed: Color::DarkRed,
+            diff_header: Color::DarkGrey,
+            diff_context: Color::Grey,
+            diff_whitespace: Color::DarkRed,
+            queued: Color::DarkMagenta,
+            mark: Color::Magenta,
+            unread: Color::Cyan,
+            scrollbar_thumb: Color::Grey,
+            scrollbar_track: Color::DarkGrey,
+            minimap_shade: rgb(60, 60, 60),
+            authors: [
+                rgb(120, 200, 220),
+                rgb(229, 192, 123),
+                rgb(152, 195, 121),
+                rgb(198, 120, 221),
+                rgb(224, 108, 117),
+                rgb(97, 175, 239),
+                rgb(209, 154, 102),
+                rgb(86, 182, 194),
+            ],
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            heading: rgb(110, 110, 110),
+            path: rgb(60, 60, 60),
+            subtle: rgb(130, 130, 130),
+            key: rgb(0, 0, 0),
+            author: rgb(0, 110, 150),
+            resolved: rgb(0, 128, 0),
+            unresolved: rgb(160, 110, 0),
+            diff_added: rgb(0, 130, 0),
+            diff_removed: rgb(180, 0, 0),
+            diff_header: rgb(120, 120, 120),
+            diff_context: rgb(70, 70, 70),
+            diff_whitespace: rgb(255, 190, 190),
+            queued: rgb(140, 0, 140),
+            mark: rgb(180, 0, 180),
+            unread: rgb(0, 120, 200),
+            scrollbar_thumb: rgb(90, 90, 90),
+            scrollbar_track: rgb(190, 190, 190),
+            minimap_shade: rgb(220, 220, 220),
+            authors: [
+                rgb(0, 110, 150),
+                rgb(150, 100, 0),
+                rgb(40, 120, 40),
+                rgb(130, 50, 160),
+                rgb(180, 40, 60),
+                rgb(30, 80, 200),
+                rgb(170, 80, 20),
+                rgb(0, 130, 120),
+            ],
+        }
+    }
+
+    pub fn solarized() -> Self {
+        Self {
+            heading: rgb(88, 110, 117),
+            path: rgb(147, 161, 161),
+            subtle: rgb(88, 110, 117),
+            key: rgb(238, 232, 213),
+            author: rgb(38, 139, 210),
+            resolved: rgb(133, 153, 0),
+            unresolved: rgb(181, 137, 0),
+            diff_added: rgb(133, 153, 0),
+            diff_removed: rgb(220, 50, 47),
+            diff_header: rgb(108, 113, 196),
+            diff_context: rgb(131, 148, 150),
+            diff_whitespace: rgb(220, 50, 47),
+            queued: rgb(211, 54, 130),
+            mark: rgb(211, 54, 130),
+            unread: rgb(42, 161, 152),
+            scrollbar_thumb: rgb(131, 148, 150),
+            scrollbar_track: rgb(7, 54, 66),
+            minimap_shade: rgb(7, 54, 66),
+            authors: [
+                rgb(38, 139, 210),
+                rgb(181, 137, 0),
+                rgb(133, 153, 0),
+                rgb(108, 113, 196),
+                rgb(220, 50, 47),
+                rgb(42, 161, 152),
+                rgb(203, 75, 22),
+                rgb(211, 54, 130),
+            ],
+        }
+    }
+
+    pub fn gruvbox() -> Self {
+        Self {
+            heading: rgb(146, 131, 116),
+            path: rgb(235, 219, 178),
+            subtle: rgb(146, 131, 116),
+            key: rgb(251, 241, 199),
+            author: rgb(131, 165, 152),
+            resolved: rgb(184, 187, 38),
+            unresolved: rgb(250, 189, 47),
+            diff_added: rgb(184, 187, 38),
+            diff_removed: rgb(251, 73, 52),
+            diff_header: rgb(142, 192, 124),
+            diff_context: rgb(213, 196, 161),
+            diff_whitespace: rgb(204, 36, 29),
+            queued: rgb(211, 134, 155),
+            mark: rgb(254, 128, 25),
+            unread: rgb(142, 192, 124),
+            scrollbar_thumb: rgb(168, 153, 132),
+            scrollbar_track: rgb(80, 73, 69),
+            minimap_shade: rgb(80, 73, 69),
+            authors: [
+                rgb(131, 165, 152),
+                rgb(250, 189, 47),
+                rgb(184, 187, 38),
+                rgb(211, 134, 155),
+                rgb(251, 73, 52),
+                rgb(142, 192, 124),
+                rgb(254, 128, 25),
+                rgb(69, 133, 136),
+            ],
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            heading: Color::White,
+            path: Color::White,
+            subtle: Color::Grey,
+            key: Color::Yellow,
+            author: Color::Cyan,
+            resolved: Color::Green,
+            unresolved: Color::Yellow,
+            diff_added: Color::Green,
+            diff_removed: Color::Red,
+            diff_header: Color::Cyan,
+            diff_context: Color::White,
+            diff_whitespace: Color::Red,
+            queued: Color::Magenta,
+            mark: Color::Magenta,
+            unread: Color::Cyan,
+            scrollbar_thumb: Color::White,
+            scrollbar_track: Color::Grey,
+            minimap_shade: Color::DarkGrey,
+            authors: [
+                Color::Cyan,
+                Color::Yellow,
+                Color::Green,
+                Color::Magenta,
+                Color::Red,
+                Color::Blue,
+                Color::White,
+                Color::DarkCyan,
+            ],
+        }
+    }
+
+    /// The colour `name` is shown in. The name is hashed with FNV-1a rather
+    /// than the std hasher so everyone keeps their colour between releases.
+    pub fn author_color(&self, name: &str) -> Color {
+        let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte.to_ascii_lowercase())).wrapping_mul(0x0100_0000_01b3)
+        });
+        self.authors[(hash % self.authors.len() as u64) as usize]
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "solarized" => Some(Self::solarized()),
+            "gruvbox" => Some(Self::gruvbox()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// Resolves the configured theme, including user-defined themes layered
+    /// over a built-in base, and fits it to what the terminal can display.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let name = config.theme.as_deref().unwrap_or("dark");
+        let mut theme = match config.themes.get(name) {
+            Some(custom) => {
+                let base = custom.base.as_deref().unwrap_or("dark");
+                let mut theme = Self::named(base)
+                    .ok_or_else(|| anyhow!("theme `{name}` has unknown base `{base}`"))?;
+                for (role, value) in &custom.colors {
+                    let color = parse_color(value)
+                        .ok_or_else(|| anyhow!("invalid color `{value}` for `{role}`"))?;
+                    if !theme.set(&role.replace('-', "_"), color) {
+                        return Err(anyhow!("unknown theme color `{role}` in `{name}`"));
+                    }
+                }
+                theme
+            }
+            None => Self::named(name).ok_or_else(|| anyhow!("unknown theme `{name}`"))?,
+        };
+        let depth = match config.color {
+            ColorMode::Auto => ColorDepth::detect(),
+            ColorMode::Never => ColorDepth::None,
+            ColorMode::Basic => ColorDepth::Basic,
+            ColorMode::Ansi256 => ColorDepth::Ansi256,
+            ColorMode::Truecolor => ColorDepth::TrueColor,
+        };
+        // An explicit colour mode overrides NO_COLOR, which crossterm honours
+        // on its own otherwise.
+        if config.color != ColorMode::Auto || depth == ColorDepth::None {
+            Colored::set_ansi_color_disabled(depth == ColorDepth::None);
+        }
+        match depth {
+            ColorDepth::TrueColor | ColorDepth::None => {}
+            ColorDepth::Ansi256 => theme.map_colors(to_ansi256),
+            ColorDepth::Basic => theme.map_colors(to_basic),
+        }
+        Ok(theme)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    None,
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    pub fn detect() -> Self {
+        if env::var("NO_COLOR").is_ok_and(|value| !value.is_empty()) {
+            return ColorDepth::None;
+        }
+        if env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+            || env::var("WT_SESSION").is_ok()
+        {
+            return ColorDepth::TrueColor;
+        }
+        match env::var("TERM") {
+            Ok(term) if term == "dumb" => ColorDepth::None,
+            Ok(term) if term.contains("256") => ColorDepth::Ansi256,
+            Ok(_) => ColorDepth::Basic,
+            Err(_) => ColorDepth::Ansi256,
+        }
+    }
+}
+
+/// Accepts `#rrggbb`, an ANSI palette index (`"208"`) or a colour name such
+/// as `dark_grey`.
+pub fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    if let Ok(index) = value.parse::<u8>() {
+        return Some(Color::AnsiValue(index));
+    }
+    Color::try_from(value.replace('-', "_").as_str()).ok()
+}
+
+const BASIC: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn ansi_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => BASIC[index as usize].1,
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = index - 16;
+            (level(n / 36), level((n / 6) % 6), level(n % 6))
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn to_ansi256(color: Color) -> Color {
+    let Color::Rgb { r, g, b } = color else {
+        return color;
+    };
+    if r == g && g == b {
+        return Color::AnsiValue(match r {
+            0..8 => 16,
+            249.. => 231,
+            _ => 232 + ((r as u16 - 8) * 24 / 241) as u8,
+        });
+    }
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    Color::AnsiValue(16 + 36 * level(r) + 6 * level(g) + level(b))
+}
+
+fn to_basic(color: Color) -> Color {
+    let (r, g, b) = match color {
+        Color::Rgb { r, g, b } => (r, g, b),
+        Color::AnsiValue(index) => ansi_to_rgb(index),
+        other => return other,
+    };
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+    BASIC
+        .iter()
+        .min_by_key(|(_, value)| distance(*value))
+        .map(|(color, _)| *color)
+        .unwrap_or(color)
+}